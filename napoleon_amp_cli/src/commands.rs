@@ -0,0 +1,771 @@
+use napoleon_amp_core::archive;
+use napoleon_amp_core::content::folder::Folder;
+use napoleon_amp_core::content::folder::content::FolderContentVariant;
+use napoleon_amp_core::content::playlist::PlaylistType;
+use napoleon_amp_core::content::playlist::data::PlaybackMode;
+use napoleon_amp_core::content::playlist::import::{ImportEvent, ImportOutcome};
+use napoleon_amp_core::content::playlist::import_job::{ImportJob, ImportProgress};
+use napoleon_amp_core::content::playlist::playlist_file;
+use napoleon_amp_core::content::playlist::playlist_file::{
+    ExportedAudioPaths, PLAYLIST_FILE_EXTS, PlaylistFileFormat, PlaylistFileImport,
+};
+use napoleon_amp_core::content::playlist::queue::Queue;
+use napoleon_amp_core::content::song::Song;
+use napoleon_amp_core::content::song::loudness::analysis::{
+    LoudnessAnalysisEvent, LoudnessAnalysisJob,
+};
+use napoleon_amp_core::content::song::song_data::MAX_RATING;
+use napoleon_amp_core::content::song::song_data::artist::ArtistRole;
+use napoleon_amp_core::content::trash;
+use napoleon_amp_core::fsck;
+use napoleon_amp_core::instance::NapoleonInstance;
+use napoleon_amp_core::itunes_library;
+use napoleon_amp_core::library_json;
+use napoleon_amp_core::migration;
+use napoleon_amp_core::paths;
+use napoleon_amp_core::read_rwlock;
+use napoleon_amp_core::tag_write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+
+const ALL_SONGS_QUERY: &str = "all";
+const INDENT: &str = "    ";
+
+pub(crate) type CommandResult = Result<(), String>;
+
+pub(crate) struct SongEdits {
+    pub(crate) title: Option<String>,
+    pub(crate) artist: Option<String>,
+    pub(crate) album: Option<String>,
+    pub(crate) user_tag: Option<String>,
+    pub(crate) rating: Option<u8>,
+}
+
+pub(crate) fn print_folders(instance: &NapoleonInstance) -> CommandResult {
+    println!(
+        "{} ({})",
+        instance.base_folder.get_folder_data().content_data.name,
+        instance.base_folder.id
+    );
+
+    print_folder_contents(&instance.base_folder, 1);
+
+    Ok(())
+}
+
+fn print_folder_contents(folder: &Rc<Folder>, depth: usize) {
+    let indent = INDENT.repeat(depth);
+
+    for content in folder.get_contents().iter() {
+        match content {
+            FolderContentVariant::Folder(sub_folder) => {
+                println!(
+                    "{}{}/ ({})",
+                    indent,
+                    sub_folder.get_folder_data().content_data.name,
+                    sub_folder.id
+                );
+
+                print_folder_contents(sub_folder, depth + 1);
+            }
+
+            FolderContentVariant::Playlist(playlist) => {
+                println!("{}{}", indent, playlist_display_str(playlist));
+            }
+        }
+    }
+}
+
+pub(crate) fn print_playlists(instance: &NapoleonInstance) -> CommandResult {
+    for playlist in instance.iter_playlists() {
+        println!("{}", playlist_display_str(&playlist));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn print_artists(instance: &mut NapoleonInstance) -> CommandResult {
+    for artist in instance.get_artist_index().artists() {
+        let role_count = |role| {
+            artist
+                .songs
+                .values()
+                .filter(|song_role| **song_role == role)
+                .count()
+        };
+
+        println!(
+            "{} | primary: {}, featured: {}, remixer: {}",
+            artist.name,
+            role_count(ArtistRole::Primary),
+            role_count(ArtistRole::Featured),
+            role_count(ArtistRole::Remixer)
+        );
+    }
+
+    Ok(())
+}
+
+pub(crate) fn print_albums(instance: &mut NapoleonInstance) -> CommandResult {
+    for album in instance.get_album_index().albums() {
+        let year_str = album
+            .year()
+            .map_or(String::new(), |year| format!(" ({})", year));
+
+        println!(
+            "{} - {}{} | {} songs, {}",
+            album.name,
+            album.album_artist,
+            year_str,
+            album.tracks().len(),
+            secs_to_str(album.total_length() as u64)
+        );
+
+        for song in album.songs() {
+            println!("{}{}", INDENT, song_display_str(&song));
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn analyze_loudness(all: bool) -> CommandResult {
+    let report = LoudnessAnalysisJob::start(all).wait(|event, progress| match event {
+        LoudnessAnalysisEvent::SongsFound(songs_found) => {
+            println!("Analyzing {} songs", songs_found)
+        }
+
+        LoudnessAnalysisEvent::Analyzed(song) => {
+            let loudness_str =
+                song.get_song_data()
+                    .inner
+                    .loudness
+                    .inner
+                    .map_or(String::new(), |loudness| {
+                        format!(
+                            "{:.1} LUFS, {:+.2} dB ({})",
+                            loudness.track_loudness,
+                            loudness.track_gain_db(),
+                            loudness.source
+                        )
+                    });
+
+            println!(
+                "[{}/{}] {} | {}",
+                progress.completed(),
+                progress.songs_found.unwrap_or_default(),
+                song_display_str(song),
+                loudness_str
+            )
+        }
+
+        _ => {}
+    });
+
+    for (song, e) in &report.failed {
+        eprintln!("{}: {}", song_display_str(song), e);
+    }
+
+    println!(
+        "Analyzed {} songs, read {} from ReplayGain tags, {} failed",
+        report.analyzed,
+        report.read_from_tags,
+        report.failed.len()
+    );
+
+    Ok(())
+}
+
+pub(crate) fn create_folder(
+    instance: &NapoleonInstance,
+    name: String,
+    parent: Option<&str>,
+) -> CommandResult {
+    let parent_folder = find_folder(instance, parent)?;
+
+    parent_folder
+        .create_folder(name)
+        .map(drop)
+        .map_err(|e| format!("Unable to create folder; {}", e))
+}
+
+pub(crate) fn create_playlist(
+    instance: &NapoleonInstance,
+    name: String,
+    folder: Option<&str>,
+    dynamic: bool,
+) -> CommandResult {
+    let parent_folder = find_folder(instance, folder)?;
+
+    let result = if dynamic {
+        parent_folder.create_dynamic_playlist(name)
+    } else {
+        parent_folder.create_standard_playlist(name)
+    };
+
+    result
+        .map(drop)
+        .map_err(|e| format!("Unable to create playlist; {}", e))
+}
+
+pub(crate) fn print_songs(instance: &mut NapoleonInstance, playlist: &str) -> CommandResult {
+    let playlist = find_playlist(instance, playlist)?;
+
+    for song in read_rwlock(&playlist.get_song_vec_unfiltered()).iter() {
+        println!("{}", song_display_str(song));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn import_songs(
+    instance: &mut NapoleonInstance,
+    playlist: &str,
+    paths: &[PathBuf],
+    delete_original: bool,
+    link_duplicates: bool,
+) -> CommandResult {
+    let playlist = find_playlist(instance, playlist)?;
+
+    if let PlaylistType::Dynamic(_) = &*playlist {
+        return Err("Songs cannot be imported directly into a dynamic playlist".to_string());
+    }
+
+    let import_report =
+        ImportJob::start(paths.to_vec(), delete_original).wait(print_import_progress);
+
+    playlist.import_existing_songs(&import_report.imported_songs());
+
+    for (song_path, outcome) in import_report.not_imported() {
+        eprintln!("{}: {}", song_path.display(), outcome);
+    }
+
+    let duplicate_songs = import_report.duplicate_songs();
+
+    if link_duplicates && !duplicate_songs.is_empty() {
+        playlist.import_existing_songs(&duplicate_songs);
+
+        println!("Linked {} existing songs", duplicate_songs.len());
+    }
+
+    println!(
+        "Imported {} of {} songs",
+        import_report.imported_count(),
+        import_report.entries.len()
+    );
+
+    let not_imported_count = import_report.not_imported().count()
+        - if link_duplicates {
+            duplicate_songs.len()
+        } else {
+            0
+        };
+
+    if not_imported_count > 0 {
+        return Err(format!("{} songs were not imported", not_imported_count));
+    }
+
+    Ok(())
+}
+
+fn print_import_progress(event: &ImportEvent, progress: ImportProgress) {
+    match event {
+        ImportEvent::FilesFound(files_found) => println!("Found {} songs", files_found),
+
+        ImportEvent::Probed(song_path) => println!(
+            "[{}/{}] {}",
+            progress.completed(),
+            progress.files_found.unwrap_or_default(),
+            song_path.display()
+        ),
+
+        _ => {}
+    }
+}
+
+pub(crate) fn edit_song(
+    instance: &mut NapoleonInstance,
+    song_query: &str,
+    edits: SongEdits,
+) -> CommandResult {
+    let song_id = NapoleonInstance::registered_song_ids()
+        .into_iter()
+        .find(|song_id| song_id.to_string() == song_query)
+        .ok_or_else(|| format!("No song with id {}", song_query))?;
+
+    if let Some(rating) = edits.rating {
+        if rating as u32 > MAX_RATING {
+            return Err(format!("Rating must be between 0 and {}", MAX_RATING));
+        }
+    }
+
+    let song = NapoleonInstance::get_song(song_id);
+
+    let mut song_data = song.get_song_data().clone();
+    let song_data_inner = &mut song_data.inner;
+
+    if let Some(title) = edits.title {
+        song_data_inner.title = title;
+    }
+
+    if let Some(artist) = edits.artist {
+        song_data_inner
+            .meta
+            .inner
+            .artist
+            .unwrapped_mut()
+            .full_artist_string = artist;
+    }
+
+    if let Some(album) = edits.album {
+        *song_data_inner.meta.inner.album.unwrapped_mut() = album;
+    }
+
+    if let Some(user_tag) = edits.user_tag {
+        song_data_inner.user_tag = user_tag;
+    }
+
+    if let Some(rating) = edits.rating {
+        song_data_inner.rating = rating;
+    }
+
+    instance.set_song_data(&song, song_data);
+
+    println!("{}", song_display_str(&song));
+
+    Ok(())
+}
+
+pub(crate) fn write_tags(song_query: &str, write: bool) -> CommandResult {
+    let song_id = NapoleonInstance::registered_song_ids()
+        .into_iter()
+        .find(|song_id| song_id.to_string() == song_query)
+        .ok_or_else(|| format!("No song with id {}", song_query))?;
+
+    let song = NapoleonInstance::get_song(song_id);
+
+    let changes = tag_write::tag_changes(&song).map_err(|e| e.to_string())?;
+
+    if changes.is_empty() {
+        println!("The tags of the audio file already match the song data");
+        return Ok(());
+    }
+
+    for change in &changes {
+        println!("{}", change);
+    }
+
+    if !write {
+        println!("Pass --write to write these changes into the audio file");
+        return Ok(());
+    }
+
+    tag_write::write_tags(&song, &changes).map_err(|e| e.to_string())?;
+
+    println!(
+        "Wrote {} tags into {}",
+        changes.len(),
+        song.song_audio_path().display()
+    );
+
+    Ok(())
+}
+
+pub(crate) fn print_queue(
+    instance: &mut NapoleonInstance,
+    playlist: &str,
+    start_index: usize,
+    playback_mode: Option<PlaybackMode>,
+) -> CommandResult {
+    let playlist = find_playlist(instance, playlist)?;
+
+    let songs = read_rwlock(&playlist.get_song_vec_unfiltered()).clone();
+
+    if start_index >= songs.len() {
+        return Err(format!(
+            "Start index {} is out of bounds for a playlist of {} songs",
+            start_index,
+            songs.len()
+        ));
+    }
+
+    let playback_mode =
+        playback_mode.unwrap_or_else(|| playlist.get_user_data().inner.playback_mode);
+
+    let queue = Queue::new(start_index, songs, playback_mode);
+    let (temporary_front, temporary_back, song_list) = queue.current_queue();
+
+    for (i, song) in temporary_front
+        .iter()
+        .chain(temporary_back)
+        .chain(song_list)
+        .enumerate()
+    {
+        println!("{:>4}. {}", i + 1, song_display_str(song));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn check_library(repair: bool) -> CommandResult {
+    let report = fsck::check_library().map_err(|e| format!("Unable to check library; {}", e))?;
+
+    if report.issues.is_empty() {
+        println!("No issues found");
+        return Ok(());
+    }
+
+    for issue in &report.issues {
+        println!("{}", issue);
+        println!("{}fix: {}", INDENT, issue.fix_description());
+    }
+
+    if !repair {
+        return Err(format!(
+            "{} issues found, run again with --repair to fix them",
+            report.issues.len()
+        ));
+    }
+
+    let failed = report.repair_all();
+
+    for (issue, e) in &failed {
+        eprintln!("Unable to repair \"{}\"; {}", issue, e);
+    }
+
+    println!(
+        "Repaired {} of {} issues",
+        report.issues.len() - failed.len(),
+        report.issues.len()
+    );
+
+    if !failed.is_empty() {
+        return Err(format!("{} issues could not be repaired", failed.len()));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn print_trash() -> CommandResult {
+    let trash_entries =
+        trash::trash_entries().map_err(|e| format!("Unable to read the trash; {}", e))?;
+
+    if trash_entries.is_empty() {
+        println!("The trash is empty");
+    }
+
+    for trash_entry in &trash_entries {
+        println!(
+            "{} \"{}\" ({}), deleted {} days ago",
+            trash_entry.content,
+            trash_entry.name,
+            trash_entry.id,
+            trash_entry.days_in_trash()
+        );
+    }
+
+    Ok(())
+}
+
+pub(crate) fn restore_from_trash(instance: &mut NapoleonInstance, entry: &str) -> CommandResult {
+    let trash_entry = trash::trash_entries()
+        .map_err(|e| format!("Unable to read the trash; {}", e))?
+        .into_iter()
+        .find(|trash_entry| trash_entry.id.to_string() == entry)
+        .ok_or_else(|| format!("No trash entry with the id {}", entry))?;
+
+    instance
+        .restore_from_trash(&trash_entry)
+        .map_err(|e| format!("Unable to restore \"{}\"; {}", trash_entry.name, e))?;
+
+    println!("Restored \"{}\"", trash_entry.name);
+
+    Ok(())
+}
+
+pub(crate) fn empty_trash() -> CommandResult {
+    trash::empty_trash().map_err(|e| format!("Unable to empty the trash; {}", e))
+}
+
+pub(crate) fn export_playlist_file(
+    instance: &mut NapoleonInstance,
+    playlist: &str,
+    file_path: &Path,
+    copy_audio: bool,
+) -> CommandResult {
+    let playlist = find_playlist(instance, playlist)?;
+
+    let format = PlaylistFileFormat::from_path(file_path).ok_or_else(|| {
+        format!(
+            "Unknown playlist file extension, expected one of {}",
+            PLAYLIST_FILE_EXTS.join(", ")
+        )
+    })?;
+
+    let audio_paths = if copy_audio {
+        ExportedAudioPaths::CopiedRelative
+    } else {
+        ExportedAudioPaths::Library
+    };
+
+    format
+        .export(&playlist, file_path, audio_paths)
+        .map_err(|e| format!("Unable to write {}; {}", file_path.display(), e))?;
+
+    println!(
+        "Exported {} to {} as {}",
+        playlist_display_str(&playlist),
+        file_path.display(),
+        format
+    );
+
+    Ok(())
+}
+
+pub(crate) fn import_playlist_file(
+    instance: &NapoleonInstance,
+    file_path: &Path,
+    folder: Option<&str>,
+) -> CommandResult {
+    let parent_folder = find_folder(instance, folder)?;
+
+    let playlist_file_import = playlist_file::import_playlist_file(&parent_folder, file_path)
+        .map_err(|e| format!("Unable to import {}; {}", file_path.display(), e))?
+        .wait(print_import_progress);
+
+    print_playlist_file_import(&playlist_file_import)
+}
+
+fn print_playlist_file_import(playlist_file_import: &PlaylistFileImport) -> CommandResult {
+    let report = &playlist_file_import.report;
+
+    for (song_path, outcome) in report.not_imported() {
+        if !matches!(outcome, ImportOutcome::Duplicate(_)) {
+            eprintln!("{}: {}", song_path.display(), outcome);
+        }
+    }
+
+    for location in &playlist_file_import.skipped {
+        eprintln!("{}: Not a local file", location);
+    }
+
+    println!(
+        "Created {}, imported {} songs and matched {} already in the library",
+        playlist_display_str(&playlist_file_import.playlist),
+        report.imported_count(),
+        report.duplicate_songs().len()
+    );
+
+    let missing_count = playlist_file_import.missing_count();
+
+    if missing_count > 0 {
+        return Err(format!("{} songs could not be added", missing_count));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn import_itunes_library(
+    instance: &NapoleonInstance,
+    library_xml_path: &Path,
+    folder: Option<&str>,
+) -> CommandResult {
+    let parent_folder = find_folder(instance, folder)?;
+
+    let itunes_import = itunes_library::import_itunes_library(&parent_folder, library_xml_path)
+        .map_err(|e| format!("Unable to import {}; {}", library_xml_path.display(), e))?;
+
+    let report = &itunes_import.import_report;
+
+    for (song_path, outcome) in report.not_imported() {
+        if !matches!(outcome, ImportOutcome::Duplicate(_)) {
+            eprintln!("{}: {}", song_path.display(), outcome);
+        }
+    }
+
+    for track in &itunes_import.skipped_tracks {
+        eprintln!("{}: Not a local file", track);
+    }
+
+    for playlist_name in &itunes_import.smart_playlists_as_standard {
+        eprintln!(
+            "{}: Smart playlist criteria can't be used, created as a standard playlist",
+            playlist_name
+        );
+    }
+
+    println!(
+        "Imported {} songs and matched {} already in the library",
+        report.imported_count(),
+        report.duplicate_songs().len()
+    );
+
+    println!(
+        "Created {} folders, {} playlists and {} dynamic playlists",
+        itunes_import.folders_created,
+        itunes_import.playlists_created,
+        itunes_import.dynamic_playlists_created
+    );
+
+    Ok(())
+}
+
+pub(crate) fn write_archive(archive_path: &Path, include_audio: bool) -> CommandResult {
+    let manifest = archive::write_archive(archive_path, include_audio)
+        .map_err(|e| format!("Unable to write {}; {}", archive_path.display(), e))?;
+
+    println!("Backed up to {} ({})", archive_path.display(), manifest);
+
+    Ok(())
+}
+
+pub(crate) fn restore_archive(archive_path: &Path) -> CommandResult {
+    let manifest = archive::restore_archive(archive_path)
+        .map_err(|e| format!("Unable to restore {}; {}", archive_path.display(), e))?;
+
+    println!(
+        "Restored from {} ({}), the replaced data was moved into {}",
+        archive_path.display(),
+        manifest,
+        paths::replaced_by_restore_dir().display()
+    );
+
+    Ok(())
+}
+
+pub(crate) fn export_library_json(json_path: &Path) -> CommandResult {
+    let library_json = library_json::export_library_json(json_path)
+        .map_err(|e| format!("Unable to write {}; {}", json_path.display(), e))?;
+
+    println!(
+        "Exported {} songs, {} folders and {} playlists to {}",
+        library_json.songs.len(),
+        library_json.folders.len(),
+        library_json.playlists.len(),
+        json_path.display()
+    );
+
+    Ok(())
+}
+
+pub(crate) fn import_library_json(json_path: &Path) -> CommandResult {
+    let report = library_json::import_library_json(json_path)
+        .map_err(|e| format!("Unable to import {}; {}", json_path.display(), e))?;
+
+    for problem in &report.problems {
+        eprintln!("{}", problem);
+    }
+
+    println!(
+        "Updated {} songs, {} folders and {} playlists",
+        report.songs_updated, report.folders_updated, report.playlists_updated
+    );
+
+    Ok(())
+}
+
+pub(crate) fn plan_migrations() -> CommandResult {
+    let report = migration::plan_migrations()
+        .map_err(|e| format!("Unable to plan library migrations; {}", e))?;
+
+    println!("{}", report);
+
+    Ok(())
+}
+
+pub(crate) fn print_library_version() -> CommandResult {
+    println!(
+        "Library is at version {} of {}",
+        migration::library_version(),
+        migration::CURRENT_LIBRARY_VERSION
+    );
+
+    Ok(())
+}
+
+fn find_playlist(instance: &mut NapoleonInstance, query: &str) -> Result<Rc<PlaylistType>, String> {
+    if query == ALL_SONGS_QUERY {
+        return Ok(instance.get_all_songs_playlist());
+    }
+
+    let mut matching = instance
+        .iter_playlists()
+        .filter(|playlist| {
+            playlist.id().to_string() == query
+                || playlist.get_user_data().inner.content_data.name == query
+        })
+        .collect::<Vec<_>>();
+
+    match matching.len() {
+        0 => Err(format!("No playlist with the id or name {}", query)),
+
+        1 => Ok(matching.remove(0)),
+
+        _ => Err(format!(
+            "Multiple playlists are named {}, use the playlist id instead",
+            query
+        )),
+    }
+}
+
+fn find_folder(instance: &NapoleonInstance, query: Option<&str>) -> Result<Rc<Folder>, String> {
+    let Some(query) = query else {
+        return Ok(Rc::clone(&instance.base_folder));
+    };
+
+    find_folder_in(&instance.base_folder, query)
+        .ok_or_else(|| format!("No folder with the id {}", query))
+}
+
+fn find_folder_in(folder: &Rc<Folder>, query: &str) -> Option<Rc<Folder>> {
+    if folder.id.to_string() == query {
+        return Some(Rc::clone(folder));
+    }
+
+    for content in folder.get_contents().iter() {
+        if let FolderContentVariant::Folder(sub_folder) = content {
+            if let Some(found) = find_folder_in(sub_folder, query) {
+                return Some(found);
+            }
+        }
+    }
+
+    None
+}
+
+fn playlist_display_str(playlist: &PlaylistType) -> String {
+    let playlist_kind = match playlist {
+        PlaylistType::Standard(_) => "standard",
+        PlaylistType::Dynamic(_) => "dynamic",
+        PlaylistType::AllSongs(_) => "all songs",
+        PlaylistType::Album(_) => "album",
+    };
+
+    format!(
+        "{} [{}] ({})",
+        playlist.get_user_data().inner.content_data.name,
+        playlist_kind,
+        playlist.id()
+    )
+}
+
+fn song_display_str(song: &Arc<Song>) -> String {
+    let song_data = song.get_song_data();
+    let song_data_inner = &song_data.inner;
+    let meta = &song_data_inner.meta.inner;
+
+    format!(
+        "{} | {} - {} [{}] {} ({})",
+        song.id(),
+        song_data_inner.title,
+        meta.artist.unwrapped_ref().full_artist_string,
+        meta.album.unwrapped_ref(),
+        secs_to_str(*meta.song_length.unwrapped_ref() as u64),
+        song_data_inner.rating
+    )
+}
+
+fn secs_to_str(secs: u64) -> String {
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
@@ -0,0 +1,339 @@
+#![deny(unused_must_use)]
+
+mod commands;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use napoleon_amp_core::content::playlist::data::PlaybackMode;
+use napoleon_amp_core::instance::NapoleonInstance;
+use napoleon_amp_core::paths::library_root::{LibraryRoot, set_library_root};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// Headless access to a Napoleon Amp library.
+///
+/// Playlists can be referred to by their id, their exact name, or "all" for the All Songs playlist.
+/// Folders can be referred to by their id, or omitted to use the base folder.
+
+#[derive(Parser)]
+#[command(name = "napoleon_amp_cli", version)]
+struct Cli {
+    /// Directory of the library to use, overrides the NAPOLEON_AMP_DIR environment variable
+    #[arg(long, global = true, conflicts_with = "portable")]
+    library: Option<PathBuf>,
+    /// Uses the library stored next to the executable
+    #[arg(long, global = true)]
+    portable: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Prints the folder tree along with the playlists inside each folder
+    Folders,
+    /// Lists every playlist in the library
+    Playlists,
+    /// Creates a new folder
+    CreateFolder {
+        name: String,
+        /// Id of the parent folder
+        #[arg(long)]
+        parent: Option<String>,
+    },
+    /// Creates a new playlist
+    CreatePlaylist {
+        name: String,
+        /// Id of the folder to create the playlist in
+        #[arg(long)]
+        folder: Option<String>,
+        /// Creates a dynamic playlist instead of a standard playlist
+        #[arg(long)]
+        dynamic: bool,
+    },
+    /// Lists the songs in a playlist
+    Songs { playlist: String },
+    /// Lists every artist credited in the library, along with how many songs they're credited on in each
+    /// role
+    Artists,
+    /// Lists every album in the library along with its tracks, ordered by disc and track number
+    Albums,
+    /// Measures the loudness of the songs without one, so they can be played normalized. Songs and albums
+    /// with ReplayGain tags use the tags instead of being decoded
+    AnalyzeLoudness {
+        /// Measures every song again, including those already analyzed or read from tags
+        #[arg(long)]
+        all: bool,
+    },
+    /// Imports audio files into a playlist
+    Import {
+        playlist: String,
+        /// Audio files, or directories to search for audio files recursively
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Deletes the original files once they have been copied into the library
+        #[arg(long)]
+        delete_original: bool,
+        /// Adds songs already in the library to the playlist instead of skipping files that are exact
+        /// duplicates of them
+        #[arg(long)]
+        link_duplicates: bool,
+    },
+    /// Edits the data of a song, any option not provided is left unchanged
+    EditSong {
+        /// Id of the song to edit
+        song: String,
+        #[arg(long)]
+        title: Option<String>,
+        #[arg(long)]
+        artist: Option<String>,
+        #[arg(long)]
+        album: Option<String>,
+        #[arg(long)]
+        user_tag: Option<String>,
+        /// A rating from 0 to 5, where 0 is unrated
+        #[arg(long)]
+        rating: Option<u8>,
+    },
+    /// Lists the tags of the audio file of a song which differ from its data, and writes the song data into
+    /// them if --write is given
+    WriteTags {
+        /// Id of the song
+        song: String,
+        /// Writes the listed changes into the audio file
+        #[arg(long)]
+        write: bool,
+    },
+    /// Prints the queue that would be played when starting a playlist
+    Queue {
+        playlist: String,
+        /// Index of the song to start at
+        #[arg(long, default_value_t = 0)]
+        start: usize,
+        /// Overrides the playback mode saved in the playlist
+        #[arg(long)]
+        mode: Option<PlaybackModeArg>,
+    },
+    /// Checks the library for missing and orphaned files
+    Fsck {
+        /// Applies the fix for every issue found
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Lists, restores or deletes the folders, playlists and songs in the trash
+    Trash {
+        #[command(subcommand)]
+        action: Option<TrashAction>,
+    },
+    /// Writes a playlist to an M3U8, XSPF or PLS file, picked by the extension of the file
+    ExportPlaylist {
+        playlist: String,
+        file: PathBuf,
+        /// Copies the audio into a directory next to the file and refers to it with relative paths,
+        /// instead of referring to the audio stored in the library
+        #[arg(long)]
+        copy_audio: bool,
+    },
+    /// Creates a standard playlist from an M3U, M3U8, XSPF or PLS file, importing every file it lists
+    /// that isn't already in the library
+    ImportPlaylist {
+        file: PathBuf,
+        /// Id of the folder to create the playlist in
+        #[arg(long)]
+        folder: Option<String>,
+    },
+    /// Imports the tracks, playlists and folders of an iTunes Library.xml, smart playlists become dynamic
+    /// playlists where their criteria allow
+    ImportItunes {
+        library_xml: PathBuf,
+        /// Id of the folder to recreate the playlists and folders in
+        #[arg(long)]
+        folder: Option<String>,
+    },
+    /// Writes the library data to a single archive file
+    Backup {
+        archive: PathBuf,
+        /// Includes the song audio, which makes the archive as large as the library
+        #[arg(long)]
+        include_audio: bool,
+    },
+    /// Replaces the library data with the contents of an archive written by backup
+    Restore { archive: PathBuf },
+    /// Writes the metadata of every song, folder and playlist to a json file, keyed by id
+    ExportJson { json: PathBuf },
+    /// Updates the songs, folders and playlists in the library from a json file written by export-json
+    ImportJson { json: PathBuf },
+    /// Applies any pending library migrations, these are also applied whenever the library is opened
+    Migrate {
+        /// Prints what each pending migration would change without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum TrashAction {
+    /// Lists every entry in the trash, this is the default
+    List,
+    /// Moves an entry back to where it was deleted from
+    Restore {
+        /// Id of the trash entry
+        entry: String,
+    },
+    /// Deletes everything in the trash for good
+    Empty,
+}
+
+#[derive(ValueEnum, Copy, Clone)]
+enum PlaybackModeArg {
+    Sequential,
+    Shuffle,
+}
+
+impl From<PlaybackModeArg> for PlaybackMode {
+    fn from(value: PlaybackModeArg) -> Self {
+        match value {
+            PlaybackModeArg::Sequential => Self::Sequential,
+            PlaybackModeArg::Shuffle => Self::Shuffle,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let library_root = if let Some(library_dir) = cli.library {
+        Some(LibraryRoot::Custom(library_dir))
+    } else if cli.portable {
+        Some(LibraryRoot::Portable)
+    } else {
+        None
+    };
+
+    if let Some(library_root) = library_root {
+        set_library_root(library_root).expect("Library root is not resolved before this");
+    }
+
+    // Opening the library applies pending migrations, so a dry run has to happen before that
+    if let Command::Migrate { dry_run: true } = cli.command {
+        return exit_code(commands::plan_migrations());
+    }
+
+    // Nothing may be loaded while restoring, or it could be saved back over the restored data
+    if let Command::Restore { archive } = &cli.command {
+        return exit_code(commands::restore_archive(archive));
+    }
+
+    let mut instance = NapoleonInstance::new_headless();
+
+    let result = match cli.command {
+        Command::Folders => commands::print_folders(&instance),
+
+        Command::Playlists => commands::print_playlists(&instance),
+
+        Command::CreateFolder { name, parent } => {
+            commands::create_folder(&instance, name, parent.as_deref())
+        }
+
+        Command::CreatePlaylist {
+            name,
+            folder,
+            dynamic,
+        } => commands::create_playlist(&instance, name, folder.as_deref(), dynamic),
+
+        Command::Songs { playlist } => commands::print_songs(&mut instance, &playlist),
+
+        Command::Artists => commands::print_artists(&mut instance),
+        Command::Albums => commands::print_albums(&mut instance),
+
+        Command::AnalyzeLoudness { all } => commands::analyze_loudness(all),
+
+        Command::Import {
+            playlist,
+            paths,
+            delete_original,
+            link_duplicates,
+        } => commands::import_songs(
+            &mut instance,
+            &playlist,
+            &paths,
+            delete_original,
+            link_duplicates,
+        ),
+
+        Command::EditSong {
+            song,
+            title,
+            artist,
+            album,
+            user_tag,
+            rating,
+        } => commands::edit_song(
+            &mut instance,
+            &song,
+            commands::SongEdits {
+                title,
+                artist,
+                album,
+                user_tag,
+                rating,
+            },
+        ),
+
+        Command::WriteTags { song, write } => commands::write_tags(&song, write),
+
+        Command::Queue {
+            playlist,
+            start,
+            mode,
+        } => commands::print_queue(&mut instance, &playlist, start, mode.map(Into::into)),
+
+        Command::Fsck { repair } => commands::check_library(repair),
+
+        Command::Trash { action } => match action.unwrap_or(TrashAction::List) {
+            TrashAction::List => commands::print_trash(),
+            TrashAction::Restore { entry } => commands::restore_from_trash(&mut instance, &entry),
+            TrashAction::Empty => commands::empty_trash(),
+        },
+
+        Command::ExportPlaylist {
+            playlist,
+            file,
+            copy_audio,
+        } => commands::export_playlist_file(&mut instance, &playlist, &file, copy_audio),
+
+        Command::ImportPlaylist { file, folder } => {
+            commands::import_playlist_file(&instance, &file, folder.as_deref())
+        }
+
+        Command::ImportItunes {
+            library_xml,
+            folder,
+        } => commands::import_itunes_library(&instance, &library_xml, folder.as_deref()),
+
+        Command::Backup {
+            archive,
+            include_audio,
+        } => commands::write_archive(&archive, include_audio),
+
+        Command::Restore { .. } => unreachable!("Restored before the library is opened"),
+
+        Command::ExportJson { json } => commands::export_library_json(&json),
+
+        Command::ImportJson { json } => commands::import_library_json(&json),
+
+        Command::Migrate { .. } => commands::print_library_version(),
+    };
+
+    exit_code(result)
+}
+
+fn exit_code(result: commands::CommandResult) -> ExitCode {
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -3,6 +3,7 @@ mod texture_pool;
 mod ui;
 
 use crate::napoleon_client::texture_pool::TexturePool;
+use crate::napoleon_client::ui::panels::album_panel::AlbumPanel;
 use crate::napoleon_client::ui::panels::folder_list::FolderList;
 use crate::napoleon_client::ui::panels::playlist_panel::PlaylistPanel;
 use crate::napoleon_client::ui::panels::top_menu_bar::TopMenuBar;
@@ -12,11 +13,14 @@ use napoleon_amp_core::instance::NapoleonInstance;
 use std::rc::Rc;
 use std::time::Duration;
 
+const LOUDNESS_ANALYSIS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 pub struct NapoleonClientApp {
     napoleon_instance: NapoleonInstance,
     folder_list: FolderList,
     top_menu_bar: TopMenuBar,
     playlist_panel: Option<PlaylistPanel>,
+    album_panel: Option<AlbumPanel>,
     texture_pool: TexturePool,
 }
 
@@ -30,6 +34,7 @@ impl NapoleonClientApp {
             folder_list: FolderList::new(current_folder),
             top_menu_bar: TopMenuBar::new(),
             playlist_panel: None,
+            album_panel: None,
             texture_pool: TexturePool::new(),
         }
     }
@@ -37,6 +42,20 @@ impl NapoleonClientApp {
 
 impl App for NapoleonClientApp {
     fn update(&mut self, ctx: &Context, _: &mut Frame) {
+        if let Some(loudness_analysis) = self.napoleon_instance.loudness_analysis() {
+            if let Some(report) = loudness_analysis.poll() {
+                for (song, e) in &report.failed {
+                    eprintln!(
+                        "Unable to analyze the loudness of \"{}\"; {}",
+                        song.get_song_data().inner.title,
+                        e
+                    );
+                }
+            } else if !loudness_analysis.is_finished() {
+                ctx.request_repaint_after(LOUDNESS_ANALYSIS_POLL_INTERVAL);
+            }
+        }
+
         TopBottomPanel::top("menu_bar").show(ctx, |ui| {
             MenuBar::new().ui(ui, |ui| {
                 self.top_menu_bar.render(ui, &mut self.napoleon_instance);
@@ -47,6 +66,7 @@ impl App for NapoleonClientApp {
             self.folder_list.render(
                 ui,
                 &mut self.playlist_panel,
+                &mut self.album_panel,
                 &mut self.texture_pool,
                 &mut self.napoleon_instance,
             );
@@ -58,7 +78,21 @@ impl App for NapoleonClientApp {
                     playlist_panel.queue_panel.render(ui, music_manager);
                 });
             }
+        }
+
+        if let Some(ref mut album_panel) = self.album_panel {
+            let album_playlist = CentralPanel::default()
+                .show(ctx, |ui| {
+                    album_panel.render(ui, &mut self.napoleon_instance)
+                })
+                .inner;
 
+            // The album is shown like a playlist once it plays, so its playback can be controlled
+            if let Some(album_playlist) = album_playlist {
+                self.playlist_panel = Some(PlaylistPanel::new(album_playlist));
+                self.album_panel = None;
+            }
+        } else if let Some(ref mut playlist_panel) = self.playlist_panel {
             CentralPanel::default().show(ctx, |ui| {
                 playlist_panel.render(ctx, ui, &mut self.napoleon_instance);
             });
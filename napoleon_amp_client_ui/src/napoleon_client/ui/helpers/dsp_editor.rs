@@ -0,0 +1,125 @@
+use crate::napoleon_client::ui::helpers::select_button::select_button_mut;
+use derive_enum_all_values::AllValues;
+use eframe::egui::{Grid, Id, ScrollArea, Slider, Ui};
+use napoleon_amp_core::content::playlist::dsp::{
+    DspSettings, EqBand, EqPreset, FilterType, MAX_BAND_FREQUENCY, MAX_BAND_GAIN_DB,
+    MIN_BAND_FREQUENCY, MIN_BAND_GAIN_DB,
+};
+
+/// Edits the equalizer, preamp, balance and mono downmix of `dsp_settings`, returns true if any of them
+/// changed
+
+pub(crate) fn dsp_editor(ui: &mut Ui, id: Id, dsp_settings: &mut DspSettings) -> bool {
+    let mut changed = ui
+        .checkbox(&mut dsp_settings.enabled, "Enabled")
+        .on_hover_text("Songs are played unchanged when disabled")
+        .changed();
+
+    ui.add_enabled_ui(dsp_settings.enabled, |ui| {
+        ui.label("Preamp:")
+            .on_hover_text("Leaves room for bands which boost, so loud songs aren't limited");
+        changed |= ui
+            .add(
+                Slider::new(
+                    &mut dsp_settings.preamp_db,
+                    MIN_BAND_GAIN_DB..=MAX_BAND_GAIN_DB,
+                )
+                .suffix(" dB"),
+            )
+            .changed();
+
+        ui.horizontal(|ui| {
+            ui.label("Equalizer:");
+
+            ui.menu_button("Apply preset", |ui| {
+                for preset in EqPreset::all_values() {
+                    if ui.button(preset.to_string()).clicked() {
+                        dsp_settings.bands = preset.bands();
+                        changed = true;
+                    }
+                }
+            });
+
+            if ui.button("Add band").clicked() {
+                dsp_settings
+                    .bands
+                    .push(EqBand::new(FilterType::Peak, 1_000.0, 0.0));
+                changed = true;
+            }
+        });
+
+        let mut removed_band = None;
+
+        ScrollArea::vertical()
+            .id_salt(id.with("bands"))
+            .max_height(300.0)
+            .show(ui, |ui| {
+                Grid::new(id.with("band_grid"))
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (index, band) in dsp_settings.bands.iter_mut().enumerate() {
+                            let previous_filter_type = band.filter_type;
+
+                            select_button_mut(ui, "Type", &mut band.filter_type);
+
+                            changed |= band.filter_type != previous_filter_type;
+
+                            changed |= ui
+                                .add(
+                                    Slider::new(
+                                        &mut band.frequency,
+                                        MIN_BAND_FREQUENCY..=MAX_BAND_FREQUENCY,
+                                    )
+                                    .logarithmic(true)
+                                    .suffix(" Hz"),
+                                )
+                                .changed();
+
+                            changed |= ui
+                                .add(
+                                    Slider::new(
+                                        &mut band.gain_db,
+                                        MIN_BAND_GAIN_DB..=MAX_BAND_GAIN_DB,
+                                    )
+                                    .suffix(" dB"),
+                                )
+                                .changed();
+
+                            changed |= ui
+                                .add(
+                                    Slider::new(&mut band.q, 0.1..=10.0)
+                                        .logarithmic(true)
+                                        .text("Q"),
+                                )
+                                .on_hover_text("Higher values affect fewer frequencies")
+                                .changed();
+
+                            if ui.button("Remove").clicked() {
+                                removed_band = Some(index);
+                            }
+
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if let Some(removed_band) = removed_band {
+            dsp_settings.bands.remove(removed_band);
+            changed = true;
+        }
+
+        ui.label("Balance:").on_hover_text(
+            "Only applied to stereo songs, -1 is only the left and 1 only the right",
+        );
+        changed |= ui
+            .add(Slider::new(&mut dsp_settings.balance, -1.0..=1.0))
+            .changed();
+
+        changed |= ui
+            .checkbox(&mut dsp_settings.mono, "Mono")
+            .on_hover_text("Mixes every channel together, so each plays the same audio")
+            .changed();
+    });
+
+    changed
+}
@@ -0,0 +1,42 @@
+use eframe::egui::{Id, Modal, ProgressBar, Ui};
+use napoleon_amp_core::content::playlist::import_job::ImportJob;
+use std::time::Duration;
+
+const IMPORT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Shows the progress of `import_job` along with a button to cancel it, and keeps repainting so the job
+/// is polled while it runs
+
+pub(crate) fn import_progress_modal(ui: &mut Ui, import_job: &ImportJob) {
+    ui.ctx().request_repaint_after(IMPORT_POLL_INTERVAL);
+
+    Modal::new(Id::new("Import Progress Modal")).show(ui.ctx(), |ui| {
+        ui.set_width(250.);
+
+        let progress = import_job.progress();
+
+        if let Some(files_found) = progress.files_found {
+            ui.heading(format!(
+                "Importing {} of {} {}",
+                progress.completed(),
+                files_found,
+                if files_found == 1 { "song" } else { "songs" }
+            ));
+        } else {
+            ui.heading("Searching for songs");
+        }
+
+        ui.add(ProgressBar::new(progress.fraction()).show_percentage());
+
+        ui.label(format!(
+            "Copied: {}, Read: {}, Failed: {}",
+            progress.copied, progress.probed, progress.failed
+        ));
+
+        if import_job.is_cancelled() {
+            ui.label("Cancelling...");
+        } else if ui.button("Cancel").clicked() {
+            import_job.cancel();
+        }
+    });
+}
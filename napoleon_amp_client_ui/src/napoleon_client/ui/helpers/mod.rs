@@ -1,4 +1,6 @@
 pub(crate) mod custom_modal;
+pub(crate) mod dsp_editor;
+pub(crate) mod import_progress;
 pub(crate) mod select_button;
 
 use crate::napoleon_client::duration_to_str;
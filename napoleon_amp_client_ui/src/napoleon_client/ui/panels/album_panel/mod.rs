@@ -0,0 +1,150 @@
+use crate::napoleon_client::secs_to_str;
+use crate::napoleon_client::ui::helpers::scroll_area_styled;
+use eframe::egui::load::Bytes;
+use eframe::egui::{Image, ImageSource, RichText, ScrollArea, Sense, TextEdit, Ui, Vec2};
+use napoleon_amp_core::content::playlist::PlaylistType;
+use napoleon_amp_core::content::song::album_index::IndexedAlbum;
+use napoleon_amp_core::discord_rpc::set_rpc_playlist;
+use napoleon_amp_core::instance::NapoleonInstance;
+use std::rc::Rc;
+use std::sync::Arc;
+
+const COVER_SIZE: f32 = 160.;
+
+enum AlbumAction {
+    Play(IndexedAlbum),
+    Queue(IndexedAlbum),
+}
+
+pub(crate) struct AlbumPanel {
+    filter_search_content: String,
+}
+
+impl AlbumPanel {
+    pub(crate) fn new() -> Self {
+        Self {
+            filter_search_content: String::new(),
+        }
+    }
+
+    /// Renders every album as a grid of covers. Returns the playlist of the album which started playing, if
+    /// one did
+
+    pub(crate) fn render(
+        &mut self,
+        ui: &mut Ui,
+        napoleon_instance: &mut NapoleonInstance,
+    ) -> Option<Rc<PlaylistType>> {
+        ui.heading("Albums");
+
+        ui.add(TextEdit::singleline(&mut self.filter_search_content).hint_text("Search albums"));
+
+        ui.separator();
+
+        let can_queue_song = napoleon_instance.can_queue_song();
+        let search_str = self.filter_search_content.to_lowercase();
+        let mut album_action = None;
+
+        scroll_area_styled(ui, ScrollArea::vertical(), |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for album in napoleon_instance.get_album_index().albums() {
+                    if !search_str.is_empty()
+                        && !album.name.to_lowercase().contains(&search_str)
+                        && !album.album_artist.to_lowercase().contains(&search_str)
+                    {
+                        continue;
+                    }
+
+                    ui.vertical(|ui| {
+                        ui.set_width(COVER_SIZE);
+
+                        if let Some(action) = Self::render_album(ui, album, can_queue_song) {
+                            album_action = Some(action);
+                        }
+                    });
+                }
+            });
+        });
+
+        match album_action? {
+            AlbumAction::Play(album) => {
+                let album_playlist = napoleon_instance.create_album_playlist(&album);
+
+                napoleon_instance.start_play_playlist(Rc::clone(&album_playlist));
+                set_rpc_playlist(album.name);
+
+                Some(album_playlist)
+            }
+
+            AlbumAction::Queue(album) => {
+                napoleon_instance
+                    .try_queue_album(&album)
+                    .expect("Checked can queue song above");
+
+                None
+            }
+        }
+    }
+
+    fn render_album(
+        ui: &mut Ui,
+        album: &IndexedAlbum,
+        can_queue_song: bool,
+    ) -> Option<AlbumAction> {
+        let mut album_action = None;
+
+        let cover_response = if let Some(cover_id) = album.cover() {
+            let image_bytes = Arc::clone(
+                &NapoleonInstance::get_song_cover_data(cover_id)
+                    .inner
+                    .bytes
+                    .inner,
+            );
+
+            ui.add(
+                Image::new(ImageSource::Bytes {
+                    uri: format!("bytes://{}-cover.jpg", cover_id).into(),
+                    bytes: Bytes::Shared(image_bytes),
+                })
+                .fit_to_exact_size(Vec2::splat(COVER_SIZE))
+                .sense(Sense::click()),
+            )
+        } else {
+            let (rect, response) = ui.allocate_exact_size(Vec2::splat(COVER_SIZE), Sense::click());
+
+            ui.put(rect, |ui: &mut Ui| ui.label("No image"));
+
+            response
+        };
+
+        if cover_response.double_clicked() {
+            album_action = Some(AlbumAction::Play(album.clone()));
+        }
+
+        ui.label(RichText::new(&album.name).strong());
+        ui.label(&album.album_artist);
+
+        let year_str = album
+            .year()
+            .map_or(String::new(), |year| format!("{}, ", year));
+
+        ui.label(format!(
+            "{}{} songs, {}",
+            year_str,
+            album.tracks().len(),
+            secs_to_str(album.total_length() as u64)
+        ));
+
+        ui.horizontal(|ui| {
+            if ui.button("Play album").clicked() {
+                album_action = Some(AlbumAction::Play(album.clone()));
+            }
+
+            if can_queue_song && ui.button("Queue album").clicked() {
+                album_action = Some(AlbumAction::Queue(album.clone()));
+            }
+        });
+
+        album_action
+    }
+}
@@ -1,6 +1,7 @@
 mod modals;
 
 use crate::napoleon_client::ui::helpers::scroll_area_styled;
+use crate::napoleon_client::ui::panels::album_panel::AlbumPanel;
 use crate::napoleon_client::ui::panels::playlist_panel::PlaylistPanel;
 use eframe::egui::{
     Button, Image, IntoAtoms, Popup, Response, RichText, ScrollArea, Sense, Ui, UiBuilder, Vec2,
@@ -13,10 +14,16 @@ use crate::napoleon_client::ui::panels::folder_list::modals::{
     CreatePlaylistVariant, EditPlaylistType, FolderListModals,
 };
 use crate::napoleon_client::ui::panels::open_location_button;
+#[cfg(not(target_os = "android"))]
+use derive_enum_all_values::AllValues;
 use napoleon_amp_core::content::SaveData;
 use napoleon_amp_core::content::folder::content::FolderContentVariant;
 use napoleon_amp_core::content::folder::{Folder, FolderData};
 use napoleon_amp_core::content::playlist::data::PlaylistUserData;
+#[cfg(not(target_os = "android"))]
+use napoleon_amp_core::content::playlist::playlist_file::{
+    ExportedAudioPaths, PLAYLIST_FILE_EXTS, PlaylistFileFormat,
+};
 use napoleon_amp_core::content::playlist::{ClearSongsCache, PlaylistType};
 use napoleon_amp_core::discord_rpc::set_rpc_playlist;
 use napoleon_amp_core::instance::NapoleonInstance;
@@ -26,6 +33,7 @@ use std::rc::{Rc, Weak};
 pub(crate) struct FolderList {
     pub(crate) current_folder: Rc<Folder>,
     current_modal: FolderListModals,
+    export_copy_audio: bool,
 }
 
 impl FolderList {
@@ -33,6 +41,7 @@ impl FolderList {
         Self {
             current_folder,
             current_modal: FolderListModals::None,
+            export_copy_audio: false,
         }
     }
 
@@ -40,6 +49,7 @@ impl FolderList {
         &mut self,
         ui: &mut Ui,
         playlist_panel: &mut Option<PlaylistPanel>,
+        album_panel: &mut Option<AlbumPanel>,
         texture_pool: &mut TexturePool,
         napoleon_instance: &mut NapoleonInstance,
     ) {
@@ -48,7 +58,13 @@ impl FolderList {
 
             self.render_header_buttons(ui);
 
-            self.render_folder_content(ui, playlist_panel, texture_pool, napoleon_instance);
+            self.render_folder_content(
+                ui,
+                playlist_panel,
+                album_panel,
+                texture_pool,
+                napoleon_instance,
+            );
         });
 
         let (_, extra_space) = ui.allocate_at_least(ui.available_size(), Sense::click());
@@ -78,6 +94,7 @@ impl FolderList {
         &mut self,
         ui: &mut Ui,
         playlist_panel: &mut Option<PlaylistPanel>,
+        album_panel: &mut Option<AlbumPanel>,
         texture_pool: &mut TexturePool,
         napoleon_instance: &mut NapoleonInstance,
     ) {
@@ -86,9 +103,22 @@ impl FolderList {
             let mut next_playlist = None;
 
             if self.current_folder.parent.is_none() {
-                if self.playlist_button(ui, "All Songs").clicked() {
+                let all_songs_button = self.playlist_button(ui, "All Songs");
+
+                if all_songs_button.clicked() {
                     next_playlist = Some(napoleon_instance.get_all_songs_playlist())
                 }
+
+                #[cfg(not(target_os = "android"))]
+                Popup::context_menu(&all_songs_button).show(|ui| {
+                    self.export_menu_button(ui, &napoleon_instance.get_all_songs_playlist());
+                });
+
+                ui.separator();
+
+                if self.playlist_button(ui, "Albums").clicked() {
+                    *album_panel = Some(AlbumPanel::new());
+                }
             }
 
             let current_folder = Rc::clone(&self.current_folder);
@@ -111,6 +141,7 @@ impl FolderList {
 
             if let Some(next_playlist_content) = next_playlist {
                 *playlist_panel = Some(PlaylistPanel::new(next_playlist_content));
+                *album_panel = None;
             }
         });
     }
@@ -193,11 +224,13 @@ impl FolderList {
                                     playlist_rc,
                                 )),
                                 PlaylistType::AllSongs(_) => panic!("Cannot edit all songs"),
+                                PlaylistType::Album(_) => panic!("Cannot edit an album"),
                             };
 
                             self.current_modal = FolderListModals::EditPlaylist {
                                 name: playlist_name.clone(),
                                 edit_playlist_type,
+                                dsp_override: user_data.inner.dsp_override.inner.clone(),
                             };
                         }
 
@@ -207,6 +240,9 @@ impl FolderList {
                             }
                         }
 
+                        #[cfg(not(target_os = "android"))]
+                        self.export_menu_button(ui, playlist);
+
                         if Self::shared_popup_ui(
                             ui,
                             "playlist",
@@ -277,11 +313,80 @@ impl FolderList {
                         CreatePlaylistVariant::Dynamic,
                     )
                 }
+
+                #[cfg(not(target_os = "android"))]
+                if ui
+                    .button("Import playlist file...")
+                    .on_hover_text(
+                        "Creates a standard playlist from an M3U, M3U8, XSPF or PLS file",
+                    )
+                    .clicked()
+                {
+                    if let Some(file_path) = rfd::FileDialog::new()
+                        .add_filter("Playlist file", &PLAYLIST_FILE_EXTS)
+                        .pick_file()
+                    {
+                        self.current_modal =
+                            FolderListModals::import_playlist_file(parent_folder, &file_path);
+                    }
+                }
             });
 
             if ui.button("Folder").clicked() {
                 self.current_modal = FolderListModals::create_folder(Rc::clone(parent_folder))
             }
+
+            #[cfg(not(target_os = "android"))]
+            if ui
+                .button("Import iTunes library...")
+                .on_hover_text(
+                    "Imports the tracks, playlists and folders of an iTunes Library.xml into this folder",
+                )
+                .clicked()
+            {
+                if let Some(file_path) = rfd::FileDialog::new()
+                    .add_filter("iTunes library", &["xml"])
+                    .pick_file()
+                {
+                    self.current_modal =
+                        FolderListModals::import_itunes_library(parent_folder, &file_path);
+                }
+            }
+        });
+    }
+
+    #[cfg(not(target_os = "android"))]
+    fn export_menu_button(&mut self, ui: &mut Ui, playlist: &PlaylistType) {
+        ui.menu_button("Export", |ui| {
+            ui.checkbox(&mut self.export_copy_audio, "Copy audio")
+                .on_hover_text(
+                    "Copies the audio next to the playlist file, so both can be moved to another device",
+                );
+
+            let audio_paths = if self.export_copy_audio {
+                ExportedAudioPaths::CopiedRelative
+            } else {
+                ExportedAudioPaths::Library
+            };
+
+            for format in PlaylistFileFormat::all_values() {
+                if ui.button(format!("{}...", format)).clicked() {
+                    let playlist_name = playlist.get_user_data().inner.content_data.name.clone();
+
+                    if let Some(file_path) = rfd::FileDialog::new()
+                        .add_filter(format.to_string(), &[format.extension()])
+                        .set_file_name(format!("{}.{}", playlist_name, format.extension()))
+                        .save_file()
+                    {
+                        self.current_modal = FolderListModals::export_playlist_file(
+                            playlist,
+                            *format,
+                            audio_paths,
+                            &file_path,
+                        );
+                    }
+                }
+            }
         });
     }
 
@@ -291,7 +396,10 @@ impl FolderList {
     fn shared_popup_ui(ui: &mut Ui, variant_text: &str, path: impl AsRef<Path>, id: Id) -> bool {
         open_location_button(ui, variant_text, path);
 
-        let delete_clicked = ui.button(format!("Delete {}", variant_text)).clicked();
+        let delete_clicked = ui
+            .button(format!("Delete {}", variant_text))
+            .on_hover_text("Moves it to the trash, it can be restored from Edit > Settings > Trash")
+            .clicked();
 
         ui.label(format!("({id})"));
 
@@ -1,14 +1,28 @@
+use crate::napoleon_client::ui::helpers::dsp_editor::dsp_editor;
+#[cfg(not(target_os = "android"))]
+use crate::napoleon_client::ui::helpers::import_progress::import_progress_modal;
+use crate::napoleon_client::ui::helpers::scroll_area_styled;
 use derive_enum_all_values::AllValues;
-use eframe::egui::{Id, Modal, Ui};
+use eframe::egui::{Id, Modal, ScrollArea, Ui};
 use napoleon_amp_core::content::folder::Folder;
+use napoleon_amp_core::content::playlist::dsp::{DspSettings, dsp_settings};
 use napoleon_amp_core::content::playlist::dynamic_playlist_data::DynamicPlaylistDataStd;
-use napoleon_amp_core::content::playlist::filter::{
-    ComparisonMethod, FilterRule, FilterRules, ValuesType,
+use napoleon_amp_core::content::playlist::filter::{ComparisonMethod, FilterRules, ValuesType};
+#[cfg(not(target_os = "android"))]
+use napoleon_amp_core::content::playlist::import::ImportOutcome;
+#[cfg(not(target_os = "android"))]
+use napoleon_amp_core::content::playlist::playlist_file::{
+    ExportedAudioPaths, PlaylistFileFormat, PlaylistFileImport, PlaylistFileImportJob,
+    import_playlist_file,
 };
 use napoleon_amp_core::content::playlist::rules::ImportFrom;
 use napoleon_amp_core::content::playlist::{ClearSongsCache, Playlist, PlaylistType};
 use napoleon_amp_core::instance::NapoleonInstance;
+#[cfg(not(target_os = "android"))]
+use napoleon_amp_core::itunes_library::import_itunes_library;
 use std::ops::Deref;
+#[cfg(not(target_os = "android"))]
+use std::path::Path;
 use std::rc::Rc;
 
 pub(super) type EditPlaylistType =
@@ -33,6 +47,17 @@ pub(super) enum FolderListModals {
     EditPlaylist {
         name: String,
         edit_playlist_type: EditPlaylistType,
+        /// Used instead of the client's dsp settings while the playlist plays
+        dsp_override: Option<DspSettings>,
+    },
+    /// The files listed by a playlist file being imported into the playlist created for it
+    #[cfg(not(target_os = "android"))]
+    ImportingPlaylistFile(PlaylistFileImportJob),
+    /// The outcome of importing or exporting a playlist file
+    PlaylistFile {
+        status: String,
+        /// The files which could not be added to the playlist, along with why
+        problems: Vec<String>,
     },
     None,
 }
@@ -71,7 +96,23 @@ impl FolderListModals {
             Self::EditPlaylist {
                 name,
                 edit_playlist_type: playlist,
-            } => Self::render_edit_playlist(ui, name, playlist, napoleon_instance),
+                dsp_override,
+            } => Self::render_edit_playlist(ui, name, playlist, dsp_override, napoleon_instance),
+
+            #[cfg(not(target_os = "android"))]
+            Self::ImportingPlaylistFile(playlist_file_import_job) => {
+                if let Some(playlist_file_import) = playlist_file_import_job.poll() {
+                    *self = Self::playlist_file_imported(&playlist_file_import);
+                } else {
+                    import_progress_modal(ui, playlist_file_import_job.import_job());
+                }
+
+                false
+            }
+
+            Self::PlaylistFile { status, problems } => {
+                Self::render_playlist_file(ui, status, problems)
+            }
 
             Self::None => false,
         };
@@ -81,6 +122,144 @@ impl FolderListModals {
         }
     }
 
+    /// Starts importing a playlist file into `current_folder`, the files it lists are imported in the
+    /// background
+    #[cfg(not(target_os = "android"))]
+    pub(super) fn import_playlist_file(current_folder: &Rc<Folder>, file_path: &Path) -> Self {
+        match import_playlist_file(current_folder, file_path) {
+            Ok(playlist_file_import_job) => Self::ImportingPlaylistFile(playlist_file_import_job),
+
+            Err(e) => Self::PlaylistFile {
+                status: format!("Unable to import {}; {}", file_path.display(), e),
+                problems: Vec::new(),
+            },
+        }
+    }
+
+    #[cfg(not(target_os = "android"))]
+    fn playlist_file_imported(playlist_file_import: &PlaylistFileImport) -> Self {
+        let report = &playlist_file_import.report;
+
+        let problems = report
+            .not_imported()
+            .filter(|(_, outcome)| !matches!(outcome, ImportOutcome::Duplicate(_)))
+            .map(|(song_path, outcome)| format!("{}: {}", song_path.display(), outcome))
+            .chain(
+                playlist_file_import
+                    .skipped
+                    .iter()
+                    .map(|location| format!("{}: Not a local file", location)),
+            )
+            .collect();
+
+        Self::PlaylistFile {
+            status: format!(
+                "Created {}, imported {} songs and matched {} already in the library",
+                playlist_file_import
+                    .playlist
+                    .get_user_data()
+                    .inner
+                    .content_data
+                    .name,
+                report.imported_count(),
+                report.duplicate_songs().len()
+            ),
+            problems,
+        }
+    }
+
+    /// Imports an iTunes library into `current_folder`, blocking until every track is imported
+    #[cfg(not(target_os = "android"))]
+    pub(super) fn import_itunes_library(current_folder: &Rc<Folder>, file_path: &Path) -> Self {
+        match import_itunes_library(current_folder, file_path) {
+            Ok(itunes_import) => {
+                let report = &itunes_import.import_report;
+
+                let problems = report
+                    .not_imported()
+                    .filter(|(_, outcome)| !matches!(outcome, ImportOutcome::Duplicate(_)))
+                    .map(|(song_path, outcome)| format!("{}: {}", song_path.display(), outcome))
+                    .chain(
+                        itunes_import
+                            .skipped_tracks
+                            .iter()
+                            .map(|track| format!("{}: Not a local file", track)),
+                    )
+                    .chain(
+                        itunes_import
+                            .smart_playlists_as_standard
+                            .iter()
+                            .map(|playlist_name| {
+                                format!(
+                                    "{}: Smart playlist criteria can't be used, created as a standard playlist",
+                                    playlist_name
+                                )
+                            }),
+                    )
+                    .collect();
+
+                Self::PlaylistFile {
+                    status: format!(
+                        "Imported {} songs and matched {} already in the library. Created {} folders, {} playlists and {} dynamic playlists",
+                        report.imported_count(),
+                        report.duplicate_songs().len(),
+                        itunes_import.folders_created,
+                        itunes_import.playlists_created,
+                        itunes_import.dynamic_playlists_created
+                    ),
+                    problems,
+                }
+            }
+
+            Err(e) => Self::PlaylistFile {
+                status: format!("Unable to import {}; {}", file_path.display(), e),
+                problems: Vec::new(),
+            },
+        }
+    }
+
+    #[cfg(not(target_os = "android"))]
+    pub(super) fn export_playlist_file(
+        playlist: &PlaylistType,
+        format: PlaylistFileFormat,
+        audio_paths: ExportedAudioPaths,
+        file_path: &Path,
+    ) -> Self {
+        let playlist_name = playlist.get_user_data().inner.content_data.name.clone();
+
+        let status = match format.export(playlist, file_path, audio_paths) {
+            Ok(()) => format!("Exported {} to {}", playlist_name, file_path.display()),
+            Err(e) => format!("Unable to export {}; {}", playlist_name, e),
+        };
+
+        Self::PlaylistFile {
+            status,
+            problems: Vec::new(),
+        }
+    }
+
+    fn render_playlist_file(ui: &mut Ui, status: &str, problems: &[String]) -> bool {
+        let modal = Modal::new(Id::new("Playlist File Modal")).show(ui.ctx(), |ui| {
+            ui.set_width(250.);
+
+            ui.label(status);
+
+            if !problems.is_empty() {
+                ui.label("The following songs could not be added:");
+
+                scroll_area_styled(ui, ScrollArea::vertical().max_height(250.0), |ui| {
+                    for problem in problems {
+                        ui.label(problem);
+                    }
+                });
+            }
+
+            ui.button("Ok").clicked()
+        });
+
+        modal.inner || modal.should_close()
+    }
+
     fn render_create_folder_content(
         ui: &mut Ui,
         variant: &CreateFolderContentDialogVariant,
@@ -153,6 +332,7 @@ impl FolderListModals {
         ui: &mut Ui,
         name: &mut String,
         edit_playlist: &mut EditPlaylistType,
+        dsp_override: &mut Option<DspSettings>,
         napoleon_instance: &mut NapoleonInstance,
     ) -> bool {
         let mut should_close = false;
@@ -259,6 +439,7 @@ impl FolderListModals {
                         let string_val = match value_type {
                             ValuesType::Str(s) => s.to_string(),
                             ValuesType::U8(int) => int.to_string(),
+                            ValuesType::U32(int) => int.to_string(),
                         };
 
                         (string_val, *cmp_method_ref)
@@ -316,46 +497,38 @@ impl FolderListModals {
                 }
 
                 ui.menu_button("Add filter", |ui| {
-                    if ui.button("Title").clicked() {
-                        dyn_user_data
-                            .rules
-                            .filters
-                            .push(FilterRules::Title(FilterRule::new(
-                                "<Title>".to_string(),
+                    for filter_rules_ty in FilterRules::values() {
+                        let filter_of_str = filter_rules_ty.get_display_str();
+
+                        if ui.button(filter_of_str).clicked() {
+                            // Number filters can't parse the placeholder, and start at 0 instead
+                            dyn_user_data.rules.filters.push(FilterRules::from_variant(
+                                filter_rules_ty,
+                                &format!("<{}>", filter_of_str),
                                 ComparisonMethod::Contains,
-                            )))
+                            ));
+                        }
                     }
+                });
+            }
 
-                    if ui.button("Artist").clicked() {
-                        dyn_user_data
-                            .rules
-                            .filters
-                            .push(FilterRules::Artist(FilterRule::new(
-                                "<Artist>".to_string(),
-                                ComparisonMethod::Contains,
-                            )))
-                    }
+            ui.separator();
 
-                    if ui.button("Album").clicked() {
-                        dyn_user_data
-                            .rules
-                            .filters
-                            .push(FilterRules::Album(FilterRule::new(
-                                "<Album>".to_string(),
-                                ComparisonMethod::Contains,
-                            )))
-                    }
+            let mut override_dsp = dsp_override.is_some();
+
+            if ui
+                .checkbox(&mut override_dsp, "Override equalizer")
+                .on_hover_text(
+                    "Plays this playlist with its own equalizer instead of the one in the settings",
+                )
+                .changed()
+            {
+                // Starts from the client's settings, so only the differences need changing
+                *dsp_override = override_dsp.then(dsp_settings);
+            }
 
-                    if ui.button("Rating").clicked() {
-                        dyn_user_data
-                            .rules
-                            .filters
-                            .push(FilterRules::Rating(FilterRule::new(
-                                0,
-                                ComparisonMethod::Contains,
-                            )))
-                    }
-                });
+            if let Some(dsp_override) = dsp_override {
+                dsp_editor(ui, Id::new("playlist_dsp_editor"), dsp_override);
             }
 
             ui.separator();
@@ -400,9 +573,16 @@ impl FolderListModals {
                     (**playlist).deref()
                 }
                 EditPlaylistType::AllSongs(playlist) => playlist,
+                EditPlaylistType::Album(playlist) => playlist,
             };
 
-            playlist.rename(name.clone()).expect("Editing playlist");
+            playlist
+                .set_dsp_override(dsp_override.clone())
+                .expect("Failed to save playlist user data");
+
+            napoleon_instance
+                .rename_playlist(playlist, name.clone())
+                .expect("Editing playlist");
         }
 
         if modal.should_close() {
@@ -3,6 +3,7 @@ use napoleon_amp_core::content::song::song_data::SongDataStd;
 use napoleon_amp_core::paths::show_file_in_explorer;
 use std::path::Path;
 
+pub(crate) mod album_panel;
 pub(crate) mod folder_list;
 pub(crate) mod playlist_panel;
 pub(crate) mod queue_panel;
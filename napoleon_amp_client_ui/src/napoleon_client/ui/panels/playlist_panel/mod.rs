@@ -13,7 +13,6 @@ use derive_enum_all_values::AllValues;
 use eframe::egui::load::Bytes;
 use eframe::egui::*;
 use egui_extras::{Column, TableBuilder};
-use napoleon_amp_core::content::SaveData;
 use napoleon_amp_core::content::playlist::PlaylistType;
 use napoleon_amp_core::content::playlist::manager::{MusicManager, SongStatus};
 use napoleon_amp_core::content::playlist::song_list::SortByVariant;
@@ -61,13 +60,27 @@ impl PlaylistPanel {
 
                 ui.heading(&user_data.content_data.name);
 
+                // Songs added to an album would only be in the library, not the album
+                #[cfg(not(target_os = "android"))]
+                let can_add_songs = !matches!(*self.current_playlist, PlaylistType::Album(_));
+
                 ui.horizontal(|ui| {
                     #[cfg(not(target_os = "android"))]
-                    if ui.button("Add Songs").clicked() {
+                    if can_add_songs && ui.button("Add Songs").clicked() {
                         if let Some(paths) = rfd::FileDialog::new().pick_files() {
                             self.playlist_modal = PlaylistModals::SongsImported {
                                 paths,
-                                song_already_exists_indexes: None,
+                                import_state: modals::ImportState::NotStarted,
+                            };
+                        }
+                    }
+
+                    #[cfg(not(target_os = "android"))]
+                    if can_add_songs && ui.button("Add Folders").clicked() {
+                        if let Some(paths) = rfd::FileDialog::new().pick_folders() {
+                            self.playlist_modal = PlaylistModals::SongsImported {
+                                paths,
+                                import_state: modals::ImportState::NotStarted,
                             };
                         }
                     }
@@ -97,8 +110,10 @@ impl PlaylistPanel {
                 });
 
                 if should_save_song_data {
-                    user_data_v
-                        .save_data(self.current_playlist.id())
+                    drop(user_data_v);
+
+                    self.current_playlist
+                        .save_user_data()
                         .expect("Save playlist user data");
                 }
             });
@@ -121,8 +136,12 @@ impl PlaylistPanel {
             self.current_playlist.set_search_query_filter(search_text);
         }
 
-        self.playlist_modal
-            .render(ui, &self.current_playlist, &mut self.delete_original_files);
+        self.playlist_modal.render(
+            ui,
+            &self.current_playlist,
+            &mut self.delete_original_files,
+            napoleon_instance,
+        );
 
         let current_playing_id = ui.make_persistent_id("currently_playing_display");
 
@@ -322,9 +341,31 @@ impl PlaylistPanel {
                                         }
 
                                         ui.menu_button("Delete", |ui| {
-                                            if ui.button("From this playlist").clicked() {
+                                            let can_delete_from_playlist = !matches!(
+                                                **current_playlist,
+                                                PlaylistType::AllSongs(_) | PlaylistType::Album(_)
+                                            );
+
+                                            if can_delete_from_playlist
+                                                && ui.button("From this playlist").clicked()
+                                            {
                                                 song_index_to_delete = Some(song_index);
                                             }
+
+                                            if ui.button("From library").clicked() {
+                                                let songs_to_delete = if is_selected {
+                                                    selected_songs
+                                                        .get_selected_songs(&songs)
+                                                        .to_vec()
+                                                } else {
+                                                    vec![Arc::clone(song)]
+                                                };
+
+                                                self.playlist_modal =
+                                                    PlaylistModals::DeleteFromLibrary {
+                                                        songs: songs_to_delete,
+                                                    };
+                                            }
                                         });
 
                                         if ui.button("Edit song data").clicked() {
@@ -338,6 +379,11 @@ impl PlaylistPanel {
                                             };
                                         }
 
+                                        if ui.button("Write tags to audio file").clicked() {
+                                            self.playlist_modal =
+                                                PlaylistModals::write_tags(Arc::clone(song));
+                                        }
+
                                         if ui.button("Debug print song data").clicked() {
                                             println!("{:?}", song_data_vers);
                                         }
@@ -348,7 +394,7 @@ impl PlaylistPanel {
 
                                         ui.menu_button("Open song location", |ui| {
                                             if ui.button("Audio file").clicked() {
-                                                show_file_in_explorer(&song.song_audio_path)
+                                                show_file_in_explorer(song.song_audio_path())
                                                     .expect("Error showing file in explorer")
                                             }
 
@@ -379,13 +425,6 @@ impl PlaylistPanel {
                                 row.col(|ui| {
                                     updated_rating_opt =
                                         render_rating(ui, song_data_vers.inner.rating).inner;
-
-                                    if let Some(updated_rating) = updated_rating_opt {
-                                        let mut song_data_cloned = song_data_vers.clone();
-                                        song_data_cloned.inner.rating = updated_rating;
-
-                                        song.save_song_data_already_borrowed(&song_data_cloned);
-                                    }
                                 });
 
                                 row.col(|ui| {
@@ -404,14 +443,21 @@ impl PlaylistPanel {
                                 });
 
                                 if let Some(updated_rating) = updated_rating_opt {
+                                    let mut song_data_cloned = song_data_vers.clone();
+                                    song_data_cloned.inner.rating = updated_rating;
+
                                     drop(song_data_vers);
-                                    song.get_song_data_mut().inner.rating = updated_rating;
+                                    napoleon_instance.set_song_data(song, song_data_cloned);
                                 }
                             });
                         }
 
                         if let Some(song_index) = song_index_to_delete {
-                            current_playlist.delete_song(song_index);
+                            if let Err(e) = napoleon_instance
+                                .delete_song_from_playlist(current_playlist, song_index)
+                            {
+                                eprintln!("Unable to delete song; {}", e);
+                            }
                         }
                     });
             });
@@ -1,16 +1,22 @@
 use crate::napoleon_client::ui::helpers::{duration_input, scroll_area_styled};
 
 use crate::napoleon_client::ui::helpers::custom_modal::custom_modal;
+use crate::napoleon_client::ui::helpers::import_progress::import_progress_modal;
 use crate::napoleon_client::ui::panels::CloseResult;
-use eframe::egui::{Id, Modal, ScrollArea, Slider, Ui};
+use eframe::egui::{DragValue, Id, Modal, ScrollArea, Slider, Ui};
 use egui_autocomplete::AutoCompleteTextEdit;
 use napoleon_amp_core::content::SaveData;
 use napoleon_amp_core::content::playlist::PlaylistType;
+use napoleon_amp_core::content::playlist::import::ImportReport;
+use napoleon_amp_core::content::playlist::import_job::ImportJob;
 use napoleon_amp_core::content::song::Song;
 use napoleon_amp_core::content::song::song_cover_pool::SongCoverData;
-use napoleon_amp_core::content::song::song_data::meta::SongDataMetaV2;
+use napoleon_amp_core::content::song::song_data::meta::SongDataMetaV3;
 use napoleon_amp_core::content::song::song_data::{SongData, SongDataStd};
+use napoleon_amp_core::instance::NapoleonInstance;
 use napoleon_amp_core::paths::show_file_in_explorer;
+use napoleon_amp_core::tag_write;
+use napoleon_amp_core::tag_write::TagChange;
 use std::mem;
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -19,7 +25,7 @@ use std::time::Duration;
 pub(super) enum PlaylistModals {
     SongsImported {
         paths: Vec<PathBuf>,
-        song_already_exists_indexes: Option<Vec<usize>>,
+        import_state: ImportState,
     },
     EditSong {
         song: Arc<Song>,
@@ -27,15 +33,37 @@ pub(super) enum PlaylistModals {
         artist_list: Vec<String>,
         album_list: Vec<String>,
     },
+    DeleteFromLibrary {
+        songs: Vec<Arc<Song>>,
+    },
+    WriteTags {
+        song: Arc<Song>,
+        tag_changes: Result<Vec<TagChange>, String>,
+    },
     None,
 }
 
+pub(super) enum ImportState {
+    NotStarted,
+    Running(ImportJob),
+    Finished(ImportReport),
+}
+
 impl PlaylistModals {
+    /// Lists the tags of the audio file of the song which would be changed by writing the song data to them
+
+    pub(super) fn write_tags(song: Arc<Song>) -> Self {
+        let tag_changes = tag_write::tag_changes(&song).map_err(|e| e.to_string());
+
+        Self::WriteTags { song, tag_changes }
+    }
+
     pub(super) fn render(
         &mut self,
         ui: &mut Ui,
         current_playlist: &PlaylistType,
         delete_original_files: &mut bool,
+        napoleon_instance: &mut NapoleonInstance,
     ) {
         let mut clear_modals = false;
         let mut save_song_data = false;
@@ -43,20 +71,35 @@ impl PlaylistModals {
         match self {
             PlaylistModals::SongsImported {
                 paths,
-                song_already_exists_indexes,
+                import_state,
             } => {
-                clear_modals =
-                    if let Some(song_already_exists_indexes) = song_already_exists_indexes {
-                        Self::draw_modal_failed_import(ui, song_already_exists_indexes, paths)
-                    } else {
-                        Self::draw_main_import_modal(
-                            ui,
-                            paths,
-                            song_already_exists_indexes,
-                            current_playlist,
-                            delete_original_files,
-                        )
+                clear_modals = match import_state {
+                    ImportState::NotStarted => {
+                        Self::draw_main_import_modal(ui, paths, import_state, delete_original_files)
+                    }
+
+                    ImportState::Running(import_job) => {
+                        if let Some(import_report) = import_job.poll() {
+                            current_playlist.import_existing_songs(&import_report.imported_songs());
+
+                            if import_report.is_all_imported() {
+                                true
+                            } else {
+                                *import_state = ImportState::Finished(import_report);
+
+                                false
+                            }
+                        } else {
+                            import_progress_modal(ui, import_job);
+
+                            false
+                        }
                     }
+
+                    ImportState::Finished(import_report) => {
+                        Self::draw_import_report_modal(ui, import_report, current_playlist)
+                    }
+                }
             }
 
             PlaylistModals::EditSong {
@@ -76,6 +119,14 @@ impl PlaylistModals {
                 save_song_data = close_result.should_save();
             }
 
+            PlaylistModals::DeleteFromLibrary { songs } => {
+                clear_modals = Self::draw_delete_from_library_modal(ui, songs, napoleon_instance);
+            }
+
+            PlaylistModals::WriteTags { song, tag_changes } => {
+                clear_modals = Self::draw_write_tags_modal(ui, song, tag_changes);
+            }
+
             PlaylistModals::None => {}
         };
 
@@ -88,7 +139,21 @@ impl PlaylistModals {
                     editing_song_data,
                     ..
                 } => {
-                    song.set_song_data_and_save(editing_song_data);
+                    napoleon_instance.set_song_data(&song, editing_song_data);
+
+                    if napoleon_instance
+                        .get_client_settings()
+                        .inner
+                        .offer_tag_write_after_edit
+                        .inner
+                    {
+                        let tag_changes = tag_write::tag_changes(&song).map_err(|e| e.to_string());
+
+                        // Nothing to offer if the tags already match
+                        if !tag_changes.as_ref().is_ok_and(Vec::is_empty) {
+                            *self = PlaylistModals::WriteTags { song, tag_changes };
+                        }
+                    }
                 }
 
                 _ => {
@@ -100,32 +165,154 @@ impl PlaylistModals {
         }
     }
 
-    fn draw_modal_failed_import(
+    fn draw_delete_from_library_modal(
         ui: &mut Ui,
-        song_already_exists_indexes: &[usize],
-        song_imported_paths: &[PathBuf],
+        songs: &[Arc<Song>],
+        napoleon_instance: &mut NapoleonInstance,
     ) -> bool {
-        let modal = Modal::new(Id::new("Failed Import Songs Modal")).show(ui.ctx(), |ui| {
-            let failed_count = song_already_exists_indexes.len();
+        let modal = Modal::new(Id::new("Delete From Library Modal")).show(ui.ctx(), |ui| {
+            ui.set_width(250.);
 
             ui.heading(format!(
-                "The following {} {} already exist, as such the files were not overwritten, nor deleted",
-                failed_count,
-                Self::songs_plural(failed_count)
+                "Delete {} {} from the library?",
+                songs.len(),
+                Self::songs_plural(songs.len())
             ));
 
+            ui.label(
+                "They will be removed from every playlist, and moved to the trash along with their audio files",
+            );
+
             scroll_area_styled(ui, ScrollArea::vertical().max_height(250.0), |ui| {
-                for failed_song_index in song_already_exists_indexes {
-                    let failed_song_path = &song_imported_paths[*failed_song_index];
-                    ui.label(failed_song_path.to_str().expect("Valid utf8 path"));
+                for song in songs {
+                    ui.label(&song.get_song_data().inner.title);
                 }
             });
 
-            if ui.button("Ok").clicked() {
-                true
-            } else {
-                false
+            ui.horizontal(|ui| {
+                if ui.button("Delete").clicked() {
+                    napoleon_instance
+                        .delete_songs_from_library(songs)
+                        .expect("Failed delete songs from library");
+
+                    return true;
+                }
+
+                ui.button("Cancel").clicked()
+            })
+            .inner
+        });
+
+        modal.inner || modal.should_close()
+    }
+
+    fn draw_write_tags_modal(
+        ui: &mut Ui,
+        song: &Song,
+        tag_changes: &mut Result<Vec<TagChange>, String>,
+    ) -> bool {
+        let modal = Modal::new(Id::new("Write Tags Modal")).show(ui.ctx(), |ui| {
+            ui.set_width(300.);
+
+            ui.heading(format!(
+                "Write tags to the audio file of {}?",
+                song.get_song_data().inner.title
+            ));
+
+            let changes = match tag_changes {
+                Ok(changes) => changes,
+
+                Err(e) => {
+                    ui.label(format!("Unable to write tags: {}", e));
+
+                    return ui.button("Ok").clicked();
+                }
+            };
+
+            if changes.is_empty() {
+                ui.label("The tags of the audio file already match the song data");
+
+                return ui.button("Ok").clicked();
+            }
+
+            ui.label("The following tags will be changed:");
+
+            scroll_area_styled(ui, ScrollArea::vertical().max_height(250.0), |ui| {
+                for change in changes.iter() {
+                    ui.label(change.to_string());
+                }
+            });
+
+            let (write_clicked, cancel_clicked) = ui
+                .horizontal(|ui| (ui.button("Write").clicked(), ui.button("Cancel").clicked()))
+                .inner;
+
+            if write_clicked {
+                // Only the changes listed are written, even if the song data changed since
+                return match tag_write::write_tags(song, changes) {
+                    Ok(()) => true,
+
+                    Err(e) => {
+                        *tag_changes = Err(e.to_string());
+
+                        false
+                    }
+                };
             }
+
+            cancel_clicked
+        });
+
+        modal.inner || modal.should_close()
+    }
+
+    fn draw_import_report_modal(
+        ui: &mut Ui,
+        import_report: &ImportReport,
+        current_playlist: &PlaylistType,
+    ) -> bool {
+        let modal = Modal::new(Id::new("Import Report Modal")).show(ui.ctx(), |ui| {
+            let total_count = import_report.entries.len();
+            let imported_count = import_report.imported_count();
+
+            ui.heading(format!(
+                "Imported {} of {} {}",
+                imported_count,
+                total_count,
+                Self::songs_plural(total_count)
+            ));
+
+            ui.label("The following files were not imported, as such they were not deleted");
+
+            scroll_area_styled(ui, ScrollArea::vertical().max_height(250.0), |ui| {
+                for (song_path, outcome) in import_report.not_imported() {
+                    ui.label(format!(
+                        "{}: {}",
+                        song_path.to_str().expect("Valid utf8 path"),
+                        outcome
+                    ));
+                }
+            });
+
+            let duplicate_songs = import_report.duplicate_songs();
+
+            ui.horizontal(|ui| {
+                if !duplicate_songs.is_empty()
+                    && ui
+                        .button("Link existing")
+                        .on_hover_text(
+                            "Adds the songs already in the library to this playlist in place of the duplicates",
+                        )
+                        .clicked()
+                {
+                    current_playlist.import_existing_songs(&duplicate_songs);
+
+                    return true;
+                }
+
+                ui.button("Ok").clicked()
+            })
+            .inner
         });
 
         modal.inner || modal.should_close()
@@ -134,34 +321,46 @@ impl PlaylistModals {
     fn draw_main_import_modal(
         ui: &mut Ui,
         songs_imported_paths: &Vec<PathBuf>,
-        song_already_exists_indexes_vec: &mut Option<Vec<usize>>,
-        current_playlist: &PlaylistType,
+        import_state: &mut ImportState,
         delete_original_files: &mut bool,
     ) -> bool {
         let modal = Modal::new(Id::new("Import Songs Modal")).show(ui.ctx(), |ui| {
             ui.set_width(250.);
 
-            let count = songs_imported_paths.len();
-
-            ui.heading(format!(
-                "Importing {} new {}",
-                count,
-                Self::songs_plural(count)
-            ));
+            let folder_count = songs_imported_paths
+                .iter()
+                .filter(|path| path.is_dir())
+                .count();
+            let file_count = songs_imported_paths.len() - folder_count;
+
+            if folder_count == 0 {
+                ui.heading(format!(
+                    "Importing {} new {}",
+                    file_count,
+                    Self::songs_plural(file_count)
+                ));
+            } else {
+                ui.heading(format!(
+                    "Importing every song in {} {}",
+                    folder_count,
+                    if folder_count == 1 {
+                        "folder"
+                    } else {
+                        "folders"
+                    }
+                ));
+            }
 
             ui.checkbox(delete_original_files, "Delete original files");
 
             ui.horizontal(|ui| {
                 if ui.button("Import").clicked() {
-                    return if let Err(song_already_exists_indexes) =
-                        current_playlist.import_songs(songs_imported_paths, *delete_original_files)
-                    {
-                        song_already_exists_indexes_vec.replace(song_already_exists_indexes);
+                    *import_state = ImportState::Running(ImportJob::start(
+                        songs_imported_paths.clone(),
+                        *delete_original_files,
+                    ));
 
-                        false
-                    } else {
-                        true
-                    };
+                    return false;
                 }
 
                 ui.button("Cancel").clicked()
@@ -199,6 +398,36 @@ impl PlaylistModals {
                 album_list,
             ));
 
+            ui.label("Album Artist:");
+            ui.text_edit_singleline(editing_song_data.meta.inner.album_artist.unwrapped_mut());
+
+            ui.label("Composer:");
+            ui.text_edit_singleline(editing_song_data.meta.inner.composer.unwrapped_mut());
+
+            ui.label("Genre:");
+            ui.text_edit_singleline(editing_song_data.meta.inner.genre.unwrapped_mut());
+
+            ui.label("Date:")
+                .on_hover_text("The release date, such as 2001 or 2001-03-12");
+            ui.text_edit_singleline(editing_song_data.meta.inner.date.unwrapped_mut());
+
+            Self::optional_number_ui(
+                ui,
+                "Track number",
+                editing_song_data.meta.inner.track_number.unwrapped_mut(),
+            );
+
+            Self::optional_number_ui(
+                ui,
+                "Disc number",
+                editing_song_data.meta.inner.disc_number.unwrapped_mut(),
+            );
+
+            Self::optional_number_ui(ui, "BPM", editing_song_data.meta.inner.bpm.unwrapped_mut());
+
+            ui.label("Comment:");
+            ui.text_edit_multiline(editing_song_data.meta.inner.comment.unwrapped_mut());
+
             ui.label("User Tag:");
             ui.text_edit_singleline(&mut editing_song_data.user_tag);
 
@@ -255,7 +484,7 @@ impl PlaylistModals {
             ui.separator();
 
             if ui.button("Clear metadata cache").clicked() {
-                editing_song_data.meta.inner = SongDataMetaV2::default().into();
+                editing_song_data.meta.inner = SongDataMetaV3::default().into();
             }
 
             ui.label("Cover Id:");
@@ -299,6 +528,20 @@ impl PlaylistModals {
         });
     }
 
+    fn optional_number_ui(ui: &mut Ui, label: &str, number: &mut Option<u32>) {
+        ui.horizontal(|ui| {
+            let mut is_checked = number.is_some();
+
+            if ui.checkbox(&mut is_checked, label).changed() {
+                *number = if is_checked { Some(1) } else { None };
+            }
+
+            if let Some(number) = number {
+                ui.add(DragValue::new(number).range(1..=u32::MAX));
+            }
+        });
+    }
+
     fn songs_plural(count: usize) -> &'static str {
         if count == 1 { "song" } else { "songs" }
     }
@@ -1,7 +1,7 @@
 mod modal;
 
 use crate::napoleon_client::ui::panels::top_menu_bar::modal::{MenuModal, MenuPage};
-use eframe::egui::Ui;
+use eframe::egui::{Button, Context, Key, Modifiers, Ui};
 use napoleon_amp_core::instance::NapoleonInstance;
 
 pub(crate) struct TopMenuBar {
@@ -14,7 +14,9 @@ impl TopMenuBar {
     }
 
     pub(crate) fn render(&mut self, ui: &mut Ui, napoleon_instance: &mut NapoleonInstance) {
-        self.render_menu_bar(ui);
+        Self::undo_keystrokes_pressed(ui.ctx(), napoleon_instance);
+
+        self.render_menu_bar(ui, napoleon_instance);
 
         let mut should_close = false;
 
@@ -27,15 +29,85 @@ impl TopMenuBar {
         }
     }
 
-    fn render_menu_bar(&mut self, ui: &mut Ui) {
+    fn render_menu_bar(&mut self, ui: &mut Ui, napoleon_instance: &mut NapoleonInstance) {
         ui.menu_button("File", |ui| {
-            ui.label("Hi, this is here so it doesn't look weird :)");
+            if ui.button("Backup and Restore").clicked() {
+                self.menu_modal = Some(MenuModal::new(MenuPage::Backup {
+                    include_audio: false,
+                    status: None,
+                }));
+            }
         });
 
         ui.menu_button("Edit", |ui| {
+            let undo_text = match napoleon_instance.next_undo() {
+                Some(entry) => format!("Undo {}", entry),
+                None => "Undo".to_string(),
+            };
+
+            let undo_button = ui.add_enabled(
+                napoleon_instance.next_undo().is_some(),
+                Button::new(undo_text),
+            );
+
+            if undo_button.clicked() {
+                Self::undo(napoleon_instance);
+            }
+
+            let redo_text = match napoleon_instance.next_redo() {
+                Some(entry) => format!("Redo {}", entry),
+                None => "Redo".to_string(),
+            };
+
+            let redo_button = ui.add_enabled(
+                napoleon_instance.next_redo().is_some(),
+                Button::new(redo_text),
+            );
+
+            if redo_button.clicked() {
+                Self::redo(napoleon_instance);
+            }
+
+            ui.separator();
+
             if ui.button("Settings").clicked() {
                 self.menu_modal = Some(MenuModal::new(MenuPage::Settings));
             }
         });
     }
+
+    /// Ctrl+Z undoes and Ctrl+Shift+Z redoes, unless a text field is focused since it has its own undo
+
+    fn undo_keystrokes_pressed(ctx: &Context, napoleon_instance: &mut NapoleonInstance) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
+        // Checked first, since Ctrl+Z also matches when shift is held
+        let redo_keystroke_pressed =
+            ctx.input_mut(|i| i.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z));
+
+        let undo_keystroke_pressed =
+            !redo_keystroke_pressed && ctx.input_mut(|i| i.consume_key(Modifiers::COMMAND, Key::Z));
+
+        if redo_keystroke_pressed {
+            Self::redo(napoleon_instance);
+        }
+
+        if undo_keystroke_pressed {
+            Self::undo(napoleon_instance);
+        }
+    }
+
+    fn undo(napoleon_instance: &mut NapoleonInstance) {
+        if let Err(e) = napoleon_instance.undo() {
+            eprintln!("Unable to undo; {}", e);
+        }
+    }
+
+    fn redo(napoleon_instance: &mut NapoleonInstance) {
+        if let Err(e) = napoleon_instance.redo() {
+            eprintln!("Unable to redo; {}", e);
+        }
+    }
 }
@@ -1,10 +1,35 @@
+use crate::napoleon_client::ui::helpers::dsp_editor::dsp_editor;
+use crate::napoleon_client::ui::helpers::select_button::select_button_mut;
 use crate::napoleon_client::ui::panels::CloseResult;
-use eframe::egui::{Id, Modal, Slider, Ui};
-use napoleon_amp_core::content::SaveData;
+use eframe::egui::{Id, Modal, ProgressBar, ScrollArea, Slider, TextEdit, Ui, ViewportCommand};
+use napoleon_amp_core::archive;
+use napoleon_amp_core::content::playlist::dsp::set_dsp_settings;
+use napoleon_amp_core::content::trash;
+use napoleon_amp_core::content::trash::TrashEntry;
+use napoleon_amp_core::fsck;
+use napoleon_amp_core::fsck::FsckReport;
 use napoleon_amp_core::instance::NapoleonInstance;
+use napoleon_amp_core::paths::library_root::{
+    library_root_dir, saved_library_root, set_saved_library_root,
+};
+use std::path::PathBuf;
 
 pub(super) enum MenuPage {
     Settings,
+    LibraryCheck {
+        report: Option<FsckReport>,
+        status: Option<String>,
+    },
+    Trash {
+        entries: Option<Vec<TrashEntry>>,
+        status: Option<String>,
+    },
+    Backup {
+        include_audio: bool,
+        status: Option<String>,
+    },
+    Loudness,
+    Equalizer,
 }
 
 impl MenuPage {
@@ -19,8 +44,407 @@ impl MenuPage {
                         .inactive_render_timeout_ms,
                     100..=8_000,
                 ));
+
+                ui.label("Days to keep deleted content in the trash:")
+                    .on_hover_text("0 keeps it until the trash is emptied");
+                ui.add(Slider::new(
+                    &mut napoleon_instance
+                        .get_client_settings()
+                        .inner
+                        .trash_retention_days
+                        .inner
+                        .0,
+                    0..=365,
+                ));
+
+                ui.checkbox(
+                    &mut napoleon_instance
+                        .get_client_settings()
+                        .inner
+                        .offer_tag_write_after_edit
+                        .inner,
+                    "Offer to write tags to the audio file after editing a song",
+                )
+                .on_hover_text("Lists the tags which would change before anything is written");
+
+                ui.label("Seconds to crossfade between songs:").on_hover_text(
+                    "0 plays songs one after the other without a gap. Consecutive tracks of an album never crossfade",
+                );
+                ui.add(Slider::new(
+                    &mut napoleon_instance
+                        .get_client_settings()
+                        .inner
+                        .crossfade_seconds
+                        .inner,
+                    0..=12,
+                ));
+
+                ui.separator();
+
+                Self::render_artist_separators(ui, napoleon_instance);
+
+                ui.separator();
+
+                Self::render_library_root(ui);
+            }
+
+            Self::LibraryCheck { report, status } => {
+                Self::render_library_check(ui, report, status);
+            }
+
+            Self::Trash { entries, status } => {
+                Self::render_trash(ui, napoleon_instance, entries, status);
+            }
+
+            Self::Backup {
+                include_audio,
+                status,
+            } => {
+                Self::render_backup(ui, napoleon_instance, include_audio, status);
+            }
+
+            Self::Loudness => {
+                Self::render_loudness(ui, napoleon_instance);
+            }
+
+            Self::Equalizer => {
+                Self::render_equalizer(ui, napoleon_instance);
+            }
+        }
+    }
+
+    fn render_equalizer(ui: &mut Ui, napoleon_instance: &mut NapoleonInstance) {
+        ui.label("Applied to the song playing as it is changed, playlists can override it from their edit menu");
+
+        let dsp_settings = &mut napoleon_instance.get_client_settings().inner.dsp.inner;
+
+        if dsp_editor(ui, Id::new("client_dsp_editor"), dsp_settings) {
+            set_dsp_settings(dsp_settings.clone());
+        }
+    }
+
+    fn render_loudness(ui: &mut Ui, napoleon_instance: &mut NapoleonInstance) {
+        ui.label("Plays every song (or album) at a similar loudness, from the next song played once saved");
+
+        select_button_mut(
+            ui,
+            "Normalization",
+            &mut napoleon_instance
+                .get_client_settings()
+                .inner
+                .normalization_mode
+                .inner,
+        );
+
+        ui.separator();
+
+        ui.label("Songs are analyzed in the background, or read from their ReplayGain tags");
+
+        match napoleon_instance.loudness_analysis() {
+            Some(loudness_analysis) if !loudness_analysis.is_finished() => {
+                let progress = loudness_analysis.progress();
+
+                if let Some(songs_found) = progress.songs_found {
+                    ui.label(format!(
+                        "Analyzing {} of {} songs",
+                        progress.completed(),
+                        songs_found
+                    ));
+                } else {
+                    ui.label("Searching for songs");
+                }
+
+                ui.add(ProgressBar::new(progress.fraction()).show_percentage());
+
+                if loudness_analysis.is_cancelled() {
+                    ui.label("Cancelling...");
+                } else if ui.button("Cancel").clicked() {
+                    loudness_analysis.cancel();
+                }
+            }
+
+            loudness_analysis => {
+                if let Some(loudness_analysis) = loudness_analysis {
+                    let progress = loudness_analysis.progress();
+
+                    ui.label(format!(
+                        "Last analysis: {} analyzed, {} failed",
+                        progress.analyzed, progress.failed
+                    ));
+                }
+
+                if ui.button("Analyze new songs").clicked() {
+                    napoleon_instance.start_loudness_analysis(false);
+                }
+
+                if ui
+                    .button("Analyze every song again")
+                    .on_hover_text("Also replaces loudness read from ReplayGain tags")
+                    .clicked()
+                {
+                    napoleon_instance.start_loudness_analysis(true);
+                }
+            }
+        }
+    }
+
+    fn render_artist_separators(ui: &mut Ui, napoleon_instance: &mut NapoleonInstance) {
+        ui.label("Artist separators, one per line:")
+            .on_hover_text("Used to find each artist credited on a song, applied once saved");
+
+        let artist_separators = &mut napoleon_instance
+            .get_client_settings()
+            .inner
+            .artist_separators
+            .inner;
+
+        ui.horizontal(|ui| {
+            for (label, separators) in [
+                ("Between artists", &mut artist_separators.primary),
+                ("Before featured artists", &mut artist_separators.featured),
+                ("Before remixers", &mut artist_separators.remixer),
+            ] {
+                ui.vertical(|ui| {
+                    ui.label(label);
+
+                    let mut separators_str = separators.join("\n");
+
+                    if ui
+                        .add(TextEdit::multiline(&mut separators_str).desired_width(120.))
+                        .changed()
+                    {
+                        *separators = separators_str.split('\n').map(String::from).collect();
+                    }
+                });
+            }
+        });
+    }
+
+    fn render_backup(
+        ui: &mut Ui,
+        napoleon_instance: &mut NapoleonInstance,
+        include_audio: &mut bool,
+        status: &mut Option<String>,
+    ) {
+        ui.label(
+            "Backs up the folders, playlists, song data, covers and settings to a single file",
+        );
+
+        ui.checkbox(include_audio, "Include song audio")
+            .on_hover_text("Makes the backup as large as the library");
+
+        #[cfg(not(target_os = "android"))]
+        if ui.button("Back up library...").clicked() {
+            if let Some(archive_path) = rfd::FileDialog::new()
+                .add_filter("Napoleon Amp backup", &[archive::ARCHIVE_EXT])
+                .set_file_name(format!("napoleon_amp_backup.{}", archive::ARCHIVE_EXT))
+                .save_file()
+            {
+                *status = match archive::write_archive(&archive_path, *include_audio) {
+                    Ok(manifest) => Some(format!(
+                        "Backed up to {} ({})",
+                        archive_path.display(),
+                        manifest
+                    )),
+                    Err(e) => Some(format!("Unable to back up the library; {}", e)),
+                };
             }
         }
+
+        #[cfg(not(target_os = "android"))]
+        if ui
+            .button("Restore from backup...")
+            .on_hover_text(
+                "Replaces the library with the backup then closes Napoleon Amp, reopen it to load the restored library",
+            )
+            .clicked()
+        {
+            if let Some(archive_path) = rfd::FileDialog::new()
+                .add_filter("Napoleon Amp backup", &[archive::ARCHIVE_EXT])
+                .pick_file()
+            {
+                // Music playing would save song data back over the restored library
+                napoleon_instance.stop_music();
+
+                match archive::restore_archive(&archive_path) {
+                    Ok(manifest) => {
+                        println!("Restored from {} ({})", archive_path.display(), manifest);
+                        ui.ctx().send_viewport_cmd(ViewportCommand::Close);
+                    }
+
+                    Err(e) => {
+                        *status = Some(format!("Unable to restore the backup; {}", e));
+                    }
+                }
+            }
+        }
+
+        if let Some(status) = status {
+            ui.label(status.as_str());
+        }
+    }
+
+    fn render_trash(
+        ui: &mut Ui,
+        napoleon_instance: &mut NapoleonInstance,
+        entries: &mut Option<Vec<TrashEntry>>,
+        status: &mut Option<String>,
+    ) {
+        let trash_entries = entries.get_or_insert_with(|| {
+            trash::trash_entries().unwrap_or_else(|e| {
+                *status = Some(format!("Unable to read the trash; {}", e));
+                Vec::new()
+            })
+        });
+
+        if let Some(status) = status {
+            ui.label(status.as_str());
+        }
+
+        if trash_entries.is_empty() {
+            ui.label("The trash is empty");
+            return;
+        }
+
+        let mut changed = false;
+
+        ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            for trash_entry in trash_entries.iter() {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} \"{}\", deleted {} days ago",
+                        trash_entry.content,
+                        trash_entry.name,
+                        trash_entry.days_in_trash()
+                    ));
+
+                    if ui.button("Restore").clicked() {
+                        *status = match napoleon_instance.restore_from_trash(trash_entry) {
+                            Ok(()) => Some(format!("Restored \"{}\"", trash_entry.name)),
+                            Err(e) => {
+                                Some(format!("Unable to restore \"{}\"; {}", trash_entry.name, e))
+                            }
+                        };
+                        changed = true;
+                    }
+
+                    if ui.button("Delete").clicked() {
+                        *status = match trash_entry.delete_permanently() {
+                            Ok(()) => None,
+                            Err(e) => {
+                                Some(format!("Unable to delete \"{}\"; {}", trash_entry.name, e))
+                            }
+                        };
+                        changed = true;
+                    }
+                });
+            }
+        });
+
+        if ui.button("Empty trash").clicked() {
+            *status = match trash::empty_trash() {
+                Ok(()) => None,
+                Err(e) => Some(format!("Unable to empty the trash; {}", e)),
+            };
+            changed = true;
+        }
+
+        if changed {
+            *entries = None;
+        }
+    }
+
+    fn render_library_check(
+        ui: &mut Ui,
+        report: &mut Option<FsckReport>,
+        status: &mut Option<String>,
+    ) {
+        if ui.button("Check library").clicked() {
+            match fsck::check_library() {
+                Ok(new_report) => {
+                    *status = None;
+                    *report = Some(new_report);
+                }
+
+                Err(e) => {
+                    *status = Some(format!("Unable to check library; {}", e));
+                    *report = None;
+                }
+            }
+        }
+
+        if let Some(status) = status {
+            ui.label(status.as_str());
+        }
+
+        let Some(fsck_report) = report else {
+            return;
+        };
+
+        if fsck_report.issues.is_empty() {
+            ui.label("No issues found");
+            return;
+        }
+
+        ui.label(format!("{} issues found", fsck_report.issues.len()));
+
+        ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            for issue in &fsck_report.issues {
+                ui.label(issue.to_string())
+                    .on_hover_text(issue.fix_description());
+            }
+        });
+
+        if ui.button("Repair all").clicked() {
+            let failed = fsck_report.repair_all();
+
+            for (issue, e) in &failed {
+                eprintln!("Unable to repair \"{}\"; {}", issue, e);
+            }
+
+            *status = Some(format!(
+                "Repaired {} of {} issues, restart to reload the library",
+                fsck_report.issues.len() - failed.len(),
+                fsck_report.issues.len()
+            ));
+            *report = None;
+        }
+    }
+
+    fn render_library_root(ui: &mut Ui) {
+        let library_root_id = Id::new("library_root_edit");
+
+        ui.label(format!(
+            "Library location: {}",
+            library_root_dir().display()
+        ));
+
+        let mut library_root_str = ui.ctx().data_mut(|d| {
+            d.get_temp_mut_or_insert_with(library_root_id, || {
+                saved_library_root()
+                    .map(|dir| dir.to_string_lossy().into_owned())
+                    .unwrap_or_default()
+            })
+            .clone()
+        });
+
+        ui.label("Library location on next start:")
+            .on_hover_text("Leave empty to use the default location");
+
+        if ui.text_edit_singleline(&mut library_root_str).changed() {
+            ui.ctx()
+                .data_mut(|d| d.insert_temp(library_root_id, library_root_str.clone()));
+        }
+
+        if ui.button("Apply on next start").clicked() {
+            let library_root = if library_root_str.is_empty() {
+                None
+            } else {
+                Some(PathBuf::from(library_root_str))
+            };
+
+            set_saved_library_root(library_root).expect("Failed save library location");
+        }
     }
 }
 
@@ -68,8 +492,7 @@ impl MenuModal {
 
             CloseResult::SaveAndClose => {
                 napoleon_instance
-                    .get_client_settings()
-                    .save_data(())
+                    .save_client_settings()
                     .expect("Failed save client settings");
             }
         };
@@ -81,5 +504,34 @@ impl MenuModal {
         if ui.button("Settings").clicked() {
             self.page = MenuPage::Settings;
         }
+
+        if ui.button("Library Check").clicked() {
+            self.page = MenuPage::LibraryCheck {
+                report: None,
+                status: None,
+            };
+        }
+
+        if ui.button("Trash").clicked() {
+            self.page = MenuPage::Trash {
+                entries: None,
+                status: None,
+            };
+        }
+
+        if ui.button("Backup").clicked() {
+            self.page = MenuPage::Backup {
+                include_audio: false,
+                status: None,
+            };
+        }
+
+        if ui.button("Loudness").clicked() {
+            self.page = MenuPage::Loudness;
+        }
+
+        if ui.button("Equalizer").clicked() {
+            self.page = MenuPage::Equalizer;
+        }
     }
 }
@@ -0,0 +1,352 @@
+//! Backs up the library into a single tar archive, and restores the library from one.
+//!
+//! The archive holds the folders, playlists, song data, covers, registered songs, client settings and
+//! library version, and optionally the song audio. A manifest at the start of the archive records which
+//! archive format and library version it was written with.
+
+use crate::content::song::song_pool::SONG_POOL;
+use crate::migration::{CURRENT_LIBRARY_VERSION, library_version, run_migrations};
+use crate::paths::song::{
+    registered_songs_data_file_v2, songs_audio_dir_v2, songs_cover_dir_v2, songs_data_dir_v2,
+};
+use crate::paths::{
+    client_settings_file_path, content_blanket_path, library_version_file, napoleon_amp_dir,
+    replaced_by_restore_dir, restore_staging_dir,
+};
+use crate::safe_write::write_atomically;
+use crate::time_now;
+use chrono::Local;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+use tar::{Archive, Builder, Header};
+
+/// Bumped whenever the layout of the archive itself changes
+const ARCHIVE_FORMAT_VERSION: u16 = 1;
+const MANIFEST_NAME: &str = "napoleon_amp_archive.txt";
+
+pub const ARCHIVE_EXT: &str = "tar";
+
+#[derive(Debug, Copy, Clone)]
+pub struct ArchiveManifest {
+    pub format_version: u16,
+    /// The version the library was migrated to when it was archived
+    pub library_version: u16,
+    /// Seconds since the unix epoch
+    pub created_at: u64,
+    pub includes_audio: bool,
+}
+
+impl ArchiveManifest {
+    fn to_manifest_string(&self) -> String {
+        format!(
+            "format_version={}\nlibrary_version={}\ncreated_at={}\nincludes_audio={}\n",
+            self.format_version, self.library_version, self.created_at, self.includes_audio
+        )
+    }
+
+    fn from_manifest_str(manifest_str: &str) -> io::Result<Self> {
+        let mut format_version = None;
+        let mut library_version = None;
+        let mut created_at = None;
+        let mut includes_audio = None;
+
+        for line in manifest_str.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+
+            match key {
+                "format_version" => format_version = value.parse().ok(),
+                "library_version" => library_version = value.parse().ok(),
+                "created_at" => created_at = value.parse().ok(),
+                "includes_audio" => includes_audio = value.parse().ok(),
+                _ => {}
+            }
+        }
+
+        match (format_version, library_version, created_at, includes_audio) {
+            (
+                Some(format_version),
+                Some(library_version),
+                Some(created_at),
+                Some(includes_audio),
+            ) => Ok(Self {
+                format_version,
+                library_version,
+                created_at,
+                includes_audio,
+            }),
+
+            _ => Err(invalid_archive("the manifest is incomplete")),
+        }
+    }
+}
+
+impl Display for ArchiveManifest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Library version {}, {}",
+            self.library_version,
+            if self.includes_audio {
+                "with audio"
+            } else {
+                "without audio"
+            }
+        )
+    }
+}
+
+/// The library paths which are archived, the audio directory last since it is by far the largest
+
+fn archived_paths(include_audio: bool) -> Vec<PathBuf> {
+    let mut archived_paths = vec![
+        library_version_file(),
+        client_settings_file_path(),
+        content_blanket_path(),
+        registered_songs_data_file_v2(),
+        songs_data_dir_v2(),
+        songs_cover_dir_v2(),
+    ];
+
+    if include_audio {
+        archived_paths.push(songs_audio_dir_v2());
+    }
+
+    archived_paths
+}
+
+/// Writes the library data to a new archive at `archive_path`, replacing any file already there once the
+/// archive is complete
+
+pub fn write_archive(archive_path: &Path, include_audio: bool) -> io::Result<ArchiveManifest> {
+    let manifest = ArchiveManifest {
+        format_version: ARCHIVE_FORMAT_VERSION,
+        library_version: library_version(),
+        created_at: time_now().as_secs(),
+        includes_audio: include_audio,
+    };
+
+    write_atomically(archive_path, |temp_path| {
+        write_archive_to(temp_path, manifest)
+    })?;
+
+    Ok(manifest)
+}
+
+fn write_archive_to(archive_path: &Path, manifest: ArchiveManifest) -> io::Result<()> {
+    let mut builder = Builder::new(File::create(archive_path)?);
+    let library_dir = napoleon_amp_dir();
+
+    let manifest_string = manifest.to_manifest_string();
+    let mut header = Header::new_gnu();
+    header.set_size(manifest_string.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(manifest.created_at);
+    header.set_cksum();
+    builder.append_data(&mut header, MANIFEST_NAME, manifest_string.as_bytes())?;
+
+    for archived_path in archived_paths(manifest.includes_audio) {
+        if !archived_path.try_exists()? {
+            continue;
+        }
+
+        let relative_path = archived_path
+            .strip_prefix(&library_dir)
+            .expect("Archived paths are inside the library");
+
+        if archived_path.is_dir() {
+            builder.append_dir_all(relative_path, &archived_path)?;
+        } else {
+            builder.append_path_with_name(&archived_path, relative_path)?;
+        }
+    }
+
+    builder.into_inner()?;
+
+    Ok(())
+}
+
+/// Reads the manifest of an archive without unpacking anything
+
+pub fn read_archive_manifest(archive_path: &Path) -> io::Result<ArchiveManifest> {
+    let mut archive = Archive::new(File::open(archive_path)?);
+
+    read_manifest_entry(&mut archive)
+}
+
+fn read_manifest_entry(archive: &mut Archive<File>) -> io::Result<ArchiveManifest> {
+    let mut manifest_entry = archive
+        .entries()?
+        .next()
+        .ok_or_else(|| invalid_archive("it is empty"))??;
+
+    if manifest_entry.path()? != Path::new(MANIFEST_NAME) {
+        return Err(invalid_archive("it has no manifest"));
+    }
+
+    let mut manifest_string = String::new();
+    manifest_entry.read_to_string(&mut manifest_string)?;
+
+    ArchiveManifest::from_manifest_str(&manifest_string)
+}
+
+/// Replaces the library data with the contents of an archive. Archives written by a newer version are
+/// refused, archives of an older library version are migrated to the current one once restored. The
+/// replaced data is moved into a new directory in [`replaced_by_restore_dir`], and the audio is left as
+/// is when the archive doesn't include it. If the library can't be swapped over completely, whatever was
+/// already moved is moved back.
+///
+/// Nothing already loaded is reloaded, so the library should be reopened straight after, without
+/// saving anything in between
+
+pub fn restore_archive(archive_path: &Path) -> io::Result<ArchiveManifest> {
+    let manifest = read_archive_manifest(archive_path)?;
+
+    if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+        return Err(io::Error::new(
+            ErrorKind::Unsupported,
+            "The archive was written by a newer version of Napoleon Amp",
+        ));
+    }
+
+    if manifest.library_version > CURRENT_LIBRARY_VERSION {
+        return Err(io::Error::new(
+            ErrorKind::Unsupported,
+            format!(
+                "The archive holds a library at version {}, this version of Napoleon Amp only supports up to version {}",
+                manifest.library_version, CURRENT_LIBRARY_VERSION
+            ),
+        ));
+    }
+
+    let staging_dir = restore_staging_dir();
+    remove_dir_if_exists(&staging_dir)?;
+    fs::create_dir_all(&staging_dir)?;
+
+    let mut archive = Archive::new(File::open(archive_path)?);
+
+    for entry in archive.entries()?.skip(1) {
+        entry?.unpack_in(&staging_dir)?;
+    }
+
+    let replaced_dir = new_replaced_by_restore_dir()?;
+    let mut moved_paths = Vec::new();
+
+    if let Err(e) = swap_in_restored_paths(&manifest, &replaced_dir, &mut moved_paths) {
+        move_back(&moved_paths);
+        let _ = fs::remove_dir_all(&staging_dir);
+
+        return Err(e);
+    }
+
+    if let Err(e) = fs::remove_dir_all(&staging_dir) {
+        eprintln!("Unable to remove {:?} after restoring; {}", staging_dir, e);
+    }
+
+    // Brings an archive of an older library version up to date. The song pool is reloaded first so the
+    // migrations see the restored songs, a failed migration is retried the next time the library opens
+    SONG_POOL.reload();
+
+    match run_migrations() {
+        Ok(report) if !report.is_up_to_date() => println!("{}", report),
+        Ok(_) => {}
+        Err(e) => eprintln!("Failed to migrate the restored library; {}", e),
+    }
+
+    Ok(manifest)
+}
+
+/// Moves each archived path of the library into `replaced_dir`, then the restored path from the staging
+/// directory in its place. Every move made is pushed onto `moved_paths` as it happens, so the moves can
+/// be undone if one fails part way through
+
+fn swap_in_restored_paths(
+    manifest: &ArchiveManifest,
+    replaced_dir: &Path,
+    moved_paths: &mut Vec<(PathBuf, PathBuf)>,
+) -> io::Result<()> {
+    let library_dir = napoleon_amp_dir();
+    let staging_dir = restore_staging_dir();
+
+    for archived_path in archived_paths(manifest.includes_audio) {
+        let relative_path = archived_path
+            .strip_prefix(&library_dir)
+            .expect("Archived paths are inside the library");
+
+        let moves = [
+            (archived_path.clone(), replaced_dir.join(relative_path)),
+            (staging_dir.join(relative_path), archived_path.clone()),
+        ];
+
+        for (from, to) in moves {
+            if move_if_exists(&from, &to)? {
+                moved_paths.push((from, to));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Undoes the moves of a failed restore, newest first, so the library is left as it was before
+
+fn move_back(moved_paths: &[(PathBuf, PathBuf)]) {
+    for (from, to) in moved_paths.iter().rev() {
+        if let Err(e) = fs::rename(to, from) {
+            eprintln!(
+                "Unable to move {:?} back to {:?} after a failed restore; {}",
+                to, from, e
+            );
+        }
+    }
+}
+
+/// A new directory in [`replaced_by_restore_dir`] for the data replaced by this restore, named after the
+/// current time so the data replaced by earlier restores is kept
+
+fn new_replaced_by_restore_dir() -> io::Result<PathBuf> {
+    let dir_name = Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
+    let mut replaced_dir = replaced_by_restore_dir().join(&dir_name);
+    let mut attempt = 1;
+
+    while replaced_dir.try_exists()? {
+        attempt += 1;
+        replaced_dir = replaced_by_restore_dir().join(format!("{}_{}", dir_name, attempt));
+    }
+
+    Ok(replaced_dir)
+}
+
+/// Returns whether there was anything at `from` to move
+
+fn move_if_exists(from: &Path, to: &Path) -> io::Result<bool> {
+    if !from.try_exists()? {
+        return Ok(false);
+    }
+
+    if let Some(to_parent) = to.parent() {
+        fs::create_dir_all(to_parent)?;
+    }
+
+    fs::rename(from, to)?;
+
+    Ok(true)
+}
+
+fn remove_dir_if_exists(dir: &Path) -> io::Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn invalid_archive(reason: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("Not a Napoleon Amp archive, {}", reason),
+    )
+}
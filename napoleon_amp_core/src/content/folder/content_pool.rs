@@ -8,14 +8,15 @@ use crate::content::playlist::dynamic_playlist_data::{
     DynamicPlaylistData, DynamicPlaylistDataStd,
 };
 use crate::content::song::song_pool::SONG_POOL;
+use crate::content::trash::TrashEntry;
 use crate::paths::{
     content_folder_file, content_playlist_song_list_file, content_playlist_user_data_file,
 };
+use crate::safe_write::SafeWrite;
 use crate::{WriteGuard, write_rwlock};
-use serbytes::prelude::{FromFileResult, SerBytesFs};
+use serbytes::prelude::FromFileResult;
 use simple_id::prelude::{Id, SmallRngIdGenerator};
 use std::cell::Cell;
-use std::io::ErrorKind;
 use std::path::PathBuf;
 use std::sync::{LazyLock, RwLock};
 use std::{fs, io};
@@ -42,16 +43,13 @@ impl ContentPoolInner {
         (self.provide_assoc_files)(id)
     }
 
-    fn remove_file_assoc(&self, id: Id) -> Result<(), RemoveAssociatedFileError> {
+    fn move_file_assoc_to_trash(&self, id: Id, trash_entry: &TrashEntry) -> RmAssocResult {
         for file_path in self.get_associated_files(id) {
-            if let Err(io_error) = fs::remove_file(&file_path) {
-                // Dont care if file doesnt exist... we're deleting it anyways lol
-                if io_error.kind() != ErrorKind::NotFound {
-                    return Err(RemoveAssociatedFileError {
-                        io_error,
-                        file_path,
-                    });
-                }
+            if let Err(io_error) = trash_entry.move_into(&file_path) {
+                return Err(RemoveAssociatedFileError {
+                    io_error,
+                    file_path,
+                });
             }
         }
 
@@ -102,7 +100,7 @@ impl ContentPool {
         if playlist_id == Id::ZERO {
             Ok(PlaylistUserData::new_all_songs().into())
         } else {
-            PlaylistUserData::from_file_path(content_playlist_user_data_file(playlist_id))
+            PlaylistUserData::from_file_path_or_backup(content_playlist_user_data_file(playlist_id))
         }
     }
 
@@ -113,7 +111,9 @@ impl ContentPool {
         if playlist_id == Id::ZERO {
             Ok(DynamicPlaylistDataStd::new(PlaylistContentData::new_all_songs()).into())
         } else {
-            DynamicPlaylistData::from_file_path(content_playlist_user_data_file(playlist_id))
+            DynamicPlaylistData::from_file_path_or_backup(content_playlist_user_data_file(
+                playlist_id,
+            ))
         }
     }
 
@@ -131,20 +131,30 @@ impl ContentPool {
 
             Ok(data)
         } else {
-            PlaylistSongListData::from_file_path(content_playlist_song_list_file(playlist_id))
+            PlaylistSongListData::from_file_path_or_backup(content_playlist_song_list_file(
+                playlist_id,
+            ))
         }
     }
 
-    pub(super) fn delete_playlist(&self, playlist_id: Id) -> RmAssocResult {
-        Self::delete_content0(&mut self.playlists_mut(), playlist_id)
+    pub(super) fn trash_playlist(
+        &self,
+        playlist_id: Id,
+        trash_entry: &TrashEntry,
+    ) -> RmAssocResult {
+        Self::trash_content0(&mut self.playlists_mut(), playlist_id, trash_entry)
     }
 
-    pub(super) fn delete_folder(&self, folder_id: Id) -> RmAssocResult {
-        Self::delete_content0(&mut self.folders_mut(), folder_id)
+    pub(super) fn trash_folder(&self, folder_id: Id, trash_entry: &TrashEntry) -> RmAssocResult {
+        Self::trash_content0(&mut self.folders_mut(), folder_id, trash_entry)
     }
 
-    fn delete_content0(content_inner: &mut ContentPoolInner, content_id: Id) -> RmAssocResult {
-        content_inner.remove_file_assoc(content_id)?;
+    fn trash_content0(
+        content_inner: &mut ContentPoolInner,
+        content_id: Id,
+        trash_entry: &TrashEntry,
+    ) -> RmAssocResult {
+        content_inner.move_file_assoc_to_trash(content_id, trash_entry)?;
 
         Ok(())
     }
@@ -193,12 +203,24 @@ impl ContentPool {
         Ok(id)
     }
 
+    /// An id which no folder has yet, for a folder which has to be moved to a new id
+
+    pub(crate) fn new_folder_id(&self) -> Id {
+        Self::generate_unique_id(&self.folders)
+    }
+
+    /// An id which no playlist has yet, for a playlist which has to be moved to a new id
+
+    pub(crate) fn new_playlist_id(&self) -> Id {
+        Self::generate_unique_id(&self.playlists)
+    }
+
     fn playlists_mut(&self) -> WriteGuard<'_, ContentPoolInner> {
         write_rwlock(&self.playlists)
     }
 
     fn folders_mut(&self) -> WriteGuard<'_, ContentPoolInner> {
-        write_rwlock(&self.playlists)
+        write_rwlock(&self.folders)
     }
 
     fn generate_unique_id(content_inner: &RwLock<ContentPoolInner>) -> Id {
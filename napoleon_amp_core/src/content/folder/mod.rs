@@ -8,8 +8,10 @@ use crate::content::playlist::all_songs_playlist::AllSongsPlaylist;
 use crate::content::playlist::{
     DynamicPlaylist, PlaylistType, PlaylistTypeVariant, StandardPlaylist,
 };
+use crate::content::trash::{TrashEntry, TrashedContent, TrashedFolderContent};
 use crate::paths::content_folder_file;
-use serbytes::prelude::{MayNotExistOrDefault, SerBytes, SerBytesFs};
+use crate::safe_write::SafeWrite;
+use serbytes::prelude::{MayNotExistOrDefault, SerBytes};
 use simple_id::prelude::Id;
 use std::cell::{OnceCell, Ref, RefCell, RefMut};
 use std::fmt::Debug;
@@ -103,78 +105,160 @@ impl Folder {
         }
     }
 
-    pub fn create_folder(self: &Rc<Self>, folder_name: String) -> io::Result<()> {
+    pub fn create_folder(self: &Rc<Self>, folder_name: String) -> io::Result<Rc<Folder>> {
         let folder_id = CONTENT_POOL.create_new_folder(folder_name, Some(self.id))?;
 
-        self.create_content(FolderDataContentVariant::Folder, folder_id);
+        let content = self.create_content(FolderDataContentVariant::Folder, folder_id);
 
-        Ok(())
+        let FolderContentVariant::Folder(folder) = content else {
+            unreachable!("Created as a folder");
+        };
+
+        Ok(folder)
     }
 
-    pub fn create_standard_playlist(self: &Rc<Self>, playlist_name: String) -> io::Result<()> {
+    pub fn create_standard_playlist(
+        self: &Rc<Self>,
+        playlist_name: String,
+    ) -> io::Result<Rc<PlaylistType>> {
         let playlist_id = CONTENT_POOL.create_new_standard_playlist(playlist_name, self.id)?;
 
-        self.create_content(
+        let content = self.create_content(
             FolderDataContentVariant::Playlist(PlaylistTypeVariant::Standard(())),
             playlist_id,
         );
 
-        Ok(())
+        let FolderContentVariant::Playlist(playlist) = content else {
+            unreachable!("Created as a playlist");
+        };
+
+        Ok(playlist)
     }
 
-    pub fn create_dynamic_playlist(self: &Rc<Self>, playlist_name: String) -> io::Result<()> {
+    pub fn create_dynamic_playlist(
+        self: &Rc<Self>,
+        playlist_name: String,
+    ) -> io::Result<Rc<PlaylistType>> {
         let playlist_id = CONTENT_POOL.create_new_dynamic_playlist(playlist_name, self.id)?;
 
-        self.create_content(
+        let content = self.create_content(
             FolderDataContentVariant::Playlist(PlaylistTypeVariant::Dynamic(())),
             playlist_id,
         );
 
-        Ok(())
+        let FolderContentVariant::Playlist(playlist) = content else {
+            unreachable!("Created as a playlist");
+        };
+
+        Ok(playlist)
     }
 
+    /// Moves the content at `content_index` into the trash, a folder is moved along with everything
+    /// inside it
+
     pub fn delete_content(self: &Rc<Self>, content_index: usize) -> Result<(), DeleteContentError> {
         let mut folder_data = self.get_folder_data_mut();
-        let folder_data_contents = &mut folder_data.contents;
 
-        if content_index < folder_data_contents.len() {
-            folder_data_contents.remove(content_index);
-            let content = self.get_contents_mut().remove(content_index);
+        let Some(element) = folder_data.contents.get(content_index).copied() else {
+            return Err(DeleteContentError::IndexOutOfBounds);
+        };
 
+        let content_name = match &self.get_contents()[content_index] {
+            FolderContentVariant::Playlist(playlist) => {
+                playlist.get_user_data().inner.content_data.name.clone()
+            }
+            FolderContentVariant::Folder(folder) => {
+                folder.get_folder_data().content_data.name.clone()
+            }
+        };
+
+        // Created before the folder is changed, so the folder is left as it is if this fails
+        let trash_entry = TrashEntry::create(
+            content_name,
+            TrashedContent::FolderContent(TrashedFolderContent {
+                element,
+                parent_id: self.id,
+            }),
+        )?;
+
+        folder_data.contents.remove(content_index);
+        let content = self.get_contents_mut().remove(content_index);
+
+        match content {
+            FolderContentVariant::Playlist(playlist) => {
+                Self::trash_playlist(&playlist, &trash_entry)?
+            }
+            FolderContentVariant::Folder(folder) => {
+                Folder::trash_self(&folder, &trash_entry)?;
+            }
+        }
+
+        folder_data.save_data(self.id)?;
+
+        Ok(())
+    }
+
+    /// Moves the files of this folder and everything inside it into the trash entry, the folder data is
+    /// left as is so the folder can be restored with its contents
+
+    fn trash_self(self: &Rc<Self>, trash_entry: &TrashEntry) -> Result<(), DeleteContentError> {
+        for content in self.get_contents().iter() {
             match content {
                 FolderContentVariant::Playlist(playlist) => {
-                    CONTENT_POOL.delete_playlist(playlist.id())?
+                    Self::trash_playlist(playlist, trash_entry)?
                 }
                 FolderContentVariant::Folder(folder) => {
-                    Folder::delete_self(&folder)?;
+                    Folder::trash_self(folder, trash_entry)?;
                 }
             }
+        }
 
-            folder_data.save_data(self.id)?;
+        CONTENT_POOL.trash_folder(self.id, trash_entry)?;
 
-            Ok(())
-        } else {
-            Err(DeleteContentError::IndexOutOfBounds)
+        Ok(())
+    }
+
+    fn trash_playlist(
+        playlist: &PlaylistType,
+        trash_entry: &TrashEntry,
+    ) -> Result<(), DeleteContentError> {
+        // "All Songs" has no files of its own, its user data is shared with every other "All Songs"
+        if !matches!(playlist, PlaylistType::AllSongs(_)) {
+            CONTENT_POOL.trash_playlist(playlist.id(), trash_entry)?;
         }
+
+        Ok(())
     }
 
-    fn delete_self(self: &Rc<Self>) -> Result<(), DeleteContentError> {
-        let contents_len = self.get_contents().len();
+    /// Adds content which was restored from the trash back into this folder
 
-        for content_index in (0..contents_len).rev() {
-            self.delete_content(content_index)?;
+    pub(crate) fn restore_content(self: &Rc<Self>, element: ContentsListElements) {
+        self.create_content(element.variant, element.id);
+    }
+
+    /// Finds this folder or a folder anywhere inside it
+
+    pub(crate) fn find_folder(self: &Rc<Self>, folder_id: Id) -> Option<Rc<Folder>> {
+        if self.id == folder_id {
+            return Some(Rc::clone(self));
         }
 
-        CONTENT_POOL.delete_folder(self.id)?;
+        for content in self.get_contents().iter() {
+            if let FolderContentVariant::Folder(folder) = content {
+                if let Some(found) = folder.find_folder(folder_id) {
+                    return Some(found);
+                }
+            }
+        }
 
-        Ok(())
+        None
     }
 
     fn get_folder_data_refcell(&self) -> &RefCell<FolderData> {
         self.folder_data.get_or_init(|| {
             let folder_path = content_folder_file(self.id);
 
-            let data = FolderData::from_file_path(folder_path).unwrap_or_else(|_| {
+            let data = FolderData::from_file_path_or_backup(folder_path).unwrap_or_else(|_| {
                 assert_eq!(self.id, Id::ZERO, "Temp fix for base folder");
                 let data = FolderData::new(FolderContentData::new("Base".to_string(), None));
 
@@ -219,6 +303,10 @@ impl Folder {
                 PlaylistTypeVariant::AllSongs(_) => FolderContentVariant::Playlist(Rc::new(
                     PlaylistType::AllSongs(AllSongsPlaylist::new(self)),
                 )),
+
+                PlaylistTypeVariant::Album(_) => {
+                    unreachable!("Album playlists are never saved in a folder")
+                }
             },
         }
     }
@@ -245,12 +333,14 @@ impl Folder {
         self.get_contents_refcell().borrow_mut()
     }
 
-    fn create_content(self: &Rc<Self>, variant: FolderDataContentVariant, id: Id) {
-        {
-            let mut contents = self.get_contents_mut();
+    fn create_content(
+        self: &Rc<Self>,
+        variant: FolderDataContentVariant,
+        id: Id,
+    ) -> FolderContentVariant {
+        let content = self.get_folder_content_variant(variant, id);
 
-            contents.push(self.get_folder_content_variant(variant, id));
-        }
+        self.get_contents_mut().push(content.clone());
 
         let mut folder_data = self.get_folder_data_mut();
 
@@ -259,8 +349,10 @@ impl Folder {
             .push(ContentsListElements { id, variant });
 
         folder_data
-            .write_to_file_path(content_folder_file(self.id))
+            .write_to_file_path_safe(content_folder_file(self.id))
             .expect("Write folder data to file");
+
+        content
     }
 }
 
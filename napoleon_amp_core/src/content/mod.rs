@@ -1,5 +1,6 @@
 use crate::content::song::Song;
 use crate::content::song::song_pool::SONG_POOL;
+use crate::safe_write::SafeWrite;
 use serbytes::prelude::{SerBytes, SerBytesFs};
 use simple_id::prelude::Id;
 use std::cell::{Ref, RefMut};
@@ -10,6 +11,7 @@ use std::sync::Arc;
 pub mod folder;
 pub mod playlist;
 pub mod song;
+pub mod trash;
 
 /// Unwraps the inner [`Ref`]
 ///
@@ -31,7 +33,7 @@ pub trait SaveData<T = Id>: SerBytesFs {
     fn get_path(input: T) -> PathBuf;
 
     fn save_data(&self, input: T) -> io::Result<()> {
-        self.write_to_file_path(Self::get_path(input))
+        self.write_to_file_path_safe(Self::get_path(input))
     }
 }
 
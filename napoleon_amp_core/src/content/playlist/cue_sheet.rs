@@ -0,0 +1,706 @@
+//! CUE sheets, which split a single audio file, usually a whole album, into tracks.
+//!
+//! Each track is imported as its own song, playing from its `INDEX 01` up to the `INDEX 01` of the next
+//! track in the same file. The audio file is stored once and shared by every track, it stays in the
+//! library until the last of them is deleted.
+
+use crate::content::playlist::import::{
+    ImportEvent, ImportOutcome, copy_new_file, original_song_name, prepare_song_file,
+    remove_partial_import,
+};
+use crate::content::playlist::playlist_file::decode_playlist_text;
+use crate::content::song::Song;
+use crate::content::song::audio_container::AudioContainer;
+use crate::content::song::content_hash::ContentHash;
+use crate::content::song::song_data::{Artist, SongData, get_song_data_from_song_file_with_paths};
+use crate::content::song::song_pool::SONG_POOL;
+use crate::paths::song::{song_audio_file_v2, song_data_file_v2};
+use crate::safe_write::SafeWrite;
+use derive_enum_all_values::AllValues;
+use simple_id::prelude::{Id, SmallRngIdGenerator};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use std::{fs, io};
+
+pub const CUE_EXT: &str = "cue";
+
+/// Positions in a CUE sheet are given in minutes, seconds and frames of a CD
+const FRAMES_PER_SEC: u64 = 75;
+
+#[derive(Debug, Default)]
+pub struct CueSheet {
+    /// The album title
+    pub title: Option<String>,
+    /// The album artist, used for tracks which don't name their own performer
+    pub performer: Option<String>,
+    pub tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug)]
+pub struct CueTrack {
+    pub number: u32,
+    /// The audio file the track is in, as written in the sheet
+    pub file: String,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// Where the track starts in the audio file, its `INDEX 01`
+    pub start: Duration,
+    /// Where the next track in the same audio file starts, `None` for the last track of the file
+    pub end: Option<Duration>,
+}
+
+impl CueSheet {
+    pub fn read(cue_path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&decode_playlist_text(&fs::read(cue_path)?)))
+    }
+
+    /// Reads every audio track which has an `INDEX 01`, anything else in the sheet is ignored
+
+    pub fn parse(cue_text: &str) -> Self {
+        let mut cue_sheet = Self::default();
+        let mut current_file = None;
+        // Each track along with its `INDEX 01` and whether it is audio, tracks without a start or which
+        // aren't audio are dropped
+        let mut tracks = Vec::<(CueTrack, Option<Duration>, bool)>::new();
+
+        for line in cue_text.lines() {
+            let arguments = split_cue_line(line);
+
+            let Some((command, arguments)) = arguments.split_first() else {
+                continue;
+            };
+
+            let current_track = tracks.last_mut().map(|(track, _, _)| track);
+
+            match (&*command.to_ascii_uppercase(), arguments) {
+                ("FILE", [file, ..]) => current_file = Some(file.clone()),
+
+                ("TRACK", [number, track_type, ..]) => {
+                    let (Some(file), Ok(number)) = (&current_file, number.parse::<u32>()) else {
+                        continue;
+                    };
+
+                    tracks.push((
+                        CueTrack {
+                            number,
+                            file: file.clone(),
+                            title: None,
+                            performer: None,
+                            start: Duration::ZERO,
+                            end: None,
+                        },
+                        None,
+                        track_type.eq_ignore_ascii_case("AUDIO"),
+                    ));
+                }
+
+                ("TITLE", [title, ..]) => match current_track {
+                    Some(track) => track.title = Some(title.clone()),
+                    None => cue_sheet.title = Some(title.clone()),
+                },
+
+                ("PERFORMER", [performer, ..]) => match current_track {
+                    Some(track) => track.performer = Some(performer.clone()),
+                    None => cue_sheet.performer = Some(performer.clone()),
+                },
+
+                ("INDEX", [index, position, ..]) if index.parse::<u32>() == Ok(1) => {
+                    if let (Some((_, start, _)), Some(position)) =
+                        (tracks.last_mut(), parse_cue_position(position))
+                    {
+                        *start = Some(position);
+                    }
+                }
+
+                _ => {}
+            }
+        }
+
+        cue_sheet.tracks = tracks
+            .into_iter()
+            .filter(|(_, _, is_audio)| *is_audio)
+            .filter_map(|(track, start, _)| {
+                Some(CueTrack {
+                    start: start?,
+                    ..track
+                })
+            })
+            .collect();
+
+        for track_index in 1..cue_sheet.tracks.len() {
+            let tracks = &mut cue_sheet.tracks;
+
+            if tracks[track_index - 1].file == tracks[track_index].file {
+                tracks[track_index - 1].end = Some(tracks[track_index].start);
+            }
+        }
+
+        cue_sheet
+    }
+
+    /// Every audio file the tracks are in, in the order they are listed
+
+    pub fn files(&self) -> Vec<&str> {
+        let mut files = Vec::<&str>::new();
+
+        for track in &self.tracks {
+            if !files.contains(&&*track.file) {
+                files.push(&track.file);
+            }
+        }
+
+        files
+    }
+}
+
+impl CueTrack {
+    /// The start offset of the song made from this track, tracks at the start of the file have none
+    fn start_offset(&self) -> Option<Duration> {
+        (!self.start.is_zero()).then_some(self.start)
+    }
+}
+
+pub fn is_cue_sheet(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(CUE_EXT))
+}
+
+/// Finds the audio file `file` of the CUE sheet at `cue_path`. Sheets are often written for the wav a CD
+/// was ripped to and kept after converting it, so a file with the same name and any supported extension
+/// is used when it doesn't exist
+
+pub fn locate_cue_audio(cue_path: &Path, file: &str) -> Option<PathBuf> {
+    let audio_path = cue_path.parent().unwrap_or(Path::new("")).join(file);
+
+    if audio_path.is_file() {
+        return Some(audio_path);
+    }
+
+    AudioContainer::all_values()
+        .iter()
+        .map(|audio_container| audio_path.with_extension(audio_container.extension()))
+        .find(|audio_path| audio_path.is_file())
+}
+
+/// Imports every track of the CUE sheet at `cue_path`, returns the outcome of each track. Tracks which
+/// are already in the library, from importing the same sheet and audio before, are duplicates
+
+pub(super) fn import_cue_sheet(
+    cue_path: &Path,
+    delete_original: bool,
+    generator: &mut SmallRngIdGenerator,
+    on_event: &dyn Fn(ImportEvent),
+) -> Vec<ImportOutcome> {
+    let cue_sheet = match CueSheet::read(cue_path) {
+        Ok(cue_sheet) if cue_sheet.tracks.is_empty() => {
+            on_event(ImportEvent::Failed(cue_path.to_path_buf()));
+
+            return vec![ImportOutcome::IoError(io::Error::new(
+                ErrorKind::InvalidData,
+                "The CUE sheet has no audio tracks",
+            ))];
+        }
+
+        Ok(cue_sheet) => cue_sheet,
+
+        Err(e) => {
+            on_event(ImportEvent::Failed(cue_path.to_path_buf()));
+
+            return vec![ImportOutcome::IoError(e)];
+        }
+    };
+
+    let mut outcomes = Vec::with_capacity(cue_sheet.tracks.len());
+    let mut audio_paths = Vec::new();
+
+    for file in cue_sheet.files() {
+        let tracks = cue_sheet
+            .tracks
+            .iter()
+            .filter(|track| track.file == file)
+            .collect::<Vec<_>>();
+
+        let Some(audio_path) = locate_cue_audio(cue_path, file) else {
+            outcomes.extend(tracks.iter().map(|_| {
+                ImportOutcome::IoError(io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("The audio file {} was not found", file),
+                ))
+            }));
+            continue;
+        };
+
+        outcomes.extend(import_cue_file(
+            cue_path,
+            &cue_sheet,
+            &audio_path,
+            &tracks,
+            generator,
+            on_event,
+        ));
+
+        audio_paths.push(audio_path);
+    }
+
+    // Like other files, the originals are kept if any track was already in the library
+    let is_all_imported = outcomes.iter().all(ImportOutcome::is_imported);
+
+    if outcomes.iter().any(ImportOutcome::is_imported) {
+        on_event(ImportEvent::Probed(cue_path.to_path_buf()));
+    } else {
+        on_event(ImportEvent::Failed(cue_path.to_path_buf()));
+    }
+
+    if delete_original && is_all_imported {
+        for original_path in audio_paths.iter().map(PathBuf::as_path).chain([cue_path]) {
+            if let Err(e) = fs::remove_file(original_path) {
+                eprintln!(
+                    "Imported {:?} but failed to remove the original file; {}",
+                    original_path, e
+                );
+            }
+        }
+    }
+
+    outcomes
+}
+
+/// Imports the tracks of a CUE sheet which are in the audio file at `audio_path`, the audio is only
+/// copied into the library if it isn't there already
+
+fn import_cue_file(
+    cue_path: &Path,
+    cue_sheet: &CueSheet,
+    audio_path: &Path,
+    tracks: &[&CueTrack],
+    generator: &mut SmallRngIdGenerator,
+    on_event: &dyn Fn(ImportEvent),
+) -> Vec<ImportOutcome> {
+    let prepared = match prepare_song_file(audio_path) {
+        Ok(prepared) => prepared,
+        Err(outcome) => return repeat_outcome(&outcome, tracks.len()),
+    };
+
+    let existing_audio_id = match SONG_POOL.find_shared_audio(audio_path, prepared.content_hash) {
+        Ok(existing_audio_id) => existing_audio_id,
+        Err(e) => return repeat_outcome(&ImportOutcome::IoError(e), tracks.len()),
+    };
+
+    let existing_songs = existing_audio_id
+        .map(|audio_id| {
+            SONG_POOL
+                .shared_audio_users(audio_id)
+                .into_iter()
+                .map(|song_id| SONG_POOL.get_song_by_id(song_id))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut outcomes = tracks
+        .iter()
+        .map(|track| {
+            existing_songs
+                .iter()
+                .find(|song| song.get_song_data().inner.start_offset.inner == track.start_offset())
+                .map(|song| ImportOutcome::Duplicate(Arc::clone(song)))
+        })
+        .collect::<Vec<_>>();
+
+    let new_track_ids = tracks
+        .iter()
+        .zip(&outcomes)
+        .filter(|(_, outcome)| outcome.is_none())
+        .map(|_| generator.generate_new_id())
+        .collect::<Vec<_>>();
+
+    let Some(first_track_id) = new_track_ids.first().copied() else {
+        return outcomes.into_iter().flatten().collect();
+    };
+
+    let audio_container = prepared.audio_container;
+    let audio_id = existing_audio_id.unwrap_or_else(|| generator.generate_new_id());
+    let stored_audio_path = song_audio_file_v2(&audio_id, audio_container);
+    let is_audio_copied = existing_audio_id.is_none();
+
+    if is_audio_copied {
+        if let Err(e) = copy_new_file(audio_path, &stored_audio_path) {
+            return fill_outcomes(outcomes, ImportOutcome::IoError(e));
+        }
+
+        on_event(ImportEvent::Copied(audio_path.to_path_buf()));
+    }
+
+    // The tags and length of the whole file, the song data of each track starts from these
+    let mut file_song_data = SongData::default();
+    file_song_data.inner.audio_container = audio_container.into();
+
+    let probe_data_path = song_data_file_v2(&first_track_id);
+
+    let probe_failure = match get_song_data_from_song_file_with_paths(
+        &stored_audio_path,
+        &probe_data_path,
+        &mut file_song_data,
+    ) {
+        Ok(true) => None,
+        Ok(false) => Some(ImportOutcome::ProbeFailed),
+        Err(e) => Some(ImportOutcome::IoError(e)),
+    };
+
+    if let Some(probe_failure) = probe_failure {
+        if is_audio_copied {
+            remove_partial_import(&[stored_audio_path.as_path(), probe_data_path.as_path()]);
+        } else {
+            remove_partial_import(&[probe_data_path.as_path()]);
+        }
+
+        return fill_outcomes(outcomes, probe_failure);
+    }
+
+    let mut new_track_ids = new_track_ids.into_iter();
+
+    for (track, outcome) in tracks.iter().zip(&mut outcomes) {
+        if outcome.is_some() {
+            continue;
+        }
+
+        let song_id = new_track_ids.next().expect("An id for every new track");
+
+        *outcome = Some(
+            match create_track_song(
+                cue_path,
+                cue_sheet,
+                track,
+                song_id,
+                audio_id,
+                prepared.content_hash,
+                &file_song_data,
+            ) {
+                Ok(song) => ImportOutcome::Imported(song),
+                Err(e) => {
+                    remove_partial_import(&[song_data_file_v2(&song_id).as_path()]);
+                    ImportOutcome::IoError(e)
+                }
+            },
+        );
+    }
+
+    let is_any_imported = outcomes.iter().flatten().any(ImportOutcome::is_imported);
+
+    if is_audio_copied && !is_any_imported {
+        remove_partial_import(&[stored_audio_path.as_path()]);
+    }
+
+    outcomes.into_iter().flatten().collect()
+}
+
+fn create_track_song(
+    cue_path: &Path,
+    cue_sheet: &CueSheet,
+    track: &CueTrack,
+    song_id: Id,
+    audio_id: Id,
+    content_hash: ContentHash,
+    file_song_data: &SongData,
+) -> io::Result<Arc<Song>> {
+    let mut song_data = file_song_data.clone();
+    let song_data_std = &mut song_data.inner;
+    let meta = &mut song_data_std.meta.inner;
+
+    let title = track
+        .title
+        .clone()
+        .unwrap_or_else(|| format!("Track {:02}", track.number));
+
+    if let Some(performer) = track.performer.as_ref().or(cue_sheet.performer.as_ref()) {
+        meta.artist = Artist {
+            full_artist_string: performer.clone(),
+        }
+        .into();
+    }
+
+    if let Some(album) = &cue_sheet.title {
+        meta.album = album.clone().into();
+    }
+
+    if let Some(album_artist) = &cue_sheet.performer {
+        meta.album_artist = album_artist.clone().into();
+    }
+
+    meta.track_number = Some(track.number).into();
+
+    let file_length_secs = meta.song_length.inner.as_ref().copied().unwrap_or(0);
+    let end_secs = track
+        .end
+        .map_or(file_length_secs, |end| end.as_secs() as u32);
+
+    meta.song_length = end_secs.saturating_sub(track.start.as_secs() as u32).into();
+
+    song_data_std.title = title.clone();
+    song_data_std.original_title = title.clone();
+    song_data_std.start_offset = track.start_offset().into();
+    song_data_std.end_time = track.end.into();
+    song_data_std.shared_audio_id = Some(audio_id).into();
+
+    song_data.write_to_file_path_safe(song_data_file_v2(&song_id))?;
+
+    SONG_POOL.register_new_song(
+        song_id,
+        format!(
+            "{} - {:02} {}",
+            original_song_name(cue_path),
+            track.number,
+            title
+        ),
+        content_hash,
+    );
+    SONG_POOL.add_shared_audio_user(audio_id, song_id);
+
+    Ok(SONG_POOL.get_song_by_id(song_id))
+}
+
+/// Splits a line of a CUE sheet into its command and arguments, quoted arguments may contain spaces
+
+fn split_cue_line(line: &str) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+
+        let mut argument = String::new();
+
+        if c == '"' {
+            argument.extend(chars.by_ref().take_while(|c| *c != '"'));
+        } else {
+            argument.push(c);
+
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                argument.push(c);
+            }
+        }
+
+        arguments.push(argument);
+    }
+
+    arguments
+}
+
+/// Parses a `mm:ss:ff` position, minutes may be above 59
+
+fn parse_cue_position(position: &str) -> Option<Duration> {
+    let mut parts = position.split(':').map(|part| part.parse::<u64>().ok());
+
+    let (Some(Some(minutes)), Some(Some(seconds)), Some(Some(frames)), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return None;
+    };
+
+    Some(
+        Duration::from_secs(minutes * 60 + seconds)
+            + Duration::from_nanos(frames * 1_000_000_000 / FRAMES_PER_SEC),
+    )
+}
+
+/// The same outcome for every track of a file which could not be imported
+
+fn repeat_outcome(outcome: &ImportOutcome, track_count: usize) -> Vec<ImportOutcome> {
+    (0..track_count).map(|_| copy_outcome(outcome)).collect()
+}
+
+/// Gives every track without an outcome yet `outcome`
+
+fn fill_outcomes(
+    outcomes: Vec<Option<ImportOutcome>>,
+    outcome: ImportOutcome,
+) -> Vec<ImportOutcome> {
+    outcomes
+        .into_iter()
+        .map(|track_outcome| track_outcome.unwrap_or_else(|| copy_outcome(&outcome)))
+        .collect()
+}
+
+fn copy_outcome(outcome: &ImportOutcome) -> ImportOutcome {
+    match outcome {
+        ImportOutcome::Imported(song) => ImportOutcome::Imported(Arc::clone(song)),
+        ImportOutcome::Duplicate(song) => ImportOutcome::Duplicate(Arc::clone(song)),
+        ImportOutcome::UnsupportedFormat => ImportOutcome::UnsupportedFormat,
+        ImportOutcome::IoError(e) => {
+            ImportOutcome::IoError(io::Error::new(e.kind(), e.to_string()))
+        }
+        ImportOutcome::ProbeFailed => ImportOutcome::ProbeFailed,
+        ImportOutcome::Cancelled => ImportOutcome::Cancelled,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cue_time(minutes: u64, seconds: u64, frames: u64) -> Duration {
+        Duration::from_secs(minutes * 60 + seconds)
+            + Duration::from_nanos(frames * 1_000_000_000 / FRAMES_PER_SEC)
+    }
+
+    #[test]
+    fn splits_quoted_and_unquoted_arguments() {
+        assert_eq!(
+            split_cue_line(r#"  FILE "01 - Side A.flac" WAVE"#),
+            ["FILE", "01 - Side A.flac", "WAVE"]
+        );
+        assert_eq!(
+            split_cue_line("\tPERFORMER Someone"),
+            ["PERFORMER", "Someone"]
+        );
+        assert_eq!(split_cue_line(r#"TITLE """#), ["TITLE", ""]);
+        assert!(split_cue_line("   ").is_empty());
+    }
+
+    #[test]
+    fn converts_frames_to_durations() {
+        assert_eq!(
+            parse_cue_position("01:02:74"),
+            Some(Duration::from_secs(62) + Duration::from_nanos(986_666_666))
+        );
+        assert_eq!(parse_cue_position("00:00:00"), Some(Duration::ZERO));
+        assert_eq!(
+            parse_cue_position("74:30:15"),
+            Some(Duration::from_secs(74 * 60 + 30) + Duration::from_millis(200))
+        );
+        assert_eq!(parse_cue_position("01:02"), None);
+        assert_eq!(parse_cue_position("01:02:03:04"), None);
+        assert_eq!(parse_cue_position("01:xx:03"), None);
+    }
+
+    #[test]
+    fn parses_album_and_track_metadata() {
+        let cue_sheet = CueSheet::parse(
+            r#"REM GENRE Rock
+PERFORMER "The Band"
+TITLE "The Album"
+FILE "The Album.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "Opening"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE Second
+    PERFORMER "The Band feat. Guest"
+    INDEX 01 03:15:40
+"#,
+        );
+
+        assert_eq!(cue_sheet.title.as_deref(), Some("The Album"));
+        assert_eq!(cue_sheet.performer.as_deref(), Some("The Band"));
+        assert_eq!(cue_sheet.tracks.len(), 2);
+
+        let (first, second) = (&cue_sheet.tracks[0], &cue_sheet.tracks[1]);
+
+        assert_eq!(first.number, 1);
+        assert_eq!(first.title.as_deref(), Some("Opening"));
+        assert_eq!(first.performer, None);
+        assert_eq!(first.start, Duration::ZERO);
+        assert_eq!(first.end, Some(cue_time(3, 15, 40)));
+        assert_eq!(first.start_offset(), None);
+
+        assert_eq!(second.number, 2);
+        assert_eq!(second.title.as_deref(), Some("Second"));
+        assert_eq!(second.performer.as_deref(), Some("The Band feat. Guest"));
+        assert_eq!(second.start, cue_time(3, 15, 40));
+        assert_eq!(second.end, None);
+        assert_eq!(second.start_offset(), Some(cue_time(3, 15, 40)));
+    }
+
+    #[test]
+    fn starts_tracks_after_their_pregap() {
+        let cue_sheet = CueSheet::parse(
+            r#"FILE "album.wav" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 00 04:01:10
+    INDEX 01 04:03:00
+  TRACK 03 AUDIO
+    INDEX 00 08:00:00
+"#,
+        );
+
+        // The third track has no `INDEX 01` so it is dropped
+        assert_eq!(cue_sheet.tracks.len(), 2);
+        assert_eq!(cue_sheet.tracks[0].end, Some(cue_time(4, 3, 0)));
+        assert_eq!(cue_sheet.tracks[1].start, cue_time(4, 3, 0));
+        assert_eq!(cue_sheet.tracks[1].end, None);
+    }
+
+    #[test]
+    fn ends_tracks_at_the_end_of_their_file() {
+        let cue_sheet = CueSheet::parse(
+            r#"FILE "disc 1.flac" WAVE
+  TRACK 01 AUDIO
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    INDEX 01 02:00:00
+FILE "disc 2.flac" WAVE
+  TRACK 03 AUDIO
+    INDEX 01 00:00:00
+  TRACK 04 AUDIO
+    INDEX 01 05:30:00
+"#,
+        );
+
+        assert_eq!(cue_sheet.files(), ["disc 1.flac", "disc 2.flac"]);
+
+        let tracks = &cue_sheet.tracks;
+
+        assert_eq!(
+            tracks.iter().map(|track| &*track.file).collect::<Vec<_>>(),
+            ["disc 1.flac", "disc 1.flac", "disc 2.flac", "disc 2.flac"]
+        );
+        assert_eq!(tracks[0].end, Some(cue_time(2, 0, 0)));
+        // The last track of the first file isn't cut at the first track of the next file
+        assert_eq!(tracks[1].end, None);
+        assert_eq!(tracks[2].end, Some(cue_time(5, 30, 0)));
+        assert_eq!(tracks[3].end, None);
+    }
+
+    #[test]
+    fn skips_tracks_which_are_not_audio() {
+        let cue_sheet = CueSheet::parse(
+            r#"FILE "mixed mode.bin" BINARY
+  TRACK 01 MODE1/2352
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "Bonus"
+    INDEX 01 10:00:00
+  TRACK 03 audio
+    INDEX 01 12:00:00
+"#,
+        );
+
+        assert_eq!(
+            cue_sheet
+                .tracks
+                .iter()
+                .map(|track| track.number)
+                .collect::<Vec<_>>(),
+            [2, 3]
+        );
+        assert_eq!(cue_sheet.tracks[0].title.as_deref(), Some("Bonus"));
+        assert_eq!(cue_sheet.tracks[0].end, Some(cue_time(12, 0, 0)));
+    }
+
+    #[test]
+    fn ignores_tracks_before_any_file() {
+        let cue_sheet = CueSheet::parse(
+            "TRACK 01 AUDIO\n  INDEX 01 00:00:00\nFILE a.wav WAVE\nTRACK 02 AUDIO\nINDEX 01 00:00:00",
+        );
+
+        assert_eq!(cue_sheet.tracks.len(), 1);
+        assert_eq!(cue_sheet.tracks[0].number, 2);
+        assert_eq!(cue_sheet.tracks[0].file, "a.wav");
+    }
+}
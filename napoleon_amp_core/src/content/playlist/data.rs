@@ -1,12 +1,15 @@
 use crate::content::SaveData;
 use crate::content::folder::ContentData;
 use crate::content::playlist::PlaylistData;
+use crate::content::playlist::dsp::DspSettings;
 use crate::content::playlist::song_list::SortBy;
 use crate::paths::{content_playlist_song_list_file, content_playlist_user_data_file};
+use crate::safe_write::SafeWrite;
 use crate::time_now;
 use derive_enum_all_values::AllValues;
 use serbytes::prelude::{
-    BBReadResult, CurrentVersion, ReadByteBufferRefMut, SerBytes, SerBytesFs, VersioningWrapper,
+    BBReadResult, CurrentVersion, MayNotExistOrDefault, ReadByteBufferRefMut, SerBytes,
+    VersioningWrapper,
 };
 use simple_id::prelude::Id;
 use std::cell::Cell;
@@ -23,6 +26,7 @@ pub type PlaylistUserData = VersioningWrapper<PlaylistUserDataStd, PlaylistUserD
 const DEFAULT_VOLUME: f32 = 1.0;
 
 #[derive(SerBytes, AllValues, Default, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PlaybackMode {
     Sequential,
     #[default]
@@ -60,6 +64,8 @@ pub struct PlaylistUserDataStd {
     pub playback_mode: PlaybackMode,
     pub volume: f32,
     pub sort_by: SortBy,
+    /// Used instead of the client's dsp settings while the playlist plays
+    pub dsp_override: MayNotExistOrDefault<Option<DspSettings>>,
 }
 
 impl PlaylistData for PlaylistContentData {
@@ -99,6 +105,7 @@ impl PlaylistUserDataStd {
             playback_mode: PlaybackMode::default(),
             volume: DEFAULT_VOLUME,
             sort_by: SortBy::default(),
+            dsp_override: None.into(),
         }
     }
 }
@@ -120,6 +127,6 @@ impl SaveData for PlaylistSongListData {
             self.last_updated.set(time_now().as_secs());
         }
 
-        self.write_to_file_path(Self::get_path(id))
+        self.write_to_file_path_safe(Self::get_path(id))
     }
 }
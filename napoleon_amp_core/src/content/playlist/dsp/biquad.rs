@@ -0,0 +1,88 @@
+use crate::content::playlist::dsp::{EqBand, FilterType};
+use std::f64::consts::PI;
+
+/// A second order filter for one band of the equalizer, with coefficients from the Audio EQ Cookbook
+
+#[derive(Clone, Debug, Default)]
+pub(super) struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    pub(super) fn new(band: &EqBand, sample_rate: u32) -> Self {
+        let mut biquad = Self::default();
+
+        biquad.set_band(band, sample_rate);
+
+        biquad
+    }
+
+    /// Changes the band the filter applies, keeping its state so the change doesn't click
+
+    pub(super) fn set_band(&mut self, band: &EqBand, sample_rate: u32) {
+        let sample_rate = sample_rate as f64;
+        // Frequencies above half the sample rate can't be represented
+        let frequency = (band.frequency as f64).clamp(1.0, sample_rate * 0.49);
+        let q = (band.q as f64).max(0.1);
+
+        let a = 10_f64.powf(band.gain_db as f64 / 40.0);
+        let w0 = 2.0 * PI * frequency / sample_rate;
+        let (sin_w0, cos_w0) = w0.sin_cos();
+        let alpha = sin_w0 / (2.0 * q);
+
+        let (b, a0, a1, a2) = match band.filter_type {
+            FilterType::Peak => (
+                [1.0 + alpha * a, -2.0 * cos_w0, 1.0 - alpha * a],
+                1.0 + alpha / a,
+                -2.0 * cos_w0,
+                1.0 - alpha / a,
+            ),
+
+            FilterType::LowShelf => {
+                let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+                (
+                    [
+                        a * ((a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha),
+                        2.0 * a * ((a - 1.0) - (a + 1.0) * cos_w0),
+                        a * ((a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha),
+                    ],
+                    (a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos_w0),
+                    (a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha,
+                )
+            }
+
+            FilterType::HighShelf => {
+                let sqrt_a_alpha = 2.0 * a.sqrt() * alpha;
+
+                (
+                    [
+                        a * ((a + 1.0) + (a - 1.0) * cos_w0 + sqrt_a_alpha),
+                        -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_w0),
+                        a * ((a + 1.0) + (a - 1.0) * cos_w0 - sqrt_a_alpha),
+                    ],
+                    (a + 1.0) - (a - 1.0) * cos_w0 + sqrt_a_alpha,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos_w0),
+                    (a + 1.0) - (a - 1.0) * cos_w0 - sqrt_a_alpha,
+                )
+            }
+        };
+
+        self.b = b.map(|b| b / a0);
+        self.a = [a1 / a0, a2 / a0];
+    }
+
+    /// Transposed direct form II
+
+    pub(super) fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.z[0];
+
+        self.z[0] = self.b[1] * input - self.a[0] * output + self.z[1];
+        self.z[1] = self.b[2] * input - self.a[1] * output;
+
+        output
+    }
+}
@@ -0,0 +1,171 @@
+mod biquad;
+pub(super) mod source;
+
+use crate::{read_rwlock, write_rwlock};
+use derive_enum_all_values::AllValues;
+use serbytes::prelude::SerBytes;
+use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, RwLock};
+
+/// Centre frequencies of the bands of every preset, in Hz
+const PRESET_FREQUENCIES: [f32; 10] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1_000.0, 2_000.0, 4_000.0, 8_000.0, 16_000.0,
+];
+/// About an octave wide, so the bands of the presets overlap smoothly
+const PRESET_Q: f32 = 1.41;
+
+pub const MIN_BAND_GAIN_DB: f32 = -18.0;
+pub const MAX_BAND_GAIN_DB: f32 = 18.0;
+pub const MIN_BAND_FREQUENCY: f32 = 20.0;
+pub const MAX_BAND_FREQUENCY: f32 = 20_000.0;
+
+/// The dsp settings songs are played with unless their playlist overrides them, set from the client
+/// settings when the instance is created
+static DSP_SETTINGS: LazyLock<RwLock<DspSettings>> = LazyLock::new(Default::default);
+/// Changed whenever the dsp settings of the songs playing may have changed, so they can be applied while the
+/// song plays
+static DSP_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The processing applied to songs between decoding and playing them
+
+#[derive(SerBytes, Clone, Debug, PartialEq)]
+pub struct DspSettings {
+    /// Songs are played unchanged when disabled
+    pub enabled: bool,
+    /// Gain applied before the equalizer, in decibels. Lowering it leaves room for bands which boost
+    pub preamp_db: f32,
+    pub bands: Vec<EqBand>,
+    /// From -1.0 (only the left channel) to 1.0 (only the right channel), only applied to stereo songs
+    pub balance: f32,
+    /// Mixes every channel together, so each plays the same audio
+    pub mono: bool,
+}
+
+impl Default for DspSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            preamp_db: 0.0,
+            bands: EqPreset::Flat.bands(),
+            balance: 0.0,
+            mono: false,
+        }
+    }
+}
+
+/// One band of the parametric equalizer
+
+#[derive(SerBytes, Copy, Clone, Debug, PartialEq)]
+pub struct EqBand {
+    pub filter_type: FilterType,
+    /// Centre frequency of a peak, or the corner frequency of a shelf, in Hz
+    pub frequency: f32,
+    pub gain_db: f32,
+    /// How narrow the band is, higher values affect fewer frequencies
+    pub q: f32,
+}
+
+impl EqBand {
+    pub fn new(filter_type: FilterType, frequency: f32, gain_db: f32) -> Self {
+        Self {
+            filter_type,
+            frequency,
+            gain_db,
+            q: PRESET_Q,
+        }
+    }
+}
+
+#[derive(SerBytes, AllValues, Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FilterType {
+    /// Boosts or cuts the frequencies around the band's frequency
+    Peak,
+    /// Boosts or cuts every frequency below the band's frequency
+    LowShelf,
+    /// Boosts or cuts every frequency above the band's frequency
+    HighShelf,
+}
+
+impl Display for FilterType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_str = match self {
+            Self::Peak => "Peak",
+            Self::LowShelf => "Low shelf",
+            Self::HighShelf => "High shelf",
+        };
+
+        f.write_str(display_str)
+    }
+}
+
+/// Equalizer curves which can be applied as a starting point, then adjusted band by band
+
+#[derive(AllValues, Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EqPreset {
+    Flat,
+    BassBoost,
+    TrebleBoost,
+    Vocal,
+    Loudness,
+    SmallSpeakers,
+}
+
+impl EqPreset {
+    /// Ten bands an octave apart, with the gains of the preset
+
+    pub fn bands(&self) -> Vec<EqBand> {
+        let gains_db: [f32; 10] = match self {
+            Self::Flat => [0.0; 10],
+            Self::BassBoost => [6.0, 5.0, 4.0, 2.0, 0.5, 0.0, 0.0, 0.0, 0.0, 0.0],
+            Self::TrebleBoost => [0.0, 0.0, 0.0, 0.0, 0.0, 0.5, 2.0, 4.0, 5.0, 6.0],
+            Self::Vocal => [-2.0, -2.0, -1.0, 0.0, 2.0, 3.0, 3.0, 2.0, 0.0, -1.0],
+            Self::Loudness => [5.0, 4.0, 2.0, 0.0, -1.0, -1.0, 0.0, 2.0, 4.0, 5.0],
+            // Small speakers can't play deep bass, so it is cut to leave room for the mids
+            Self::SmallSpeakers => [-8.0, -5.0, -1.0, 1.0, 1.0, 0.0, 1.0, 2.0, 2.0, 1.0],
+        };
+
+        PRESET_FREQUENCIES
+            .iter()
+            .zip(gains_db)
+            .map(|(frequency, gain_db)| EqBand::new(FilterType::Peak, *frequency, gain_db))
+            .collect()
+    }
+}
+
+impl Display for EqPreset {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_str = match self {
+            Self::Flat => "Flat",
+            Self::BassBoost => "Bass boost",
+            Self::TrebleBoost => "Treble boost",
+            Self::Vocal => "Vocal",
+            Self::Loudness => "Loudness",
+            Self::SmallSpeakers => "Small speakers",
+        };
+
+        f.write_str(display_str)
+    }
+}
+
+pub fn dsp_settings() -> DspSettings {
+    read_rwlock(&DSP_SETTINGS).clone()
+}
+
+/// Changes the dsp settings of every song played without a playlist override, including the song playing now
+
+pub fn set_dsp_settings(dsp_settings: DspSettings) {
+    *write_rwlock(&DSP_SETTINGS) = dsp_settings;
+
+    notify_dsp_changed();
+}
+
+/// Makes the songs playing read their dsp settings again
+
+pub(super) fn notify_dsp_changed() {
+    DSP_GENERATION.fetch_add(1, Ordering::Relaxed);
+}
+
+fn dsp_generation() -> u64 {
+    DSP_GENERATION.load(Ordering::Relaxed)
+}
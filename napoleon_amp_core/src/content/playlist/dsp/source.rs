@@ -0,0 +1,233 @@
+use crate::content::playlist::dsp::biquad::Biquad;
+use crate::content::playlist::dsp::{DspSettings, dsp_generation, dsp_settings};
+use crate::content::song::loudness::db_to_amplitude;
+use crate::read_rwlock;
+use rodio::source::SeekError;
+use rodio::{ChannelCount, Sample, SampleRate, Source};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Applies the dsp settings of the playlist playing, or the client's if it doesn't override them. Changes to
+/// the settings are picked up while the song plays
+
+pub(in crate::content::playlist) struct DspSource<S> {
+    source: S,
+    playlist_override: Arc<RwLock<Option<DspSettings>>>,
+    /// The generation of the settings `chain` was built from
+    generation: u64,
+    /// `None` while the settings are disabled, the samples are played unchanged
+    chain: Option<DspChain>,
+    /// The frame being played, the whole frame is needed to mix it to mono
+    frame: Vec<Sample>,
+    frame_position: usize,
+}
+
+impl<S: Source> DspSource<S> {
+    pub(in crate::content::playlist) fn new(
+        source: S,
+        playlist_override: Arc<RwLock<Option<DspSettings>>>,
+    ) -> Self {
+        let mut dsp_source = Self {
+            source,
+            playlist_override,
+            generation: dsp_generation(),
+            chain: None,
+            frame: Vec::new(),
+            frame_position: 0,
+        };
+
+        dsp_source.update_chain();
+
+        dsp_source
+    }
+
+    fn update_chain(&mut self) {
+        let settings = read_rwlock(&self.playlist_override)
+            .clone()
+            .unwrap_or_else(dsp_settings);
+
+        if !settings.enabled {
+            self.chain = None;
+            return;
+        }
+
+        let channels = self.source.channels() as usize;
+        let sample_rate = self.source.sample_rate();
+
+        match &mut self.chain {
+            Some(chain) if chain.channels() == channels && chain.sample_rate == sample_rate => {
+                chain.update(&settings);
+            }
+
+            chain => *chain = Some(DspChain::new(&settings, channels, sample_rate)),
+        }
+    }
+}
+
+impl<S: Source> Iterator for DspSource<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(sample) = self.frame.get(self.frame_position) {
+            self.frame_position += 1;
+            return Some(*sample);
+        }
+
+        // Settings are only changed between frames
+        let generation = dsp_generation();
+
+        let format_changed = self.chain.as_ref().is_some_and(|chain| {
+            chain.channels() != self.source.channels() as usize
+                || chain.sample_rate != self.source.sample_rate()
+        });
+
+        if generation != self.generation || format_changed {
+            self.generation = generation;
+            self.update_chain();
+        }
+
+        self.frame.clear();
+        self.frame_position = 0;
+
+        for _ in 0..self.source.channels() {
+            let Some(sample) = self.source.next() else {
+                break;
+            };
+
+            self.frame.push(sample);
+        }
+
+        if let Some(chain) = &mut self.chain {
+            chain.process(&mut self.frame);
+        }
+
+        let sample = *self.frame.first()?;
+
+        self.frame_position = 1;
+
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let buffered = self.frame.len() - self.frame_position;
+        let (lower, upper) = self.source.size_hint();
+
+        (lower + buffered, upper.map(|upper| upper + buffered))
+    }
+}
+
+impl<S: Source> Source for DspSource<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        let buffered = self.frame.len() - self.frame_position;
+
+        self.source
+            .current_span_len()
+            .map(|span_len| span_len + buffered)
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.source.try_seek(pos)?;
+
+        self.frame.clear();
+        self.frame_position = 0;
+
+        Ok(())
+    }
+}
+
+/// The preamp, equalizer, mono downmix and balance, in the order they are applied
+
+struct DspChain {
+    preamp: f64,
+    /// The filters of every band which changes the sound, for each channel
+    channel_filters: Vec<Vec<Biquad>>,
+    mono: bool,
+    channel_gains: Vec<f32>,
+    sample_rate: SampleRate,
+}
+
+impl DspChain {
+    fn new(settings: &DspSettings, channels: usize, sample_rate: SampleRate) -> Self {
+        let mut chain = Self {
+            preamp: 1.0,
+            channel_filters: vec![Vec::new(); channels],
+            mono: false,
+            channel_gains: vec![1.0; channels],
+            sample_rate,
+        };
+
+        chain.update(settings);
+
+        chain
+    }
+
+    fn channels(&self) -> usize {
+        self.channel_filters.len()
+    }
+
+    /// Applies `settings`, the filters of bands which are still there keep their state
+
+    fn update(&mut self, settings: &DspSettings) {
+        self.preamp = db_to_amplitude(settings.preamp_db) as f64;
+        self.mono = settings.mono;
+
+        // A band without any gain leaves the sound as it is
+        let bands = settings
+            .bands
+            .iter()
+            .filter(|band| band.gain_db != 0.0)
+            .collect::<Vec<_>>();
+
+        for filters in &mut self.channel_filters {
+            filters.truncate(bands.len());
+
+            for (index, band) in bands.iter().enumerate() {
+                match filters.get_mut(index) {
+                    Some(filter) => filter.set_band(band, self.sample_rate),
+                    None => filters.push(Biquad::new(band, self.sample_rate)),
+                }
+            }
+        }
+
+        let balance = settings.balance.clamp(-1.0, 1.0);
+
+        if let [left_gain, right_gain] = self.channel_gains.as_mut_slice() {
+            *left_gain = (1.0 - balance).min(1.0);
+            *right_gain = (1.0 + balance).min(1.0);
+        }
+    }
+
+    fn process(&mut self, frame: &mut [Sample]) {
+        for (sample, filters) in frame.iter_mut().zip(&mut self.channel_filters) {
+            let mut value = *sample as f64 * self.preamp;
+
+            for filter in filters {
+                value = filter.process(value);
+            }
+
+            *sample = value as f32;
+        }
+
+        if self.mono && !frame.is_empty() {
+            let mean = frame.iter().sum::<f32>() / frame.len() as f32;
+
+            frame.fill(mean);
+        }
+
+        for (sample, gain) in frame.iter_mut().zip(&self.channel_gains) {
+            *sample *= gain;
+        }
+    }
+}
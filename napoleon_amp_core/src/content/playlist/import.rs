@@ -0,0 +1,356 @@
+use crate::content::playlist::cue_sheet::{CueSheet, is_cue_sheet, locate_cue_audio};
+use crate::content::song::Song;
+use crate::content::song::audio_container::AudioContainer;
+use crate::content::song::content_hash::{ContentHash, hash_file};
+use crate::content::song::song_data::{SongData, get_song_data_from_song_file_with_paths};
+use crate::content::song::song_pool::SONG_POOL;
+use crate::paths::SONG_DATA_EXT_NO_PER;
+use crate::paths::song::{
+    song_audio_file_v2, song_data_file_v2, songs_audio_dir_v2, songs_data_dir_v2,
+};
+use crate::safe_write::SafeWrite;
+use simple_id::prelude::SmallRngIdGenerator;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::{fs, io};
+
+/// What happened to a single file when importing songs
+
+#[derive(Debug)]
+pub enum ImportOutcome {
+    Imported(Arc<Song>),
+    /// The file is an exact copy of a song already in the library, so it was not copied again
+    Duplicate(Arc<Song>),
+    UnsupportedFormat,
+    IoError(io::Error),
+    /// The file looked like audio, but symphonia was unable to read it
+    ProbeFailed,
+    /// The import was cancelled before this file was reached
+    Cancelled,
+}
+
+impl ImportOutcome {
+    pub fn is_imported(&self) -> bool {
+        matches!(self, Self::Imported(_))
+    }
+}
+
+impl Display for ImportOutcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Imported(_) => write!(f, "Imported"),
+            Self::Duplicate(song) => write!(
+                f,
+                "Already in the library as \"{}\"",
+                song.get_song_data().inner.title
+            ),
+            Self::UnsupportedFormat => write!(f, "Unsupported format"),
+            Self::IoError(e) => write!(f, "I/O error; {}", e),
+            Self::ProbeFailed => write!(f, "Unable to read audio"),
+            Self::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+/// The outcome of every file found when importing songs, directories that could not be read are
+/// included as well
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub entries: Vec<(PathBuf, ImportOutcome)>,
+}
+
+impl ImportReport {
+    pub fn imported_count(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|(_, outcome)| outcome.is_imported())
+            .count()
+    }
+
+    pub fn is_all_imported(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|(_, outcome)| outcome.is_imported())
+    }
+
+    /// Every entry which was not imported
+
+    pub fn not_imported(&self) -> impl Iterator<Item = &(PathBuf, ImportOutcome)> {
+        self.entries
+            .iter()
+            .filter(|(_, outcome)| !outcome.is_imported())
+    }
+
+    pub fn imported_songs(&self) -> Vec<Arc<Song>> {
+        self.entries
+            .iter()
+            .filter_map(|(_, outcome)| match outcome {
+                ImportOutcome::Imported(song) => Some(Arc::clone(song)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The existing songs which imported files were duplicates of, these can be linked into the
+    /// playlist instead
+
+    pub fn duplicate_songs(&self) -> Vec<Arc<Song>> {
+        self.entries
+            .iter()
+            .filter_map(|(_, outcome)| match outcome {
+                ImportOutcome::Duplicate(song) => Some(Arc::clone(song)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Progress of an import, sent from the import threads as each step of each file completes
+
+#[derive(Debug)]
+pub enum ImportEvent {
+    /// Every path has been scanned, with the number of files which will be imported
+    FilesFound(usize),
+    Copied(PathBuf),
+    Probed(PathBuf),
+    /// The file was not imported, the reason is in the [`ImportReport`]
+    Failed(PathBuf),
+    Finished(ImportReport),
+}
+
+/// Expands directories into every audio file and CUE sheet inside them (recursively), files given
+/// directly are kept as is so that unsupported files are still reported
+
+pub(super) fn collect_song_paths(
+    paths: &[PathBuf],
+    cancelled: &AtomicBool,
+) -> (Vec<PathBuf>, Vec<(PathBuf, ImportOutcome)>) {
+    let mut song_paths = Vec::new();
+    let mut failed = Vec::new();
+
+    for path in paths {
+        if path.is_dir() {
+            collect_song_paths_in_dir(path, &mut song_paths, &mut failed, cancelled);
+        } else {
+            song_paths.push(path.clone());
+        }
+    }
+
+    (song_paths, failed)
+}
+
+fn collect_song_paths_in_dir(
+    dir: &Path,
+    song_paths: &mut Vec<PathBuf>,
+    failed: &mut Vec<(PathBuf, ImportOutcome)>,
+    cancelled: &AtomicBool,
+) {
+    if cancelled.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let mut dir_paths = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir
+            .flatten()
+            .map(|dir_entry| dir_entry.path())
+            .collect::<Vec<_>>(),
+
+        Err(e) => {
+            failed.push((dir.to_path_buf(), ImportOutcome::IoError(e)));
+            return;
+        }
+    };
+
+    dir_paths.sort();
+
+    // Audio split by a CUE sheet is imported through the sheet instead, as the tracks it lists
+    let cue_audio_paths = dir_paths
+        .iter()
+        .filter(|path| is_cue_sheet(path))
+        .filter_map(|cue_path| {
+            let cue_sheet = CueSheet::read(cue_path).ok()?;
+
+            Some(
+                cue_sheet
+                    .files()
+                    .into_iter()
+                    .filter_map(|file| locate_cue_audio(cue_path, file))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .flatten()
+        .collect::<HashSet<_>>();
+
+    for path in dir_paths {
+        if path.is_dir() {
+            collect_song_paths_in_dir(&path, song_paths, failed, cancelled);
+        } else if is_cue_sheet(&path) {
+            song_paths.push(path);
+        } else if !cue_audio_paths.contains(&path)
+            && path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(AudioContainer::from_extension)
+                .is_some()
+        {
+            song_paths.push(path);
+        }
+    }
+}
+
+pub(super) fn create_song_dirs() -> io::Result<()> {
+    fs::create_dir_all(songs_audio_dir_v2())?;
+    fs::create_dir_all(songs_data_dir_v2())
+}
+
+/// A file which is known to be audio and has been hashed, but has not been copied yet
+
+pub(super) struct PreparedSongFile {
+    pub(super) audio_container: AudioContainer,
+    pub(super) content_hash: ContentHash,
+}
+
+/// Detects the container of a file and hashes it, returns the outcome early if it can't be imported
+
+pub(super) fn prepare_song_file(
+    original_song_path: &Path,
+) -> Result<PreparedSongFile, ImportOutcome> {
+    if original_song_path.extension().and_then(|ext| ext.to_str()) == Some(SONG_DATA_EXT_NO_PER) {
+        return Err(ImportOutcome::UnsupportedFormat);
+    }
+
+    let audio_container = match AudioContainer::from_path(original_song_path) {
+        Ok(Some(audio_container)) => audio_container,
+        Ok(None) => return Err(ImportOutcome::UnsupportedFormat),
+        Err(e) => return Err(ImportOutcome::IoError(e)),
+    };
+
+    let content_hash = hash_file(original_song_path).map_err(ImportOutcome::IoError)?;
+
+    Ok(PreparedSongFile {
+        audio_container,
+        content_hash,
+    })
+}
+
+/// Imports a file which has been prepared, anything written to the library is removed again if a step
+/// fails
+
+pub(super) fn import_prepared_song_file(
+    original_song_path: &Path,
+    prepared: PreparedSongFile,
+    delete_original: bool,
+    generator: &mut SmallRngIdGenerator,
+    on_event: &dyn Fn(ImportEvent),
+) -> ImportOutcome {
+    let PreparedSongFile {
+        audio_container,
+        content_hash,
+    } = prepared;
+
+    match SONG_POOL.find_duplicate(original_song_path, content_hash) {
+        Ok(Some(existing_song)) => return ImportOutcome::Duplicate(existing_song),
+        Ok(None) => {}
+        Err(e) => return ImportOutcome::IoError(e),
+    }
+
+    let song_id = generator.generate_new_id();
+    let new_song_audio_path = song_audio_file_v2(&song_id, audio_container);
+    let new_song_data_path = song_data_file_v2(&song_id);
+
+    if let Err(e) = copy_new_file(original_song_path, &new_song_audio_path) {
+        return ImportOutcome::IoError(e);
+    }
+
+    on_event(ImportEvent::Copied(original_song_path.to_path_buf()));
+
+    let partial_import = [new_song_audio_path.as_path(), new_song_data_path.as_path()];
+
+    let mut song_data = SongData::default();
+    song_data.inner.audio_container = audio_container.into();
+
+    match get_song_data_from_song_file_with_paths(
+        &new_song_audio_path,
+        &new_song_data_path,
+        &mut song_data,
+    ) {
+        Ok(true) => {}
+
+        Ok(false) => {
+            remove_partial_import(&partial_import);
+            return ImportOutcome::ProbeFailed;
+        }
+
+        Err(e) => {
+            remove_partial_import(&partial_import);
+            return ImportOutcome::IoError(e);
+        }
+    }
+
+    if song_data.inner.title.is_empty() {
+        song_data.inner.title = original_song_name(original_song_path);
+
+        if let Err(e) = song_data.write_to_file_path_safe(&new_song_data_path) {
+            remove_partial_import(&partial_import);
+            return ImportOutcome::IoError(e);
+        }
+    }
+
+    on_event(ImportEvent::Probed(original_song_path.to_path_buf()));
+
+    SONG_POOL.register_new_song(
+        song_id,
+        original_song_name(original_song_path),
+        content_hash,
+    );
+
+    if delete_original {
+        if let Err(e) = fs::remove_file(original_song_path) {
+            eprintln!(
+                "Imported {:?} but failed to remove the original file; {}",
+                original_song_path, e
+            );
+        }
+    }
+
+    ImportOutcome::Imported(SONG_POOL.get_song_by_id(song_id))
+}
+
+pub(super) fn original_song_name(original_song_path: &Path) -> String {
+    original_song_path
+        .file_stem()
+        .map(|file_stem| file_stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Unnamed Song".to_string())
+}
+
+/// Copies `from` into a file which must not exist yet, the new file is removed again if the copy fails
+
+pub(super) fn copy_new_file(from: &Path, to: &Path) -> io::Result<()> {
+    let mut from_file = File::open(from)?;
+    let mut to_file = File::create_new(to)?;
+
+    let copy_result = io::copy(&mut from_file, &mut to_file).and_then(|_| to_file.sync_all());
+
+    if copy_result.is_err() {
+        drop(to_file);
+        remove_partial_import(&[to]);
+    }
+
+    copy_result
+}
+
+pub(super) fn remove_partial_import(paths: &[&Path]) {
+    for path in paths {
+        if let Err(e) = fs::remove_file(path) {
+            if e.kind() != ErrorKind::NotFound {
+                eprintln!("Unable to remove partially imported file {:?}; {}", path, e);
+            }
+        }
+    }
+}
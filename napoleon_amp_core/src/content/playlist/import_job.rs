@@ -0,0 +1,295 @@
+use crate::content::playlist::cue_sheet::{import_cue_sheet, is_cue_sheet};
+use crate::content::playlist::import::{
+    ImportEvent, ImportOutcome, ImportReport, collect_song_paths, create_song_dirs,
+    import_prepared_song_file, prepare_song_file,
+};
+use crate::content::song::song_pool::SONG_POOL;
+use crate::{POISONED_LOCK_MESSAGE, unlock_mutex};
+use simple_id::prelude::SmallRngIdGenerator;
+use std::collections::HashSet;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::thread::JoinHandle;
+
+const MAX_IMPORT_THREADS: usize = 4;
+
+/// How far along an [`ImportJob`] is
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ImportProgress {
+    /// `None` until every path has been scanned
+    pub files_found: Option<usize>,
+    pub copied: usize,
+    pub probed: usize,
+    pub failed: usize,
+}
+
+impl ImportProgress {
+    /// Number of files which have either been imported or failed
+    pub fn completed(&self) -> usize {
+        self.probed + self.failed
+    }
+
+    pub fn fraction(&self) -> f32 {
+        match self.files_found {
+            Some(0) => 1.0,
+            Some(files_found) => self.completed() as f32 / files_found as f32,
+            None => 0.0,
+        }
+    }
+}
+
+/// An import of files and directories running on background threads. The ui thread should call
+/// [`ImportJob::poll`] each frame, and add the songs in the returned report to the playlist.
+
+pub struct ImportJob {
+    event_receiver: Receiver<ImportEvent>,
+    cancelled: Arc<AtomicBool>,
+    progress: ImportProgress,
+    _thread: JoinHandle<()>,
+}
+
+impl ImportJob {
+    pub fn start(paths: Vec<PathBuf>, delete_original: bool) -> Self {
+        let (event_sender, event_receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let thread_cancelled = Arc::clone(&cancelled);
+
+        let thread = thread::spawn(move || {
+            let report = run_import(&paths, delete_original, &thread_cancelled, &event_sender);
+
+            let _ = event_sender.send(ImportEvent::Finished(report));
+        });
+
+        Self {
+            event_receiver,
+            cancelled,
+            progress: ImportProgress::default(),
+            _thread: thread,
+        }
+    }
+
+    /// Stops the import after the files currently being imported, the rest are reported as cancelled
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn progress(&self) -> ImportProgress {
+        self.progress
+    }
+
+    /// Handles every event received since the last poll, returns the report once the import has finished
+
+    pub fn poll(&mut self) -> Option<ImportReport> {
+        loop {
+            match self.event_receiver.try_recv() {
+                Ok(event) => {
+                    self.update_progress(&event);
+
+                    if let ImportEvent::Finished(report) = event {
+                        return Some(report);
+                    }
+                }
+
+                Err(TryRecvError::Empty) => return None,
+
+                Err(TryRecvError::Disconnected) => {
+                    eprintln!("Import thread stopped without finishing");
+                    return Some(ImportReport::default());
+                }
+            }
+        }
+    }
+
+    /// Blocks until the import has finished, `on_event` is called with every event and the progress
+    /// including that event
+
+    pub fn wait(mut self, mut on_event: impl FnMut(&ImportEvent, ImportProgress)) -> ImportReport {
+        while let Ok(event) = self.event_receiver.recv() {
+            self.update_progress(&event);
+
+            on_event(&event, self.progress);
+
+            if let ImportEvent::Finished(report) = event {
+                return report;
+            }
+        }
+
+        eprintln!("Import thread stopped without finishing");
+
+        ImportReport::default()
+    }
+
+    fn update_progress(&mut self, event: &ImportEvent) {
+        match event {
+            ImportEvent::FilesFound(files_found) => self.progress.files_found = Some(*files_found),
+            ImportEvent::Copied(_) => self.progress.copied += 1,
+            ImportEvent::Probed(_) => self.progress.probed += 1,
+            ImportEvent::Failed(_) => self.progress.failed += 1,
+            ImportEvent::Finished(_) => {}
+        }
+    }
+}
+
+fn run_import(
+    paths: &[PathBuf],
+    delete_original: bool,
+    cancelled: &AtomicBool,
+    event_sender: &Sender<ImportEvent>,
+) -> ImportReport {
+    let on_event = |event: ImportEvent| {
+        let _ = event_sender.send(event);
+    };
+
+    let (song_paths, failed) = collect_song_paths(paths, cancelled);
+
+    on_event(ImportEvent::FilesFound(song_paths.len()));
+
+    // Each CUE sheet imports several tracks from the same audio, so they are imported one at a time after
+    // the other files
+    let (cue_paths, song_paths) = song_paths
+        .into_iter()
+        .partition::<Vec<_>, _>(|song_path| is_cue_sheet(song_path));
+
+    let mut report = ImportReport { entries: failed };
+
+    if let Err(e) = create_song_dirs() {
+        report
+            .entries
+            .extend(song_paths.into_iter().chain(cue_paths).map(|song_path| {
+                on_event(ImportEvent::Failed(song_path.clone()));
+
+                let outcome = ImportOutcome::IoError(io::Error::new(e.kind(), e.to_string()));
+
+                (song_path, outcome)
+            }));
+
+        return report;
+    }
+
+    // Done once up front, otherwise every thread would try to hash the same songs
+    SONG_POOL.hash_unhashed_songs();
+
+    let outcomes = Mutex::new(
+        song_paths
+            .iter()
+            .map(|_| None)
+            .collect::<Vec<Option<ImportOutcome>>>(),
+    );
+
+    let record_outcome = |index: usize, outcome: ImportOutcome| {
+        if !outcome.is_imported() {
+            on_event(ImportEvent::Failed(song_paths[index].clone()));
+        }
+
+        unlock_mutex(&outcomes)[index] = Some(outcome);
+    };
+
+    let next_index = AtomicUsize::new(0);
+    let claimed_hashes = Mutex::new(HashSet::new());
+    let deferred = Mutex::new(Vec::new());
+
+    let thread_count = thread::available_parallelism()
+        .map(|parallelism| parallelism.get())
+        .unwrap_or(1)
+        .min(MAX_IMPORT_THREADS)
+        .min(song_paths.len())
+        .max(1);
+
+    thread::scope(|scope| {
+        for _ in 0..thread_count {
+            scope.spawn(|| {
+                let mut generator = SmallRngIdGenerator::default();
+
+                while !cancelled.load(Ordering::Relaxed) {
+                    let index = next_index.fetch_add(1, Ordering::Relaxed);
+
+                    let Some(song_path) = song_paths.get(index) else {
+                        break;
+                    };
+
+                    let outcome = match prepare_song_file(song_path) {
+                        Ok(prepared) => {
+                            if !unlock_mutex(&claimed_hashes).insert(prepared.content_hash) {
+                                // Another file in this import may have the same contents, import this
+                                // one after that file has been registered so it is found as a duplicate
+                                unlock_mutex(&deferred).push((index, prepared));
+                                continue;
+                            }
+
+                            import_prepared_song_file(
+                                song_path,
+                                prepared,
+                                delete_original,
+                                &mut generator,
+                                &on_event,
+                            )
+                        }
+
+                        Err(outcome) => outcome,
+                    };
+
+                    record_outcome(index, outcome);
+                }
+            });
+        }
+    });
+
+    let mut generator = SmallRngIdGenerator::default();
+
+    for (index, prepared) in deferred.into_inner().expect(POISONED_LOCK_MESSAGE) {
+        let outcome = if cancelled.load(Ordering::Relaxed) {
+            ImportOutcome::Cancelled
+        } else {
+            import_prepared_song_file(
+                &song_paths[index],
+                prepared,
+                delete_original,
+                &mut generator,
+                &on_event,
+            )
+        };
+
+        record_outcome(index, outcome);
+    }
+
+    let outcomes = outcomes.into_inner().expect(POISONED_LOCK_MESSAGE);
+
+    report.entries.extend(
+        song_paths
+            .into_iter()
+            .zip(outcomes)
+            .map(|(song_path, outcome)| (song_path, outcome.unwrap_or(ImportOutcome::Cancelled))),
+    );
+
+    for cue_path in cue_paths {
+        if cancelled.load(Ordering::Relaxed) {
+            report.entries.push((cue_path, ImportOutcome::Cancelled));
+            continue;
+        }
+
+        let track_outcomes =
+            import_cue_sheet(&cue_path, delete_original, &mut generator, &on_event);
+
+        report.entries.extend(
+            track_outcomes
+                .into_iter()
+                .map(|outcome| (cue_path.clone(), outcome)),
+        );
+    }
+
+    if let Err(e) = SONG_POOL.save_registered_songs() {
+        eprintln!("Unable to save registered songs after import; {}", e);
+    }
+
+    report
+}
@@ -1,8 +1,12 @@
 use crate::content::playlist::PlaybackMode;
+use crate::content::playlist::dsp::source::DspSource;
+use crate::content::playlist::dsp::{DspSettings, notify_dsp_changed};
+use crate::content::playlist::normalized_source::NormalizedSource;
 use crate::content::playlist::queue::Queue;
+use crate::content::playlist::song_source::{SongPosition, SongSource};
 use crate::content::song::Song;
+use crate::content::song::album_index::is_same_album;
 use crate::discord_rpc::{RPCAction, SetSongData, send_rpc_action};
-use crate::paths::song::song_audio_file_v2;
 use crate::{ReadGuard, WriteGuard, read_rwlock, write_rwlock};
 use derive_enum_all_values::AllValues;
 use rodio::cpal::traits::HostTrait;
@@ -17,14 +21,23 @@ use std::ops::{Deref, DerefMut};
 use std::sync::mpsc::Sender;
 use std::sync::{Arc, RwLock, mpsc};
 use std::thread::JoinHandle;
-use std::time::Duration;
-use std::{io, thread};
+use std::time::{Duration, Instant};
+use std::{io, mem, thread};
 
 static DEAD_MUSIC_THREAD_MESSAGE: &'static str =
     "Music thread should be dead, and this should be cleaned up";
 
 const LISTEN_TIME_COUNT_AS_INCREMENT: f32 = 0.75;
 
+/// How long before the end of a song the next one is appended to the sink, so it starts without a gap
+const PRELOAD_AHEAD: Duration = Duration::from_secs(5);
+/// How often the volume of a song fading out is changed during a crossfade
+const CROSSFADE_STEP: Duration = Duration::from_millis(20);
+
+/// How long songs fade into each other for, set from the client settings when the instance is created. Zero
+/// plays them one after the other without a gap
+static CROSSFADE_DURATION: RwLock<Duration> = RwLock::new(Duration::ZERO);
+
 pub(super) enum SwitchSongMusicCommand {
     Previous,
     Next,
@@ -44,6 +57,7 @@ pub(super) enum MusicCommand {
 pub struct SongStatus {
     pub(super) song: Arc<Song>,
     pub(super) total_duration: Option<Duration>,
+    pub(super) position: SongPosition,
 }
 
 impl SongStatus {
@@ -81,6 +95,22 @@ impl<T> DerefMut for DebugWrapper<T> {
     }
 }
 
+/// A song appended to the sink behind the one playing
+
+struct PreloadedSong {
+    song: Arc<Song>,
+    position: SongPosition,
+    total_duration: Option<Duration>,
+}
+
+/// The sink of the previous song while it fades out during a crossfade
+
+struct FadingSink {
+    sink: Sink,
+    started: Instant,
+    duration: Duration,
+}
+
 #[derive(AllValues, Debug, Copy, Clone)]
 pub enum LoopMode {
     None,
@@ -106,6 +136,8 @@ pub struct MusicManager {
     pub(super) queue: Arc<RwLock<Queue>>,
     song_status: Arc<RwLock<SongStatus>>,
     loop_mode: Cell<LoopMode>,
+    /// The dsp settings of the playlist playing, if it overrides the client's
+    dsp_override: Arc<RwLock<Option<DspSettings>>>,
 }
 
 impl MusicManager {
@@ -114,6 +146,7 @@ impl MusicManager {
         start_index: usize,
         mut playlist_volume: f32,
         playback_mode: PlaybackMode,
+        dsp_override: Option<DspSettings>,
     ) -> Option<Self> {
         // TODO: return result instead of option
         let songs = read_rwlock(&songs_arc);
@@ -129,11 +162,14 @@ impl MusicManager {
         let song_status = Arc::new(RwLock::new(SongStatus {
             song: Arc::clone(&queue.song_list[0]),
             total_duration: None,
+            position: SongPosition::default(),
         }));
         let song_status_thread = Arc::clone(&song_status);
 
         let (sink, output_stream) = create_sink();
 
+        sink.set_volume(playlist_volume);
+
         let stream = RwLock::new(output_stream);
 
         let sink = Arc::new(RwLock::new(sink));
@@ -142,12 +178,16 @@ impl MusicManager {
         let queue = Arc::new(RwLock::new(queue));
         let queue_thread = Arc::clone(&queue);
 
+        let dsp_override = Arc::new(RwLock::new(dsp_override));
+        let dsp_override_thread = Arc::clone(&dsp_override);
+
         let playing_handle = thread::Builder::new()
             .name("Music Manager".to_string())
             .spawn(move || {
                 let sink_arc = sink_thread;
                 let queue = queue_thread;
                 let song_status = song_status_thread;
+                let dsp_override = dsp_override_thread;
                 // let songs = songs_thread;
 
                 let mut audio_device_in_use = cpal::default_host().default_output_device();
@@ -156,6 +196,10 @@ impl MusicManager {
                 let mut is_playing = true;
 
                 let mut last_song: Option<Arc<Song>> = None;
+                // Appended to the sink behind the last song so it starts without a gap, it is taken from
+                // the queue once it starts playing
+                let mut preloaded_song: Option<PreloadedSong> = None;
+                let mut fading_sink: Option<FadingSink> = None;
                 let mut loop_mode = LoopMode::None;
 
                 loop {
@@ -165,23 +209,26 @@ impl MusicManager {
                         audio_device_in_use = cpal::default_host().default_output_device();
 
                         let mut sink = write_rwlock(&sink_arc);
-                        let song_pos = sink.get_pos();
+                        let song = Arc::clone(&read_rwlock(&song_status).song);
+                        let song_pos = read_rwlock(&song_status).position.get();
 
                         let (new_sink, new_stream) = create_sink();
 
-                        let song = &read_rwlock(&song_status).song;
-
-                        new_sink.set_volume(
-                            playlist_volume * song.get_song_data().inner.custom_volume.inner,
-                        );
+                        new_sink.set_volume(playlist_volume);
 
                         *sink = new_sink;
 
+                        // Both were playing on the previous device
+                        fading_sink = None;
+                        preloaded_song = None;
+
                         *write_rwlock(&stream) = new_stream;
 
-                        if let Ok(source) = get_decoder_for_song(&song) {
+                        if let Ok((source, position)) = get_source_for_song(&song, &dsp_override) {
                             sink.append(source);
                             sink.try_seek(song_pos).ok();
+
+                            write_rwlock(&song_status).position = position;
                         }
 
                         audio_device_changed = false;
@@ -201,6 +248,7 @@ impl MusicManager {
                             MusicCommand::Pause => {
                                 is_playing = false;
                                 sink.pause();
+                                fading_sink = None;
                                 send_rpc_action(RPCAction::StopMusic);
                             }
 
@@ -212,10 +260,11 @@ impl MusicManager {
 
                             MusicCommand::SwitchSong(switch_song_command) => {
                                 let mut queue = write_rwlock(&queue);
+                                let song_pos = read_rwlock(&song_status).position.get();
 
                                 match switch_song_command {
                                     SwitchSongMusicCommand::Previous => {
-                                        if sink.get_pos().as_secs() > 3 {
+                                        if song_pos.as_secs() > 3 {
                                             queue.restart_song();
                                         } else {
                                             queue.previous();
@@ -224,29 +273,22 @@ impl MusicManager {
 
                                     SwitchSongMusicCommand::Next => {
                                         // Queue has already incremented, do nothing
-                                        switched_song_pos = Some(sink.get_pos());
+                                        switched_song_pos = Some(song_pos);
                                     }
 
                                     SwitchSongMusicCommand::SkipToQueueIndex(index) => {
                                         queue.set_index_from_queue(index);
-                                        switched_song_pos = Some(sink.get_pos());
+                                        switched_song_pos = Some(song_pos);
                                     }
                                 }
 
+                                fading_sink = None;
                                 sink.clear();
                             }
 
                             MusicCommand::SetVolume(volume) => {
                                 playlist_volume = volume;
-                                sink.set_volume(
-                                    playlist_volume
-                                        * read_rwlock(&song_status)
-                                            .song
-                                            .get_song_data()
-                                            .inner
-                                            .custom_volume
-                                            .inner,
-                                );
+                                sink.set_volume(playlist_volume);
                             }
 
                             MusicCommand::SetLoopMode(lm) => {
@@ -255,21 +297,12 @@ impl MusicManager {
                         }
                     }
 
-                    if let Some(end_time) = read_rwlock(&song_status)
-                        .song
-                        .get_song_data()
-                        .inner
-                        .end_time
-                        .inner
-                    {
-                        let pos = sink.get_pos();
-
-                        if pos >= end_time {
-                            sink.clear();
-                        }
-                    }
+                    let mut start_crossfade = false;
 
                     if sink.empty() {
+                        // A preloaded song is cleared along with the sink
+                        preloaded_song = None;
+
                         if let Some(ls) = &last_song {
                             let should_increment = if let Some(pos) = switched_song_pos {
                                 let song_status = read_rwlock(&song_status);
@@ -285,86 +318,154 @@ impl MusicManager {
                                 true
                             };
 
-                            let mut ls_song_data = ls.get_song_data_mut();
-                            let ls_song_data_inner = &mut ls_song_data.inner;
+                            count_song_played(ls, should_increment);
+                        }
 
-                            if should_increment {
-                                ls_song_data_inner.times_listened += 1;
-                            } else {
-                                ls_song_data_inner.times_skipped.inner += 1;
-                            }
+                        let Some(song) =
+                            take_next_song(&mut write_rwlock(&queue), loop_mode, &last_song)
+                        else {
+                            // Entire queue is probably 0, some bug occurred, don't need to constantly loop clearing the queue. Just kill the thread
+                            break;
+                        };
 
-                            ls.save_song_data_already_borrowed(&ls_song_data);
-                        }
+                        start_song(&sink, &song_status, &song, &dsp_override);
 
-                        let mut queue_mut = write_rwlock(&queue);
-                        let next_song = match loop_mode {
-                            LoopMode::None => queue_mut.get_next_song(),
+                        last_song = Some(song);
+                    } else if sink.len() == 1 && preloaded_song.is_some() {
+                        let preloaded = preloaded_song.take().expect("Checked is some above");
 
-                            LoopMode::Single => {
-                                // last song should always be some, only way it isnt is if somehow the loop mode is set before the first song is ever played
-                                match last_song {
-                                    Some(ls) => Some(ls),
+                        // The last song ended and the preloaded song started playing
+                        if let Some(ls) = &last_song {
+                            count_song_played(ls, true);
+                        }
 
-                                    None => queue_mut.get_next_song(),
-                                }
-                            }
+                        let Some(song) =
+                            take_next_song(&mut write_rwlock(&queue), loop_mode, &last_song)
+                        else {
+                            break;
                         };
 
-                        let song = if let Some(song) = next_song {
-                            song
+                        if song == preloaded.song {
+                            set_playing_song(
+                                &song_status,
+                                &song,
+                                preloaded.position,
+                                preloaded.total_duration,
+                            );
                         } else {
-                            queue_mut.reset_queue();
+                            // The queue changed after the song was preloaded, the song queued since is
+                            // played instead
+                            sink.clear();
+                            start_song(&sink, &song_status, &song, &dsp_override);
+                        }
 
-                            if let Some(song) = queue_mut.get_next_song() {
-                                song
-                            } else {
-                                // Entire queue is probably 0, some bug occurred, don't need to constantly loop clearing the queue. Just kill the thread
-                                break;
+                        last_song = Some(song);
+                    } else if is_playing && preloaded_song.is_none() && sink.len() == 1 {
+                        let song_status = read_rwlock(&song_status);
+
+                        // The total duration already ends at the end time of the song
+                        let remaining = song_status
+                            .total_duration
+                            .map(|end| end.saturating_sub(song_status.position.get()));
+
+                        let next_song = peek_next_song(&read_rwlock(&queue), loop_mode, &last_song);
+
+                        if let (Some(remaining), Some(next_song)) = (remaining, next_song) {
+                            let crossfade = crossfade_duration();
+
+                            // Consecutive tracks of an album are always gapless, they are often
+                            // meant to flow into each other
+                            let should_crossfade = !crossfade.is_zero()
+                                && next_song != song_status.song
+                                && !is_same_album(&song_status.song, &next_song);
+
+                            if should_crossfade {
+                                if remaining <= crossfade {
+                                    start_crossfade = true;
+                                }
+                            } else if remaining <= PRELOAD_AHEAD {
+                                match get_source_for_song(&next_song, &dsp_override) {
+                                    Ok((source, position)) => {
+                                        let total_duration = source.total_duration();
+
+                                        sink.append(source);
+
+                                        preloaded_song = Some(PreloadedSong {
+                                            song: next_song,
+                                            position,
+                                            total_duration,
+                                        });
+                                    }
+
+                                    Err(_) => {
+                                        println!(
+                                            "Invalid or corrupted audio file detected, skipping"
+                                        );
+
+                                        take_next_song(
+                                            &mut write_rwlock(&queue),
+                                            loop_mode,
+                                            &last_song,
+                                        );
+                                    }
+                                }
                             }
-                        };
+                        }
+                    }
 
-                        // Skip if invalid file
-                        if let Ok(source) = get_decoder_for_song(&song) {
-                            let mut song_status = write_rwlock(&song_status);
+                    drop(sink);
 
-                            song_status.song = Arc::clone(&song);
+                    if start_crossfade {
+                        let Some(song) =
+                            take_next_song(&mut write_rwlock(&queue), loop_mode, &last_song)
+                        else {
+                            break;
+                        };
 
-                            let total_song_duration =
-                                if let Some(total_duration) = source.total_duration() {
-                                    Some(total_duration)
-                                } else {
-                                    None
-                                };
+                        match get_source_for_song(&song, &dsp_override) {
+                            Ok((source, position)) => {
+                                let crossfade = crossfade_duration();
+                                let total_duration = source.total_duration();
 
-                            song_status.total_duration = total_song_duration;
+                                let new_sink = Sink::connect_new(&read_rwlock(&stream).mixer());
 
-                            let song_data = &song.get_song_data().inner;
+                                new_sink.set_volume(playlist_volume);
+                                new_sink.append(source.fade_in(crossfade));
 
-                            send_rpc_action(RPCAction::SetSong(SetSongData {
-                                song_title: song_data.title.clone(),
-                                song_artist: song_data.meta.inner.artist.unwrapped_ref().clone(),
-                                song_duration: total_song_duration,
-                            }));
+                                let previous_sink =
+                                    mem::replace(&mut *write_rwlock(&sink_arc), new_sink);
 
-                            sink.append(source);
+                                fading_sink = Some(FadingSink {
+                                    sink: previous_sink,
+                                    started: Instant::now(),
+                                    duration: crossfade,
+                                });
 
-                            sink.set_volume(playlist_volume * song_data.custom_volume.inner);
+                                if let Some(ls) = &last_song {
+                                    count_song_played(ls, true);
+                                }
 
-                            sink.play();
+                                set_playing_song(&song_status, &song, position, total_duration);
 
-                            if let Some(start_offset) = song_data.start_offset.inner {
-                                sink.try_seek(start_offset)
-                                    .expect("Unable to seek to start offset");
+                                last_song = Some(song);
                             }
-                        } else {
-                            println!("Invalid or corrupted audio file detected, skipping")
+
+                            // The previous song keeps playing, the next one is tried on the next tick
+                            Err(e) => eprintln!(
+                                "Invalid or corrupted audio file detected, skipping; {}",
+                                e
+                            ),
                         }
+                    }
 
-                        last_song = Some(song);
+                    if let Some(fading) = &fading_sink {
+                        let progress =
+                            fading.started.elapsed().as_secs_f32() / fading.duration.as_secs_f32();
 
-                        if matches!(loop_mode, LoopMode::None) {
-                            queue_mut.next();
+                        if progress >= 1.0 || fading.sink.empty() {
+                            fading_sink = None;
+                        } else {
+                            fading.sink.set_volume(playlist_volume * (1.0 - progress));
                         }
                     }
 
@@ -385,7 +486,12 @@ impl MusicManager {
                         }
                     }
 
-                    thread::sleep(Duration::from_millis(100))
+                    // The volume of a fading song is stepped more often so the fade is smooth
+                    if fading_sink.is_some() {
+                        thread::sleep(CROSSFADE_STEP)
+                    } else {
+                        thread::sleep(Duration::from_millis(100))
+                    }
                 }
 
                 // End of music thread... cleanup
@@ -401,6 +507,7 @@ impl MusicManager {
             queue,
             song_status,
             loop_mode: Cell::new(LoopMode::None),
+            dsp_override,
         })
     }
 
@@ -416,10 +523,18 @@ impl MusicManager {
         self.send_command(MusicCommand::SetVolume(volume));
     }
 
+    /// Changes the dsp settings the playlist overrides the client's with, including for the song playing now
+
+    pub(super) fn set_dsp_override(&self, dsp_override: Option<DspSettings>) {
+        *write_rwlock(&self.dsp_override) = dsp_override;
+
+        notify_dsp_changed();
+    }
+
     /// Gets the current playhead position in the song.
 
     pub fn get_song_pos(&self) -> Duration {
-        self.get_sink().get_pos()
+        read_rwlock(&self.song_status).position.get()
     }
 
     pub fn try_seek(&self, pos: Duration) -> Result<(), SeekError> {
@@ -505,9 +620,159 @@ fn create_sink() -> (Sink, OutputStream) {
     (sink, output_stream)
 }
 
+/// How long songs fade into each other for, zero if they play without a gap
+
+pub fn crossfade_duration() -> Duration {
+    *read_rwlock(&CROSSFADE_DURATION)
+}
+
+/// Changes how long songs fade into each other for, from the next song played. Consecutive tracks of an
+/// album never crossfade
+
+pub fn set_crossfade_duration(crossfade_duration: Duration) {
+    *write_rwlock(&CROSSFADE_DURATION) = crossfade_duration;
+}
+
+/// Adds a listen, or a skip if it wasn't listened to for long enough, to the song which stopped playing
+
+fn count_song_played(song: &Song, listened: bool) {
+    let mut song_data = song.get_song_data_mut();
+    let song_data_inner = &mut song_data.inner;
+
+    if listened {
+        song_data_inner.times_listened += 1;
+    } else {
+        song_data_inner.times_skipped.inner += 1;
+    }
+
+    song.save_song_data_already_borrowed(&song_data);
+}
+
+/// The song [`take_next_song`] would return, without changing the queue
+
+fn peek_next_song(
+    queue: &Queue,
+    loop_mode: LoopMode,
+    last_song: &Option<Arc<Song>>,
+) -> Option<Arc<Song>> {
+    match (loop_mode, last_song) {
+        (LoopMode::Single, Some(last_song)) => Some(Arc::clone(last_song)),
+        _ => queue.peek_next_song().cloned(),
+    }
+}
+
+/// Takes the song to play after `last_song` from the queue, starting the queue over once it has run out.
+/// Returns `None` if the queue is empty
+
+fn take_next_song(
+    queue: &mut Queue,
+    loop_mode: LoopMode,
+    last_song: &Option<Arc<Song>>,
+) -> Option<Arc<Song>> {
+    let next_song = match loop_mode {
+        LoopMode::None => queue.get_next_song(),
+
+        LoopMode::Single => {
+            // last song should always be some, only way it isnt is if somehow the loop mode is set before the first song is ever played
+            match last_song {
+                Some(ls) => Some(Arc::clone(ls)),
+
+                None => queue.get_next_song(),
+            }
+        }
+    };
+
+    let song = if let Some(song) = next_song {
+        song
+    } else {
+        queue.reset_queue();
+
+        queue.get_next_song()?
+    };
+
+    if matches!(loop_mode, LoopMode::None) {
+        queue.next();
+    }
+
+    Some(song)
+}
+
+/// Plays `song` straight away on `sink`, which should be empty
+
+fn start_song(
+    sink: &Sink,
+    song_status: &RwLock<SongStatus>,
+    song: &Arc<Song>,
+    dsp_override: &Arc<RwLock<Option<DspSettings>>>,
+) {
+    // Skip if invalid file
+    let Ok((source, position)) = get_source_for_song(song, dsp_override) else {
+        println!("Invalid or corrupted audio file detected, skipping");
+        return;
+    };
+
+    set_playing_song(song_status, song, position, source.total_duration());
+
+    sink.append(source);
+
+    sink.play();
+}
+
+/// Shows `song` as the song playing, once its audio has started
+
+fn set_playing_song(
+    song_status: &RwLock<SongStatus>,
+    song: &Arc<Song>,
+    position: SongPosition,
+    total_duration: Option<Duration>,
+) {
+    let mut song_status = write_rwlock(song_status);
+
+    song_status.song = Arc::clone(song);
+    song_status.total_duration = total_duration;
+    song_status.position = position;
+
+    let song_data = &song.get_song_data().inner;
+
+    send_rpc_action(RPCAction::SetSong(SetSongData {
+        song_title: song_data.title.clone(),
+        song_artist: song_data.meta.inner.artist.unwrapped_ref().clone(),
+        song_duration: total_duration,
+    }));
+}
+
+/// The decoded song between its start offset and end time, processed with the dsp settings and played with
+/// its custom volume and the gain of the current normalization mode. Also returns the position of the song as
+/// it plays
+
+fn get_source_for_song(
+    song: &Song,
+    dsp_override: &Arc<RwLock<Option<DspSettings>>>,
+) -> io::Result<(
+    NormalizedSource<DspSource<SongSource<Decoder<Cursor<Vec<u8>>>>>>,
+    SongPosition,
+)> {
+    let decoder = get_decoder_for_song(song)?;
+
+    let (start_offset, end_time) = {
+        let song_data = song.get_song_data();
+
+        (
+            song_data.inner.start_offset.inner,
+            song_data.inner.end_time.inner,
+        )
+    };
+
+    let song_source = SongSource::new(decoder, start_offset, end_time);
+    let position = song_source.position();
+
+    let dsp_source = DspSource::new(song_source, Arc::clone(dsp_override));
+
+    Ok((NormalizedSource::new(dsp_source, song), position))
+}
+
 fn get_decoder_for_song(song: &Song) -> io::Result<Decoder<Cursor<Vec<u8>>>> {
-    let mut file = File::open(song_audio_file_v2(&song.id))
-        .expect(&format!("Unable to open song file for: {:?}", song.id));
+    let mut file = File::open(song.song_audio_path())?;
 
     let mut buf = Vec::new();
     file.read_to_end(&mut buf)?;
@@ -518,6 +783,7 @@ fn get_decoder_for_song(song: &Song) -> io::Result<Decoder<Cursor<Vec<u8>>>> {
         .with_data(Cursor::new(buf))
         .with_byte_len(buf_len)
         .with_seekable(true)
+        .with_hint(song.audio_container().extension())
         .build()
         .map_err(|_| ErrorKind::InvalidData.into())
 }
@@ -1,41 +1,48 @@
+pub mod cue_sheet;
 pub mod data;
+pub mod dsp;
+pub mod import;
+pub mod import_job;
 pub mod manager;
+mod normalized_source;
+pub mod playlist_file;
 pub mod playlists;
 pub mod queue;
 pub mod song_list;
+mod song_source;
 
 use crate::content::folder::Folder;
 use crate::content::folder::content_pool::CONTENT_POOL;
+use crate::content::playlist::album_playlist::AlbumPlaylist;
 use crate::content::playlist::all_songs_playlist::AllSongsPlaylist;
 use crate::content::playlist::data::{
     PlaybackMode, PlaylistContentData, PlaylistSongListData, PlaylistUserData,
 };
+use crate::content::playlist::dsp::DspSettings;
 use crate::content::playlist::dynamic_playlist_data::DynamicPlaylistData;
+use crate::content::playlist::import::ImportReport;
+use crate::content::playlist::import_job::ImportJob;
 use crate::content::playlist::manager::MusicManager;
 use crate::content::playlist::song_list::{SongVec, SortBy};
 use crate::content::song::Song;
 use crate::content::song::song_data::SongData;
-use crate::content::song::song_pool::SONG_POOL;
 use crate::content::{SaveData, unwrap_inner_ref, unwrap_inner_ref_mut};
-use crate::paths::SONG_DATA_EXT_NO_PER;
-use crate::paths::song::{song_audio_file_v2, songs_audio_dir_v2, songs_data_dir_v2};
 use crate::{read_rwlock, time_now, write_rwlock};
 pub use playlists::*;
 use serbytes::prelude::SerBytes;
-use simple_id::prelude::{Id, SmallRngIdGenerator};
+use simple_id::prelude::Id;
 use std::cell::{Ref, RefMut};
 use std::collections::HashSet;
-use std::fs::File;
+use std::io;
 use std::ops::{Deref, RangeInclusive};
 use std::path::PathBuf;
 use std::rc::Weak;
 use std::sync::Arc;
-use std::{fs, io};
 
-pub type PlaylistTypeVariant = PlaylistType<(), (), ()>;
+pub type PlaylistTypeVariant = PlaylistType<(), (), (), ()>;
 
 pub type PlaylistDataTypeVariant =
-    PlaylistType<PlaylistUserData, DynamicPlaylistData, PlaylistUserData>;
+    PlaylistType<PlaylistUserData, DynamicPlaylistData, PlaylistUserData, PlaylistUserData>;
 
 pub trait ClearSongsCache {
     fn clear_songs_cache(&self);
@@ -194,6 +201,7 @@ pub trait Playlist {
             actual_index,
             playlist_data.volume,
             playlist_data.playback_mode,
+            playlist_data.dsp_override.inner.clone(),
         );
 
         inner.music_manager.replace(music_manager);
@@ -227,6 +235,22 @@ pub trait Playlist {
         self.get_user_data().inner.volume
     }
 
+    /// Overrides the client's dsp settings while this playlist plays, `None` goes back to the client's
+
+    fn set_dsp_override(&self, dsp_override: Option<DspSettings>) -> io::Result<()> {
+        if let Some(manager) = &*self.get_music_manager() {
+            manager.set_dsp_override(dsp_override.clone());
+        }
+
+        self.get_user_data_mut().inner.dsp_override.inner = dsp_override;
+
+        self.save_user_data()
+    }
+
+    fn get_dsp_override(&self) -> Option<DspSettings> {
+        self.get_user_data().inner.dsp_override.inner.clone()
+    }
+
     fn delete_song(&self, song_index: usize) {
         delete_song_default(self, song_index);
     }
@@ -246,16 +270,23 @@ pub trait Playlist {
         string_set.into_iter().collect()
     }
 
+    /// Every individual artist credited on the songs of this playlist, see
+    /// [`crate::content::song::song_data::artist::Artist::credits`]
+
     fn get_artist_list(&self) -> Vec<String> {
-        self.get_string_list(&|song_data| {
-            &song_data
-                .inner
-                .meta
-                .inner
-                .artist
-                .unwrapped_ref()
-                .full_artist_string
-        })
+        let mut artist_set = HashSet::new();
+
+        for song in read_rwlock(&self.get_song_vec()).iter() {
+            let song_data = song.get_song_data();
+
+            for credit in song_data.inner.meta.inner.artist.unwrapped_ref().credits() {
+                if !artist_set.contains(credit.name) {
+                    artist_set.insert(credit.name.to_string());
+                }
+            }
+        }
+
+        artist_set.into_iter().collect()
     }
 
     fn get_album_list(&self) -> Vec<String> {
@@ -278,6 +309,29 @@ pub trait Playlist {
         }
 
         self.sort_songs(self.get_user_data().inner.sort_by);
+
+        self.save_song_list();
+    }
+
+    /// Removes every song in `song_ids` from this playlist and saves the song list
+
+    fn remove_songs(&self, song_ids: &HashSet<Id>) {
+        let inner = self.get_inner();
+
+        // Makes sure the songs are loaded before removing from them
+        self.get_song_vec_unfiltered();
+
+        inner
+            .songs
+            .borrow_mut()
+            .retain_songs(|song| !song_ids.contains(&song.id));
+
+        inner.total_length.borrow_mut().take();
+
+        self.save_song_list();
+
+        let search_str = inner.current_search_str.borrow().clone();
+        self.set_search_query_filter(&search_str);
     }
 
     fn get_selected_songs(&self) -> SelectedSongsVariant {
@@ -306,88 +360,21 @@ pub trait Playlist {
             })
     }
 
-    fn import_songs(
-        &self,
-        song_paths: &[PathBuf],
-        delete_original: bool,
-    ) -> Result<(), Vec<usize>> {
-        let mut already_exists = Vec::new();
-        {
-            let mut songs = self.get_inner().songs.borrow_mut();
-
-            songs.reserve(song_paths.len());
-
-            let dirs_to_create = [songs_audio_dir_v2(), songs_data_dir_v2()];
-
-            for dir in dirs_to_create {
-                if !fs::exists(&dir).expect("Verified existence of song directory") {
-                    fs::create_dir_all(dir).expect("Directories created");
-                }
-            }
-
-            let mut generator = SmallRngIdGenerator::default();
-
-            for (i, original_song_path) in song_paths.iter().enumerate() {
-                if original_song_path.extension().unwrap() == SONG_DATA_EXT_NO_PER {
-                    continue;
-                }
-
-                let mut original_song_path1 = original_song_path.clone();
-
-                original_song_path1.set_extension("");
-
-                let original_song_file_name = original_song_path1
-                    .file_name()
-                    .expect("Valid filename")
-                    .to_str()
-                    .expect("Valid osstr")
-                    .to_string();
-
-                let song_id = generator.generate_new_id();
-
-                let new_song_audio_path = song_audio_file_v2(&song_id);
-
-                // TODO: handle if new song location already exists, also just handling all the errors here properly. esp invalid format
+    /// Imports files and directories into this playlist, blocking until finished. Use [`ImportJob`]
+    /// directly to import in the background
 
-                if fs::exists(&new_song_audio_path).expect(&format!(
-                    "Unable to verify new song path does not exist at path: {:?}",
-                    new_song_audio_path
-                )) {
-                    already_exists.push(i);
-                } else {
-                    File::create(&new_song_audio_path).expect(&format!(
-                        "Unable to create new song file to copy to; path: {:?}",
-                        new_song_audio_path
-                    ));
+    fn import_songs(&self, song_paths: &[PathBuf], delete_original: bool) -> ImportReport {
+        let report = ImportJob::start(song_paths.to_vec(), delete_original).wait(|_, _| {});
 
-                    fs::copy(original_song_path, &new_song_audio_path)
-                        .expect("Failed copy song to dest");
+        self.import_existing_songs(&report.imported_songs());
 
-                    if delete_original {
-                        fs::remove_file(original_song_path)
-                            .expect("Failed to remove original file");
-                    }
-                }
-
-                songs
-                    .push_new_song(song_id, &original_song_file_name)
-                    .expect("Push new song");
-            }
-        }
-
-        SONG_POOL
-            .save_registered_songs()
-            .expect("save registered songs");
-
-        self.sort_songs(self.get_user_data().inner.sort_by);
-
-        if !already_exists.is_empty() {
-            println!("Imported songs and saved successfully, but some failed to import");
-            Err(already_exists)
-        } else {
+        if report.is_all_imported() {
             println!("Imported songs and saved successfully");
-            Ok(())
+        } else {
+            println!("Imported songs and saved successfully, but some failed to import");
         }
+
+        report
     }
 
     fn set_search_query_filter(&self, search_str: &str) {
@@ -487,6 +474,30 @@ pub trait Playlist {
     fn sort_songs(&self, sort_by: SortBy) {
         self.get_inner().songs.borrow_mut().sort_songs(sort_by);
     }
+
+    /// Drops the loaded songs and song list without saving them
+
+    fn clear_loaded_songs(&self) {
+        self.get_inner().clear_songs_cache();
+    }
+
+    /// Drops the loaded songs so they are read from the song list again the next time they are needed.
+    /// Does nothing if the songs were never loaded
+
+    fn reload_songs(&self) {
+        let inner = self.get_inner();
+
+        if !inner.has_loaded_songs.get() && inner.playlist_song_list_data.borrow().is_none() {
+            return;
+        }
+
+        self.clear_loaded_songs();
+
+        self.set_selected_songs(SelectedSongsVariant::None);
+
+        let search_str = inner.current_search_str.borrow().clone();
+        self.set_search_query_filter(&search_str);
+    }
 }
 
 fn delete_song_default<P>(playlist: &P, song_index: usize)
@@ -551,10 +562,17 @@ fn default_save_user_data(playlist_user_data: &PlaylistUserData, id: Id) -> io::
 }
 
 #[derive(SerBytes, Debug, Eq, PartialEq, Copy, Clone)]
-pub enum PlaylistType<S = StandardPlaylist, D = DynamicPlaylist, A = AllSongsPlaylist> {
+pub enum PlaylistType<
+    S = StandardPlaylist,
+    D = DynamicPlaylist,
+    A = AllSongsPlaylist,
+    L = AlbumPlaylist,
+> {
     Standard(S),
     Dynamic(D),
     AllSongs(A),
+    /// Only exists in memory while an album is played, so is never part of a folder
+    Album(L),
 }
 
 impl PartialEq for dyn Playlist {
@@ -573,6 +591,7 @@ impl Deref for PlaylistType {
             Self::Standard(standard_playlist) => standard_playlist,
             Self::Dynamic(dynamic_playlist) => dynamic_playlist,
             Self::AllSongs(all_songs_playlist) => all_songs_playlist,
+            Self::Album(album_playlist) => album_playlist,
         }
     }
 }
@@ -0,0 +1,91 @@
+use crate::content::song::Song;
+use crate::content::song::loudness::{db_to_amplitude, normalization_mode};
+use rodio::source::SeekError;
+use rodio::{ChannelCount, Sample, SampleRate, Source};
+use std::time::Duration;
+
+/// Samples are kept below this once normalized, leaving a little headroom for the output's resampling
+const LIMITER_THRESHOLD: f32 = 0.98;
+/// How long the limiter takes to stop reducing the gain after a peak, in seconds
+const LIMITER_RELEASE_SECONDS: f32 = 0.1;
+
+/// Plays a song with its custom volume and the gain of the current normalization mode. Peaks which the gain
+/// would push past full scale are limited instead of clipping
+
+pub(super) struct NormalizedSource<S> {
+    source: S,
+    gain: f32,
+    /// The highest recent peak, falling back towards 0 over the release time
+    envelope: f32,
+    release_coefficient: f32,
+}
+
+impl<S: Source> NormalizedSource<S> {
+    pub(super) fn new(source: S, song: &Song) -> Self {
+        let song_data = song.get_song_data();
+
+        let gain_db = song_data
+            .inner
+            .loudness
+            .inner
+            .map_or(0.0, |loudness| loudness.gain_db(normalization_mode()));
+
+        let custom_volume = song_data.inner.custom_volume.inner;
+
+        drop(song_data);
+
+        let samples_per_second = source.sample_rate() as f32 * source.channels() as f32;
+
+        Self {
+            source,
+            gain: db_to_amplitude(gain_db) * custom_volume,
+            envelope: 0.0,
+            release_coefficient: (-1.0 / (LIMITER_RELEASE_SECONDS * samples_per_second)).exp(),
+        }
+    }
+}
+
+impl<S: Source> Iterator for NormalizedSource<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Limited even without any gain, as the equalizer may have boosted the song past full scale
+        let sample = self.source.next()? * self.gain;
+
+        self.envelope = sample.abs().max(self.envelope * self.release_coefficient);
+
+        if self.envelope > LIMITER_THRESHOLD {
+            Some(sample * LIMITER_THRESHOLD / self.envelope)
+        } else {
+            Some(sample)
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.source.size_hint()
+    }
+}
+
+impl<S: Source> Source for NormalizedSource<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.source.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.source.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.envelope = 0.0;
+
+        self.source.try_seek(pos)
+    }
+}
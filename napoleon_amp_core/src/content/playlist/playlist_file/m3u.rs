@@ -0,0 +1,103 @@
+//! Extended M3U playlists. Files are always written as utf-8, as an M3U8 is, and read as utf-8 with a
+//! latin-1 fallback for older M3U files.
+
+use crate::content::folder::Folder;
+use crate::content::playlist::PlaylistType;
+use crate::content::playlist::playlist_file::{
+    ExportedAudioPaths, PlaylistFileEntries, PlaylistFileImportJob, decode_playlist_text,
+    exported_songs, import_playlist_file_entries, playlist_dir, playlist_name_from_path,
+    resolve_location, single_line, write_playlist_file,
+};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+use std::{fs, io};
+
+pub const M3U8_EXT: &str = "m3u8";
+pub const M3U_EXT: &str = "m3u";
+
+const HEADER: &str = "#EXTM3U";
+const PLAYLIST_DIRECTIVE: &str = "#PLAYLIST:";
+const EXTINF_DIRECTIVE: &str = "#EXTINF:";
+
+/// Writes every song in `playlist` to an extended M3U file at `m3u_path`, with the length, artist and
+/// title of each song
+
+pub fn export_m3u(
+    playlist: &PlaylistType,
+    m3u_path: &Path,
+    audio_paths: ExportedAudioPaths,
+) -> io::Result<()> {
+    let exported_songs = exported_songs(playlist, m3u_path, audio_paths)?;
+
+    write_playlist_file(m3u_path, |writer| {
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(
+            writer,
+            "{}{}",
+            PLAYLIST_DIRECTIVE,
+            single_line(&playlist.get_user_data().inner.content_data.name)
+        )?;
+
+        for exported_song in exported_songs {
+            writeln!(
+                writer,
+                "{}{},{} - {}",
+                EXTINF_DIRECTIVE,
+                exported_song
+                    .length_secs
+                    .map_or(-1, |length_secs| length_secs as i64),
+                single_line(&exported_song.artist),
+                single_line(&exported_song.title)
+            )?;
+            writeln!(writer, "{}", exported_song.path.display())?;
+        }
+
+        Ok(())
+    })
+}
+
+/// Creates a new standard playlist in `folder` from the M3U file at `m3u_path`, importing each file it
+/// lists in the background unless it is already in the library
+
+pub fn import_m3u(folder: &Rc<Folder>, m3u_path: &Path) -> io::Result<PlaylistFileImportJob> {
+    import_playlist_file_entries(folder, read_m3u(m3u_path)?)
+}
+
+fn read_m3u(m3u_path: &Path) -> io::Result<PlaylistFileEntries> {
+    let m3u_text = decode_playlist_text(&fs::read(m3u_path)?);
+    let base_dir = playlist_dir(m3u_path);
+
+    let mut name = None;
+    let mut paths = Vec::new();
+    let mut skipped = Vec::new();
+
+    for line in m3u_text.lines() {
+        let line = line.trim();
+
+        if let Some(playlist_name) = line.strip_prefix(PLAYLIST_DIRECTIVE) {
+            name = Some(playlist_name.trim().to_string());
+            continue;
+        }
+
+        // Any other directive, or a comment
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match resolve_location(base_dir, line) {
+            Some(path) => paths.push(path),
+            None => skipped.push(line.to_string()),
+        }
+    }
+
+    Ok(PlaylistFileEntries {
+        name: name
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| playlist_name_from_path(m3u_path)),
+        paths,
+        skipped,
+        track_extensions: HashMap::new(),
+    })
+}
@@ -0,0 +1,506 @@
+//! Exports playlists to, and imports playlists from, the playlist files used by other players.
+//!
+//! Every format lists the songs by the location of their audio. Exported playlists either point at the
+//! audio stored in the library, or at copies of it placed next to the playlist file. Imported playlists
+//! become standard playlists, each file they reference is imported or matched to a song already in the
+//! library.
+
+pub mod m3u;
+pub mod pls;
+pub mod xspf;
+
+use crate::content::folder::Folder;
+use crate::content::playlist::PlaylistType;
+use crate::content::playlist::import::{ImportEvent, ImportOutcome, ImportReport};
+use crate::content::playlist::import_job::{ImportJob, ImportProgress};
+use crate::content::playlist::playlist_file::xspf::{TrackExtension, apply_track_extensions};
+use crate::read_rwlock;
+use crate::safe_write::write_atomically;
+use derive_enum_all_values::AllValues;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::{fs, io, mem};
+
+/// The extensions of every playlist file which can be imported
+pub const PLAYLIST_FILE_EXTS: [&str; 4] =
+    [m3u::M3U8_EXT, m3u::M3U_EXT, xspf::XSPF_EXT, pls::PLS_EXT];
+
+#[derive(AllValues, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PlaylistFileFormat {
+    M3u8,
+    Xspf,
+    Pls,
+}
+
+impl PlaylistFileFormat {
+    /// The extension files of this format are exported with
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::M3u8 => m3u::M3U8_EXT,
+            Self::Xspf => xspf::XSPF_EXT,
+            Self::Pls => pls::PLS_EXT,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        match extension.as_str() {
+            m3u::M3U8_EXT | m3u::M3U_EXT => Some(Self::M3u8),
+            xspf::XSPF_EXT => Some(Self::Xspf),
+            pls::PLS_EXT => Some(Self::Pls),
+            _ => None,
+        }
+    }
+
+    /// Writes every song in `playlist` to a file of this format at `path`
+
+    pub fn export(
+        &self,
+        playlist: &PlaylistType,
+        path: &Path,
+        audio_paths: ExportedAudioPaths,
+    ) -> io::Result<()> {
+        match self {
+            Self::M3u8 => m3u::export_m3u(playlist, path, audio_paths),
+            Self::Xspf => xspf::export_xspf(playlist, path, audio_paths),
+            Self::Pls => pls::export_pls(playlist, path, audio_paths),
+        }
+    }
+}
+
+impl Display for PlaylistFileFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_str = match self {
+            Self::M3u8 => "M3U8",
+            Self::Xspf => "XSPF",
+            Self::Pls => "PLS",
+        };
+
+        f.write_str(display_str)
+    }
+}
+
+/// Creates a new standard playlist in `folder` from the playlist file at `path`, and starts importing the
+/// files it lists in the background. The format is picked by its extension
+
+pub fn import_playlist_file(folder: &Rc<Folder>, path: &Path) -> io::Result<PlaylistFileImportJob> {
+    match PlaylistFileFormat::from_path(path) {
+        Some(PlaylistFileFormat::M3u8) => m3u::import_m3u(folder, path),
+        Some(PlaylistFileFormat::Xspf) => xspf::import_xspf(folder, path),
+        Some(PlaylistFileFormat::Pls) => pls::import_pls(folder, path),
+
+        None => Err(io::Error::new(
+            ErrorKind::Unsupported,
+            format!(
+                "Unknown playlist file extension, expected one of {}",
+                PLAYLIST_FILE_EXTS.join(", ")
+            ),
+        )),
+    }
+}
+
+/// Where the songs of an exported playlist point to
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExportedAudioPaths {
+    /// Absolute paths to the audio stored in the library, the playlist only works on this machine
+    Library,
+    /// The audio is copied into a directory named after the playlist file, next to it, and referenced
+    /// with relative paths so both can be moved together
+    CopiedRelative,
+}
+
+/// A song as it is written to a playlist file
+#[derive(Debug)]
+pub(crate) struct ExportedSong {
+    /// Absolute, or relative to the directory of the playlist file
+    pub(crate) path: PathBuf,
+    pub(crate) title: String,
+    pub(crate) artist: String,
+    pub(crate) album: String,
+    pub(crate) rating: u8,
+    pub(crate) user_tag: String,
+    /// `None` if the length could not be read when the song was imported
+    pub(crate) length_secs: Option<u32>,
+}
+
+/// Every song in `playlist` in the order it is sorted in, copying the audio next to `playlist_path`
+/// first if `audio_paths` asks for it
+
+pub(crate) fn exported_songs(
+    playlist: &PlaylistType,
+    playlist_path: &Path,
+    audio_paths: ExportedAudioPaths,
+) -> io::Result<Vec<ExportedSong>> {
+    let copied_dir_name = match audio_paths {
+        ExportedAudioPaths::Library => None,
+
+        ExportedAudioPaths::CopiedRelative => {
+            let dir_name = playlist_path.file_stem().ok_or_else(|| {
+                io::Error::new(ErrorKind::InvalidFilename, "The playlist has no file name")
+            })?;
+
+            fs::create_dir_all(playlist_dir(playlist_path).join(dir_name))?;
+
+            Some(PathBuf::from(dir_name))
+        }
+    };
+
+    let songs_vec = playlist.get_song_vec_unfiltered();
+    let songs = read_rwlock(&songs_vec);
+    let mut exported_songs = Vec::with_capacity(songs.len());
+
+    for (song_index, song) in songs.iter().enumerate() {
+        let song_data = song.get_song_data();
+        let meta = &song_data.inner.meta.inner;
+
+        let title = song_data.inner.title.clone();
+        let artist = meta.artist.unwrapped_ref().full_artist_string.clone();
+
+        let path = match &copied_dir_name {
+            None => std::path::absolute(song.song_audio_path())?,
+
+            Some(copied_dir_name) => {
+                let file_name = sanitize_file_name(&format!(
+                    "{:03} - {} - {}.{}",
+                    song_index + 1,
+                    artist,
+                    title,
+                    song.audio_container().extension()
+                ));
+
+                let relative_path = copied_dir_name.join(file_name);
+
+                fs::copy(
+                    song.song_audio_path(),
+                    playlist_dir(playlist_path).join(&relative_path),
+                )?;
+
+                relative_path
+            }
+        };
+
+        exported_songs.push(ExportedSong {
+            path,
+            title,
+            artist,
+            album: meta.album.unwrapped_ref().clone(),
+            rating: song_data.inner.rating,
+            user_tag: song_data.inner.user_tag.clone(),
+            length_secs: meta.song_length.inner.as_ref().ok().copied(),
+        });
+    }
+
+    Ok(exported_songs)
+}
+
+/// The songs read from a playlist file, before they are imported
+#[derive(Debug)]
+pub(crate) struct PlaylistFileEntries {
+    pub(crate) name: String,
+    pub(crate) paths: Vec<PathBuf>,
+    /// Entries which don't point to a local file, such as streams
+    pub(crate) skipped: Vec<String>,
+    /// The napoleon data of each XSPF track, applied to the songs imported from it
+    pub(crate) track_extensions: HashMap<PathBuf, TrackExtension>,
+}
+
+/// A playlist created from a playlist file
+
+#[derive(Debug)]
+pub struct PlaylistFileImport {
+    pub playlist: Rc<PlaylistType>,
+    /// Files which were already in the library are reported as duplicates, and were added to the
+    /// playlist as well
+    pub report: ImportReport,
+    /// Entries which don't point to a local file, such as streams, and were left out
+    pub skipped: Vec<String>,
+}
+
+impl PlaylistFileImport {
+    /// Number of songs which could not be added to the playlist
+    pub fn missing_count(&self) -> usize {
+        self.report.not_imported().count() - self.report.duplicate_songs().len()
+            + self.skipped.len()
+    }
+}
+
+/// The import of the files listed by a playlist file, running on background threads. The ui thread
+/// should call [`PlaylistFileImportJob::poll`] each frame, the songs are added to the playlist once the
+/// import finishes
+
+pub struct PlaylistFileImportJob {
+    playlist: Rc<PlaylistType>,
+    import_job: ImportJob,
+    skipped: Vec<String>,
+    track_extensions: HashMap<PathBuf, TrackExtension>,
+}
+
+impl PlaylistFileImportJob {
+    /// The import of the files, for its progress or to cancel it
+    pub fn import_job(&self) -> &ImportJob {
+        &self.import_job
+    }
+
+    /// Handles every event received since the last poll, returns the finished import once every file
+    /// has been imported and added to the playlist
+
+    pub fn poll(&mut self) -> Option<PlaylistFileImport> {
+        let report = self.import_job.poll()?;
+
+        Some(finish_playlist_file_import(
+            Rc::clone(&self.playlist),
+            report,
+            mem::take(&mut self.skipped),
+            &self.track_extensions,
+        ))
+    }
+
+    /// Blocks until every file has been imported and added to the playlist, `on_event` is called with
+    /// every event of the import
+
+    pub fn wait(self, on_event: impl FnMut(&ImportEvent, ImportProgress)) -> PlaylistFileImport {
+        let report = self.import_job.wait(on_event);
+
+        finish_playlist_file_import(self.playlist, report, self.skipped, &self.track_extensions)
+    }
+}
+
+/// Creates a new standard playlist in `folder` for the songs in `entries`, and starts importing the files
+/// into the library unless they are already in it
+
+pub(crate) fn import_playlist_file_entries(
+    folder: &Rc<Folder>,
+    entries: PlaylistFileEntries,
+) -> io::Result<PlaylistFileImportJob> {
+    let playlist = folder.create_standard_playlist(entries.name)?;
+
+    Ok(PlaylistFileImportJob {
+        playlist,
+        import_job: ImportJob::start(entries.paths, false),
+        skipped: entries.skipped,
+        track_extensions: entries.track_extensions,
+    })
+}
+
+/// Adds every imported song, and every song already in the library, to the playlist in the order the
+/// playlist file lists them
+
+fn finish_playlist_file_import(
+    playlist: Rc<PlaylistType>,
+    report: ImportReport,
+    skipped: Vec<String>,
+    track_extensions: &HashMap<PathBuf, TrackExtension>,
+) -> PlaylistFileImport {
+    let songs = report
+        .entries
+        .iter()
+        .filter_map(|(_, outcome)| match outcome {
+            ImportOutcome::Imported(song) | ImportOutcome::Duplicate(song) => {
+                Some(Arc::clone(song))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    playlist.import_existing_songs(&songs);
+
+    apply_track_extensions(&report, track_extensions);
+
+    PlaylistFileImport {
+        playlist,
+        report,
+        skipped,
+    }
+}
+
+/// Writes a playlist file to a temporary file next to `path` which is then renamed over it, so a failed
+/// export never leaves a half written file in place of an earlier one
+
+pub(crate) fn write_playlist_file(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    write_atomically(path, |temp_path| {
+        let mut writer = BufWriter::new(File::create(temp_path)?);
+        write(&mut writer)?;
+
+        writer.flush()
+    })
+}
+
+/// Resolves a location in a playlist file to a local path, relative paths are relative to `base_dir`.
+/// Returns `None` for urls other than `file://`
+
+pub(crate) fn resolve_location(base_dir: &Path, location: &str) -> Option<PathBuf> {
+    let location = location.trim();
+
+    if location.is_empty() {
+        return None;
+    }
+
+    let path = if let Some(file_path) = strip_prefix_ignore_case(location, "file://") {
+        let file_path = percent_decode(file_path);
+        let file_path = file_path.strip_prefix("localhost").unwrap_or(&file_path);
+
+        // file:///C:/Music/song.mp3 on windows
+        let file_path = match file_path.as_bytes() {
+            [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => &file_path[1..],
+            _ => file_path,
+        };
+
+        PathBuf::from(file_path)
+    } else if is_url(location) {
+        return None;
+    } else {
+        PathBuf::from(location)
+    };
+
+    if path.is_absolute() {
+        Some(path)
+    } else {
+        Some(base_dir.join(path))
+    }
+}
+
+/// The location of `path` as a uri, as used by XSPF. Relative paths stay relative
+
+pub(crate) fn path_to_uri(path: &Path) -> String {
+    let path_str = path.to_string_lossy();
+
+    let path_str = if cfg!(windows) {
+        path_str.replace('\\', "/")
+    } else {
+        path_str.into_owned()
+    };
+
+    let encoded_path = percent_encode(&path_str);
+
+    if !path.is_absolute() {
+        encoded_path
+    } else if encoded_path.starts_with('/') {
+        format!("file://{}", encoded_path)
+    } else {
+        // C:/Music/song.mp3 on windows
+        format!("file:///{}", encoded_path)
+    }
+}
+
+/// Decodes a playlist file as utf-8, falling back to latin-1 for older files which are not valid utf-8
+
+pub(crate) fn decode_playlist_text(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text.to_string(),
+        Err(_) => bytes.iter().map(|byte| *byte as char).collect(),
+    }
+}
+
+/// The name of a playlist read from `playlist_path`, used when the file doesn't name it
+
+pub(crate) fn playlist_name_from_path(playlist_path: &Path) -> String {
+    playlist_path
+        .file_stem()
+        .map(|file_stem| file_stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "Imported playlist".to_string())
+}
+
+/// Line based formats have one entry per line, so line breaks in names are replaced
+
+pub(crate) fn single_line(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
+
+/// The directory paths in a playlist file are relative to
+
+pub(crate) fn playlist_dir(playlist_path: &Path) -> &Path {
+    playlist_path.parent().unwrap_or(Path::new(""))
+}
+
+fn is_url(location: &str) -> bool {
+    match location.split_once("://") {
+        Some((scheme, _)) => {
+            // Longer than a windows drive letter
+            scheme.len() > 1
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+
+        None => false,
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    if s.len() >= prefix.len()
+        && s.is_char_boundary(prefix.len())
+        && s[..prefix.len()].eq_ignore_ascii_case(prefix)
+    {
+        Some(&s[prefix.len()..])
+    } else {
+        None
+    }
+}
+
+pub(crate) fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex_byte = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match hex_byte {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~' | b'/' | b':') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    encoded
+}
+
+/// Replaces the characters which aren't allowed in file names on some platforms
+
+fn sanitize_file_name(file_name: &str) -> String {
+    file_name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
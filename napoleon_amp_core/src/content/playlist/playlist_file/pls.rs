@@ -0,0 +1,112 @@
+//! PLS playlists, as written by Winamp and most internet radio players. The format has no field for the
+//! playlist name, so imported playlists are named after the file.
+
+use crate::content::folder::Folder;
+use crate::content::playlist::PlaylistType;
+use crate::content::playlist::playlist_file::{
+    ExportedAudioPaths, PlaylistFileEntries, PlaylistFileImportJob, decode_playlist_text,
+    exported_songs, import_playlist_file_entries, playlist_dir, playlist_name_from_path,
+    resolve_location, single_line, write_playlist_file,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+use std::{fs, io};
+
+pub const PLS_EXT: &str = "pls";
+
+/// Writes every song in `playlist` to a PLS file at `pls_path`, with the length, artist and title of
+/// each song
+
+pub fn export_pls(
+    playlist: &PlaylistType,
+    pls_path: &Path,
+    audio_paths: ExportedAudioPaths,
+) -> io::Result<()> {
+    let exported_songs = exported_songs(playlist, pls_path, audio_paths)?;
+
+    write_playlist_file(pls_path, |writer| {
+        writeln!(writer, "[playlist]")?;
+
+        for (song_index, exported_song) in exported_songs.iter().enumerate() {
+            let entry_number = song_index + 1;
+
+            writeln!(
+                writer,
+                "File{}={}",
+                entry_number,
+                exported_song.path.display()
+            )?;
+            writeln!(
+                writer,
+                "Title{}={} - {}",
+                entry_number,
+                single_line(&exported_song.artist),
+                single_line(&exported_song.title)
+            )?;
+            writeln!(
+                writer,
+                "Length{}={}",
+                entry_number,
+                exported_song
+                    .length_secs
+                    .map_or(-1, |length_secs| length_secs as i64)
+            )?;
+        }
+
+        writeln!(writer, "NumberOfEntries={}", exported_songs.len())?;
+        writeln!(writer, "Version=2")?;
+
+        Ok(())
+    })
+}
+
+/// Creates a new standard playlist in `folder` from the PLS file at `pls_path`, importing each file it
+/// lists in the background unless it is already in the library
+
+pub fn import_pls(folder: &Rc<Folder>, pls_path: &Path) -> io::Result<PlaylistFileImportJob> {
+    import_playlist_file_entries(folder, read_pls(pls_path)?)
+}
+
+fn read_pls(pls_path: &Path) -> io::Result<PlaylistFileEntries> {
+    let pls_text = decode_playlist_text(&fs::read(pls_path)?);
+    let base_dir = playlist_dir(pls_path);
+
+    // Entries can be listed in any order, they are sorted by their number
+    let mut locations = BTreeMap::new();
+
+    for line in pls_text.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+
+        let key = key.trim().to_ascii_lowercase();
+
+        let Some(entry_number) = key
+            .strip_prefix("file")
+            .and_then(|entry_number| entry_number.parse::<usize>().ok())
+        else {
+            continue;
+        };
+
+        locations.insert(entry_number, value.trim().to_string());
+    }
+
+    let mut paths = Vec::new();
+    let mut skipped = Vec::new();
+
+    for location in locations.into_values() {
+        match resolve_location(base_dir, &location) {
+            Some(path) => paths.push(path),
+            None => skipped.push(location),
+        }
+    }
+
+    Ok(PlaylistFileEntries {
+        name: playlist_name_from_path(pls_path),
+        paths,
+        skipped,
+        track_extensions: HashMap::new(),
+    })
+}
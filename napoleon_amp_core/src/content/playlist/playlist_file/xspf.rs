@@ -0,0 +1,256 @@
+//! XSPF playlists. Along with the standard title, creator, album and duration of each track, the rating
+//! and user tag are kept in a napoleon extension element so they survive a round trip.
+
+use crate::content::folder::Folder;
+use crate::content::playlist::PlaylistType;
+use crate::content::playlist::import::{ImportOutcome, ImportReport};
+use crate::content::playlist::playlist_file::{
+    ExportedAudioPaths, PlaylistFileEntries, PlaylistFileImportJob, decode_playlist_text,
+    exported_songs, import_playlist_file_entries, path_to_uri, percent_decode, playlist_dir,
+    playlist_name_from_path, resolve_location, write_playlist_file,
+};
+use crate::content::song::song_data::MAX_RATING;
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::{fs, io};
+
+pub const XSPF_EXT: &str = "xspf";
+
+const XSPF_NAMESPACE: &str = "http://xspf.org/ns/0/";
+/// Identifies the extension element holding the napoleon data of a track
+const NAPOLEON_APPLICATION: &str = "https://github.com/ltsoveranakin/napoleon_amp";
+
+/// The napoleon data of a track, read from its extension element
+#[derive(Debug)]
+pub(crate) struct TrackExtension {
+    rating: Option<u8>,
+    user_tag: Option<String>,
+}
+
+/// Writes every song in `playlist` to an XSPF file at `xspf_path`
+
+pub fn export_xspf(
+    playlist: &PlaylistType,
+    xspf_path: &Path,
+    audio_paths: ExportedAudioPaths,
+) -> io::Result<()> {
+    let exported_songs = exported_songs(playlist, xspf_path, audio_paths)?;
+
+    write_playlist_file(xspf_path, |writer| {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            writer,
+            r#"<playlist version="1" xmlns="{}" xmlns:napoleon="{}">"#,
+            XSPF_NAMESPACE, NAPOLEON_APPLICATION
+        )?;
+        writeln!(
+            writer,
+            "  <title>{}</title>",
+            escape_xml(&playlist.get_user_data().inner.content_data.name)
+        )?;
+        writeln!(writer, "  <trackList>")?;
+
+        for exported_song in exported_songs {
+            writeln!(writer, "    <track>")?;
+            writeln!(
+                writer,
+                "      <location>{}</location>",
+                escape_xml(&path_to_uri(&exported_song.path))
+            )?;
+            writeln!(
+                writer,
+                "      <title>{}</title>",
+                escape_xml(&exported_song.title)
+            )?;
+            writeln!(
+                writer,
+                "      <creator>{}</creator>",
+                escape_xml(&exported_song.artist)
+            )?;
+            writeln!(
+                writer,
+                "      <album>{}</album>",
+                escape_xml(&exported_song.album)
+            )?;
+
+            if let Some(length_secs) = exported_song.length_secs {
+                writeln!(
+                    writer,
+                    "      <duration>{}</duration>",
+                    length_secs as u64 * 1000
+                )?;
+            }
+
+            writeln!(
+                writer,
+                r#"      <extension application="{}">"#,
+                NAPOLEON_APPLICATION
+            )?;
+            writeln!(
+                writer,
+                "        <napoleon:rating>{}</napoleon:rating>",
+                exported_song.rating
+            )?;
+            writeln!(
+                writer,
+                "        <napoleon:user_tag>{}</napoleon:user_tag>",
+                escape_xml(&exported_song.user_tag)
+            )?;
+            writeln!(writer, "      </extension>")?;
+            writeln!(writer, "    </track>")?;
+        }
+
+        writeln!(writer, "  </trackList>")?;
+        writeln!(writer, "</playlist>")?;
+
+        Ok(())
+    })
+}
+
+/// Creates a new standard playlist in `folder` from the XSPF file at `xspf_path`, importing each file it
+/// lists in the background unless it is already in the library. Newly imported songs are given the
+/// rating and user tag from the napoleon extension, songs already in the library are left as they are
+
+pub fn import_xspf(folder: &Rc<Folder>, xspf_path: &Path) -> io::Result<PlaylistFileImportJob> {
+    import_playlist_file_entries(folder, read_xspf(xspf_path)?)
+}
+
+/// Gives each song newly imported from a track with a napoleon extension its rating and user tag
+
+pub(crate) fn apply_track_extensions(
+    report: &ImportReport,
+    track_extensions: &HashMap<PathBuf, TrackExtension>,
+) {
+    for (song_path, outcome) in &report.entries {
+        let (ImportOutcome::Imported(song), Some(track_extension)) =
+            (outcome, track_extensions.get(song_path))
+        else {
+            continue;
+        };
+
+        {
+            let mut song_data = song.get_song_data_mut();
+
+            if let Some(rating) = track_extension.rating {
+                song_data.inner.rating = rating.min(MAX_RATING as u8);
+            }
+
+            if let Some(user_tag) = &track_extension.user_tag {
+                song_data.inner.user_tag = user_tag.clone();
+            }
+        }
+
+        song.save_song_data();
+    }
+}
+
+fn read_xspf(xspf_path: &Path) -> io::Result<PlaylistFileEntries> {
+    let xspf_text = decode_playlist_text(&fs::read(xspf_path)?);
+    let base_dir = playlist_dir(xspf_path);
+
+    let document =
+        Document::parse(&xspf_text).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+
+    let playlist_element = document.root_element();
+
+    if !playlist_element.has_tag_name("playlist") {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "Not an XSPF playlist, it has no playlist element",
+        ));
+    }
+
+    let name = child_text(playlist_element, "title")
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| playlist_name_from_path(xspf_path));
+
+    let mut paths = Vec::new();
+    let mut skipped = Vec::new();
+    let mut track_extensions = HashMap::new();
+
+    let tracks = child_elements(playlist_element, "trackList")
+        .flat_map(|track_list| child_elements(track_list, "track"));
+
+    for track in tracks {
+        let Some(location) = child_text(track, "location") else {
+            continue;
+        };
+
+        let Some(path) = resolve_uri(base_dir, location) else {
+            skipped.push(location.trim().to_string());
+            continue;
+        };
+
+        if let Some(track_extension) = read_track_extension(track) {
+            track_extensions.insert(path.clone(), track_extension);
+        }
+
+        paths.push(path);
+    }
+
+    Ok(PlaylistFileEntries {
+        name,
+        paths,
+        skipped,
+        track_extensions,
+    })
+}
+
+/// Locations in XSPF are uris, so relative locations are percent encoded as well
+
+fn resolve_uri(base_dir: &Path, location: &str) -> Option<PathBuf> {
+    let location = location.trim();
+
+    if location.contains("://") {
+        resolve_location(base_dir, location)
+    } else {
+        resolve_location(base_dir, &percent_decode(location))
+    }
+}
+
+fn read_track_extension(track: Node) -> Option<TrackExtension> {
+    let extension = child_elements(track, "extension")
+        .find(|extension| extension.attribute("application") == Some(NAPOLEON_APPLICATION))?;
+
+    Some(TrackExtension {
+        rating: child_text(extension, "rating").and_then(|rating| rating.trim().parse().ok()),
+        // An empty user tag is still applied, only a missing one is not
+        user_tag: child_elements(extension, "user_tag")
+            .next()
+            .map(|user_tag| user_tag.text().unwrap_or_default().to_string()),
+    })
+}
+
+fn child_elements<'a, 'input>(
+    node: Node<'a, 'input>,
+    tag_name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(move |child| child.has_tag_name(tag_name))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, tag_name: &'static str) -> Option<&'a str> {
+    child_elements(node, tag_name).next()?.text()
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
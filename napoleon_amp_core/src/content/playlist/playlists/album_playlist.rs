@@ -0,0 +1,107 @@
+use crate::content::folder::Folder;
+use crate::content::playlist::data::{
+    PlaybackMode, PlaylistContentData, PlaylistSongListData, PlaylistUserData, PlaylistUserDataStd,
+};
+use crate::content::playlist::song_list::SongVec;
+use crate::content::playlist::{InnerPlaylist, Playlist};
+use crate::content::song::album_index::IndexedAlbum;
+use crate::time_now;
+use simple_id::prelude::{Id, SmallRngIdGenerator};
+use std::cell::{Ref, RefCell, RefMut};
+use std::ops::Deref;
+use std::rc::Rc;
+
+/// The tracks of an album played as a playlist. It only exists in memory, so nothing about it is saved
+
+#[derive(Debug)]
+pub struct AlbumPlaylist {
+    inner_playlist: InnerPlaylist,
+    playlist_user_data: RefCell<PlaylistUserData>,
+    song_ids: Vec<Id>,
+}
+
+impl AlbumPlaylist {
+    pub fn new(album: &IndexedAlbum, base_folder: &Rc<Folder>) -> Self {
+        let mut playlist_data = PlaylistUserDataStd::new(PlaylistContentData {
+            name: album.name.clone(),
+            parent: base_folder.id,
+        });
+
+        playlist_data.playback_mode = PlaybackMode::Sequential;
+
+        Self {
+            inner_playlist: InnerPlaylist::new(
+                SmallRngIdGenerator::default().generate_new_id(),
+                base_folder,
+            ),
+            playlist_user_data: RefCell::new(playlist_data.into()),
+            song_ids: album.tracks().iter().map(|track| track.song_id).collect(),
+        }
+    }
+}
+
+impl Playlist for AlbumPlaylist {
+    fn get_inner(&self) -> &InnerPlaylist {
+        &self.inner_playlist
+    }
+
+    fn get_user_data(&self) -> Ref<'_, PlaylistUserData> {
+        self.playlist_user_data.borrow()
+    }
+
+    fn get_user_data_mut(&self) -> RefMut<'_, PlaylistUserData> {
+        self.playlist_user_data.borrow_mut()
+    }
+
+    /// Does nothing since the playlist isn't saved, changes such as the volume last until it stops
+
+    fn save_user_data(&self) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    /// Loads the songs in the order of the album's tracks instead of the sort order
+
+    fn get_song_vec_unfiltered(&self) -> SongVec {
+        let inner = self.get_inner();
+
+        if !inner.has_loaded_songs.get() {
+            let song_list_data = self.get_song_list_data();
+
+            inner
+                .songs
+                .borrow_mut()
+                .push_songs_without_save(&song_list_data.song_ids);
+
+            inner.has_loaded_songs.set(true);
+        }
+
+        inner.songs.borrow().songs_arc()
+    }
+
+    fn load_song_list_data(&self) -> PlaylistSongListData {
+        PlaylistSongListData {
+            song_ids: self.song_ids.clone(),
+            last_updated: time_now().as_secs().into(),
+        }
+    }
+
+    /// Does nothing since the songs are always the tracks of the album
+
+    fn save_song_list(&self) {}
+}
+
+impl PartialEq for AlbumPlaylist {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner_playlist == other.inner_playlist
+    }
+}
+
+impl Eq for AlbumPlaylist {}
+
+impl Deref for AlbumPlaylist {
+    type Target = InnerPlaylist;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner_playlist
+    }
+}
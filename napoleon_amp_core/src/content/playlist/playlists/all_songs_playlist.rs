@@ -2,8 +2,11 @@ use crate::content::folder::Folder;
 use crate::content::playlist::data::PlaylistUserData;
 use crate::content::playlist::playlists::get_user_data_ref_cell;
 use crate::content::playlist::{InnerPlaylist, Playlist, default_save_user_data};
+use crate::content::song::delete::delete_songs_and_reload_playlists;
+use crate::read_rwlock;
 use simple_id::prelude::Id;
 use std::cell::{OnceCell, Ref, RefCell, RefMut};
+use std::collections::HashSet;
 use std::ops::Deref;
 use std::rc::Rc;
 
@@ -43,13 +46,20 @@ impl Playlist for AllSongsPlaylist {
 
     fn save_song_list(&self) {}
 
-    /// Does nothing (currently)
+    /// Every song is in "All Songs", so removing one deletes it from the library
 
-    fn delete_song(&self, _: usize)
-    where
-        Self: Sized,
-    {
-        todo!()
+    fn delete_song(&self, song_index: usize) {
+        let song_id = read_rwlock(&self.get_song_vec())[song_index].id;
+
+        let Some(base_folder) = self.parent.parent.upgrade() else {
+            return;
+        };
+
+        if let Err(e) = delete_songs_and_reload_playlists(&HashSet::from([song_id]), base_folder) {
+            eprintln!("Unable to delete song {} from the library; {}", song_id, e);
+        }
+
+        self.reload_songs();
     }
 }
 
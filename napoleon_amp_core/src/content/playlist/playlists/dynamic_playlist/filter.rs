@@ -3,11 +3,13 @@ use derive_enum_all_values::AllValues;
 use serbytes::prelude::SerBytes;
 use std::borrow::Cow;
 use std::fmt::{Display, Formatter};
+use std::iter;
 use std::str::FromStr;
 
-pub type FilterRules = FilterRulesTyped<FilterRule<String>, FilterRule<u8>>;
+pub type FilterRules = FilterRulesTyped<FilterRule<String>, FilterRule<u8>, FilterRule<u32>>;
 
 #[derive(SerBytes, AllValues, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ComparisonMethod {
     LessThan,
     EqualTo,
@@ -35,6 +37,7 @@ impl Display for ComparisonMethod {
 }
 
 #[derive(SerBytes, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FilterRule<T> {
     pub value: T,
     pub comparison_method: ComparisonMethod,
@@ -55,9 +58,10 @@ where
 {
     fn does_value_pass(&self, test_value: &T) -> bool {
         match self.comparison_method {
-            ComparisonMethod::LessThan => &self.value < test_value,
+            // The song's value is compared against the rule's, as the rule reads in the ui
+            ComparisonMethod::LessThan => test_value < &self.value,
             ComparisonMethod::EqualTo => &self.value == test_value,
-            ComparisonMethod::GreaterThan => &self.value > test_value,
+            ComparisonMethod::GreaterThan => test_value > &self.value,
             ComparisonMethod::NotEqualTo => &self.value != test_value,
             ComparisonMethod::Contains => {
                 test_value
@@ -70,6 +74,31 @@ where
     }
 }
 
+impl FilterRule<String> {
+    /// Checks the rule against each value, such as each artist credited on a song. A song is only not equal
+    /// to the rule's value if none of its values are, for any other comparison one value passing is enough
+
+    fn does_any_value_pass<'v>(&self, mut test_values: impl Iterator<Item = &'v str>) -> bool {
+        let value_passes = |test_value: &str| self.does_value_pass(&test_value.to_string());
+
+        match self.comparison_method {
+            ComparisonMethod::NotEqualTo => test_values.all(value_passes),
+            _ => test_values.any(value_passes),
+        }
+    }
+}
+
+impl FilterRule<u32> {
+    /// Songs without the value only pass rules which check it isn't equal to something
+
+    fn does_optional_value_pass(&self, test_value: Option<u32>) -> bool {
+        match test_value {
+            Some(test_value) => self.does_value_pass(&test_value),
+            None => matches!(self.comparison_method, ComparisonMethod::NotEqualTo),
+        }
+    }
+}
+
 pub trait AsStr {
     fn as_cow_str(&self) -> Cow<'_, str>;
 }
@@ -86,12 +115,27 @@ impl AsStr for u8 {
     }
 }
 
+impl AsStr for u32 {
+    fn as_cow_str(&self) -> Cow<'_, str> {
+        Cow::Owned(self.to_string())
+    }
+}
+
 #[derive(SerBytes, Debug, Clone)]
-pub enum FilterRulesTyped<S, U> {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FilterRulesTyped<S, U, N> {
     Title(S),
     Artist(S),
     Album(S),
     Rating(U),
+    Genre(S),
+    AlbumArtist(S),
+    Composer(S),
+    Comment(S),
+    TrackNumber(N),
+    DiscNumber(N),
+    Year(N),
+    Bpm(N),
 }
 
 impl FilterRules {
@@ -102,10 +146,32 @@ impl FilterRules {
         match self {
             Self::Title(title) => title.does_value_pass(&song_data.title),
             Self::Artist(artist) => {
-                artist.does_value_pass(&meta.artist.unwrapped_ref().full_artist_string)
+                let song_artist = meta.artist.unwrapped_ref();
+                let credits = song_artist.credits();
+
+                // The whole artist string is tested as well, so rules written before artists were split
+                // into credits still match
+                artist.does_any_value_pass(
+                    iter::once(song_artist.full_artist_string.as_str())
+                        .chain(credits.iter().map(|credit| credit.name)),
+                )
             }
             Self::Album(album) => album.does_value_pass(meta.album.unwrapped_ref()),
             Self::Rating(rating) => rating.does_value_pass(&song_data.rating),
+            Self::Genre(genre) => genre.does_value_pass(meta.genre.unwrapped_ref()),
+            Self::AlbumArtist(album_artist) => {
+                album_artist.does_value_pass(meta.album_artist.unwrapped_ref())
+            }
+            Self::Composer(composer) => composer.does_value_pass(meta.composer.unwrapped_ref()),
+            Self::Comment(comment) => comment.does_value_pass(meta.comment.unwrapped_ref()),
+            Self::TrackNumber(track_number) => {
+                track_number.does_optional_value_pass(*meta.track_number.unwrapped_ref())
+            }
+            Self::DiscNumber(disc_number) => {
+                disc_number.does_optional_value_pass(*meta.disc_number.unwrapped_ref())
+            }
+            Self::Year(year) => year.does_optional_value_pass(meta.year()),
+            Self::Bpm(bpm) => bpm.does_optional_value_pass(*meta.bpm.unwrapped_ref()),
         }
     }
 
@@ -127,25 +193,52 @@ impl FilterRules {
                 ValuesType::U8(&mut rating.value),
                 &mut rating.comparison_method,
             ),
+            Self::Genre(str_rule)
+            | Self::AlbumArtist(str_rule)
+            | Self::Composer(str_rule)
+            | Self::Comment(str_rule) => (
+                ValuesType::Str(&mut str_rule.value),
+                &mut str_rule.comparison_method,
+            ),
+            Self::TrackNumber(number_rule)
+            | Self::DiscNumber(number_rule)
+            | Self::Year(number_rule)
+            | Self::Bpm(number_rule) => (
+                ValuesType::U32(&mut number_rule.value),
+                &mut number_rule.comparison_method,
+            ),
         }
     }
 
     pub fn try_assign_from_str(&mut self, s: &str) -> Result<(), ()> {
         match self {
-            Self::Title(str_rule) | Self::Artist(str_rule) | Self::Album(str_rule) => {
+            Self::Title(str_rule)
+            | Self::Artist(str_rule)
+            | Self::Album(str_rule)
+            | Self::Genre(str_rule)
+            | Self::AlbumArtist(str_rule)
+            | Self::Composer(str_rule)
+            | Self::Comment(str_rule) => {
                 str_rule.value = s.to_string();
             }
 
             Self::Rating(rating) => {
                 rating.value = u8::from_str(s).map_err(|_| ())?;
             }
+
+            Self::TrackNumber(number_rule)
+            | Self::DiscNumber(number_rule)
+            | Self::Year(number_rule)
+            | Self::Bpm(number_rule) => {
+                number_rule.value = u32::from_str(s).map_err(|_| ())?;
+            }
         }
 
         Ok(())
     }
 
     pub fn from_variant(
-        filter_rules: FilterRulesTyped<(), ()>,
+        filter_rules: FilterRulesTyped<(), (), ()>,
         current_str_value: &str,
         cmp_method: ComparisonMethod,
     ) -> Self {
@@ -163,26 +256,70 @@ impl FilterRules {
                 u8::from_str(current_str_value).unwrap_or_default(),
                 cmp_method,
             )),
+            FilterRulesTyped::Genre(_) => {
+                Self::Genre(FilterRule::new(current_str_value.to_string(), cmp_method))
+            }
+            FilterRulesTyped::AlbumArtist(_) => {
+                Self::AlbumArtist(FilterRule::new(current_str_value.to_string(), cmp_method))
+            }
+            FilterRulesTyped::Composer(_) => {
+                Self::Composer(FilterRule::new(current_str_value.to_string(), cmp_method))
+            }
+            FilterRulesTyped::Comment(_) => {
+                Self::Comment(FilterRule::new(current_str_value.to_string(), cmp_method))
+            }
+            FilterRulesTyped::TrackNumber(_) => Self::TrackNumber(FilterRule::new(
+                u32::from_str(current_str_value).unwrap_or_default(),
+                cmp_method,
+            )),
+            FilterRulesTyped::DiscNumber(_) => Self::DiscNumber(FilterRule::new(
+                u32::from_str(current_str_value).unwrap_or_default(),
+                cmp_method,
+            )),
+            FilterRulesTyped::Year(_) => Self::Year(FilterRule::new(
+                u32::from_str(current_str_value).unwrap_or_default(),
+                cmp_method,
+            )),
+            FilterRulesTyped::Bpm(_) => Self::Bpm(FilterRule::new(
+                u32::from_str(current_str_value).unwrap_or_default(),
+                cmp_method,
+            )),
         }
     }
 }
 
-impl<S, U> FilterRulesTyped<S, U> {
+impl<S, U, N> FilterRulesTyped<S, U, N> {
     pub fn get_display_str(&self) -> &'static str {
         match self {
             Self::Title(_) => "Title",
             Self::Artist(_) => "Artist",
             Self::Album(_) => "Album",
             Self::Rating(_) => "Rating",
+            Self::Genre(_) => "Genre",
+            Self::AlbumArtist(_) => "Album Artist",
+            Self::Composer(_) => "Composer",
+            Self::Comment(_) => "Comment",
+            Self::TrackNumber(_) => "Track Number",
+            Self::DiscNumber(_) => "Disc Number",
+            Self::Year(_) => "Year",
+            Self::Bpm(_) => "BPM",
         }
     }
 
-    pub fn values() -> [FilterRulesTyped<(), ()>; 4] {
+    pub fn values() -> [FilterRulesTyped<(), (), ()>; 12] {
         [
             FilterRulesTyped::Title(()),
             FilterRulesTyped::Artist(()),
             FilterRulesTyped::Album(()),
             FilterRulesTyped::Rating(()),
+            FilterRulesTyped::Genre(()),
+            FilterRulesTyped::AlbumArtist(()),
+            FilterRulesTyped::Composer(()),
+            FilterRulesTyped::Comment(()),
+            FilterRulesTyped::TrackNumber(()),
+            FilterRulesTyped::DiscNumber(()),
+            FilterRulesTyped::Year(()),
+            FilterRulesTyped::Bpm(()),
         ]
     }
 }
@@ -190,4 +327,5 @@ impl<S, U> FilterRulesTyped<S, U> {
 pub enum ValuesType<'a> {
     Str(&'a mut String),
     U8(&'a mut u8),
+    U32(&'a mut u32),
 }
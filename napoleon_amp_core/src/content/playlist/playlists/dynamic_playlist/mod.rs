@@ -110,6 +110,12 @@ impl Playlist for DynamicPlaylist {
 
         song_list_res.song_list_data
     }
+
+    /// Clears the cached song list as well, so it is rebuilt from the rules
+
+    fn clear_loaded_songs(&self) {
+        self.clear_songs_cache();
+    }
 }
 
 impl ClearSongsCache for DynamicPlaylist {
@@ -1,3 +1,4 @@
+pub mod album_playlist;
 pub mod all_songs_playlist;
 pub mod dynamic_playlist;
 pub mod standard_playlist;
@@ -60,6 +61,7 @@ impl ClearSongsCache for InnerPlaylist {
         self.has_loaded_songs.set(false);
         self.songs.borrow_mut().clear_songs_cache_mut();
         self.playlist_song_list_data.borrow_mut().take();
+        self.total_length.borrow_mut().take();
     }
 }
 
@@ -14,7 +14,7 @@ pub struct Queue {
 }
 
 impl Queue {
-    pub(super) fn new(
+    pub fn new(
         mut start_index: usize,
         mut song_list: Vec<Arc<Song>>,
         playback_mode: PlaybackMode,
@@ -69,6 +69,14 @@ impl Queue {
         )
     }
 
+    /// The song [`Queue::get_next_song`] would return, without taking it from the temporary queue
+
+    pub(super) fn peek_next_song(&self) -> Option<&Arc<Song>> {
+        self.temporary_queue
+            .front()
+            .or_else(|| self.song_list.get(self.index))
+    }
+
     pub(super) fn next(&mut self) {
         if self.temporary_queue.is_empty() {
             self.index = self.get_wrapped_index(self.index as i32 + 1);
@@ -14,6 +14,7 @@ use std::sync::{Arc, RwLock};
 pub type SongVec = Arc<RwLock<Vec<Arc<Song>>>>;
 
 #[derive(SerBytes, AllValues, Default, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SortByVariant {
     #[default]
     Title,
@@ -22,6 +23,13 @@ pub enum SortByVariant {
     Rating,
     Length,
     TimesListened,
+    TrackNumber,
+    DiscNumber,
+    Year,
+    Genre,
+    AlbumArtist,
+    Composer,
+    Bpm,
 }
 
 impl Display for SortByVariant {
@@ -38,6 +46,20 @@ impl Display for SortByVariant {
             Self::Length => "Length",
 
             Self::TimesListened => "Times Listened",
+
+            Self::TrackNumber => "Track Number",
+
+            Self::DiscNumber => "Disc Number",
+
+            Self::Year => "Year",
+
+            Self::Genre => "Genre",
+
+            Self::AlbumArtist => "Album Artist",
+
+            Self::Composer => "Composer",
+
+            Self::Bpm => "BPM",
         };
 
         f.write_str(display_str)
@@ -45,6 +67,7 @@ impl Display for SortByVariant {
 }
 
 #[derive(SerBytes, Default, Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SortBy {
     pub sort_by_variant: SortByVariant,
     pub inverted: bool,
@@ -110,6 +133,14 @@ impl SongList {
     const RATING_INDEX: usize = 3;
     const LENGTH_INDEX: usize = 4;
     const TIMES_LISTENED_INDEX: usize = 5;
+    const DISC_NUMBER_INDEX: usize = 6;
+    const TRACK_NUMBER_INDEX: usize = 7;
+    const DATE_INDEX: usize = 8;
+    const GENRE_INDEX: usize = 9;
+    const ALBUM_ARTIST_INDEX: usize = 10;
+    const COMPOSER_INDEX: usize = 11;
+    const BPM_INDEX: usize = 12;
+    const SORT_PROPERTY_COUNT: usize = 13;
 
     pub(super) fn new() -> Self {
         Self {
@@ -126,8 +157,7 @@ impl SongList {
         songs_vec.reserve_exact(song_id_list.len());
 
         for song_id in song_id_list {
-            Self::push_song0(*song_id, songs_set, &mut songs_vec, None)
-                .expect("Will not fail if original_name is none");
+            Self::push_song0(*song_id, songs_set, &mut songs_vec);
         }
 
         songs_set.shrink_to_fit();
@@ -148,19 +178,20 @@ impl SongList {
         }
     }
 
-    pub(super) fn push_new_song(&mut self, song_id: Id, original_name: &str) -> Result<(), ()> {
+    pub(super) fn remove_song_at(&mut self, index: usize) {
         let songs_set = &mut self.songs_set;
         let mut songs_vec = write_rwlock(&self.songs_vec);
 
-        Self::push_song0(song_id, songs_set, &mut songs_vec, Some(original_name))
+        let song = songs_vec.remove(index);
+        songs_set.remove(&song);
     }
 
-    pub(super) fn remove_song_at(&mut self, index: usize) {
+    pub(super) fn retain_songs(&mut self, mut keep: impl FnMut(&Arc<Song>) -> bool) {
         let songs_set = &mut self.songs_set;
         let mut songs_vec = write_rwlock(&self.songs_vec);
 
-        let song = songs_vec.remove(index);
-        songs_set.remove(&song);
+        songs_vec.retain(|song| keep(song));
+        songs_set.retain(|song| keep(song));
     }
 
     pub(super) fn reserve(&mut self, additional: usize) {
@@ -188,6 +219,13 @@ impl SongList {
                 SortByVariant::Rating => Self::RATING_INDEX,
                 SortByVariant::Length => Self::LENGTH_INDEX,
                 SortByVariant::TimesListened => Self::TIMES_LISTENED_INDEX,
+                SortByVariant::TrackNumber => Self::TRACK_NUMBER_INDEX,
+                SortByVariant::DiscNumber => Self::DISC_NUMBER_INDEX,
+                SortByVariant::Year => Self::DATE_INDEX,
+                SortByVariant::Genre => Self::GENRE_INDEX,
+                SortByVariant::AlbumArtist => Self::ALBUM_ARTIST_INDEX,
+                SortByVariant::Composer => Self::COMPOSER_INDEX,
+                SortByVariant::Bpm => Self::BPM_INDEX,
             };
 
             let a_sort_properties = Self::get_sort_properties(a_song_data, index);
@@ -204,20 +242,33 @@ impl SongList {
     fn get_sort_properties(
         song_data: &SongDataStd,
         swap_index: usize,
-    ) -> [SortableProperty<'_>; 6] {
-        let mut sort_properties = [SortableProperty::Int(0); 6];
+    ) -> [SortableProperty<'_>; Self::SORT_PROPERTY_COUNT] {
+        let mut sort_properties = [SortableProperty::Int(0); Self::SORT_PROPERTY_COUNT];
         let meta = &song_data.meta.inner;
 
         sort_properties[Self::TITLE_INDEX] = SortableProperty::Str(&song_data.title);
         sort_properties[Self::ALBUM_INDEX] = SortableProperty::Str(&meta.album.unwrapped_ref());
         sort_properties[Self::ARTIST_INDEX] =
-            SortableProperty::Str(&meta.artist.unwrapped_ref().full_artist_string);
+            SortableProperty::Str(meta.artist.unwrapped_ref().main_artist());
         sort_properties[Self::RATING_INDEX] =
             SortableProperty::Int(MAX_RATING - song_data.rating as u32);
         sort_properties[Self::LENGTH_INDEX] =
             SortableProperty::Int(*meta.song_length.unwrapped_ref());
         sort_properties[Self::TIMES_LISTENED_INDEX] =
             SortableProperty::Int(song_data.times_listened);
+        // Songs without a number are sorted after those with one
+        sort_properties[Self::DISC_NUMBER_INDEX] =
+            SortableProperty::Int(meta.disc_number.unwrapped_ref().unwrap_or(u32::MAX));
+        sort_properties[Self::TRACK_NUMBER_INDEX] =
+            SortableProperty::Int(meta.track_number.unwrapped_ref().unwrap_or(u32::MAX));
+        sort_properties[Self::DATE_INDEX] = SortableProperty::Str(meta.date.unwrapped_ref());
+        sort_properties[Self::GENRE_INDEX] = SortableProperty::Str(meta.genre.unwrapped_ref());
+        sort_properties[Self::ALBUM_ARTIST_INDEX] =
+            SortableProperty::Str(meta.album_artist.unwrapped_ref());
+        sort_properties[Self::COMPOSER_INDEX] =
+            SortableProperty::Str(meta.composer.unwrapped_ref());
+        sort_properties[Self::BPM_INDEX] =
+            SortableProperty::Int(meta.bpm.unwrapped_ref().unwrap_or(u32::MAX));
 
         let temp = sort_properties[swap_index];
 
@@ -230,23 +281,14 @@ impl SongList {
         sort_properties
     }
 
-    fn push_song0(
-        song_id: Id,
-        songs_set: &mut HashSet<Arc<Song>>,
-        songs_vec: &mut Vec<Arc<Song>>,
-        original_name: Option<&str>,
-    ) -> Result<(), ()> {
-        if let Some(original_name) = original_name {
-            SONG_POOL.register_new_song(song_id, original_name.to_string())?;
-        }
-
+    fn push_song0(song_id: Id, songs_set: &mut HashSet<Arc<Song>>, songs_vec: &mut Vec<Arc<Song>>) {
         let song = SONG_POOL.get_song_by_id(song_id);
 
         {
             let mut song_data = song.get_song_data().clone();
 
             if song_data.inner.title.is_empty() {
-                song_data.inner.title = original_name.unwrap_or("Unnamed Song").to_string();
+                song_data.inner.title = "Unnamed Song".to_string();
                 song.set_song_data_and_save(song_data);
             }
         }
@@ -255,8 +297,6 @@ impl SongList {
             songs_set.insert(Arc::clone(&song));
             songs_vec.push(song);
         }
-
-        Ok(())
     }
 }
 
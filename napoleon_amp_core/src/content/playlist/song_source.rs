@@ -0,0 +1,132 @@
+use rodio::source::SeekError;
+use rodio::{ChannelCount, Sample, SampleRate, Source};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// The position of a song from its start offset as it plays. Each song appended to the sink has its own, so
+/// the position of the song which is playing can be read while the next one is already queued behind it
+
+#[derive(Clone, Debug, Default)]
+pub(super) struct SongPosition(Arc<AtomicU64>);
+
+impl SongPosition {
+    pub(super) fn get(&self) -> Duration {
+        Duration::from_micros(self.0.load(Ordering::Relaxed))
+    }
+
+    fn set(&self, position: Duration) {
+        self.0.store(position.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// The audio of a song played from its start offset to its end time, so it ends on its own without the
+/// music thread having to stop it
+
+pub(super) struct SongSource<S> {
+    source: S,
+    position: SongPosition,
+    /// Where the song starts in its audio file, the songs of a CUE sheet share one file
+    start_offset: Duration,
+    /// The position in the audio file the song was started or last seeked at
+    base_position: Duration,
+    /// Samples played since `base_position`
+    samples_played: u64,
+    end_time: Option<Duration>,
+}
+
+impl<S: Source> SongSource<S> {
+    pub(super) fn new(
+        mut source: S,
+        start_offset: Option<Duration>,
+        end_time: Option<Duration>,
+    ) -> Self {
+        let start_offset = start_offset
+            .filter(|start_offset| source.try_seek(*start_offset).is_ok())
+            .unwrap_or_default();
+
+        Self {
+            source,
+            position: SongPosition::default(),
+            start_offset,
+            base_position: start_offset,
+            samples_played: 0,
+            end_time,
+        }
+    }
+
+    pub(super) fn position(&self) -> SongPosition {
+        self.position.clone()
+    }
+
+    fn current_position(&self) -> Duration {
+        let samples_per_second = self.source.sample_rate() as f64 * self.source.channels() as f64;
+
+        self.base_position
+            + Duration::from_secs_f64(self.samples_played as f64 / samples_per_second)
+    }
+}
+
+impl<S: Source> Iterator for SongSource<S> {
+    type Item = Sample;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Only checked at the start of a frame, so the song never ends part way through one
+        if self.samples_played % self.source.channels() as u64 == 0 {
+            let current_position = self.current_position();
+
+            self.position
+                .set(current_position.saturating_sub(self.start_offset));
+
+            if self
+                .end_time
+                .is_some_and(|end_time| current_position >= end_time)
+            {
+                return None;
+            }
+        }
+
+        let sample = self.source.next()?;
+
+        self.samples_played += 1;
+
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.source.size_hint().1)
+    }
+}
+
+impl<S: Source> Source for SongSource<S> {
+    fn current_span_len(&self) -> Option<usize> {
+        self.source.current_span_len()
+    }
+
+    fn channels(&self) -> ChannelCount {
+        self.source.channels()
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.source.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.end_time
+            .or(self.source.total_duration())
+            .map(|end_time| end_time.saturating_sub(self.start_offset))
+    }
+
+    /// Seeks to `pos` from the start offset of the song
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        let file_pos = self.start_offset + pos;
+
+        self.source.try_seek(file_pos)?;
+
+        self.base_position = file_pos;
+        self.samples_played = 0;
+        self.position.set(pos);
+
+        Ok(())
+    }
+}
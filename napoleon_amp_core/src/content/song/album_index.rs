@@ -0,0 +1,249 @@
+use crate::content::song::song_cover_pool::SongCoverId;
+use crate::content::song::song_data::artist::{ArtistSeparators, artist_separators};
+use crate::content::song::song_data::meta::SongDataMetaV3;
+use crate::content::song::song_pool::SONG_POOL;
+use crate::content::song::{Song, UNKNOWN_ALBUM_STR};
+use simple_id::prelude::Id;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+/// The lowercase album name and album artist, so differently cased tags are the same album
+
+type AlbumKey = (String, String);
+
+/// The album name and album artist a song is indexed by, `None` if it has no album
+
+fn album_of(meta: &SongDataMetaV3) -> Option<(&String, &str)> {
+    let album = meta.album.unwrapped_ref();
+
+    if album.is_empty() || album == UNKNOWN_ALBUM_STR {
+        return None;
+    }
+
+    let album_artist = match meta.album_artist.unwrapped_ref().trim() {
+        "" => meta.artist.unwrapped_ref().main_artist(),
+        album_artist => album_artist,
+    };
+
+    Some((album, album_artist))
+}
+
+fn album_key(album: &str, album_artist: &str) -> AlbumKey {
+    (album.to_lowercase(), album_artist.to_lowercase())
+}
+
+/// Whether both songs are on the same album, as they would be indexed
+
+pub(crate) fn is_same_album(song: &Song, other_song: &Song) -> bool {
+    let song_key = album_of(&song.get_song_data().inner.meta.inner)
+        .map(|(album, album_artist)| album_key(album, album_artist));
+
+    song_key.is_some_and(|song_key| {
+        album_of(&other_song.get_song_data().inner.meta.inner)
+            .is_some_and(|(album, album_artist)| album_key(album, album_artist) == song_key)
+    })
+}
+
+/// A song on an indexed album, with the parts of its song data the album is built from
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlbumTrack {
+    pub song_id: Id,
+    pub disc_number: Option<u32>,
+    pub track_number: Option<u32>,
+    pub length: u32,
+    pub cover: Option<SongCoverId>,
+    pub year: Option<u32>,
+}
+
+impl AlbumTrack {
+    /// Tracks without a disc or track number are ordered after those with one
+
+    fn order_key(&self) -> (u32, u32) {
+        (
+            self.disc_number.unwrap_or(u32::MAX),
+            self.track_number.unwrap_or(u32::MAX),
+        )
+    }
+}
+
+/// Songs which share an album name and album artist. Songs without an album artist use their main artist
+/// instead, see [`Artist::main_artist`](crate::content::song::song_data::artist::Artist::main_artist)
+
+#[derive(Debug, Clone)]
+pub struct IndexedAlbum {
+    /// The album name as it was first tagged
+    pub name: String,
+    /// The album artist as it was first tagged
+    pub album_artist: String,
+    /// Ordered by disc number, then track number
+    tracks: Vec<AlbumTrack>,
+}
+
+impl IndexedAlbum {
+    /// Every track of the album, ordered by disc number, then track number
+
+    pub fn tracks(&self) -> &[AlbumTrack] {
+        &self.tracks
+    }
+
+    /// The songs of the album, in the order of its tracks
+
+    pub fn songs(&self) -> Vec<Arc<Song>> {
+        self.tracks
+            .iter()
+            .map(|track| SONG_POOL.get_song_by_id(track.song_id))
+            .collect()
+    }
+
+    /// The cover of the first track which has one
+
+    pub fn cover(&self) -> Option<SongCoverId> {
+        self.tracks.iter().find_map(|track| track.cover)
+    }
+
+    /// The length of every track added together, in seconds
+
+    pub fn total_length(&self) -> u32 {
+        self.tracks.iter().map(|track| track.length).sum()
+    }
+
+    /// The earliest year any track was released in
+
+    pub fn year(&self) -> Option<u32> {
+        self.tracks.iter().filter_map(|track| track.year).min()
+    }
+
+    fn insert_track(&mut self, track: AlbumTrack) {
+        let order_key = track.order_key();
+        let index = self
+            .tracks
+            .partition_point(|other_track| other_track.order_key() <= order_key);
+
+        self.tracks.insert(index, track);
+    }
+}
+
+/// Every album of the songs in the library. Songs without an album aren't indexed
+
+#[derive(Debug, Default)]
+pub struct AlbumIndex {
+    /// The separators the songs were indexed with, every song is indexed again when they change since the
+    /// main artist may be different
+    separators: Option<ArtistSeparators>,
+    /// The album and track each song was indexed with, a song is indexed again when either changes
+    indexed_songs: HashMap<Id, (AlbumKey, AlbumTrack)>,
+    albums: BTreeMap<AlbumKey, IndexedAlbum>,
+}
+
+impl AlbumIndex {
+    /// Indexes songs which were registered or had their album data changed since the last refresh, and
+    /// removes songs which are no longer registered
+
+    pub fn refresh(&mut self) {
+        let separators = artist_separators();
+
+        if self.separators.as_ref() != Some(&separators) {
+            *self = Self {
+                separators: Some(separators),
+                ..Default::default()
+            };
+        }
+
+        let song_ids = SONG_POOL
+            .get_registered_songs()
+            .name_map
+            .values()
+            .copied()
+            .collect::<HashSet<_>>();
+
+        let removed_song_ids = self
+            .indexed_songs
+            .keys()
+            .filter(|song_id| !song_ids.contains(song_id))
+            .copied()
+            .collect::<Vec<_>>();
+
+        for song_id in removed_song_ids {
+            self.remove_song(song_id);
+        }
+
+        for song_id in song_ids {
+            let song = SONG_POOL.get_song_by_id(song_id);
+            let song_data = song.get_song_data();
+            let meta = &song_data.inner.meta.inner;
+
+            let Some((album, album_artist)) = album_of(meta) else {
+                self.remove_song(song_id);
+                continue;
+            };
+
+            let key = album_key(album, album_artist);
+
+            let track = AlbumTrack {
+                song_id,
+                disc_number: *meta.disc_number.unwrapped_ref(),
+                track_number: *meta.track_number.unwrapped_ref(),
+                length: *meta.song_length.unwrapped_ref(),
+                cover: *meta.cover.unwrapped_ref(),
+                year: meta.year(),
+            };
+
+            if self
+                .indexed_songs
+                .get(&song_id)
+                .is_some_and(|(indexed_key, indexed_track)| {
+                    *indexed_key == key && *indexed_track == track
+                })
+            {
+                continue;
+            }
+
+            let (name, album_artist) = (album.clone(), album_artist.to_string());
+
+            self.remove_song(song_id);
+            self.add_song(key, name, album_artist, track);
+        }
+    }
+
+    /// Every album, ordered by name then album artist, ignoring case
+
+    pub fn albums(&self) -> impl Iterator<Item = &IndexedAlbum> {
+        self.albums.values()
+    }
+
+    /// The album named `name` by `album_artist`, ignoring case
+
+    pub fn get(&self, name: &str, album_artist: &str) -> Option<&IndexedAlbum> {
+        self.albums.get(&album_key(name, album_artist))
+    }
+
+    fn add_song(&mut self, key: AlbumKey, name: String, album_artist: String, track: AlbumTrack) {
+        self.albums
+            .entry(key.clone())
+            .or_insert_with(|| IndexedAlbum {
+                name,
+                album_artist,
+                tracks: Vec::new(),
+            })
+            .insert_track(track.clone());
+
+        self.indexed_songs.insert(track.song_id, (key, track));
+    }
+
+    fn remove_song(&mut self, song_id: Id) {
+        let Some((key, _)) = self.indexed_songs.remove(&song_id) else {
+            return;
+        };
+
+        let Some(album) = self.albums.get_mut(&key) else {
+            return;
+        };
+
+        album.tracks.retain(|track| track.song_id != song_id);
+
+        if album.tracks.is_empty() {
+            self.albums.remove(&key);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::content::song::UNKNOWN_ARTIST_STR;
+use crate::content::song::song_data::artist::{
+    Artist, ArtistRole, ArtistSeparators, artist_separators,
+};
+use crate::content::song::song_pool::SONG_POOL;
+use simple_id::prelude::Id;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// An artist credited on at least one song in the library
+
+#[derive(Debug, Clone)]
+pub struct IndexedArtist {
+    /// The name as it was first credited
+    pub name: String,
+    /// Every song the artist is credited on, along with the role they are credited with
+    pub songs: HashMap<Id, ArtistRole>,
+}
+
+/// Every individual artist credited on the songs of the library, see [`Artist::credits`]
+
+#[derive(Debug, Default)]
+pub struct ArtistIndex {
+    /// The separators the songs were indexed with, every song is indexed again when they change
+    separators: Option<ArtistSeparators>,
+    /// The artist string each song was indexed with, a song is indexed again when its artist changes
+    indexed_songs: HashMap<Id, String>,
+    /// Keyed by the lowercase name, so differently cased credits are the same artist
+    artists: BTreeMap<String, IndexedArtist>,
+}
+
+impl ArtistIndex {
+    /// Indexes songs which were registered or had their artist changed since the last refresh, and
+    /// removes songs which are no longer registered
+
+    pub fn refresh(&mut self) {
+        let separators = artist_separators();
+
+        if self.separators.as_ref() != Some(&separators) {
+            *self = Self {
+                separators: Some(separators),
+                ..Default::default()
+            };
+        }
+
+        let song_ids = SONG_POOL
+            .get_registered_songs()
+            .name_map
+            .values()
+            .copied()
+            .collect::<HashSet<_>>();
+
+        let removed_song_ids = self
+            .indexed_songs
+            .keys()
+            .filter(|song_id| !song_ids.contains(song_id))
+            .copied()
+            .collect::<Vec<_>>();
+
+        for song_id in removed_song_ids {
+            self.remove_song(song_id);
+        }
+
+        for song_id in song_ids {
+            let song = SONG_POOL.get_song_by_id(song_id);
+            let artist = song
+                .get_song_data()
+                .inner
+                .meta
+                .inner
+                .artist
+                .unwrapped_ref()
+                .clone();
+
+            if self.indexed_songs.get(&song_id) == Some(&artist.full_artist_string) {
+                continue;
+            }
+
+            self.remove_song(song_id);
+            self.add_song(song_id, artist);
+        }
+    }
+
+    /// Every artist, ordered by name ignoring case
+
+    pub fn artists(&self) -> impl Iterator<Item = &IndexedArtist> {
+        self.artists.values()
+    }
+
+    /// The artist with `name`, ignoring case
+
+    pub fn get(&self, name: &str) -> Option<&IndexedArtist> {
+        self.artists.get(&name.to_lowercase())
+    }
+
+    fn add_song(&mut self, song_id: Id, artist: Artist) {
+        let separators = self.separators.as_ref().expect("Set by refresh");
+
+        if artist.full_artist_string != UNKNOWN_ARTIST_STR {
+            for credit in artist.credits_with(separators) {
+                self.artists
+                    .entry(credit.name.to_lowercase())
+                    .or_insert_with(|| IndexedArtist {
+                        name: credit.name.to_string(),
+                        songs: HashMap::new(),
+                    })
+                    .songs
+                    .insert(song_id, credit.role);
+            }
+        }
+
+        self.indexed_songs
+            .insert(song_id, artist.full_artist_string);
+    }
+
+    fn remove_song(&mut self, song_id: Id) {
+        let Some(full_artist_string) = self.indexed_songs.remove(&song_id) else {
+            return;
+        };
+
+        let separators = self.separators.as_ref().expect("Set by refresh");
+        let artist = Artist { full_artist_string };
+
+        for credit in artist.credits_with(separators) {
+            let key = credit.name.to_lowercase();
+
+            let Some(indexed_artist) = self.artists.get_mut(&key) else {
+                continue;
+            };
+
+            indexed_artist.songs.remove(&song_id);
+
+            if indexed_artist.songs.is_empty() {
+                self.artists.remove(&key);
+            }
+        }
+    }
+}
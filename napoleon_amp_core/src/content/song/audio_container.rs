@@ -0,0 +1,129 @@
+use derive_enum_all_values::AllValues;
+use serbytes::prelude::SerBytes;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+const SNIFF_LEN: usize = 12;
+
+/// The container format of a stored audio file, which also decides the extension it is stored under
+
+#[derive(SerBytes, AllValues, Default, Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AudioContainer {
+    /// Every song imported before the container was recorded was stored as mp3
+    #[default]
+    Mp3,
+    Flac,
+    Ogg,
+    Wav,
+    M4a,
+    Aac,
+}
+
+impl AudioContainer {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::Flac => "flac",
+            Self::Ogg => "ogg",
+            Self::Wav => "wav",
+            Self::M4a => "m4a",
+            Self::Aac => "aac",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Mp3 => "audio/mpeg",
+            Self::Flac => "audio/flac",
+            Self::Ogg => "audio/ogg",
+            Self::Wav => "audio/wav",
+            Self::M4a => "audio/mp4",
+            Self::Aac => "audio/aac",
+        }
+    }
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        let container = match &*extension.to_ascii_lowercase() {
+            "mp3" => Self::Mp3,
+            "flac" => Self::Flac,
+            "ogg" | "oga" => Self::Ogg,
+            "wav" | "wave" => Self::Wav,
+            "m4a" | "mp4" => Self::M4a,
+            "aac" => Self::Aac,
+            _ => return None,
+        };
+
+        Some(container)
+    }
+
+    /// Detects the container from the first few bytes of an audio file
+
+    pub fn sniff(header: &[u8]) -> Option<Self> {
+        if header.starts_with(b"fLaC") {
+            Some(Self::Flac)
+        } else if header.starts_with(b"OggS") {
+            Some(Self::Ogg)
+        } else if header.starts_with(b"RIFF") && header.get(8..12) == Some(&b"WAVE"[..]) {
+            Some(Self::Wav)
+        } else if header.get(4..8) == Some(&b"ftyp"[..]) {
+            Some(Self::M4a)
+        } else if header.starts_with(b"ID3") {
+            Some(Self::Mp3)
+        } else if let [0xFF, second, ..] = header {
+            // Frame sync, an mpeg layer of 0 is adts aac, anything else is mpeg audio
+            if second & 0xF6 == 0xF0 {
+                Some(Self::Aac)
+            } else if second & 0xE0 == 0xE0 {
+                Some(Self::Mp3)
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    }
+
+    pub fn sniff_file(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        let mut header = Vec::with_capacity(SNIFF_LEN);
+
+        File::open(path)?
+            .take(SNIFF_LEN as u64)
+            .read_to_end(&mut header)?;
+
+        Ok(Self::sniff(&header))
+    }
+
+    /// Detects the container of an audio file from its contents, falling back to its extension
+
+    pub fn from_path(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        let path = path.as_ref();
+
+        if let Some(container) = Self::sniff_file(path)? {
+            return Ok(Some(container));
+        }
+
+        Ok(path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(Self::from_extension))
+    }
+}
+
+impl Display for AudioContainer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_str = match self {
+            Self::Mp3 => "MP3",
+            Self::Flac => "FLAC",
+            Self::Ogg => "Ogg",
+            Self::Wav => "WAV",
+            Self::M4a => "M4A",
+            Self::Aac => "AAC",
+        };
+
+        f.write_str(display_str)
+    }
+}
@@ -0,0 +1,66 @@
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use xxhash_rust::xxh3::Xxh3;
+
+const CHUNK_LEN: u64 = 64 * 1024;
+/// Changed whenever [`hash_file`] hashes differently, so hashes stored by an earlier version are computed
+/// again. 0 was an `FxHasher`, which isn't stable across releases or between 32 and 64 bit targets
+pub(crate) const CONTENT_HASH_VERSION: u8 = 1;
+
+/// Hash of the full contents of an audio file, used to find songs which were imported more than once
+/// under different names. Stored in the library, so it is XXH3 which hashes the same on every platform
+
+pub type ContentHash = u64;
+
+pub(crate) fn hash_file(path: impl AsRef<Path>) -> io::Result<ContentHash> {
+    let mut file = File::open(path)?;
+    let mut hasher = Xxh3::new();
+    let mut chunk = Vec::with_capacity(CHUNK_LEN as usize);
+
+    loop {
+        chunk.clear();
+
+        // Always hash full chunks so the output does not depend on how many bytes each read returns
+        (&mut file).take(CHUNK_LEN).read_to_end(&mut chunk)?;
+
+        if chunk.is_empty() {
+            break;
+        }
+
+        hasher.update(&chunk);
+    }
+
+    Ok(hasher.digest())
+}
+
+/// Compares two files byte for byte, hashes can collide so this is used to confirm a duplicate
+
+pub(crate) fn files_equal(a: impl AsRef<Path>, b: impl AsRef<Path>) -> io::Result<bool> {
+    let mut a_file = File::open(a)?;
+    let mut b_file = File::open(b)?;
+
+    if a_file.metadata()?.len() != b_file.metadata()?.len() {
+        return Ok(false);
+    }
+
+    let mut a_chunk = Vec::with_capacity(CHUNK_LEN as usize);
+    let mut b_chunk = Vec::with_capacity(CHUNK_LEN as usize);
+
+    loop {
+        a_chunk.clear();
+        b_chunk.clear();
+
+        (&mut a_file).take(CHUNK_LEN).read_to_end(&mut a_chunk)?;
+        (&mut b_file).take(CHUNK_LEN).read_to_end(&mut b_chunk)?;
+
+        if a_chunk != b_chunk {
+            return Ok(false);
+        }
+
+        if a_chunk.is_empty() {
+            return Ok(true);
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use crate::content::folder::{Folder, FolderData, FolderDataContentVariant};
+use crate::content::playlist::PlaylistTypeVariant;
+use crate::content::playlist::data::PlaylistSongListData;
+use crate::content::song::locate_song_audio_container;
+use crate::content::song::song_data::SongData;
+use crate::content::song::song_pool::SONG_POOL;
+use crate::content::trash::{TrashEntry, TrashedContent, TrashedSong};
+use crate::instance::iter_playlists::IterPlaylists;
+use crate::paths::song::{song_audio_file_v2, song_data_file_v2};
+use crate::paths::{
+    content_folder_path, content_playlist_song_list_file, content_playlist_song_list_path,
+};
+use crate::safe_write::{SafeWrite, is_temp_file};
+use crate::time_now;
+use serbytes::prelude::SerBytesFs;
+use simple_id::prelude::Id;
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::path::Path;
+use std::rc::Rc;
+use std::{fs, io};
+
+/// Moves songs from the library into the trash, then reloads every playlist under `base_folder` which had already
+/// loaded its songs, so that none of them save the deleted songs back into their song list. Returns the
+/// ids of the trash entries created
+
+pub(crate) fn delete_songs_and_reload_playlists(
+    song_ids: &HashSet<Id>,
+    base_folder: Rc<Folder>,
+) -> io::Result<Vec<Id>> {
+    let trash_entry_ids = delete_songs_from_library(song_ids)?;
+
+    for playlist in IterPlaylists::new(base_folder) {
+        playlist.reload_songs();
+    }
+
+    Ok(trash_entry_ids)
+}
+
+/// Removes songs from the library; from every playlist song list and from the registered songs. The
+/// audio and data of each song is moved into its own trash entry, along with which standard playlists
+/// it was in so it can be restored. Audio shared with other songs is left in the library, see
+/// [`TrashEntry::delete_permanently`]. Playlists which are already loaded still hold the songs. Returns
+/// the ids of the trash entries created.
+///
+/// Songs are only removed from the song lists and registered songs once they are in the trash, if moving
+/// one fails the songs moved before it are still removed, and it and the songs after it are left as they
+/// were
+
+pub(crate) fn delete_songs_from_library(song_ids: &HashSet<Id>) -> io::Result<Vec<Id>> {
+    let mut playlists_containing = standard_playlists_containing(song_ids)?;
+    let mut trash_entry_ids = Vec::with_capacity(song_ids.len());
+    let mut trashed_songs = Vec::with_capacity(song_ids.len());
+
+    let trash_result: io::Result<()> = (|| {
+        for song_id in song_ids.iter().copied() {
+            let playlist_ids = playlists_containing.remove(&song_id).unwrap_or_default();
+            let (trash_entry, shared_audio_id) = move_song_into_trash(song_id, playlist_ids)?;
+
+            trash_entry_ids.push(trash_entry.id);
+            trashed_songs.push((song_id, shared_audio_id));
+        }
+
+        Ok(())
+    })();
+
+    let trashed_song_ids = trashed_songs
+        .iter()
+        .map(|(song_id, _)| *song_id)
+        .collect::<HashSet<_>>();
+
+    let song_lists_result = remove_songs_from_song_lists(&trashed_song_ids);
+
+    for (song_id, shared_audio_id) in trashed_songs {
+        SONG_POOL.unregister_song(song_id);
+
+        if let Some(audio_id) = shared_audio_id {
+            SONG_POOL.remove_shared_audio_user(audio_id, song_id);
+        }
+    }
+
+    SONG_POOL.save_registered_songs()?;
+    song_lists_result?;
+    trash_result?;
+
+    Ok(trash_entry_ids)
+}
+
+/// Creates the trash entry of a song and moves its audio and data into it, returning the entry along
+/// with the id of the shared audio the song uses. If a file fails to move, the files already moved are
+/// put back and the entry is removed
+
+fn move_song_into_trash(
+    song_id: Id,
+    playlist_ids: Vec<Id>,
+) -> io::Result<(TrashEntry, Option<Id>)> {
+    let registered_name = SONG_POOL
+        .get_registered_songs()
+        .name_map
+        .iter()
+        .find(|(_, registered_id)| **registered_id == song_id)
+        .map(|(name, _)| name.clone())
+        .unwrap_or_default();
+
+    let song_data_path = song_data_file_v2(&song_id);
+
+    let (title, audio_container, shared_audio_id) = match SongData::from_file_path(&song_data_path)
+    {
+        Ok(song_data) => (
+            song_data.inner.title.clone(),
+            Some(song_data.inner.audio_container.inner),
+            song_data.inner.shared_audio_id.inner,
+        ),
+
+        Err(_) => (
+            registered_name.clone(),
+            locate_song_audio_container(&song_id),
+            None,
+        ),
+    };
+
+    let trash_entry = TrashEntry::create(
+        title,
+        TrashedContent::Song(TrashedSong {
+            song_id,
+            registered_name,
+            playlist_ids,
+        }),
+    )?;
+
+    let move_result = (|| {
+        // Shared audio isn't part of any one song, so it stays in the library until the last song using
+        // it is deleted from the trash for good
+        if let (None, Some(audio_container)) = (shared_audio_id, audio_container) {
+            trash_entry.move_into(&song_audio_file_v2(&song_id, audio_container))?;
+        }
+
+        trash_entry.move_into(&song_data_path)
+    })();
+
+    if let Err(e) = move_result {
+        if let Err(restore_error) = trash_entry.restore_files() {
+            eprintln!(
+                "Unable to move the files of song {} back out of the trash; {}",
+                song_id, restore_error
+            );
+        }
+
+        return Err(e);
+    }
+
+    Ok((trash_entry, shared_audio_id))
+}
+
+/// Finds which standard playlists each song is in, by going through every folder
+
+fn standard_playlists_containing(song_ids: &HashSet<Id>) -> io::Result<HashMap<Id, Vec<Id>>> {
+    let mut playlists_containing = HashMap::<Id, Vec<Id>>::new();
+
+    let folder_dir = content_folder_path();
+
+    if !folder_dir.try_exists()? {
+        return Ok(playlists_containing);
+    }
+
+    for dir_entry in folder_dir.read_dir()? {
+        let Ok(folder_data) = FolderData::from_file_path(dir_entry?.path()) else {
+            continue;
+        };
+
+        for content in &folder_data.contents {
+            if !matches!(
+                content.variant,
+                FolderDataContentVariant::Playlist(PlaylistTypeVariant::Standard(_))
+            ) {
+                continue;
+            }
+
+            let Ok(song_list_data) =
+                PlaylistSongListData::from_file_path(content_playlist_song_list_file(content.id))
+            else {
+                continue;
+            };
+
+            for song_id in &song_list_data.song_ids {
+                if song_ids.contains(song_id) {
+                    playlists_containing
+                        .entry(*song_id)
+                        .or_default()
+                        .push(content.id);
+                }
+            }
+        }
+    }
+
+    Ok(playlists_containing)
+}
+
+/// Removes songs from the song list file of every playlist, the caches of dynamic playlists included.
+/// Song lists which can't be read are skipped, the library check reports them
+
+pub(crate) fn remove_songs_from_song_lists(song_ids: &HashSet<Id>) -> io::Result<()> {
+    let song_list_dir = content_playlist_song_list_path();
+
+    if !song_list_dir.try_exists()? {
+        return Ok(());
+    }
+
+    for dir_entry in song_list_dir.read_dir()? {
+        let song_list_path = dir_entry?.path();
+
+        // Backups of files outside the library are kept in a directory next to them
+        if !song_list_path.is_file() || is_temp_file(&song_list_path) {
+            continue;
+        }
+
+        if let Err(e) = remove_songs_from_song_list(&song_list_path, song_ids) {
+            eprintln!(
+                "Unable to remove the deleted songs from {:?}; {}",
+                song_list_path, e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn remove_songs_from_song_list(
+    song_list_path: &Path,
+    song_ids: &HashSet<Id>,
+) -> io::Result<()> {
+    let mut song_list_data = PlaylistSongListData::from_file_path(song_list_path)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e.to_string()))?;
+
+    let song_count = song_list_data.song_ids.len();
+
+    song_list_data
+        .song_ids
+        .retain(|song_id| !song_ids.contains(song_id));
+
+    if song_list_data.song_ids.len() == song_count {
+        return Ok(());
+    }
+
+    if song_list_data.last_updated.get() != 0 {
+        // Dynamic playlists compare against this to know when to rebuild their song list
+        song_list_data.last_updated.set(time_now().as_secs());
+    }
+
+    song_list_data.write_to_file_path_safe(song_list_path)
+}
+
+pub(crate) fn remove_file_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,603 @@
+use crate::content::song::Song;
+use crate::content::song::album_index::AlbumIndex;
+use crate::content::song::loudness::meter::{LoudnessMeter, TrackMeasurement, integrated_loudness};
+use crate::content::song::loudness::{Loudness, LoudnessSource, REFERENCE_LOUDNESS};
+use crate::content::song::song_pool::SONG_POOL;
+use crate::{POISONED_LOCK_MESSAGE, unlock_mutex};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::{MediaSourceStream, MediaSourceStreamOptions};
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::Hint;
+use symphonia::core::units::Time;
+use symphonia::default::{get_codecs, get_probe};
+
+/// Decoding is much heavier than importing, so fewer threads are used to keep playback smooth
+const MAX_ANALYSIS_THREADS: usize = 2;
+
+pub enum LoudnessAnalysisEvent {
+    /// Number of songs which will be analyzed
+    SongsFound(usize),
+    Analyzed(Arc<Song>),
+    Failed(Arc<Song>, String),
+    Finished(LoudnessAnalysisReport),
+}
+
+/// How far along a [`LoudnessAnalysisJob`] is
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct LoudnessAnalysisProgress {
+    /// `None` until the songs to analyze have been found
+    pub songs_found: Option<usize>,
+    pub analyzed: usize,
+    pub failed: usize,
+}
+
+impl LoudnessAnalysisProgress {
+    /// Number of songs which have either been analyzed or failed
+    pub fn completed(&self) -> usize {
+        self.analyzed + self.failed
+    }
+
+    pub fn fraction(&self) -> f32 {
+        match self.songs_found {
+            Some(0) => 1.0,
+            Some(songs_found) => self.completed() as f32 / songs_found as f32,
+            None => 0.0,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LoudnessAnalysisReport {
+    /// Number of songs measured by decoding their audio
+    pub analyzed: usize,
+    /// Number of songs whose loudness was read from their ReplayGain tags
+    pub read_from_tags: usize,
+    pub failed: Vec<(Arc<Song>, String)>,
+    pub cancelled: bool,
+}
+
+/// Songs whose loudness is found together, either every track of an album or a single song which isn't on
+/// one
+
+struct AnalysisUnit {
+    songs: Vec<Arc<Song>>,
+    is_album: bool,
+}
+
+impl AnalysisUnit {
+    /// Whether every song already has its loudness, along with its album loudness if it's on an album
+
+    fn is_analyzed(&self) -> bool {
+        self.songs.iter().all(|song| {
+            song.get_song_data()
+                .inner
+                .loudness
+                .inner
+                .is_some_and(|loudness| !self.is_album || loudness.album_loudness.is_some())
+        })
+    }
+}
+
+/// Finds the loudness of the songs in the library on a background thread, so they can be played normalized.
+/// The ui thread should call [`LoudnessAnalysisJob::poll`] each frame
+
+pub struct LoudnessAnalysisJob {
+    event_receiver: Receiver<LoudnessAnalysisEvent>,
+    cancelled: Arc<AtomicBool>,
+    progress: LoudnessAnalysisProgress,
+    finished: bool,
+    _thread: JoinHandle<()>,
+}
+
+impl LoudnessAnalysisJob {
+    /// Starts analyzing every song without a loudness, and every album with a track missing one. If
+    /// `reanalyze` is true every song is analyzed again
+
+    pub fn start(reanalyze: bool) -> Self {
+        let (event_sender, event_receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let thread_cancelled = Arc::clone(&cancelled);
+
+        let thread = thread::spawn(move || {
+            let report = run_analysis(reanalyze, &thread_cancelled, &event_sender);
+
+            let _ = event_sender.send(LoudnessAnalysisEvent::Finished(report));
+        });
+
+        Self {
+            event_receiver,
+            cancelled,
+            progress: LoudnessAnalysisProgress::default(),
+            finished: false,
+            _thread: thread,
+        }
+    }
+
+    /// Stops the analysis after the songs currently being analyzed
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn progress(&self) -> LoudnessAnalysisProgress {
+        self.progress
+    }
+
+    /// Handles every event received since the last poll, returns the report once the analysis has finished
+
+    pub fn poll(&mut self) -> Option<LoudnessAnalysisReport> {
+        if self.finished {
+            return None;
+        }
+
+        loop {
+            match self.event_receiver.try_recv() {
+                Ok(event) => {
+                    self.update_progress(&event);
+
+                    if let LoudnessAnalysisEvent::Finished(report) = event {
+                        return Some(report);
+                    }
+                }
+
+                Err(TryRecvError::Empty) => return None,
+
+                Err(TryRecvError::Disconnected) => {
+                    eprintln!("Loudness analysis thread stopped without finishing");
+                    self.finished = true;
+                    return Some(LoudnessAnalysisReport::default());
+                }
+            }
+        }
+    }
+
+    /// Blocks until the analysis has finished, `on_event` is called with every event and the progress
+    /// including that event
+
+    pub fn wait(
+        mut self,
+        mut on_event: impl FnMut(&LoudnessAnalysisEvent, LoudnessAnalysisProgress),
+    ) -> LoudnessAnalysisReport {
+        while let Ok(event) = self.event_receiver.recv() {
+            self.update_progress(&event);
+
+            on_event(&event, self.progress);
+
+            if let LoudnessAnalysisEvent::Finished(report) = event {
+                return report;
+            }
+        }
+
+        eprintln!("Loudness analysis thread stopped without finishing");
+
+        LoudnessAnalysisReport::default()
+    }
+
+    fn update_progress(&mut self, event: &LoudnessAnalysisEvent) {
+        match event {
+            LoudnessAnalysisEvent::SongsFound(songs_found) => {
+                self.progress.songs_found = Some(*songs_found)
+            }
+            LoudnessAnalysisEvent::Analyzed(_) => self.progress.analyzed += 1,
+            LoudnessAnalysisEvent::Failed(_, _) => self.progress.failed += 1,
+            LoudnessAnalysisEvent::Finished(_) => self.finished = true,
+        }
+    }
+}
+
+fn run_analysis(
+    reanalyze: bool,
+    cancelled: &AtomicBool,
+    event_sender: &Sender<LoudnessAnalysisEvent>,
+) -> LoudnessAnalysisReport {
+    let on_event = |event: LoudnessAnalysisEvent| {
+        let _ = event_sender.send(event);
+    };
+
+    let units = find_analysis_units()
+        .into_iter()
+        .filter(|unit| reanalyze || !unit.is_analyzed())
+        .collect::<Vec<_>>();
+
+    on_event(LoudnessAnalysisEvent::SongsFound(
+        units.iter().map(|unit| unit.songs.len()).sum(),
+    ));
+
+    let report = Mutex::new(LoudnessAnalysisReport::default());
+    let next_index = AtomicUsize::new(0);
+
+    let thread_count = thread::available_parallelism()
+        .map(|parallelism| parallelism.get())
+        .unwrap_or(1)
+        .min(MAX_ANALYSIS_THREADS)
+        .min(units.len())
+        .max(1);
+
+    thread::scope(|scope| {
+        for _ in 0..thread_count {
+            scope.spawn(|| {
+                while !cancelled.load(Ordering::Relaxed) {
+                    let index = next_index.fetch_add(1, Ordering::Relaxed);
+
+                    let Some(unit) = units.get(index) else {
+                        break;
+                    };
+
+                    analyze_unit(unit, cancelled, &report, &on_event);
+                }
+            });
+        }
+    });
+
+    let mut report = report.into_inner().expect(POISONED_LOCK_MESSAGE);
+
+    report.cancelled = cancelled.load(Ordering::Relaxed);
+
+    report
+}
+
+/// Every album in the library, followed by every song which isn't on an album
+
+fn find_analysis_units() -> Vec<AnalysisUnit> {
+    let mut album_index = AlbumIndex::default();
+
+    album_index.refresh();
+
+    let mut album_song_ids = HashSet::new();
+
+    let mut units = album_index
+        .albums()
+        .map(|album| {
+            album_song_ids.extend(album.tracks().iter().map(|track| track.song_id));
+
+            AnalysisUnit {
+                songs: album.songs(),
+                is_album: true,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let single_song_ids = SONG_POOL
+        .get_registered_songs()
+        .name_map
+        .values()
+        .filter(|song_id| !album_song_ids.contains(song_id))
+        .copied()
+        .collect::<Vec<_>>();
+
+    units.extend(single_song_ids.into_iter().map(|song_id| AnalysisUnit {
+        songs: vec![SONG_POOL.get_song_by_id(song_id)],
+        is_album: false,
+    }));
+
+    units
+}
+
+/// Uses the ReplayGain tags of the songs if every song has them, otherwise measures every song
+
+fn analyze_unit(
+    unit: &AnalysisUnit,
+    cancelled: &AtomicBool,
+    report: &Mutex<LoudnessAnalysisReport>,
+    on_event: &impl Fn(LoudnessAnalysisEvent),
+) {
+    let tagged_loudness = unit
+        .songs
+        .iter()
+        .map(|song| {
+            read_replay_gain_tags(song)
+                .filter(|loudness| !unit.is_album || loudness.album_loudness.is_some())
+        })
+        .collect::<Option<Vec<_>>>();
+
+    if let Some(tagged_loudness) = tagged_loudness {
+        for (song, loudness) in unit.songs.iter().zip(tagged_loudness) {
+            save_loudness(song, loudness);
+
+            unlock_mutex(report).read_from_tags += 1;
+            on_event(LoudnessAnalysisEvent::Analyzed(Arc::clone(song)));
+        }
+
+        return;
+    }
+
+    let mut measured = Vec::with_capacity(unit.songs.len());
+
+    for song in &unit.songs {
+        match measure_song(song, cancelled) {
+            Ok(Some(measurement)) => measured.push((song, measurement)),
+
+            // Nothing is saved for a cancelled album, since its loudness depends on every track
+            Ok(None) => return,
+
+            Err(e) => {
+                unlock_mutex(report)
+                    .failed
+                    .push((Arc::clone(song), e.clone()));
+
+                on_event(LoudnessAnalysisEvent::Failed(Arc::clone(song), e));
+            }
+        }
+    }
+
+    // The album loudness is only known if every track could be measured
+    let album_measured = unit.is_album && measured.len() == unit.songs.len();
+
+    let album_loudness = album_measured.then(|| {
+        integrated_loudness(
+            measured
+                .iter()
+                .flat_map(|(_, measurement)| measurement.block_powers.iter().copied()),
+        )
+    });
+
+    let album_peak = album_measured.then(|| {
+        measured
+            .iter()
+            .map(|(_, measurement)| measurement.true_peak)
+            .fold(0.0, f32::max)
+    });
+
+    for (song, measurement) in measured {
+        save_loudness(
+            song,
+            Loudness {
+                track_loudness: measurement.integrated_loudness(),
+                track_peak: measurement.true_peak,
+                album_loudness,
+                album_peak,
+                source: LoudnessSource::Analyzed,
+            },
+        );
+
+        unlock_mutex(report).analyzed += 1;
+        on_event(LoudnessAnalysisEvent::Analyzed(Arc::clone(song)));
+    }
+}
+
+fn save_loudness(song: &Song, loudness: Loudness) {
+    let mut song_data = song.get_song_data_mut();
+
+    song_data.inner.loudness.inner = Some(loudness);
+
+    song.save_song_data_already_borrowed(&song_data);
+}
+
+/// Probes the audio of the song. Also returns the tags found before the container, such as ID3v2, which
+/// aren't part of the format reader's metadata
+
+fn probe_song(song: &Song) -> Result<(Box<dyn FormatReader>, Option<MetadataRevision>), String> {
+    let song_file = File::open(song.song_audio_path()).map_err(|e| e.to_string())?;
+    let audio_container = song.audio_container();
+
+    let mss = MediaSourceStream::new(Box::new(song_file), MediaSourceStreamOptions::default());
+
+    let mut probe_result = get_probe()
+        .format(
+            Hint::new()
+                .with_extension(audio_container.extension())
+                .mime_type(audio_container.mime_type()),
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let metadata_revision = probe_result
+        .metadata
+        .get()
+        .and_then(|metadata| metadata.current().cloned());
+
+    Ok((probe_result.format, metadata_revision))
+}
+
+/// Reads the loudness from the ReplayGain tags of the song. Returns `None` if it doesn't have a track gain,
+/// or if its audio is shared with other tracks of a CUE sheet, since the tags are for the whole file
+
+fn read_replay_gain_tags(song: &Song) -> Option<Loudness> {
+    if song.get_song_data().inner.shared_audio_id.inner.is_some() {
+        return None;
+    }
+
+    let (mut format, probed_metadata_revision) = probe_song(song).ok()?;
+
+    let mut track_gain = None;
+    let mut track_peak = None;
+    let mut album_gain = None;
+    let mut album_peak = None;
+
+    let mut read_revision = |metadata_revision: &MetadataRevision| {
+        for tag in metadata_revision.tags() {
+            let value = tag.value.to_string();
+
+            match tag.std_key {
+                Some(StandardTagKey::ReplayGainTrackGain) => track_gain = parse_gain(&value),
+                Some(StandardTagKey::ReplayGainTrackPeak) => track_peak = parse_gain(&value),
+                Some(StandardTagKey::ReplayGainAlbumGain) => album_gain = parse_gain(&value),
+                Some(StandardTagKey::ReplayGainAlbumPeak) => album_peak = parse_gain(&value),
+                _ => {}
+            }
+        }
+    };
+
+    if let Some(metadata_revision) = &probed_metadata_revision {
+        read_revision(metadata_revision);
+    }
+
+    if let Some(metadata_revision) = format.metadata().current() {
+        read_revision(metadata_revision);
+    }
+
+    let track_gain = track_gain?;
+    let track_peak = track_peak.unwrap_or(1.0);
+
+    Some(Loudness {
+        track_loudness: REFERENCE_LOUDNESS - track_gain,
+        track_peak,
+        album_loudness: album_gain.map(|album_gain| REFERENCE_LOUDNESS - album_gain),
+        album_peak: album_gain.map(|_| album_peak.unwrap_or(track_peak)),
+        source: LoudnessSource::ReplayGainTags,
+    })
+}
+
+/// Parses a gain such as "-7.20 dB", or a peak such as "0.988525"
+
+fn parse_gain(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let value = value
+        .strip_suffix("dB")
+        .or_else(|| value.strip_suffix("db"))
+        .unwrap_or(value);
+
+    value
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|gain| gain.is_finite())
+}
+
+/// Decodes the part of the song between its start offset and end time, returns `Ok(None)` if the analysis
+/// was cancelled part way through
+
+fn measure_song(song: &Song, cancelled: &AtomicBool) -> Result<Option<TrackMeasurement>, String> {
+    let (start_offset, end_time) = {
+        let song_data = song.get_song_data();
+
+        (
+            song_data.inner.start_offset.inner,
+            song_data.inner.end_time.inner,
+        )
+    };
+
+    let (mut format, _) = probe_song(song)?;
+
+    let track = format
+        .default_track()
+        .ok_or_else(|| "No audio track".to_string())?;
+
+    let track_id = track.id;
+    let codec_params = track.codec_params.clone();
+
+    let mut decoder = get_codecs()
+        .make(&codec_params, &DecoderOptions::default())
+        .map_err(|e| e.to_string())?;
+
+    if let Some(start_offset) = start_offset {
+        format
+            .seek(
+                SeekMode::Accurate,
+                SeekTo::Time {
+                    time: Time::from(start_offset.as_secs_f64()),
+                    track_id: Some(track_id),
+                },
+            )
+            .map_err(|e| e.to_string())?;
+    }
+
+    let mut meter = None;
+    let mut sample_buffer = None;
+
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+
+            Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(e.to_string()),
+        };
+
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+
+            // A corrupt packet is skipped, the same as during playback
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(e.to_string()),
+        };
+
+        let spec = *decoded.spec();
+        let channel_count = spec.channels.count();
+
+        if channel_count == 0 {
+            continue;
+        }
+
+        let sample_buffer = sample_buffer
+            .get_or_insert_with(|| SampleBuffer::<f32>::new(decoded.capacity() as u64, spec));
+
+        if sample_buffer.capacity() < decoded.capacity() * channel_count {
+            *sample_buffer = SampleBuffer::new(decoded.capacity() as u64, spec);
+        }
+
+        sample_buffer.copy_interleaved_ref(decoded);
+
+        let meter = meter.get_or_insert_with(|| LoudnessMeter::new(spec.rate, spec.channels));
+
+        let packet_seconds =
+            codec_params
+                .time_base
+                .map_or(packet.ts() as f64 / spec.rate as f64, |time_base| {
+                    let time = time_base.calc_time(packet.ts());
+
+                    time.seconds as f64 + time.frac
+                });
+
+        let frames = sample_buffer.samples().len() / channel_count;
+        let packet_start_frame = (packet_seconds * spec.rate as f64).round() as usize;
+        let to_frame = |time: Duration| (time.as_secs_f64() * spec.rate as f64).round() as usize;
+
+        let first_frame = start_offset
+            .map_or(0, |start_offset| {
+                to_frame(start_offset).saturating_sub(packet_start_frame)
+            })
+            .min(frames);
+
+        let last_frame = end_time.map_or(frames, |end_time| {
+            to_frame(end_time)
+                .saturating_sub(packet_start_frame)
+                .min(frames)
+        });
+
+        if first_frame < last_frame {
+            meter.push_interleaved(
+                &sample_buffer.samples()[first_frame * channel_count..last_frame * channel_count],
+            );
+        }
+
+        if last_frame < frames {
+            break;
+        }
+    }
+
+    meter
+        .map(|meter| Some(meter.finish()))
+        .ok_or_else(|| "No audio could be decoded".to_string())
+}
@@ -0,0 +1,304 @@
+//! Loudness and true peak measurement following ITU-R BS.1770-4, which EBU R128 is based on
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use symphonia::core::audio::Channels;
+
+/// Length of a gating block, in seconds
+const BLOCK_SECONDS: f64 = 0.4;
+/// Gating blocks overlap by 75%, so one starts every quarter of a block
+const BLOCK_STEPS: usize = 4;
+/// Blocks quieter than this are never part of the integrated loudness, in LUFS
+const ABSOLUTE_GATE: f64 = -70.0;
+/// Blocks quieter than the loudness of the blocks above the absolute gate by this much are left out, in LU
+const RELATIVE_GATE: f64 = -10.0;
+
+/// The true peak is measured by oversampling 4 times, enough for sample rates up to 96kHz
+const OVERSAMPLING: usize = 4;
+const TAPS_PER_PHASE: usize = 12;
+
+/// The measurements of a single track, which can be combined with those of the other tracks of its album
+
+#[derive(Debug, Default)]
+pub(super) struct TrackMeasurement {
+    /// Mean square of every gating block, weighted by channel
+    pub(super) block_powers: Vec<f64>,
+    pub(super) true_peak: f32,
+}
+
+impl TrackMeasurement {
+    pub(super) fn integrated_loudness(&self) -> f32 {
+        integrated_loudness(self.block_powers.iter().copied())
+    }
+}
+
+/// Integrated loudness of the gating blocks of one or more tracks, in LUFS
+
+pub(super) fn integrated_loudness(block_powers: impl Iterator<Item = f64> + Clone) -> f32 {
+    let absolute_gated = block_powers.filter(|power| power_to_loudness(*power) > ABSOLUTE_GATE);
+
+    let Some(absolute_mean) = mean(absolute_gated.clone()) else {
+        return ABSOLUTE_GATE as f32;
+    };
+
+    let relative_gate = power_to_loudness(absolute_mean) + RELATIVE_GATE;
+
+    let relative_mean =
+        mean(absolute_gated.filter(|power| power_to_loudness(*power) > relative_gate))
+            .unwrap_or(absolute_mean);
+
+    power_to_loudness(relative_mean) as f32
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), value| {
+        (sum + value, count + 1)
+    });
+
+    (count != 0).then_some(sum / count as f64)
+}
+
+fn power_to_loudness(power: f64) -> f64 {
+    -0.691 + 10.0 * power.max(f64::MIN_POSITIVE).log10()
+}
+
+/// Measures interleaved samples as they're decoded
+
+pub(super) struct LoudnessMeter {
+    channels: Vec<ChannelState>,
+    /// Samples in a quarter of a gating block
+    step_len: usize,
+    /// The weighted sum of squares of the last quarters of a block, a block is complete once there are 4
+    step_sums: VecDeque<f64>,
+    current_step_sum: f64,
+    current_step_len: usize,
+    measurement: TrackMeasurement,
+}
+
+impl LoudnessMeter {
+    pub(super) fn new(sample_rate: u32, channels: Channels) -> Self {
+        let k_weighting = KWeighting::new(sample_rate as f64);
+        let oversampling_phases = oversampling_phases();
+
+        Self {
+            channels: channels
+                .iter()
+                .map(|channel| ChannelState {
+                    weight: channel_weight(channel),
+                    k_weighting: k_weighting.clone(),
+                    history: VecDeque::from(vec![0.0; TAPS_PER_PHASE]),
+                    oversampling_phases: oversampling_phases.clone(),
+                })
+                .collect(),
+            step_len: (sample_rate as f64 * BLOCK_SECONDS / BLOCK_STEPS as f64).round() as usize,
+            step_sums: VecDeque::with_capacity(BLOCK_STEPS),
+            current_step_sum: 0.0,
+            current_step_len: 0,
+            measurement: TrackMeasurement::default(),
+        }
+    }
+
+    /// Measures samples interleaved by channel, any trailing partial frame is ignored
+
+    pub(super) fn push_interleaved(&mut self, samples: &[f32]) {
+        let channel_count = self.channels.len();
+
+        if channel_count == 0 {
+            return;
+        }
+
+        for frame in samples.chunks_exact(channel_count) {
+            let mut frame_sum = 0.0;
+
+            for (channel, sample) in self.channels.iter_mut().zip(frame) {
+                let filtered = channel.k_weighting.process(*sample as f64);
+                frame_sum += channel.weight * filtered * filtered;
+
+                let true_peak = channel.true_peak(*sample);
+                self.measurement.true_peak = self.measurement.true_peak.max(true_peak);
+            }
+
+            self.current_step_sum += frame_sum;
+            self.current_step_len += 1;
+
+            if self.current_step_len == self.step_len {
+                self.finish_step();
+            }
+        }
+    }
+
+    pub(super) fn finish(self) -> TrackMeasurement {
+        self.measurement
+    }
+
+    fn finish_step(&mut self) {
+        if self.step_sums.len() == BLOCK_STEPS {
+            self.step_sums.pop_front();
+        }
+
+        self.step_sums.push_back(self.current_step_sum);
+        self.current_step_sum = 0.0;
+        self.current_step_len = 0;
+
+        if self.step_sums.len() == BLOCK_STEPS {
+            let block_sum = self.step_sums.iter().sum::<f64>();
+
+            self.measurement
+                .block_powers
+                .push(block_sum / (self.step_len * BLOCK_STEPS) as f64);
+        }
+    }
+}
+
+struct ChannelState {
+    weight: f64,
+    k_weighting: KWeighting,
+    /// The latest samples, newest first
+    history: VecDeque<f32>,
+    oversampling_phases: Vec<[f32; TAPS_PER_PHASE]>,
+}
+
+impl ChannelState {
+    /// The highest peak between the previous sample and `sample`
+
+    fn true_peak(&mut self, sample: f32) -> f32 {
+        self.history.pop_back();
+        self.history.push_front(sample);
+
+        self.oversampling_phases
+            .iter()
+            .map(|phase| {
+                phase
+                    .iter()
+                    .zip(&self.history)
+                    .map(|(coefficient, sample)| coefficient * sample)
+                    .sum::<f32>()
+                    .abs()
+            })
+            .fold(sample.abs(), f32::max)
+    }
+}
+
+/// Surround channels are louder to the listener, and the LFE channel isn't measured at all
+
+fn channel_weight(channel: Channels) -> f64 {
+    if channel.intersects(Channels::LFE1 | Channels::LFE2) {
+        0.0
+    } else if channel.intersects(
+        Channels::SIDE_LEFT | Channels::SIDE_RIGHT | Channels::REAR_LEFT | Channels::REAR_RIGHT,
+    ) {
+        1.41
+    } else {
+        1.0
+    }
+}
+
+/// The coefficients of each phase of a windowed sinc interpolation filter, each phase sums to 1
+
+fn oversampling_phases() -> Vec<[f32; TAPS_PER_PHASE]> {
+    let taps = OVERSAMPLING * TAPS_PER_PHASE;
+    let center = (taps - 1) as f64 / 2.0;
+
+    (0..OVERSAMPLING)
+        .map(|phase| {
+            let mut coefficients = [0.0; TAPS_PER_PHASE];
+
+            for (tap, coefficient) in coefficients.iter_mut().enumerate() {
+                let n = tap * OVERSAMPLING + phase;
+                let x = (n as f64 - center) / OVERSAMPLING as f64;
+
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (PI * x).sin() / (PI * x)
+                };
+
+                // Hann window
+                let window = 0.5 - 0.5 * (2.0 * PI * (n as f64 + 0.5) / taps as f64).cos();
+
+                *coefficient = (sinc * window) as f32;
+            }
+
+            let sum = coefficients.iter().sum::<f32>();
+
+            coefficients.map(|coefficient| coefficient / sum)
+        })
+        .collect()
+}
+
+/// The two filters applied before measuring loudness, a high shelf modelling the head and a high pass
+
+#[derive(Clone)]
+struct KWeighting {
+    shelf: Biquad,
+    high_pass: Biquad,
+}
+
+impl KWeighting {
+    /// Coefficients for any sample rate, matching those given for 48kHz in BS.1770
+
+    fn new(sample_rate: f64) -> Self {
+        let shelf = {
+            let f0 = 1681.974450955533;
+            let gain_db = 3.999843853973347;
+            let q = 0.7071752369554196;
+
+            let k = (PI * f0 / sample_rate).tan();
+            let vh = 10_f64.powf(gain_db / 20.0);
+            let vb = vh.powf(0.4996667741545416);
+            let a0 = 1.0 + k / q + k * k;
+
+            Biquad::new(
+                [
+                    (vh + vb * k / q + k * k) / a0,
+                    2.0 * (k * k - vh) / a0,
+                    (vh - vb * k / q + k * k) / a0,
+                ],
+                [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            )
+        };
+
+        let high_pass = {
+            let f0 = 38.13547087602444;
+            let q = 0.5003270373238773;
+
+            let k = (PI * f0 / sample_rate).tan();
+            let a0 = 1.0 + k / q + k * k;
+
+            Biquad::new(
+                [1.0, -2.0, 1.0],
+                [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+            )
+        };
+
+        Self { shelf, high_pass }
+    }
+
+    fn process(&mut self, sample: f64) -> f64 {
+        self.high_pass.process(self.shelf.process(sample))
+    }
+}
+
+#[derive(Clone)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    z: [f64; 2],
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 2]) -> Self {
+        Self { b, a, z: [0.0; 2] }
+    }
+
+    /// Transposed direct form II
+
+    fn process(&mut self, input: f64) -> f64 {
+        let output = self.b[0] * input + self.z[0];
+
+        self.z[0] = self.b[1] * input - self.a[0] * output + self.z[1];
+        self.z[1] = self.b[2] * input - self.a[1] * output;
+
+        output
+    }
+}
@@ -0,0 +1,112 @@
+pub mod analysis;
+mod meter;
+
+use crate::{read_rwlock, write_rwlock};
+use derive_enum_all_values::AllValues;
+use serbytes::prelude::SerBytes;
+use std::fmt::{Display, Formatter};
+use std::sync::RwLock;
+
+/// The loudness songs are normalized to, in LUFS. The same as ReplayGain 2.0, so gains read from tags
+/// can be used as they are
+pub const REFERENCE_LOUDNESS: f32 = -18.0;
+
+/// The normalization mode songs are played with, set from the client settings when the instance is created
+static NORMALIZATION_MODE: RwLock<NormalizationMode> = RwLock::new(NormalizationMode::Off);
+
+/// Loudness of a song measured with EBU R128, or read from its ReplayGain tags
+
+#[derive(SerBytes, Copy, Clone, Debug, PartialEq)]
+pub struct Loudness {
+    /// Integrated loudness of the song, in LUFS
+    pub track_loudness: f32,
+    /// Highest true peak of the song, where 1.0 is full scale
+    pub track_peak: f32,
+    /// Integrated loudness of every track on the album of the song measured together, in LUFS. `None` if the
+    /// song isn't on an album
+    pub album_loudness: Option<f32>,
+    /// Highest true peak of every track on the album of the song
+    pub album_peak: Option<f32>,
+    pub source: LoudnessSource,
+}
+
+impl Loudness {
+    /// The gain in decibels which brings the track to [`REFERENCE_LOUDNESS`]
+
+    pub fn track_gain_db(&self) -> f32 {
+        REFERENCE_LOUDNESS - self.track_loudness
+    }
+
+    /// The gain in decibels which brings the album to [`REFERENCE_LOUDNESS`], if the song is on one
+
+    pub fn album_gain_db(&self) -> Option<f32> {
+        self.album_loudness
+            .map(|album_loudness| REFERENCE_LOUDNESS - album_loudness)
+    }
+
+    /// The gain in decibels to play the song with in `mode`. Album mode uses the track gain for songs which
+    /// aren't on an album
+
+    pub fn gain_db(&self, mode: NormalizationMode) -> f32 {
+        match mode {
+            NormalizationMode::Off => 0.0,
+            NormalizationMode::Track => self.track_gain_db(),
+            NormalizationMode::Album => self.album_gain_db().unwrap_or(self.track_gain_db()),
+        }
+    }
+}
+
+#[derive(SerBytes, Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LoudnessSource {
+    Analyzed,
+    ReplayGainTags,
+}
+
+impl Display for LoudnessSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_str = match self {
+            Self::Analyzed => "Analyzed",
+            Self::ReplayGainTags => "ReplayGain tags",
+        };
+
+        f.write_str(display_str)
+    }
+}
+
+/// Which gain songs are played with so they all play at a similar loudness
+
+#[derive(SerBytes, AllValues, Default, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum NormalizationMode {
+    #[default]
+    Off,
+    /// Every song plays at the same loudness
+    Track,
+    /// Every album plays at the same loudness, keeping the differences between its tracks
+    Album,
+}
+
+impl Display for NormalizationMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_str = match self {
+            Self::Off => "Off",
+            Self::Track => "Track",
+            Self::Album => "Album",
+        };
+
+        f.write_str(display_str)
+    }
+}
+
+pub fn normalization_mode() -> NormalizationMode {
+    *read_rwlock(&NORMALIZATION_MODE)
+}
+
+/// Changes the normalization mode songs are played with, from the next song played
+
+pub fn set_normalization_mode(mode: NormalizationMode) {
+    *write_rwlock(&NORMALIZATION_MODE) = mode;
+}
+
+pub(crate) fn db_to_amplitude(db: f32) -> f32 {
+    10_f32.powf(db / 20.0)
+}
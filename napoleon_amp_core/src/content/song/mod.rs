@@ -1,12 +1,20 @@
+pub mod album_index;
+pub mod artist_index;
+pub mod audio_container;
+pub mod content_hash;
+pub(crate) mod delete;
+pub mod loudness;
 pub mod song_cover_pool;
 pub mod song_data;
 pub(crate) mod song_pool;
 
+use crate::content::song::audio_container::AudioContainer;
 use crate::content::song::song_data::v4::DEFAULT_CUSTOM_VOLUME;
 use crate::content::song::song_data::{SongData, get_song_data_from_song_file};
 use crate::paths::song::{song_audio_file_v2, song_data_file_v2};
+use crate::safe_write::SafeWrite;
 use crate::{ReadGuard, WriteGuard, read_rwlock, write_rwlock};
-use serbytes::prelude::SerBytesFs;
+use derive_enum_all_values::AllValues;
 use simple_id::prelude::Id;
 use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
@@ -18,24 +26,28 @@ pub static UNKNOWN_ALBUM_STR: &str = "Unknown Album";
 #[derive(Debug)]
 pub struct Song {
     pub(crate) id: Id,
-    pub song_audio_path: PathBuf,
     pub song_data_path: PathBuf,
+    /// Set once the song data has been loaded, since the extension depends on the audio container
+    song_audio_path: OnceLock<PathBuf>,
     pub(super) song_data: OnceLock<RwLock<SongData>>,
 }
 
 impl Song {
     pub(crate) fn new(song_id: Id) -> Self {
-        let song_audio_path = song_audio_file_v2(&song_id);
         let song_data_path = song_data_file_v2(&song_id);
 
         Self {
             song_data: OnceLock::new(),
             song_data_path,
-            song_audio_path,
+            song_audio_path: OnceLock::new(),
             id: song_id,
         }
     }
 
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
     pub fn get_song_data(&self) -> ReadGuard<'_, SongData> {
         let song_data_lock = self.get_song_data_rwlock();
 
@@ -50,12 +62,17 @@ impl Song {
 
     pub fn get_song_data_rwlock(&self) -> &RwLock<SongData> {
         self.song_data.get_or_init(|| {
-            let mut song_data = match SongData::from_file_path(&self.song_data_path) {
+            let mut song_data = match SongData::from_file_path_or_backup(&self.song_data_path) {
                 Ok(song_data) => song_data,
                 Err(e) => {
                     eprintln!("{}", e);
 
                     let mut sd = SongData::default();
+
+                    if let Some(audio_container) = locate_song_audio_container(&self.id) {
+                        sd.inner.audio_container = audio_container.into();
+                    }
+
                     get_song_data_from_song_file(&self, &mut sd);
 
                     sd
@@ -79,15 +96,10 @@ impl Song {
             // sdi.start_offset.inner = None;
             // sdi.end_time.inner = None;
 
+            // Metadata missing from the song data is read from the audio and saved as the song loads. The
+            // library migrations read it for every song up front, this covers songs whose audio was missing
             if sdi.meta.inner.has_err() {
                 get_song_data_from_song_file(&self, &mut song_data);
-            } else {
-                if song_data.did_update() {
-                    println!("updating song data");
-                    song_data
-                        .write_to_file_path(song_data_file_v2(&self.id))
-                        .expect("unable to update song data to latest version");
-                }
             }
 
             let meta = &mut song_data.inner.meta.inner;
@@ -100,10 +112,29 @@ impl Song {
                 *meta.album.unwrapped_mut() = UNKNOWN_ALBUM_STR.into();
             }
 
+            let _ = self.song_audio_path.set(song_audio_file_v2(
+                &song_data.inner.audio_id(self.id),
+                song_data.inner.audio_container.inner,
+            ));
+
             RwLock::new(song_data)
         })
     }
 
+    /// Path to the stored audio file of this song, loads the song data if it isn't already
+
+    pub fn song_audio_path(&self) -> &PathBuf {
+        self.get_song_data_rwlock();
+
+        self.song_audio_path
+            .get()
+            .expect("Set when the song data is loaded")
+    }
+
+    pub fn audio_container(&self) -> AudioContainer {
+        self.get_song_data().inner.audio_container.inner
+    }
+
     pub fn set_song_data_and_save(&self, new_song_data: SongData) {
         let mut song_data = self.get_song_data_mut();
 
@@ -120,7 +151,7 @@ impl Song {
 
     pub fn save_song_data_already_borrowed(&self, song_data: &SongData) {
         song_data
-            .write_to_file_path(&self.song_data_path)
+            .write_to_file_path_safe(&self.song_data_path)
             .expect("Write song data to file");
     }
 }
@@ -144,3 +175,17 @@ impl Hash for Song {
         self.id.hash(state);
     }
 }
+
+/// Finds which container the audio file for a song was stored as, for when there is no song data to
+/// read it from
+
+fn locate_song_audio_container(song_id: &Id) -> Option<AudioContainer> {
+    AudioContainer::all_values()
+        .iter()
+        .copied()
+        .find(|audio_container| {
+            song_audio_file_v2(song_id, *audio_container)
+                .try_exists()
+                .unwrap_or(false)
+        })
+}
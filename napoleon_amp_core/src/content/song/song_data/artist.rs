@@ -1,9 +1,24 @@
 use crate::content::song::UNKNOWN_ARTIST_STR;
+use crate::{read_rwlock, write_rwlock};
 use serbytes::prelude::SerBytes;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, LazyLock, RwLock};
+
+/// The separators artist credits are parsed with, set from the client settings when the instance is created
+static ARTIST_SEPARATORS: LazyLock<RwLock<ArtistSeparators>> = LazyLock::new(Default::default);
+/// The credits parsed from each artist string with [`ARTIST_SEPARATORS`], as the byte range of each name
+/// in the string so every artist with the same string can borrow them. Cleared when the separators change
+static PARSED_CREDITS: LazyLock<RwLock<HashMap<String, Arc<[ParsedCredit]>>>> =
+    LazyLock::new(Default::default);
+
+type ParsedCredit = (usize, usize, ArtistRole);
 
 #[derive(SerBytes, Clone, Debug)]
 pub struct Artist {
-    /// The full artist string which includes all artists that contributed to the song, separated by slashes (/)
+    /// The full artist string which includes all artists that contributed to the song, separated by any of
+    /// the [`ArtistSeparators`]
     pub full_artist_string: String,
 }
 
@@ -14,11 +29,101 @@ impl Artist {
         }
     }
 
+    /// The first artist credited, or the unknown artist if there are none
+
     pub fn main_artist(&self) -> &str {
-        self.full_artist_string
-            .split("/")
-            .next()
-            .unwrap_or(UNKNOWN_ARTIST_STR)
+        self.credits()
+            .first()
+            .map_or(UNKNOWN_ARTIST_STR, |credit| credit.name)
+    }
+
+    /// Every artist credited, ordered as [`Artist::credits_with`] orders them, parsed with the separators
+    /// from the settings. Parsed once for each artist string until the separators change
+
+    pub fn credits(&self) -> Vec<ArtistCredit<'_>> {
+        let cached_credits = read_rwlock(&PARSED_CREDITS)
+            .get(&self.full_artist_string)
+            .cloned();
+
+        let parsed_credits = match cached_credits {
+            Some(parsed_credits) => parsed_credits,
+
+            None => {
+                // Held while caching, so credits parsed with separators which were just replaced aren't cached
+                let separators = read_rwlock(&ARTIST_SEPARATORS);
+                let string_start = self.full_artist_string.as_ptr() as usize;
+
+                let parsed_credits = self
+                    .credits_with(&separators)
+                    .iter()
+                    .map(|credit| {
+                        // Every name is a slice of the full artist string
+                        let start = credit.name.as_ptr() as usize - string_start;
+
+                        (start, start + credit.name.len(), credit.role)
+                    })
+                    .collect::<Arc<[_]>>();
+
+                write_rwlock(&PARSED_CREDITS)
+                    .insert(self.full_artist_string.clone(), Arc::clone(&parsed_credits));
+
+                parsed_credits
+            }
+        };
+
+        parsed_credits
+            .iter()
+            .map(|&(start, end, role)| ArtistCredit {
+                name: &self.full_artist_string[start..end],
+                role,
+            })
+            .collect()
+    }
+
+    /// Every artist credited, primary artists first, then featured artists, then remixers. An artist
+    /// credited more than once is only listed the first time
+
+    pub fn credits_with(&self, separators: &ArtistSeparators) -> Vec<ArtistCredit<'_>> {
+        let mut credits = Vec::new();
+        let mut credit_groups = Vec::new();
+        let mut main_part = self.full_artist_string.trim();
+
+        // Trailing groups such as "(feat. B)" or "[B Remix]", any other bracketed text is part of the name
+        while let Some((rest, group)) = split_trailing_group(main_part) {
+            let Some(group_credit) = group_credit(group, separators) else {
+                break;
+            };
+
+            credit_groups.push(group_credit);
+            main_part = rest;
+        }
+
+        let (main_part, remixer_part) = split_at_separator(main_part, &separators.remixer);
+        let (main_part, featured_part) = split_at_separator(main_part, &separators.featured);
+
+        push_credits(&mut credits, main_part, ArtistRole::Primary, separators);
+
+        if let Some(featured_part) = featured_part {
+            push_credits(
+                &mut credits,
+                featured_part,
+                ArtistRole::Featured,
+                separators,
+            );
+        }
+
+        // Groups were found from the end, so are reversed back into the order they were written in
+        for (group_part, role) in credit_groups.into_iter().rev() {
+            push_credits(&mut credits, group_part, role, separators);
+        }
+
+        if let Some(remixer_part) = remixer_part {
+            push_credits(&mut credits, remixer_part, ArtistRole::Remixer, separators);
+        }
+
+        credits.sort_by_key(|credit| credit.role);
+
+        credits
     }
 }
 
@@ -27,3 +132,193 @@ impl Default for Artist {
         Self::new(UNKNOWN_ARTIST_STR)
     }
 }
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ArtistRole {
+    Primary,
+    Featured,
+    Remixer,
+}
+
+impl Display for ArtistRole {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_str = match self {
+            Self::Primary => "Primary",
+            Self::Featured => "Featured",
+            Self::Remixer => "Remixer",
+        };
+
+        f.write_str(display_str)
+    }
+}
+
+/// A single artist credited on a song
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ArtistCredit<'a> {
+    pub name: &'a str,
+    pub role: ArtistRole,
+}
+
+/// The text artist credits are split on, matched ignoring case and surrounding whitespace. Separators which
+/// start or end with a letter, such as "feat.", only match as a whole word
+
+#[derive(SerBytes, Clone, Debug, PartialEq)]
+pub struct ArtistSeparators {
+    /// Separate artists with equal credit, such as "A / B". "&" and "," aren't separators unless added,
+    /// since they are part of names such as "Simon & Garfunkel" or "Tyler, The Creator"
+    pub primary: Vec<String>,
+    /// Precede the featured artists, such as "A feat. B"
+    pub featured: Vec<String>,
+    /// Precede the remixers, such as "A remixed by B". "(B Remix)" is always read as a remixer
+    pub remixer: Vec<String>,
+}
+
+impl Default for ArtistSeparators {
+    fn default() -> Self {
+        Self {
+            primary: ["/", ";"].map(String::from).to_vec(),
+            featured: ["feat.", "feat", "ft.", "ft", "featuring"]
+                .map(String::from)
+                .to_vec(),
+            remixer: ["remixed by", "remix by"].map(String::from).to_vec(),
+        }
+    }
+}
+
+pub fn artist_separators() -> ArtistSeparators {
+    read_rwlock(&ARTIST_SEPARATORS).clone()
+}
+
+/// Changes the separators every artist credit is parsed with from now on
+
+pub fn set_artist_separators(separators: ArtistSeparators) {
+    let mut artist_separators = write_rwlock(&ARTIST_SEPARATORS);
+
+    if *artist_separators != separators {
+        *artist_separators = separators;
+        write_rwlock(&PARSED_CREDITS).clear();
+    }
+}
+
+/// Splits the text before the first of `separators` from the text after it
+
+fn split_at_separator<'a>(
+    artist_str: &'a str,
+    separators: &[String],
+) -> (&'a str, Option<&'a str>) {
+    match find_separator(artist_str, separators) {
+        Some((start, end)) => (&artist_str[..start], Some(&artist_str[end..])),
+        None => (artist_str, None),
+    }
+}
+
+fn push_credits<'a>(
+    credits: &mut Vec<ArtistCredit<'a>>,
+    credit_part: &'a str,
+    role: ArtistRole,
+    separators: &ArtistSeparators,
+) {
+    let mut rest = Some(credit_part);
+
+    while let Some(current) = rest {
+        let (name, next) = split_at_separator(current, &separators.primary);
+        let name = name.trim();
+
+        if !name.is_empty()
+            && !credits
+                .iter()
+                .any(|credit| credit.name.eq_ignore_ascii_case(name))
+        {
+            credits.push(ArtistCredit { name, role });
+        }
+
+        rest = next;
+    }
+}
+
+/// The byte range of the earliest separator in `artist_str`, preferring the longest when several start at
+/// the same place
+
+fn find_separator(artist_str: &str, separators: &[String]) -> Option<(usize, usize)> {
+    // Only ascii is lowercased, so byte indices are the same in both
+    let lowercase = artist_str.to_ascii_lowercase();
+
+    separators
+        .iter()
+        .map(|separator| separator.trim().to_ascii_lowercase())
+        .filter(|separator| !separator.is_empty())
+        .filter_map(|separator| {
+            lowercase
+                .match_indices(&separator)
+                .map(|(start, _)| (start, start + separator.len()))
+                .find(|&(start, end)| is_separator_boundary(&lowercase, start, end))
+        })
+        .min_by_key(|&(start, end)| (start, Reverse(end)))
+}
+
+fn is_separator_boundary(artist_str: &str, start: usize, end: usize) -> bool {
+    let separator = &artist_str[start..end];
+
+    let starts_word = separator.starts_with(char::is_alphanumeric);
+    let ends_word = separator.ends_with(char::is_alphanumeric);
+
+    let before_is_word = artist_str[..start]
+        .chars()
+        .next_back()
+        .is_some_and(char::is_alphanumeric);
+    let after_is_word = artist_str[end..]
+        .chars()
+        .next()
+        .is_some_and(char::is_alphanumeric);
+
+    !(starts_word && before_is_word) && !(ends_word && after_is_word)
+}
+
+/// Splits a trailing group in brackets from the rest of `artist_str`
+
+fn split_trailing_group(artist_str: &str) -> Option<(&str, &str)> {
+    let artist_str = artist_str.trim_end();
+
+    let open_bracket = match artist_str.chars().next_back()? {
+        ')' => '(',
+        ']' => '[',
+        _ => return None,
+    };
+
+    let open_index = artist_str.rfind(open_bracket)?;
+
+    Some((
+        &artist_str[..open_index],
+        &artist_str[open_index + 1..artist_str.len() - 1],
+    ))
+}
+
+/// The artists and their role in a bracketed group, if it credits any
+
+fn group_credit<'a>(
+    group: &'a str,
+    separators: &ArtistSeparators,
+) -> Option<(&'a str, ArtistRole)> {
+    let group = group.trim();
+
+    for (role_separators, role) in [
+        (&separators.featured, ArtistRole::Featured),
+        (&separators.remixer, ArtistRole::Remixer),
+    ] {
+        if let Some((0, end)) = find_separator(group, role_separators) {
+            return Some((&group[end..], role));
+        }
+    }
+
+    let remix_start = group.len().checked_sub("remix".len())?;
+
+    if group.is_char_boundary(remix_start)
+        && group[remix_start..].eq_ignore_ascii_case("remix")
+        && group[..remix_start].ends_with(char::is_whitespace)
+    {
+        return Some((&group[..remix_start], ArtistRole::Remixer));
+    }
+
+    None
+}
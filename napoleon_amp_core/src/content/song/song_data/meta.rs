@@ -24,24 +24,6 @@ pub struct SongDataMetaV2 {
     pub cover: ResultBlock<Option<SongCoverId>>,
 }
 
-impl SongDataMetaV2 {
-    pub(super) fn default_ok() -> Self {
-        Self {
-            artist: Artist::default().into(),
-            album: UNKNOWN_ALBUM_STR.to_string().into(),
-            song_length: 0.into(),
-            cover: None.into(),
-        }
-    }
-
-    pub(crate) fn has_err(&self) -> bool {
-        self.artist.inner.is_err()
-            || self.album.inner.is_err()
-            || self.song_length.inner.is_err()
-            || self.cover.inner.is_err()
-    }
-}
-
 impl Default for SongDataMetaV2 {
     fn default() -> Self {
         Self {
@@ -73,3 +55,119 @@ impl<T> AssignIfError<T> for BBReadResult<T> {
         }
     }
 }
+
+/// Any part of song data that can be retrieved with parsing the audio file, text fields are empty and
+/// number fields are `None` when the audio file doesn't have the tag
+
+#[derive(SerBytes, Clone, Debug)]
+pub struct SongDataMetaV3 {
+    pub artist: ResultBlock<Artist>,
+    pub album: ResultBlock<String>,
+    pub song_length: ResultBlock<u32>,
+    pub cover: ResultBlock<Option<SongCoverId>>,
+    pub track_number: ResultBlock<Option<u32>>,
+    pub disc_number: ResultBlock<Option<u32>>,
+    /// The release date as written in the tag, usually a year or an ISO 8601 date
+    pub date: ResultBlock<String>,
+    pub genre: ResultBlock<String>,
+    pub album_artist: ResultBlock<String>,
+    pub composer: ResultBlock<String>,
+    pub bpm: ResultBlock<Option<u32>>,
+    pub comment: ResultBlock<String>,
+}
+
+impl SongDataMetaV3 {
+    pub(super) fn default_ok() -> Self {
+        Self {
+            artist: Artist::default().into(),
+            album: UNKNOWN_ALBUM_STR.to_string().into(),
+            song_length: 0.into(),
+            cover: None.into(),
+            track_number: None.into(),
+            disc_number: None.into(),
+            date: String::new().into(),
+            genre: String::new().into(),
+            album_artist: String::new().into(),
+            composer: String::new().into(),
+            bpm: None.into(),
+            comment: String::new().into(),
+        }
+    }
+
+    pub(crate) fn has_err(&self) -> bool {
+        self.artist.inner.is_err()
+            || self.album.inner.is_err()
+            || self.song_length.inner.is_err()
+            || self.cover.inner.is_err()
+            || self.track_number.inner.is_err()
+            || self.disc_number.inner.is_err()
+            || self.date.inner.is_err()
+            || self.genre.inner.is_err()
+            || self.album_artist.inner.is_err()
+            || self.composer.inner.is_err()
+            || self.bpm.inner.is_err()
+            || self.comment.inner.is_err()
+    }
+
+    /// The year of the release date, if it starts with one
+
+    pub fn year(&self) -> Option<u32> {
+        let date = self.date.inner.as_deref().ok()?;
+        let year = date.get(..4)?;
+
+        year.parse().ok()
+    }
+
+    /// Sets every field which failed to be read from the audio file to its default, so it isn't read again
+
+    pub(super) fn fill_errors(&mut self) {
+        self.artist.inner.assign_if_err_callback(Artist::default);
+        self.album
+            .inner
+            .assign_if_err_callback(|| UNKNOWN_ALBUM_STR.to_string());
+        self.song_length.inner.assign_if_err(0);
+        self.cover.inner.assign_if_err(None);
+        self.track_number.inner.assign_if_err(None);
+        self.disc_number.inner.assign_if_err(None);
+        self.date.inner.assign_if_err(String::new());
+        self.genre.inner.assign_if_err(String::new());
+        self.album_artist.inner.assign_if_err(String::new());
+        self.composer.inner.assign_if_err(String::new());
+        self.bpm.inner.assign_if_err(None);
+        self.comment.inner.assign_if_err(String::new());
+    }
+}
+
+impl Default for SongDataMetaV3 {
+    fn default() -> Self {
+        Self {
+            artist: Err(ReadError::default()).into(),
+            album: Err(ReadError::default()).into(),
+            song_length: Err(ReadError::default()).into(),
+            cover: Err(ReadError::default()).into(),
+            track_number: Err(ReadError::default()).into(),
+            disc_number: Err(ReadError::default()).into(),
+            date: Err(ReadError::default()).into(),
+            genre: Err(ReadError::default()).into(),
+            album_artist: Err(ReadError::default()).into(),
+            composer: Err(ReadError::default()).into(),
+            bpm: Err(ReadError::default()).into(),
+            comment: Err(ReadError::default()).into(),
+        }
+    }
+}
+
+impl From<SongDataMetaV2> for SongDataMetaV3 {
+    /// The fields V2 doesn't have are left as errors, so they are read from the audio file when the song
+    /// is next loaded
+
+    fn from(meta_v2: SongDataMetaV2) -> Self {
+        Self {
+            artist: meta_v2.artist,
+            album: meta_v2.album,
+            song_length: meta_v2.song_length,
+            cover: meta_v2.cover,
+            ..Default::default()
+        }
+    }
+}
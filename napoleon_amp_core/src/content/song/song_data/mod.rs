@@ -1,4 +1,4 @@
-mod artist;
+pub mod artist;
 pub mod meta;
 mod util;
 mod v1;
@@ -6,7 +6,9 @@ mod v2;
 mod v3;
 pub(super) mod v4;
 pub mod v5;
+pub mod v6;
 
+use crate::content::song::Song;
 use crate::content::song::song_cover_pool::SONG_COVER_POOL;
 pub(crate) use crate::content::song::song_data::artist::Artist;
 use crate::content::song::song_data::meta::{AssignIfError, SongDataMetaV2};
@@ -15,12 +17,14 @@ use crate::content::song::song_data::v2::SongDataStdV2;
 use crate::content::song::song_data::v3::SongDataStdV3;
 use crate::content::song::song_data::v4::SongDataStdV4;
 use crate::content::song::song_data::v5::SongDataStdV5;
-use crate::content::song::{Song, UNKNOWN_ALBUM_STR};
+use crate::content::song::song_data::v6::SongDataStdV6;
+use crate::paths::song::song_audio_file_v2;
+use crate::safe_write::SafeWrite;
 use serbytes::prelude::{
-    BBReadResult, CurrentVersion, ReadByteBufferRefMut, SerBytes, SerBytesFs, SizedBlock,
-    VersioningWrapper,
+    BBReadResult, CurrentVersion, ReadByteBufferRefMut, SerBytes, SizedBlock, VersioningWrapper,
 };
 use std::fs::File;
+use std::io;
 use std::path::PathBuf;
 use std::time::Duration;
 use symphonia::core::formats::FormatOptions;
@@ -31,7 +35,7 @@ use symphonia::default::get_probe;
 
 pub const MAX_RATING: u32 = 5;
 
-pub type SongDataStd = SongDataStdV5;
+pub type SongDataStd = SongDataStdV6;
 pub type SongData = VersioningWrapper<SongDataStd, SongDataVersion>;
 
 #[derive(SerBytes, Default, Debug, Copy, Clone, Eq, PartialEq)]
@@ -41,15 +45,16 @@ pub enum SongDataVersion {
     V3,
 
     V4,
-    #[default]
     V5,
+    #[default]
+    V6,
 }
 
 impl CurrentVersion for SongDataVersion {
     type Output = SongDataStd;
 
     fn get_data_from_buf(&self, buf: &mut ReadByteBufferRefMut) -> BBReadResult<Self::Output> {
-        match self {
+        let sd_v5 = match self {
             Self::V1 => {
                 let sd_v1 = SongDataStdV1::from_buf(buf)?;
 
@@ -71,7 +76,7 @@ impl CurrentVersion for SongDataVersion {
                     ..Default::default()
                 };
 
-                Ok(sd_v5)
+                sd_v5
             }
 
             Self::V2 => {
@@ -95,7 +100,7 @@ impl CurrentVersion for SongDataVersion {
                     ..Default::default()
                 };
 
-                Ok(sd_v5)
+                sd_v5
             }
 
             Self::V3 => {
@@ -119,13 +124,13 @@ impl CurrentVersion for SongDataVersion {
                     ..Default::default()
                 };
 
-                Ok(sd_v5)
+                sd_v5
             }
 
             Self::V4 => {
                 let sd_v4 = SongDataStdV4::from_buf(buf)?;
 
-                Ok(SongDataStdV5 {
+                SongDataStdV5 {
                     title: sd_v4.title.clone(),
                     original_title: sd_v4.title,
                     custom_tags: sd_v4.custom_tags,
@@ -133,11 +138,15 @@ impl CurrentVersion for SongDataVersion {
                     user_tag: sd_v4.user_tag,
                     meta: sd_v4.meta,
                     ..Default::default()
-                })
+                }
             }
 
-            Self::V5 => SongDataStdV5::from_buf(buf),
-        }
+            Self::V5 => SongDataStdV5::from_buf(buf)?,
+
+            Self::V6 => return SongDataStdV6::from_buf(buf),
+        };
+
+        Ok(sd_v5.into())
     }
 
     fn current_version() -> Self {
@@ -145,33 +154,42 @@ impl CurrentVersion for SongDataVersion {
     }
 }
 
+/// Reads the metadata from the audio file of the song, the audio path is resolved from the container in
+/// `song_data` as this is called while the song data is still being loaded
+
 pub(crate) fn get_song_data_from_song_file(song: &Song, song_data: &mut SongData) {
-    get_song_data_from_song_file_with_paths(&song.song_audio_path, &song.song_data_path, song_data);
+    let song_audio_path = song_audio_file_v2(
+        &song_data.inner.audio_id(song.id),
+        song_data.inner.audio_container.inner,
+    );
+
+    get_song_data_from_song_file_with_paths(&song_audio_path, &song.song_data_path, song_data)
+        .expect("Read song data from song file");
 }
 
-pub(super) fn get_song_data_from_song_file_with_paths(
+/// Reads the metadata from the audio file at `song_audio_path` into `song_data`, then saves the song
+/// data. Returns `Ok(false)` if symphonia was unable to probe the audio file
+
+pub(crate) fn get_song_data_from_song_file_with_paths(
     song_audio_path: &PathBuf,
     song_data_path: &PathBuf,
     song_data: &mut SongData,
-) -> bool {
+) -> io::Result<bool> {
     let song_data_std = &mut song_data.inner;
-    let song_file = File::open(&song_audio_path).expect("Open new song file");
+    let song_file = File::open(&song_audio_path)?;
 
-    let ext = song_audio_path
-        .extension()
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .to_string();
+    let audio_container = song_data_std.audio_container.inner;
 
     let mss_options = MediaSourceStreamOptions::default();
 
     let mss = MediaSourceStream::new(Box::new(song_file), mss_options);
 
-    let mut did_err = false;
+    let mut probed = true;
 
     match get_probe().format(
-        Hint::new().with_extension(&ext),
+        Hint::new()
+            .with_extension(audio_container.extension())
+            .mime_type(audio_container.mime_type()),
         mss,
         &FormatOptions::default(),
         &MetadataOptions::default(),
@@ -223,51 +241,74 @@ pub(super) fn get_song_data_from_song_file_with_paths(
                         }));
                     }
 
-                    for tag in meta_revision.tags() {
-                        if let Some(std_key) = tag.std_key {
-                            match std_key {
-                                StandardTagKey::Artist => match &tag.value {
-                                    Value::String(artist_string) => {
-                                        song_data_std
-                                            .meta
-                                            .inner
-                                            .artist
-                                            .inner
-                                            .assign_if_err_callback(|| Artist::new(artist_string));
-                                    }
-
-                                    _ => {
-                                        unreachable!()
-                                    }
-                                },
-
-                                StandardTagKey::Album => match &tag.value {
-                                    Value::String(album) => song_data_std
-                                        .meta
-                                        .inner
-                                        .album
-                                        .inner
-                                        .assign_if_err_callback(|| album.clone()),
-
-                                    _ => {
-                                        unreachable!()
-                                    }
-                                },
+                    let song_meta = &mut song_data_std.meta.inner;
 
-                                StandardTagKey::TrackTitle => match &tag.value {
-                                    Value::String(title) => {
-                                        if song_data_std.title != "" {
-                                            song_data_std.title = title.clone();
-                                        }
-                                    }
-
-                                    _ => {
-                                        unreachable!()
-                                    }
-                                },
+                    for tag in meta_revision.tags() {
+                        let Some(std_key) = tag.std_key else {
+                            continue;
+                        };
 
-                                _ => {}
+                        match std_key {
+                            StandardTagKey::Artist => song_meta
+                                .artist
+                                .inner
+                                .assign_if_err_callback(|| Artist::new(tag.value.to_string())),
+
+                            StandardTagKey::Album => song_meta
+                                .album
+                                .inner
+                                .assign_if_err_callback(|| tag.value.to_string()),
+
+                            StandardTagKey::TrackTitle => {
+                                // Never replaces a title which was already set, such as one edited by the user
+                                if song_data_std.title.is_empty() {
+                                    song_data_std.title = tag.value.to_string();
+                                }
                             }
+
+                            StandardTagKey::TrackNumber => song_meta
+                                .track_number
+                                .inner
+                                .assign_if_err_callback(|| tag_number(&tag.value)),
+
+                            StandardTagKey::DiscNumber => song_meta
+                                .disc_number
+                                .inner
+                                .assign_if_err_callback(|| tag_number(&tag.value)),
+
+                            StandardTagKey::Date
+                            | StandardTagKey::ReleaseDate
+                            | StandardTagKey::OriginalDate => song_meta
+                                .date
+                                .inner
+                                .assign_if_err_callback(|| tag.value.to_string()),
+
+                            StandardTagKey::Genre => song_meta
+                                .genre
+                                .inner
+                                .assign_if_err_callback(|| tag.value.to_string()),
+
+                            StandardTagKey::AlbumArtist => song_meta
+                                .album_artist
+                                .inner
+                                .assign_if_err_callback(|| tag.value.to_string()),
+
+                            StandardTagKey::Composer => song_meta
+                                .composer
+                                .inner
+                                .assign_if_err_callback(|| tag.value.to_string()),
+
+                            StandardTagKey::Bpm => song_meta
+                                .bpm
+                                .inner
+                                .assign_if_err_callback(|| tag_number(&tag.value)),
+
+                            StandardTagKey::Comment => song_meta
+                                .comment
+                                .inner
+                                .assign_if_err_callback(|| tag.value.to_string()),
+
+                            _ => {}
                         }
                     }
                 }
@@ -282,27 +323,11 @@ pub(super) fn get_song_data_from_song_file_with_paths(
 
             // panic!("uhh here");
 
-            did_err = true;
+            probed = false;
         }
     }
 
-    let meta = &mut song_data.inner.meta.inner;
-
-    if meta.song_length.inner.is_err() {
-        meta.song_length.inner = Ok(0);
-    }
-
-    if meta.artist.inner.is_err() {
-        meta.artist.inner = Ok(Artist::default());
-    }
-
-    if meta.album.inner.is_err() {
-        meta.album.inner = Ok(UNKNOWN_ALBUM_STR.to_string());
-    }
-
-    if meta.cover.inner.is_err() {
-        meta.cover.inner = Ok(None);
-    }
+    song_data.inner.meta.inner.fill_errors();
 
     // let cover_id = song_data.inner.meta.inner.cover.unwrapped_ref().unwrap();
     // println!("sdat: song: {} data: {:?}", song_data.inner.title, cover_id);
@@ -312,11 +337,31 @@ pub(super) fn get_song_data_from_song_file_with_paths(
     //     panic!("No cover");
     // });
 
-    song_data
-        .write_to_file_path(song_data_path)
-        .expect("Clean write to song data file");
+    song_data.write_to_file_path_safe(song_data_path)?;
+
+    Ok(probed)
+}
+
+/// Reads a number tag such as the track number, which may be written as "3/12"
 
-    did_err
+fn tag_number(value: &Value) -> Option<u32> {
+    match value {
+        Value::UnsignedInt(number) => u32::try_from(*number).ok(),
+        Value::SignedInt(number) => u32::try_from(*number).ok(),
+        Value::Float(number) => Some(number.round() as u32),
+
+        value => {
+            let value = value.to_string();
+            let number = value.split('/').next().unwrap_or_default().trim();
+
+            number.parse::<u32>().ok().or_else(|| {
+                number
+                    .parse::<f64>()
+                    .ok()
+                    .map(|number| number.round() as u32)
+            })
+        }
+    }
 }
 
 fn get_visual_score(visual: &Visual) -> u8 {
@@ -1,7 +1,9 @@
+use crate::content::song::audio_container::AudioContainer;
 use crate::content::song::song_data::meta::SongDataMetaV2;
 use crate::content::song::song_data::util::CustomVolumeDataProvider;
 use crate::content::song::song_data::v4::DEFAULT_CUSTOM_VOLUME;
 use serbytes::prelude::{MayNotExistOrDefault, MayNotExistOrElse, SerBytes, SizedBlock};
+use simple_id::prelude::Id;
 use std::time::Duration;
 
 #[derive(SerBytes, Clone, Debug)]
@@ -22,6 +24,11 @@ pub struct SongDataStdV5 {
     pub start_offset: MayNotExistOrDefault<Option<Duration>>,
     pub end_time: MayNotExistOrDefault<Option<Duration>>,
     pub custom_volume: MayNotExistOrElse<f32, CustomVolumeDataProvider>,
+    /// The container the audio file is stored as, song data saved before this existed is always mp3
+    pub audio_container: MayNotExistOrDefault<AudioContainer>,
+    /// Set when the audio file is shared with other songs, such as the tracks of a CUE sheet. The audio
+    /// is then stored under this id rather than the id of the song
+    pub shared_audio_id: MayNotExistOrDefault<Option<Id>>,
 }
 
 impl Default for SongDataStdV5 {
@@ -38,6 +45,8 @@ impl Default for SongDataStdV5 {
             start_offset: None.into(),
             end_time: None.into(),
             custom_volume: DEFAULT_CUSTOM_VOLUME.into(),
+            audio_container: AudioContainer::default().into(),
+            shared_audio_id: None.into(),
         }
     }
 }
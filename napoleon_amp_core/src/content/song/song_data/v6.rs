@@ -0,0 +1,87 @@
+use crate::content::song::audio_container::AudioContainer;
+use crate::content::song::loudness::Loudness;
+use crate::content::song::song_data::meta::SongDataMetaV3;
+use crate::content::song::song_data::util::CustomVolumeDataProvider;
+use crate::content::song::song_data::v4::DEFAULT_CUSTOM_VOLUME;
+use crate::content::song::song_data::v5::SongDataStdV5;
+use serbytes::prelude::{MayNotExistOrDefault, MayNotExistOrElse, SerBytes, SizedBlock};
+use simple_id::prelude::Id;
+use std::time::Duration;
+
+#[derive(SerBytes, Clone, Debug)]
+pub struct SongDataStdV6 {
+    /// Track title of the song
+    pub title: String,
+    pub original_title: String,
+    pub custom_tags: Vec<String>,
+    /// A rating of the song from 0 to 5
+    /// where 0 represents unrated and 1-5 represent a rating
+    pub rating: u8,
+    /// A custom user defined tag
+    pub user_tag: String,
+    /// Metadata related to a song, this is never of type Err once the song data is loaded, and can be
+    /// unwrapped with no issue
+    pub meta: SizedBlock<SongDataMetaV3>,
+    pub times_listened: u32,
+    pub times_skipped: MayNotExistOrDefault<u32>,
+    pub start_offset: MayNotExistOrDefault<Option<Duration>>,
+    pub end_time: MayNotExistOrDefault<Option<Duration>>,
+    pub custom_volume: MayNotExistOrElse<f32, CustomVolumeDataProvider>,
+    /// The container the audio file is stored as
+    pub audio_container: MayNotExistOrDefault<AudioContainer>,
+    /// Set when the audio file is shared with other songs, such as the tracks of a CUE sheet. The audio
+    /// is then stored under this id rather than the id of the song
+    pub shared_audio_id: MayNotExistOrDefault<Option<Id>>,
+    /// Set once the loudness of the song is analyzed, or read from its ReplayGain tags
+    pub loudness: MayNotExistOrDefault<Option<Loudness>>,
+}
+
+impl SongDataStdV6 {
+    /// The id the audio file of the song with `song_id` is stored under
+
+    pub fn audio_id(&self, song_id: Id) -> Id {
+        self.shared_audio_id.inner.unwrap_or(song_id)
+    }
+}
+
+impl Default for SongDataStdV6 {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            original_title: String::new(),
+            custom_tags: Vec::new(),
+            rating: 0,
+            user_tag: String::new(),
+            meta: SizedBlock::new(SongDataMetaV3::default()),
+            times_listened: 0,
+            times_skipped: 0.into(),
+            start_offset: None.into(),
+            end_time: None.into(),
+            custom_volume: DEFAULT_CUSTOM_VOLUME.into(),
+            audio_container: AudioContainer::default().into(),
+            shared_audio_id: None.into(),
+            loudness: None.into(),
+        }
+    }
+}
+
+impl From<SongDataStdV5> for SongDataStdV6 {
+    fn from(sd_v5: SongDataStdV5) -> Self {
+        Self {
+            title: sd_v5.title,
+            original_title: sd_v5.original_title,
+            custom_tags: sd_v5.custom_tags,
+            rating: sd_v5.rating,
+            user_tag: sd_v5.user_tag,
+            meta: SizedBlock::new(sd_v5.meta.inner.into()),
+            times_listened: sd_v5.times_listened,
+            times_skipped: sd_v5.times_skipped,
+            start_offset: sd_v5.start_offset,
+            end_time: sd_v5.end_time,
+            custom_volume: sd_v5.custom_volume,
+            audio_container: sd_v5.audio_container,
+            shared_audio_id: sd_v5.shared_audio_id,
+            loudness: None.into(),
+        }
+    }
+}
@@ -1,10 +1,15 @@
 use crate::content::song::Song;
+use crate::content::song::content_hash::{
+    CONTENT_HASH_VERSION, ContentHash, files_equal, hash_file,
+};
 use crate::paths::song::registered_songs_data_file_v2;
+use crate::safe_write::SafeWrite;
 use crate::{ReadGuard, read_rwlock, time_now, write_rwlock};
-use serbytes::prelude::{ReadError, SerBytes, SerBytesFs};
+use serbytes::prelude::{MayNotExistOrDefault, ReadError, SerBytes};
 use simple_id::prelude::Id;
 use std::collections::HashMap;
 use std::io;
+use std::path::Path;
 use std::sync::{Arc, LazyLock, RwLock};
 
 pub(crate) static SONG_POOL: LazyLock<SongPool> = LazyLock::new(SongPool::new);
@@ -15,6 +20,14 @@ type WeakArc<T> = std::sync::Weak<T>;
 pub(crate) struct RegisteredSongs {
     pub(crate) name_map: HashMap<String, Id>,
     pub(crate) last_updated: Result<u64, ReadError<'static>>,
+    /// Content hash of the audio file of each song, songs registered before this existed are hashed the
+    /// next time songs are imported
+    pub(crate) content_hashes: MayNotExistOrDefault<HashMap<Id, ContentHash>>,
+    /// The songs using each shared audio file, by the id the audio is stored under. The file is kept in
+    /// the library until the last of these songs is deleted
+    pub(crate) shared_audio_users: MayNotExistOrDefault<HashMap<Id, Vec<Id>>>,
+    /// The [`CONTENT_HASH_VERSION`] the content hashes were computed with
+    pub(crate) content_hash_version: MayNotExistOrDefault<u8>,
 }
 
 impl Default for RegisteredSongs {
@@ -22,6 +35,9 @@ impl Default for RegisteredSongs {
         Self {
             name_map: HashMap::default(),
             last_updated: Err(ReadError::default()),
+            content_hashes: HashMap::default().into(),
+            shared_audio_users: HashMap::default().into(),
+            content_hash_version: CONTENT_HASH_VERSION.into(),
         }
     }
 }
@@ -29,7 +45,7 @@ impl Default for RegisteredSongs {
 impl RegisteredSongs {
     fn save_registered_songs(&mut self) -> io::Result<()> {
         self.last_updated = Ok(time_now().as_secs());
-        self.write_to_file_path(registered_songs_data_file_v2())
+        self.write_to_file_path_safe(registered_songs_data_file_v2())
     }
 }
 
@@ -42,13 +58,30 @@ impl SongPool {
     fn new() -> Self {
         Self {
             songs: RwLock::new(HashMap::new()),
-            registered_songs: LazyLock::new(Self::load_registered_songs),
+            registered_songs: LazyLock::new(|| RwLock::new(Self::load_registered_songs())),
         }
     }
 
-    fn load_registered_songs() -> RwLock<RegisteredSongs> {
+    /// Forgets every loaded song and reads the registered songs from disk again, for when the library
+    /// files were replaced underneath the pool
+
+    pub(crate) fn reload(&self) {
+        write_rwlock(&self.songs).clear();
+        *write_rwlock(&self.registered_songs) = Self::load_registered_songs();
+    }
+
+    fn load_registered_songs() -> RegisteredSongs {
         let mut registered_songs =
-            RegisteredSongs::from_file_path(registered_songs_data_file_v2()).unwrap_or_default();
+            RegisteredSongs::from_file_path_or_backup(registered_songs_data_file_v2())
+                .unwrap_or_default();
+
+        if registered_songs.content_hash_version.inner != CONTENT_HASH_VERSION {
+            // Hashed again the next time songs are imported, as they no longer match new hashes
+            registered_songs.content_hashes.inner.clear();
+            registered_songs.content_hash_version.inner = CONTENT_HASH_VERSION;
+
+            let _ = registered_songs.save_registered_songs();
+        }
 
         if registered_songs.last_updated.is_err() {
             registered_songs.last_updated = Ok(time_now().as_secs());
@@ -57,7 +90,7 @@ impl SongPool {
             let _ = registered_songs.save_registered_songs();
         }
 
-        RwLock::new(registered_songs)
+        registered_songs
     }
 
     pub(crate) fn get_song_by_id(&self, song_id: Id) -> Arc<Song> {
@@ -80,16 +113,214 @@ impl SongPool {
         song
     }
 
-    pub(crate) fn register_new_song(&self, song_id: Id, name: String) -> Result<(), ()> {
-        let name_map = &mut write_rwlock(&self.registered_songs).name_map;
+    /// Registers a new song under its original file name, if a different song was already registered
+    /// with the same name a number is appended to keep the name unique
+
+    pub(crate) fn register_new_song(&self, song_id: Id, name: String, content_hash: ContentHash) {
+        let mut registered_songs = write_rwlock(&self.registered_songs);
+
+        let mut unique_name = name.clone();
+        let mut duplicate_count = 1;
 
-        if name_map.contains_key(&name) {
-            return Err(());
+        while registered_songs.name_map.contains_key(&unique_name) {
+            duplicate_count += 1;
+            unique_name = format!("{} ({})", name, duplicate_count);
         }
 
-        name_map.insert(name, song_id);
+        registered_songs.name_map.insert(unique_name, song_id);
+
+        registered_songs
+            .content_hashes
+            .inner
+            .insert(song_id, content_hash);
+    }
+
+    /// Replaces the content hash of a song whose audio file was changed in place
+
+    pub(crate) fn update_content_hash(&self, song_id: Id, content_hash: ContentHash) {
+        write_rwlock(&self.registered_songs)
+            .content_hashes
+            .inner
+            .insert(song_id, content_hash);
+    }
+
+    /// Finds a registered song whose audio file has the exact same contents as the file at `audio_path`
+
+    pub(crate) fn find_duplicate(
+        &self,
+        audio_path: &Path,
+        content_hash: ContentHash,
+    ) -> io::Result<Option<Arc<Song>>> {
+        self.hash_unhashed_songs();
+
+        let candidate_ids = read_rwlock(&self.registered_songs)
+            .content_hashes
+            .inner
+            .iter()
+            .filter(|(_, registered_hash)| **registered_hash == content_hash)
+            .map(|(song_id, _)| *song_id)
+            .collect::<Vec<_>>();
+
+        for song_id in candidate_ids {
+            let song = self.get_song_by_id(song_id);
+
+            // A song sharing its audio is only part of the file, so the file isn't a copy of it
+            if song.get_song_data().inner.shared_audio_id.inner.is_some() {
+                continue;
+            }
+
+            if files_equal(audio_path, song.song_audio_path())? {
+                return Ok(Some(song));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Finds a shared audio file with the exact same contents as the file at `audio_path`, returns the id
+    /// it is stored under
+
+    pub(crate) fn find_shared_audio(
+        &self,
+        audio_path: &Path,
+        content_hash: ContentHash,
+    ) -> io::Result<Option<Id>> {
+        self.hash_unhashed_songs();
+
+        let candidate_ids = {
+            let registered_songs = read_rwlock(&self.registered_songs);
+
+            registered_songs
+                .shared_audio_users
+                .inner
+                .values()
+                .filter_map(|user_ids| user_ids.first())
+                .filter(|song_id| {
+                    registered_songs.content_hashes.inner.get(song_id) == Some(&content_hash)
+                })
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        for song_id in candidate_ids {
+            let song = self.get_song_by_id(song_id);
+
+            let Some(audio_id) = song.get_song_data().inner.shared_audio_id.inner else {
+                continue;
+            };
+
+            if files_equal(audio_path, song.song_audio_path())? {
+                return Ok(Some(audio_id));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// The songs using the shared audio file stored under `audio_id`
+
+    pub(crate) fn shared_audio_users(&self, audio_id: Id) -> Vec<Id> {
+        read_rwlock(&self.registered_songs)
+            .shared_audio_users
+            .inner
+            .get(&audio_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn add_shared_audio_user(&self, audio_id: Id, song_id: Id) {
+        let mut registered_songs = write_rwlock(&self.registered_songs);
+
+        let user_ids = registered_songs
+            .shared_audio_users
+            .inner
+            .entry(audio_id)
+            .or_default();
+
+        if !user_ids.contains(&song_id) {
+            user_ids.push(song_id);
+        }
+    }
+
+    /// Stops `song_id` from using the shared audio file stored under `audio_id`. Returns true if no other
+    /// song uses the file anymore, so it can be removed from the library
+
+    pub(crate) fn remove_shared_audio_user(&self, audio_id: Id, song_id: Id) -> bool {
+        let mut registered_songs = write_rwlock(&self.registered_songs);
+        let shared_audio_users = &mut registered_songs.shared_audio_users.inner;
+
+        let Some(user_ids) = shared_audio_users.get_mut(&audio_id) else {
+            return true;
+        };
+
+        user_ids.retain(|user_id| *user_id != song_id);
+
+        if user_ids.is_empty() {
+            shared_audio_users.remove(&audio_id);
+            return true;
+        }
+
+        false
+    }
+
+    /// Computes the content hash of every registered song that does not have one yet
+
+    pub(crate) fn hash_unhashed_songs(&self) {
+        let unhashed_ids = {
+            let registered_songs = read_rwlock(&self.registered_songs);
+
+            registered_songs
+                .name_map
+                .values()
+                .filter(|song_id| !registered_songs.content_hashes.inner.contains_key(song_id))
+                .copied()
+                .collect::<Vec<_>>()
+        };
+
+        if unhashed_ids.is_empty() {
+            return;
+        }
+
+        println!(
+            "Hashing {} songs for duplicate detection",
+            unhashed_ids.len()
+        );
+
+        let hashes = unhashed_ids
+            .into_iter()
+            .filter_map(|song_id| {
+                let song = self.get_song_by_id(song_id);
+
+                match hash_file(song.song_audio_path()) {
+                    Ok(content_hash) => Some((song_id, content_hash)),
+
+                    Err(e) => {
+                        eprintln!("Unable to hash song {}; {}", song_id, e);
+                        None
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+
+        write_rwlock(&self.registered_songs)
+            .content_hashes
+            .inner
+            .extend(hashes);
+    }
+
+    /// Removes a song from the registered songs, none of its files are deleted
+
+    pub(crate) fn unregister_song(&self, song_id: Id) {
+        {
+            let mut registered_songs = write_rwlock(&self.registered_songs);
+
+            registered_songs
+                .name_map
+                .retain(|_, registered_id| *registered_id != song_id);
+            registered_songs.content_hashes.inner.remove(&song_id);
+        }
 
-        Ok(())
+        write_rwlock(&self.songs).remove(&song_id);
     }
 
     pub(crate) fn get_registered_songs(&self) -> ReadGuard<'_, RegisteredSongs> {
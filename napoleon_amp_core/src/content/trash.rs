@@ -0,0 +1,468 @@
+//! Deleted folders, playlists and songs are moved into the trash instead of being removed, so they can
+//! be restored until the trash is emptied or the entry is older than the retention period.
+
+use crate::content::folder::{ContentsListElements, Folder, FolderDataContentVariant};
+use crate::content::playlist::data::PlaylistSongListData;
+use crate::content::song::audio_container::AudioContainer;
+use crate::content::song::content_hash::hash_file;
+use crate::content::song::delete::remove_file_if_exists;
+use crate::content::song::song_cover_pool::SongCoverId;
+use crate::content::song::song_data::SongData;
+use crate::content::song::song_pool::SONG_POOL;
+use crate::paths::song::{
+    song_audio_file_v2, song_cover_file, song_data_file_v2, songs_data_dir_v2,
+};
+use crate::paths::{
+    content_playlist_song_list_file, napoleon_amp_dir, trash_dir, trash_entry_dir, trash_entry_file,
+};
+use crate::safe_write::{SafeWrite, remove_backups};
+use crate::time_now;
+use serbytes::prelude::{SerBytes, SerBytesFs};
+use simple_id::prelude::{Id, SmallRngIdGenerator};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::{fs, io};
+
+const SECS_PER_DAY: u64 = 60 * 60 * 24;
+
+/// How many days entries are kept in the trash before they are deleted for good, 0 keeps them forever
+
+#[derive(SerBytes, Debug, Copy, Clone)]
+pub struct TrashRetentionDays(pub u16);
+
+impl Default for TrashRetentionDays {
+    fn default() -> Self {
+        Self(30)
+    }
+}
+
+#[derive(SerBytes, Debug, Clone)]
+pub struct TrashedFolderContent {
+    pub element: ContentsListElements,
+    /// The folder it was deleted from, it is restored into the base folder if this no longer exists
+    pub parent_id: Id,
+}
+
+#[derive(SerBytes, Debug, Clone)]
+pub struct TrashedSong {
+    pub song_id: Id,
+    pub registered_name: String,
+    /// The standard playlists the song was in, dynamic playlists pick it up again on their own
+    pub playlist_ids: Vec<Id>,
+}
+
+#[derive(SerBytes, Debug, Clone)]
+pub enum TrashedContent {
+    /// A playlist, or a folder along with everything inside it
+    FolderContent(TrashedFolderContent),
+    Song(TrashedSong),
+}
+
+impl Display for TrashedContent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_str = match self {
+            Self::FolderContent(folder_content) => match folder_content.element.variant {
+                FolderDataContentVariant::Folder => "Folder",
+                FolderDataContentVariant::Playlist(_) => "Playlist",
+            },
+
+            Self::Song(_) => "Song",
+        };
+
+        f.write_str(display_str)
+    }
+}
+
+#[derive(SerBytes, Debug, Clone)]
+pub struct TrashEntry {
+    pub id: Id,
+    pub name: String,
+    /// Seconds since the unix epoch
+    pub deleted_at: u64,
+    pub content: TrashedContent,
+}
+
+impl TrashEntry {
+    /// Creates an empty entry in the trash, files are moved into it with [`TrashEntry::move_into`]
+
+    pub(crate) fn create(name: String, content: TrashedContent) -> io::Result<Self> {
+        let mut generator = SmallRngIdGenerator::default();
+
+        let id = loop {
+            let id = generator.generate_new_id();
+
+            if !trash_entry_dir(id).try_exists()? {
+                break id;
+            }
+        };
+
+        let trash_entry = Self {
+            id,
+            name,
+            deleted_at: time_now().as_secs(),
+            content,
+        };
+
+        fs::create_dir_all(trash_entry_dir(id))?;
+        trash_entry.write_to_file_path_safe(trash_entry_file(&trash_entry_dir(id)))?;
+
+        Ok(trash_entry)
+    }
+
+    /// Moves a file out of the library and into this entry, files which don't exist are skipped
+
+    pub(crate) fn move_into(&self, library_path: &Path) -> io::Result<()> {
+        if !library_path.try_exists()? {
+            return Ok(());
+        }
+
+        let trashed_path = self.trashed_path(library_path)?;
+
+        if let Some(trashed_dir) = trashed_path.parent() {
+            fs::create_dir_all(trashed_dir)?;
+        }
+
+        fs::rename(library_path, trashed_path)
+    }
+
+    /// Where a file of the library is kept while in this entry
+
+    fn trashed_path(&self, library_path: &Path) -> io::Result<PathBuf> {
+        let relative_path = library_path.strip_prefix(napoleon_amp_dir()).map_err(|_| {
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} is not inside the library", library_path.display()),
+            )
+        })?;
+
+        Ok(trash_entry_dir(self.id).join(relative_path))
+    }
+
+    pub fn days_in_trash(&self) -> u64 {
+        time_now().as_secs().saturating_sub(self.deleted_at) / SECS_PER_DAY
+    }
+
+    /// Deletes the entry and everything in it for good, along with the shared audio of a song in it once
+    /// no other song in the library or the trash uses it
+
+    pub fn delete_permanently(&self) -> io::Result<()> {
+        let cover_ids = self.song_cover_id().into_iter().collect::<HashSet<_>>();
+        let shared_audio = self.shared_audio().into_iter().collect::<HashMap<_, _>>();
+        let entry_dir = trash_entry_dir(self.id);
+
+        remove_trashed_file_backups(&entry_dir)?;
+        fs::remove_dir_all(entry_dir)?;
+
+        remove_unreferenced_covers(cover_ids)?;
+        remove_unused_shared_audio(shared_audio)
+    }
+
+    /// Moves every file back into the library, then adds the content back to where it was deleted from.
+    /// Nothing is moved if any of the files have been replaced since
+
+    pub(crate) fn restore(&self, base_folder: &Rc<Folder>) -> io::Result<()> {
+        self.restore_files()?;
+
+        match &self.content {
+            TrashedContent::FolderContent(folder_content) => {
+                let parent_folder = base_folder
+                    .find_folder(folder_content.parent_id)
+                    .unwrap_or_else(|| Rc::clone(base_folder));
+
+                parent_folder.restore_content(folder_content.element);
+            }
+
+            TrashedContent::Song(trashed_song) => {
+                let song = SONG_POOL.get_song_by_id(trashed_song.song_id);
+                let content_hash = hash_file(song.song_audio_path())?;
+
+                if let Some(audio_id) = song.get_song_data().inner.shared_audio_id.inner {
+                    SONG_POOL.add_shared_audio_user(audio_id, trashed_song.song_id);
+                }
+
+                SONG_POOL.register_new_song(
+                    trashed_song.song_id,
+                    trashed_song.registered_name.clone(),
+                    content_hash,
+                );
+                SONG_POOL.save_registered_songs()?;
+
+                for playlist_id in trashed_song.playlist_ids.iter().copied() {
+                    add_song_to_song_list(playlist_id, trashed_song.song_id)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Moves every file back into the library and removes the entry. Nothing is moved if any of the files
+    /// have been replaced since
+
+    pub(crate) fn restore_files(&self) -> io::Result<()> {
+        let entry_dir = trash_entry_dir(self.id);
+        let trashed_files = trashed_files(&entry_dir)?;
+
+        for (_, library_path) in &trashed_files {
+            if library_path.try_exists()? {
+                return Err(io::Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!("{} already exists", library_path.display()),
+                ));
+            }
+        }
+
+        for (trashed_path, library_path) in &trashed_files {
+            if let Some(library_dir) = library_path.parent() {
+                fs::create_dir_all(library_dir)?;
+            }
+
+            fs::rename(trashed_path, library_path)?;
+        }
+
+        fs::remove_dir_all(entry_dir)
+    }
+
+    fn song_cover_id(&self) -> Option<SongCoverId> {
+        let song_data = self.trashed_song_data()?;
+
+        song_data.inner.meta.inner.cover.inner.ok().flatten()
+    }
+
+    /// The id and container of the shared audio used by the song in this entry, which is left in the
+    /// library while the song is in the trash
+
+    fn shared_audio(&self) -> Option<(Id, AudioContainer)> {
+        let song_data = self.trashed_song_data()?;
+        let audio_id = song_data.inner.shared_audio_id.inner?;
+
+        Some((audio_id, song_data.inner.audio_container.inner))
+    }
+
+    fn trashed_song_data(&self) -> Option<SongData> {
+        let TrashedContent::Song(trashed_song) = &self.content else {
+            return None;
+        };
+
+        let trashed_data_path = self
+            .trashed_path(&song_data_file_v2(&trashed_song.song_id))
+            .ok()?;
+
+        SongData::from_file_path(trashed_data_path).ok()
+    }
+}
+
+/// Every entry in the trash, most recently deleted first. Entries which can't be read are skipped
+
+pub fn trash_entries() -> io::Result<Vec<TrashEntry>> {
+    let trash_dir = trash_dir();
+
+    if !trash_dir.try_exists()? {
+        return Ok(Vec::new());
+    }
+
+    let mut trash_entries = Vec::new();
+
+    for dir_entry in trash_dir.read_dir()? {
+        let entry_file = trash_entry_file(&dir_entry?.path());
+
+        match TrashEntry::from_file_path(&entry_file) {
+            Ok(trash_entry) => trash_entries.push(trash_entry),
+            Err(e) => eprintln!("Unable to read trash entry {:?}; {}", entry_file, e),
+        }
+    }
+
+    trash_entries.sort_by(|a, b| b.deleted_at.cmp(&a.deleted_at));
+
+    Ok(trash_entries)
+}
+
+/// Deletes everything in the trash for good
+
+pub fn empty_trash() -> io::Result<()> {
+    let cover_ids = trashed_song_cover_ids()?;
+    let shared_audio = trashed_shared_audio()?;
+    let trash_dir = trash_dir();
+
+    if trash_dir.try_exists()? {
+        for dir_entry in trash_dir.read_dir()? {
+            remove_trashed_file_backups(&dir_entry?.path())?;
+        }
+
+        fs::remove_dir_all(trash_dir)?;
+    }
+
+    remove_unreferenced_covers(cover_ids)?;
+    remove_unused_shared_audio(shared_audio)
+}
+
+/// Deletes every entry which has been in the trash for longer than `retention_days`. An entry which can't
+/// be deleted is left for the next purge, the covers and shared audio of the others are cleaned up once
+/// they are all gone
+
+pub(crate) fn purge_expired_entries(retention_days: TrashRetentionDays) -> io::Result<()> {
+    if retention_days.0 == 0 {
+        return Ok(());
+    }
+
+    let mut cover_ids = HashSet::new();
+    let mut shared_audio = HashMap::new();
+
+    for trash_entry in trash_entries()? {
+        if trash_entry.days_in_trash() < retention_days.0 as u64 {
+            continue;
+        }
+
+        // Read from the song data in the entry, so before it is removed
+        let cover_id = trash_entry.song_cover_id();
+        let audio = trash_entry.shared_audio();
+        let entry_dir = trash_entry_dir(trash_entry.id);
+
+        if let Err(e) =
+            remove_trashed_file_backups(&entry_dir).and_then(|_| fs::remove_dir_all(&entry_dir))
+        {
+            eprintln!("Unable to delete trash entry {:?}; {}", entry_dir, e);
+            continue;
+        }
+
+        cover_ids.extend(cover_id);
+        shared_audio.extend(audio);
+    }
+
+    remove_unreferenced_covers(cover_ids)?;
+    remove_unused_shared_audio(shared_audio)
+}
+
+/// The covers used by songs in the trash, these are kept so restored songs still have their cover
+
+pub(crate) fn trashed_song_cover_ids() -> io::Result<HashSet<SongCoverId>> {
+    Ok(trash_entries()?
+        .iter()
+        .filter_map(TrashEntry::song_cover_id)
+        .collect())
+}
+
+/// The shared audio used by songs in the trash along with its container, it is kept in the library so
+/// restored songs can still play it
+
+pub(crate) fn trashed_shared_audio() -> io::Result<HashMap<Id, AudioContainer>> {
+    Ok(trash_entries()?
+        .iter()
+        .filter_map(TrashEntry::shared_audio)
+        .collect())
+}
+
+/// Deletes each shared audio file unless a song in the library or the trash still uses it
+
+fn remove_unused_shared_audio(mut shared_audio: HashMap<Id, AudioContainer>) -> io::Result<()> {
+    if shared_audio.is_empty() {
+        return Ok(());
+    }
+
+    for audio_id in trashed_shared_audio()?.keys() {
+        shared_audio.remove(audio_id);
+    }
+
+    for (audio_id, audio_container) in shared_audio {
+        if SONG_POOL.shared_audio_users(audio_id).is_empty() {
+            remove_file_if_exists(&song_audio_file_v2(&audio_id, audio_container))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Deletes each cover unless a song in the library or the trash still uses it
+
+fn remove_unreferenced_covers(mut cover_ids: HashSet<SongCoverId>) -> io::Result<()> {
+    if cover_ids.is_empty() {
+        return Ok(());
+    }
+
+    for cover_id in trashed_song_cover_ids()? {
+        cover_ids.remove(&cover_id);
+    }
+
+    for dir_entry in songs_data_dir_v2().read_dir()? {
+        if let Ok(song_data) = SongData::from_file_path(dir_entry?.path()) {
+            if let Ok(Some(cover_id)) = &song_data.inner.meta.inner.cover.inner {
+                cover_ids.remove(cover_id);
+            }
+        }
+    }
+
+    for cover_id in &cover_ids {
+        remove_file_if_exists(&song_cover_file(cover_id))?;
+    }
+
+    Ok(())
+}
+
+fn add_song_to_song_list(playlist_id: Id, song_id: Id) -> io::Result<()> {
+    let song_list_path = content_playlist_song_list_file(playlist_id);
+
+    // The playlist was deleted as well
+    let Ok(mut song_list_data) = PlaylistSongListData::from_file_path(&song_list_path) else {
+        return Ok(());
+    };
+
+    if song_list_data.song_ids.contains(&song_id) {
+        return Ok(());
+    }
+
+    song_list_data.song_ids.push(song_id);
+    song_list_data.last_updated.set(time_now().as_secs());
+
+    song_list_data.write_to_file_path_safe(song_list_path)
+}
+
+/// Every file in a trash entry other than the entry file, along with where it was in the library
+
+fn trashed_files(entry_dir: &Path) -> io::Result<Vec<(PathBuf, PathBuf)>> {
+    let entry_file = trash_entry_file(entry_dir);
+
+    let mut trashed_paths = Vec::new();
+    files_in_dir_recursive(entry_dir, &mut trashed_paths)?;
+
+    Ok(trashed_paths
+        .into_iter()
+        .filter(|trashed_path| *trashed_path != entry_file)
+        .map(|trashed_path| {
+            let relative_path = trashed_path
+                .strip_prefix(entry_dir)
+                .expect("Found by walking the entry directory");
+
+            let library_path = napoleon_amp_dir().join(relative_path);
+
+            (trashed_path, library_path)
+        })
+        .collect())
+}
+
+/// The backups of a file are kept while it is in the trash so a restored file can still be recovered,
+/// they are only removed once the file is deleted for good
+
+fn remove_trashed_file_backups(entry_dir: &Path) -> io::Result<()> {
+    for (_, library_path) in trashed_files(entry_dir)? {
+        remove_backups(&library_path)?;
+    }
+
+    Ok(())
+}
+
+fn files_in_dir_recursive(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for dir_entry in dir.read_dir()? {
+        let dir_entry = dir_entry?;
+
+        if dir_entry.file_type()?.is_dir() {
+            files_in_dir_recursive(&dir_entry.path(), files)?;
+        } else {
+            files.push(dir_entry.path());
+        }
+    }
+
+    Ok(())
+}
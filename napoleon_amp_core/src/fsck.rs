@@ -0,0 +1,618 @@
+//! Checks the library files for references to content which no longer exists, and files which nothing
+//! refers to.
+//!
+//! Files which can't be read are restored from their newest backup which can be, as they would be when
+//! loaded. Repairs work directly on the files, so anything already loaded (folders, playlists) should be
+//! reloaded afterwards, ie. by restarting.
+
+use crate::content::SaveData;
+use crate::content::folder::content_pool::CONTENT_POOL;
+use crate::content::folder::{
+    ContentsListElements, FolderContentData, FolderData, FolderDataContentVariant,
+};
+use crate::content::playlist::PlaylistTypeVariant;
+use crate::content::playlist::data::{PlaylistContentData, PlaylistSongListData, PlaylistUserData};
+use crate::content::playlist::dynamic_playlist_data::DynamicPlaylistData;
+use crate::content::song::delete::{delete_songs_from_library, remove_songs_from_song_list};
+use crate::content::song::song_data::SongData;
+use crate::content::song::song_pool::SONG_POOL;
+use crate::content::trash::{trashed_shared_audio, trashed_song_cover_ids};
+use crate::paths::song::{songs_audio_dir_v2, songs_cover_dir_v2, songs_data_dir_v2};
+use crate::paths::{
+    content_folder_file, content_folder_path, content_playlist_song_list_file,
+    content_playlist_song_list_path, content_playlist_user_data_file,
+    content_playlist_user_data_path, lost_found_dir,
+};
+use crate::safe_write::{SafeWrite, is_temp_file, remove_backups};
+use serbytes::prelude::SerBytesFs;
+use simple_id::prelude::Id;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+#[derive(Debug, Clone)]
+pub enum FsckIssue {
+    /// A song data or audio file whose id is not registered
+    UnregisteredSongFile { path: PathBuf },
+    /// A registered song which has no audio file
+    MissingSongAudio { song_id: Id, name: String },
+    /// A playlist song list which contains songs that are not registered
+    UnknownPlaylistSongs { path: PathBuf, song_ids: Vec<Id> },
+    /// A folder which lists a folder or playlist whose files no longer exist
+    MissingFolderContent {
+        path: PathBuf,
+        folder_name: String,
+        content_id: Id,
+    },
+    /// A cover which no song data refers to
+    UnreferencedCover { path: PathBuf },
+    /// A file which could not be read, nor restored from a backup
+    Unreadable {
+        path: PathBuf,
+        kind: LibraryFileKind,
+        error: String,
+    },
+}
+
+/// What a library file holds, so it can be read again when it is repaired
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LibraryFileKind {
+    SongData,
+    PlaylistSongList,
+    Folder,
+}
+
+impl FsckIssue {
+    /// What [`FsckIssue::repair`] will do for this issue
+
+    pub fn fix_description(&self) -> &'static str {
+        match self {
+            Self::UnregisteredSongFile { .. } | Self::UnreferencedCover { .. } => {
+                "Move to lost and found"
+            }
+            Self::Unreadable {
+                kind: LibraryFileKind::Folder,
+                ..
+            } => {
+                "Restore the newest backup which can be read, or move what the folder holds into the base folder and the folder to lost and found"
+            }
+            Self::Unreadable { .. } => {
+                "Restore the newest backup which can be read, or move to lost and found"
+            }
+            Self::MissingSongAudio { .. } => {
+                "Move the song to the trash, removing it from every playlist"
+            }
+            Self::UnknownPlaylistSongs { .. } => "Remove the unknown songs from the playlist",
+            Self::MissingFolderContent { .. } => "Remove the missing content from the folder",
+        }
+    }
+
+    /// Applies the fix for this issue. Every fix re-reads the files it changes, so repairing an issue
+    /// which was already fixed by another repair does nothing
+
+    pub fn repair(&self) -> io::Result<()> {
+        match self {
+            // The song data using a cover may only be unreadable, so the cover is kept
+            Self::UnregisteredSongFile { path } | Self::UnreferencedCover { path } => {
+                move_to_lost_found(path)
+            }
+
+            Self::Unreadable { path, kind, .. } => repair_unreadable(path, *kind),
+
+            Self::MissingSongAudio { song_id, .. } => {
+                delete_songs_from_library(&HashSet::from([*song_id])).map(|_| ())
+            }
+
+            Self::UnknownPlaylistSongs { path, song_ids } => {
+                remove_songs_from_song_list(path, &song_ids.iter().copied().collect())
+            }
+
+            Self::MissingFolderContent {
+                path, content_id, ..
+            } => {
+                let mut folder_data = FolderData::from_file_path(path).map_err(invalid_data)?;
+
+                folder_data
+                    .contents
+                    .retain(|content| content.id != *content_id);
+
+                folder_data.write_to_file_path_safe(path)
+            }
+        }
+    }
+}
+
+impl Display for FsckIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnregisteredSongFile { path } => {
+                write!(f, "Song file is not registered: {}", path.display())
+            }
+
+            Self::MissingSongAudio { song_id, name } => {
+                write!(f, "Song \"{}\" ({}) has no audio file", name, song_id)
+            }
+
+            Self::UnknownPlaylistSongs { path, song_ids } => write!(
+                f,
+                "Playlist song list {} contains {} unknown songs",
+                path.display(),
+                song_ids.len()
+            ),
+
+            Self::MissingFolderContent {
+                folder_name,
+                content_id,
+                ..
+            } => write!(
+                f,
+                "Folder \"{}\" contains {} which no longer exists",
+                folder_name, content_id
+            ),
+
+            Self::UnreferencedCover { path } => {
+                write!(f, "Cover is not used by any song: {}", path.display())
+            }
+
+            Self::Unreadable { path, error } => {
+                write!(f, "Unable to read {}; {}", path.display(), error)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FsckReport {
+    pub issues: Vec<FsckIssue>,
+}
+
+impl FsckReport {
+    /// Repairs every issue, returning the issues which failed to repair along with why
+
+    pub fn repair_all(&self) -> Vec<(&FsckIssue, io::Error)> {
+        let mut failed = Vec::new();
+
+        for issue in &self.issues {
+            if let Err(e) = issue.repair() {
+                failed.push((issue, e));
+            }
+        }
+
+        failed
+    }
+}
+
+/// Walks the song, cover, folder and playlist files of the library. Nothing is changed, besides restoring
+/// files which can't be read from their backups
+
+pub fn check_library() -> io::Result<FsckReport> {
+    let mut report = FsckReport::default();
+
+    let registered_songs = SONG_POOL
+        .get_registered_songs()
+        .name_map
+        .iter()
+        .map(|(name, song_id)| (song_id.to_string(), (*song_id, name.clone())))
+        .collect::<HashMap<_, _>>();
+
+    let registered_ids = registered_songs
+        .values()
+        .map(|(song_id, _)| *song_id)
+        .collect::<HashSet<_>>();
+
+    // Songs in the trash keep their cover so it is still there if they are restored
+    let mut referenced_covers = trashed_song_cover_ids()?
+        .iter()
+        .map(|cover_id| cover_id.to_string())
+        .collect::<HashSet<_>>();
+
+    // Songs sharing their audio with other songs, such as the tracks of a CUE sheet, along with the id
+    // the audio is stored under
+    let mut shared_audio_ids = HashMap::new();
+
+    for data_path in files_in_dir(&songs_data_dir_v2())? {
+        if !registered_songs.contains_key(&file_stem_str(&data_path)) {
+            report
+                .issues
+                .push(FsckIssue::UnregisteredSongFile { path: data_path });
+            continue;
+        }
+
+        match SongData::from_file_path_or_backup(&data_path) {
+            Ok(song_data) => {
+                if let Ok(Some(cover_id)) = &song_data.inner.meta.inner.cover.inner {
+                    referenced_covers.insert(cover_id.to_string());
+                }
+
+                if let Some(audio_id) = song_data.inner.shared_audio_id.inner {
+                    shared_audio_ids.insert(file_stem_str(&data_path), audio_id.to_string());
+                }
+            }
+
+            Err(e) => report.issues.push(FsckIssue::Unreadable {
+                path: data_path,
+                kind: LibraryFileKind::SongData,
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    // Shared audio stays in the library while a song using it is in the trash
+    let trashed_shared_audio_ids = trashed_shared_audio()?
+        .keys()
+        .map(|audio_id| audio_id.to_string())
+        .collect::<Vec<_>>();
+
+    let used_shared_audio_ids = shared_audio_ids
+        .values()
+        .chain(&trashed_shared_audio_ids)
+        .collect::<HashSet<_>>();
+    let mut stored_audio_ids = HashSet::new();
+
+    for audio_path in files_in_dir(&songs_audio_dir_v2())? {
+        let audio_id_str = file_stem_str(&audio_path);
+
+        if registered_songs.contains_key(&audio_id_str)
+            || used_shared_audio_ids.contains(&audio_id_str)
+        {
+            stored_audio_ids.insert(audio_id_str);
+        } else {
+            report
+                .issues
+                .push(FsckIssue::UnregisteredSongFile { path: audio_path });
+        }
+    }
+
+    for (song_id_str, (song_id, name)) in &registered_songs {
+        let audio_id_str = shared_audio_ids.get(song_id_str).unwrap_or(song_id_str);
+
+        if !stored_audio_ids.contains(audio_id_str) {
+            report.issues.push(FsckIssue::MissingSongAudio {
+                song_id: *song_id,
+                name: name.clone(),
+            });
+        }
+    }
+
+    for cover_path in files_in_dir(&songs_cover_dir_v2())? {
+        if !referenced_covers.contains(&file_stem_str(&cover_path)) {
+            report
+                .issues
+                .push(FsckIssue::UnreferencedCover { path: cover_path });
+        }
+    }
+
+    for song_list_path in files_in_dir(&content_playlist_song_list_path())? {
+        match PlaylistSongListData::from_file_path_or_backup(&song_list_path) {
+            Ok(song_list_data) => {
+                let unknown_song_ids = song_list_data
+                    .song_ids
+                    .iter()
+                    .filter(|song_id| !registered_ids.contains(song_id))
+                    .copied()
+                    .collect::<Vec<_>>();
+
+                if !unknown_song_ids.is_empty() {
+                    report.issues.push(FsckIssue::UnknownPlaylistSongs {
+                        path: song_list_path,
+                        song_ids: unknown_song_ids,
+                    });
+                }
+            }
+
+            Err(e) => report.issues.push(FsckIssue::Unreadable {
+                path: song_list_path,
+                kind: LibraryFileKind::PlaylistSongList,
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    for folder_path in files_in_dir(&content_folder_path())? {
+        let folder_data = match FolderData::from_file_path_or_backup(&folder_path) {
+            Ok(folder_data) => folder_data,
+
+            Err(e) => {
+                report.issues.push(FsckIssue::Unreadable {
+                    path: folder_path,
+                    kind: LibraryFileKind::Folder,
+                    error: e.to_string(),
+                });
+                continue;
+            }
+        };
+
+        for content in &folder_data.contents {
+            let content_path = match content.variant {
+                FolderDataContentVariant::Folder => content_folder_file(content.id),
+
+                FolderDataContentVariant::Playlist(
+                    PlaylistTypeVariant::AllSongs(_) | PlaylistTypeVariant::Album(_),
+                ) => continue,
+
+                FolderDataContentVariant::Playlist(_) => {
+                    content_playlist_user_data_file(content.id)
+                }
+            };
+
+            if !content_path.try_exists()? {
+                report.issues.push(FsckIssue::MissingFolderContent {
+                    path: folder_path.clone(),
+                    folder_name: folder_data.content_data.name.clone(),
+                    content_id: content.id,
+                });
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+fn files_in_dir(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    if !dir.try_exists()? {
+        return Ok(Vec::new());
+    }
+
+    let mut files = Vec::new();
+
+    for dir_entry in dir.read_dir()? {
+        let dir_entry = dir_entry?;
+
+        // Left behind by an interrupted safe write, which never replaced anything
+        if dir_entry.file_type()?.is_file() && !is_temp_file(&dir_entry.path()) {
+            files.push(dir_entry.path());
+        }
+    }
+
+    files.sort();
+
+    Ok(files)
+}
+
+fn file_stem_str(path: &Path) -> String {
+    path.file_stem()
+        .map(|file_stem| file_stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Restores an unreadable file from its newest backup which can be read, without one it is moved to lost
+/// and found
+
+fn repair_unreadable(path: &Path, kind: LibraryFileKind) -> io::Result<()> {
+    if !path.try_exists()? {
+        return Ok(());
+    }
+
+    let restored = match kind {
+        LibraryFileKind::SongData => SongData::from_file_path_or_backup(path).is_ok(),
+        LibraryFileKind::PlaylistSongList => {
+            PlaylistSongListData::from_file_path_or_backup(path).is_ok()
+        }
+        LibraryFileKind::Folder => FolderData::from_file_path_or_backup(path).is_ok(),
+    };
+
+    if restored {
+        return Ok(());
+    }
+
+    match kind {
+        LibraryFileKind::Folder => replace_unreadable_folder(path),
+        _ => move_to_lost_found(path),
+    }
+}
+
+/// Moves an unreadable folder to lost and found, once the folders and playlists inside it have been moved
+/// into the base folder. Only the unreadable folder recorded their ids, so each is given a new one, which
+/// dynamic playlists importing from them no longer find
+
+fn replace_unreadable_folder(folder_path: &Path) -> io::Result<()> {
+    let folder_id_str = file_stem_str(folder_path);
+
+    let mut rehomed_contents = rehome_child_folders(&folder_id_str)?;
+    rehomed_contents.extend(rehome_child_playlists(&folder_id_str)?);
+
+    remove_from_parent_folders(&folder_id_str)?;
+    move_to_lost_found(folder_path)?;
+
+    let base_folder_path = content_folder_file(Id::ZERO);
+
+    // The base folder may be the unreadable one, in which case a new one holds what it did
+    let mut base_folder_data = if folder_id_str == Id::ZERO.to_string() {
+        FolderData {
+            content_data: FolderContentData {
+                name: "Base".to_string(),
+                parent: None,
+            },
+            contents: Vec::new(),
+            expanded: true.into(),
+        }
+    } else {
+        FolderData::from_file_path_or_backup(&base_folder_path).map_err(invalid_data)?
+    };
+
+    base_folder_data.contents.extend(rehomed_contents);
+    base_folder_data.write_to_file_path_safe(base_folder_path)
+}
+
+/// Gives every folder recorded as inside the folder with the id `parent_id_str` a new id in the base
+/// folder, returning them to be listed there
+
+fn rehome_child_folders(parent_id_str: &str) -> io::Result<Vec<ContentsListElements>> {
+    let mut rehomed_folders = Vec::new();
+
+    for folder_path in files_in_dir(&content_folder_path())? {
+        let Ok(mut folder_data) = FolderData::from_file_path(&folder_path) else {
+            continue;
+        };
+
+        if !folder_data
+            .content_data
+            .parent
+            .is_some_and(|parent_id| parent_id.to_string() == parent_id_str)
+        {
+            continue;
+        }
+
+        let folder_id = CONTENT_POOL.new_folder_id();
+        folder_data.content_data.parent = Some(Id::ZERO);
+        folder_data.save_data(folder_id)?;
+
+        for content in &folder_data.contents {
+            if let Err(e) = set_content_parent(content, folder_id) {
+                eprintln!(
+                    "Unable to record the new id of its parent folder in {}; {}",
+                    content.id, e
+                );
+            }
+        }
+
+        remove_file_and_backups(&folder_path)?;
+
+        rehomed_folders.push(ContentsListElements {
+            variant: FolderDataContentVariant::Folder,
+            id: folder_id,
+        });
+    }
+
+    Ok(rehomed_folders)
+}
+
+/// Gives every playlist recorded as inside the folder with the id `parent_id_str` a new id in the base
+/// folder, returning them to be listed there
+
+fn rehome_child_playlists(parent_id_str: &str) -> io::Result<Vec<ContentsListElements>> {
+    let mut rehomed_playlists = Vec::new();
+    let song_list_paths = files_in_dir(&content_playlist_song_list_path())?;
+
+    for user_data_path in files_in_dir(&content_playlist_user_data_path())? {
+        let is_child =
+            |content_data: &PlaylistContentData| content_data.parent.to_string() == parent_id_str;
+
+        let dynamic_data = DynamicPlaylistData::from_file_path(&user_data_path)
+            .ok()
+            .filter(|dynamic_data| is_child(&dynamic_data.inner.user_data.inner.content_data));
+
+        let (playlist_id, variant) = if let Some(mut dynamic_data) = dynamic_data {
+            let playlist_id = CONTENT_POOL.new_playlist_id();
+            dynamic_data.inner.user_data.inner.content_data.parent = Id::ZERO;
+            dynamic_data.save_data(playlist_id)?;
+
+            (playlist_id, PlaylistTypeVariant::Dynamic(()))
+        } else {
+            let Some(mut user_data) = PlaylistUserData::from_file_path(&user_data_path)
+                .ok()
+                .filter(|user_data| is_child(&user_data.inner.content_data))
+            else {
+                continue;
+            };
+
+            let playlist_id = CONTENT_POOL.new_playlist_id();
+            user_data.inner.content_data.parent = Id::ZERO;
+            user_data.save_data(playlist_id)?;
+
+            (playlist_id, PlaylistTypeVariant::Standard(()))
+        };
+
+        let playlist_id_str = file_stem_str(&user_data_path);
+
+        if let Some(song_list_path) = song_list_paths
+            .iter()
+            .find(|song_list_path| file_stem_str(song_list_path) == playlist_id_str)
+        {
+            fs::rename(song_list_path, content_playlist_song_list_file(playlist_id))?;
+            remove_backups(song_list_path)?;
+        }
+
+        remove_file_and_backups(&user_data_path)?;
+
+        rehomed_playlists.push(ContentsListElements {
+            variant: FolderDataContentVariant::Playlist(variant),
+            id: playlist_id,
+        });
+    }
+
+    Ok(rehomed_playlists)
+}
+
+/// Records `parent_id` as the parent of a folder or playlist
+
+fn set_content_parent(content: &ContentsListElements, parent_id: Id) -> io::Result<()> {
+    match content.variant {
+        FolderDataContentVariant::Folder => {
+            let folder_path = content_folder_file(content.id);
+            let mut folder_data = FolderData::from_file_path(&folder_path).map_err(invalid_data)?;
+
+            folder_data.content_data.parent = Some(parent_id);
+            folder_data.write_to_file_path_safe(folder_path)
+        }
+
+        FolderDataContentVariant::Playlist(PlaylistTypeVariant::Standard(_)) => {
+            let user_data_path = content_playlist_user_data_file(content.id);
+            let mut user_data =
+                PlaylistUserData::from_file_path(&user_data_path).map_err(invalid_data)?;
+
+            user_data.inner.content_data.parent = parent_id;
+            user_data.write_to_file_path_safe(user_data_path)
+        }
+
+        FolderDataContentVariant::Playlist(PlaylistTypeVariant::Dynamic(_)) => {
+            let user_data_path = content_playlist_user_data_file(content.id);
+            let mut dynamic_data =
+                DynamicPlaylistData::from_file_path(&user_data_path).map_err(invalid_data)?;
+
+            dynamic_data.inner.user_data.inner.content_data.parent = parent_id;
+            dynamic_data.write_to_file_path_safe(user_data_path)
+        }
+
+        FolderDataContentVariant::Playlist(_) => Ok(()),
+    }
+}
+
+/// Removes the folder with the id `folder_id_str` from every folder listing it
+
+fn remove_from_parent_folders(folder_id_str: &str) -> io::Result<()> {
+    for folder_path in files_in_dir(&content_folder_path())? {
+        let Ok(mut folder_data) = FolderData::from_file_path(&folder_path) else {
+            continue;
+        };
+
+        let content_count = folder_data.contents.len();
+
+        folder_data
+            .contents
+            .retain(|content| content.id.to_string() != folder_id_str);
+
+        if folder_data.contents.len() != content_count {
+            folder_data.write_to_file_path_safe(&folder_path)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn remove_file_and_backups(path: &Path) -> io::Result<()> {
+    fs::remove_file(path)?;
+
+    remove_backups(path)
+}
+
+fn move_to_lost_found(path: &Path) -> io::Result<()> {
+    if !path.try_exists()? {
+        return Ok(());
+    }
+
+    let lost_found_dir = lost_found_dir();
+
+    fs::create_dir_all(&lost_found_dir)?;
+
+    let file_name = path.file_name().ok_or(ErrorKind::InvalidFilename)?;
+
+    fs::rename(path, lost_found_dir.join(file_name))
+}
+
+fn invalid_data(e: impl Display) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, e.to_string())
+}
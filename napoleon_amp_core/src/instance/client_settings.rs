@@ -1,7 +1,12 @@
 use crate::content::SaveData;
+use crate::content::playlist::dsp::DspSettings;
+use crate::content::song::loudness::NormalizationMode;
+use crate::content::song::song_data::artist::ArtistSeparators;
+use crate::content::trash::TrashRetentionDays;
 use crate::paths::client_settings_file_path;
 use serbytes::prelude::{
-    BBReadResult, CurrentVersion, ReadByteBufferRefMut, SerBytes, VersioningWrapper,
+    BBReadResult, CurrentVersion, MayNotExistOrDefault, ReadByteBufferRefMut, SerBytes,
+    VersioningWrapper,
 };
 use std::path::PathBuf;
 
@@ -29,12 +34,31 @@ impl CurrentVersion for ClientSettingsVers {
 #[derive(SerBytes)]
 pub struct ClientSettingsStd {
     pub inactive_render_timeout_ms: u16,
+    /// Directory of the library to use instead of this one, only read from the settings of the default
+    /// library. See [`crate::paths::library_root`]
+    pub library_root: MayNotExistOrDefault<Option<String>>,
+    pub trash_retention_days: MayNotExistOrDefault<TrashRetentionDays>,
+    /// Whether to list the tags of the audio file which differ after editing a song, and offer to write them
+    pub offer_tag_write_after_edit: MayNotExistOrDefault<bool>,
+    pub artist_separators: MayNotExistOrDefault<ArtistSeparators>,
+    pub normalization_mode: MayNotExistOrDefault<NormalizationMode>,
+    /// How long songs fade into each other for, 0 plays them without a gap
+    pub crossfade_seconds: MayNotExistOrDefault<u8>,
+    /// The equalizer, balance and mono downmix songs are played with, unless their playlist overrides them
+    pub dsp: MayNotExistOrDefault<DspSettings>,
 }
 
 impl Default for ClientSettingsStd {
     fn default() -> Self {
         Self {
             inactive_render_timeout_ms: 1000,
+            library_root: None.into(),
+            trash_retention_days: TrashRetentionDays::default().into(),
+            offer_tag_write_after_edit: false.into(),
+            artist_separators: ArtistSeparators::default().into(),
+            normalization_mode: NormalizationMode::default().into(),
+            crossfade_seconds: 0.into(),
+            dsp: DspSettings::default().into(),
         }
     }
 }
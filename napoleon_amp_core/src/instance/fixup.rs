@@ -1,73 +0,0 @@
-//
-// pub(super) fn fixup_needed() -> io::Result<()> {
-//     if songs_dir_v1().try_exists()? {
-//         println!("Migrating from songsv1 to songsv2");
-//         fixup_songs_v1_to_v2()?;
-//     }
-//
-//     Ok(())
-// }
-//
-// fn fixup_songs_v1_to_v2() -> io::Result<()> {
-//     let mut generator = IdGenerator::new();
-//
-//     for dir_entry in songs_dir_v1().read_dir()?.flatten() {
-//         let old_path = dir_entry.path();
-//         let extension = if let Some(ext) = old_path.extension().and_then(|ext| ext.to_str()) {
-//             ext
-//         } else {
-//             continue;
-//         };
-//
-//         if extension != SONG_DATA_EXT_NO_PER {
-//             fixup_song_audio_file(old_path, &mut generator)?;
-//         }
-//     }
-//
-//     Ok(())
-// }
-//
-// fn fixup_song_audio_file(
-//     old_song_audio_path: PathBuf,
-//     generator: &mut IdGenerator,
-// ) -> io::Result<()> {
-//     let song_id = generator.generate_new_id();
-//
-//     let mut old_song_data_path = old_song_audio_path.clone();
-//     old_song_data_path.set_extension(SONG_DATA_EXT_NO_PER);
-//
-//     // println!("{:?}", old_song_data_path);
-//     //
-//     // panic!();
-//
-//     let new_song_data_path = song_data_file_v2(&song_id);
-//     let new_song_audio_path = song_audio_file_v2(&song_id);
-//
-//     let mut old_song_data = SongData::from_file_path(&old_song_data_path)?;
-//     old_song_data.audio_file = new_song_audio_path
-//         .to_str()
-//         .ok_or(ErrorKind::InvalidFilename)?
-//         .to_string()
-//         .into();
-//
-//     if !new_song_data_path.try_exists()? {
-//         fs::create_dir_all(
-//             new_song_data_path
-//                 .parent()
-//                 .ok_or(ErrorKind::InvalidFilename)?,
-//         )?;
-//     }
-//
-//     if !new_song_audio_path.try_exists()? {
-//         fs::create_dir_all(
-//             new_song_audio_path
-//                 .parent()
-//                 .ok_or(ErrorKind::InvalidFilename)?,
-//         )?;
-//     }
-//
-//     fs::copy(old_song_audio_path, new_song_audio_path)?;
-//     fs::copy(old_song_data_path, new_song_data_path)?;
-//
-//     Ok(())
-// }
@@ -11,7 +11,7 @@ pub struct IterPlaylists {
 }
 
 impl IterPlaylists {
-    pub(super) fn new(base_folder: Rc<Folder>) -> Self {
+    pub(crate) fn new(base_folder: Rc<Folder>) -> Self {
         Self {
             folder_queue: LinkedList::new(),
             folder: base_folder,
@@ -0,0 +1,170 @@
+use crate::content::song::song_data::SongData;
+use simple_id::prelude::Id;
+use std::fmt::{Display, Formatter};
+
+/// How many changes can be undone, the oldest are forgotten first
+const MAX_JOURNAL_LEN: usize = 100;
+
+/// A change made to the library, along with everything needed to revert it
+
+#[derive(Debug, Clone)]
+pub enum JournalEntry {
+    SetSongData {
+        song_id: Id,
+        before: SongData,
+        after: SongData,
+    },
+    RenamePlaylist {
+        playlist_id: Id,
+        before: String,
+        after: String,
+    },
+    AddSongsToPlaylist {
+        playlist_id: Id,
+        song_ids: Vec<Id>,
+    },
+    RemoveSongsFromPlaylist {
+        playlist_id: Id,
+        song_ids: Vec<Id>,
+    },
+    /// The songs were moved into the trash, one trash entry per song
+    DeleteSongsFromLibrary {
+        song_ids: Vec<Id>,
+        trash_entry_ids: Vec<Id>,
+    },
+    RestoreSongsFromTrash {
+        song_ids: Vec<Id>,
+        trash_entry_ids: Vec<Id>,
+    },
+}
+
+impl JournalEntry {
+    /// The change which reverts this one
+
+    pub fn inverse(self) -> Self {
+        match self {
+            Self::SetSongData {
+                song_id,
+                before,
+                after,
+            } => Self::SetSongData {
+                song_id,
+                before: after,
+                after: before,
+            },
+
+            Self::RenamePlaylist {
+                playlist_id,
+                before,
+                after,
+            } => Self::RenamePlaylist {
+                playlist_id,
+                before: after,
+                after: before,
+            },
+
+            Self::AddSongsToPlaylist {
+                playlist_id,
+                song_ids,
+            } => Self::RemoveSongsFromPlaylist {
+                playlist_id,
+                song_ids,
+            },
+
+            Self::RemoveSongsFromPlaylist {
+                playlist_id,
+                song_ids,
+            } => Self::AddSongsToPlaylist {
+                playlist_id,
+                song_ids,
+            },
+
+            Self::DeleteSongsFromLibrary {
+                song_ids,
+                trash_entry_ids,
+            } => Self::RestoreSongsFromTrash {
+                song_ids,
+                trash_entry_ids,
+            },
+
+            Self::RestoreSongsFromTrash {
+                song_ids,
+                trash_entry_ids,
+            } => Self::DeleteSongsFromLibrary {
+                song_ids,
+                trash_entry_ids,
+            },
+        }
+    }
+}
+
+impl Display for JournalEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SetSongData { .. } => write!(f, "Edit song"),
+
+            Self::RenamePlaylist { after, .. } => write!(f, "Rename playlist to \"{}\"", after),
+
+            Self::AddSongsToPlaylist { song_ids, .. } => {
+                write!(f, "Add {} songs to playlist", song_ids.len())
+            }
+
+            Self::RemoveSongsFromPlaylist { song_ids, .. } => {
+                write!(f, "Remove {} songs from playlist", song_ids.len())
+            }
+
+            Self::DeleteSongsFromLibrary { song_ids, .. } => {
+                write!(f, "Delete {} songs from library", song_ids.len())
+            }
+
+            Self::RestoreSongsFromTrash { song_ids, .. } => {
+                write!(f, "Restore {} songs from trash", song_ids.len())
+            }
+        }
+    }
+}
+
+/// The changes which can be undone and redone, kept in memory only
+
+#[derive(Debug, Default)]
+pub(crate) struct Journal {
+    undo_entries: Vec<JournalEntry>,
+    redo_entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    /// Records a change which was just made, nothing can be redone after a new change
+
+    pub(crate) fn record(&mut self, entry: JournalEntry) {
+        self.redo_entries.clear();
+        self.push_undo(entry);
+    }
+
+    pub(crate) fn push_undo(&mut self, entry: JournalEntry) {
+        if self.undo_entries.len() == MAX_JOURNAL_LEN {
+            self.undo_entries.remove(0);
+        }
+
+        self.undo_entries.push(entry);
+    }
+
+    pub(crate) fn push_redo(&mut self, entry: JournalEntry) {
+        self.redo_entries.push(entry);
+    }
+
+    pub(crate) fn pop_undo(&mut self) -> Option<JournalEntry> {
+        self.undo_entries.pop()
+    }
+
+    pub(crate) fn pop_redo(&mut self) -> Option<JournalEntry> {
+        self.redo_entries.pop()
+    }
+
+    pub(crate) fn next_undo(&self) -> Option<&JournalEntry> {
+        self.undo_entries.last()
+    }
+
+    pub(crate) fn next_redo(&self) -> Option<&JournalEntry> {
+        self.redo_entries.last()
+    }
+}
@@ -1,29 +1,50 @@
-mod client_settings;
-mod fixup;
-mod iter_playlists;
+pub(crate) mod client_settings;
+pub(crate) mod iter_playlists;
+pub mod journal;
 
 use crate::content::SaveData;
 use crate::content::folder::Folder;
 use crate::content::folder::content_pool::CONTENT_POOL;
-use crate::content::playlist::PlaylistType;
+use crate::content::playlist::album_playlist::AlbumPlaylist;
 use crate::content::playlist::all_songs_playlist::AllSongsPlaylist;
 use crate::content::playlist::data::PlaybackMode;
+use crate::content::playlist::dsp::set_dsp_settings;
 use crate::content::playlist::dynamic_playlist_data::DynamicPlaylistData;
+use crate::content::playlist::manager::set_crossfade_duration;
+use crate::content::playlist::{Playlist, PlaylistType};
 use crate::content::song::Song;
+use crate::content::song::album_index::{AlbumIndex, IndexedAlbum};
+use crate::content::song::artist_index::ArtistIndex;
+use crate::content::song::delete::delete_songs_and_reload_playlists;
+use crate::content::song::loudness::analysis::LoudnessAnalysisJob;
+use crate::content::song::loudness::{
+    NormalizationMode, normalization_mode, set_normalization_mode,
+};
 use crate::content::song::song_cover_pool::{SONG_COVER_POOL, SongCoverData, SongCoverId};
+use crate::content::song::song_data::SongData;
+use crate::content::song::song_data::artist::set_artist_separators;
+use crate::content::song::song_pool::SONG_POOL;
+use crate::content::trash;
+use crate::content::trash::TrashEntry;
 use crate::discord_rpc::discord_rpc_thread;
 use crate::instance::client_settings::ClientSettings;
 use crate::instance::iter_playlists::IterPlaylists;
+use crate::instance::journal::{Journal, JournalEntry};
+use crate::migration;
 use crate::paths::client_settings_file_path;
 use crate::read_rwlock;
+use crate::safe_write::SafeWrite;
 use rand::{RngExt, rng};
-use serbytes::prelude::{FromFileResult, SerBytesFs};
+use serbytes::prelude::FromFileResult;
 use simple_id::prelude::Id;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::io::ErrorKind;
 use std::rc::{Rc, Weak};
 use std::sync::Arc;
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::Duration;
 
 pub struct NapoleonInstance {
     pub base_folder: Rc<Folder>,
@@ -32,11 +53,60 @@ pub struct NapoleonInstance {
     currently_playing_playlist: Option<Rc<PlaylistType>>,
     playlist_user_data_cache: HashMap<Id, FromFileResult<'static, DynamicPlaylistData>>,
     client_settings: Option<ClientSettings>,
+    artist_index: ArtistIndex,
+    album_index: AlbumIndex,
+    loudness_analysis: Option<LoudnessAnalysisJob>,
+    journal: Journal,
     _discord_rpc_thread: Option<JoinHandle<()>>,
 }
 
 impl NapoleonInstance {
     pub fn new() -> Self {
+        let mut instance = Self::new_with_rpc_thread(Some(thread::spawn(|| {
+            if discord_rpc_thread().is_ok() {
+                println!("rpc thread fin ok");
+            } else {
+                println!("rpc thread err");
+            }
+        })));
+
+        // Songs imported since the last start are analyzed in the background, so they play normalized
+        if normalization_mode() != NormalizationMode::Off {
+            instance.start_loudness_analysis(false);
+        }
+
+        instance
+    }
+
+    /// Creates an instance without the discord rpc thread, for use without a ui (cli, scripts, etc.)
+
+    pub fn new_headless() -> Self {
+        Self::new_with_rpc_thread(None)
+    }
+
+    fn new_with_rpc_thread(discord_rpc_thread: Option<JoinHandle<()>>) -> Self {
+        match migration::run_migrations() {
+            Ok(report) if !report.is_up_to_date() => println!("{}", report),
+            Ok(_) => {}
+            Err(e) => eprintln!("Failed to migrate the library; {}", e),
+        }
+
+        let client_settings = ClientSettings::from_file_path_or_backup(client_settings_file_path())
+            .unwrap_or_default();
+
+        set_artist_separators(client_settings.inner.artist_separators.inner.clone());
+        set_normalization_mode(client_settings.inner.normalization_mode.inner);
+        set_crossfade_duration(Duration::from_secs(
+            client_settings.inner.crossfade_seconds.inner as u64,
+        ));
+        set_dsp_settings(client_settings.inner.dsp.inner.clone());
+
+        if let Err(e) =
+            trash::purge_expired_entries(client_settings.inner.trash_retention_days.inner)
+        {
+            eprintln!("Failed to remove expired entries from the trash; {}", e);
+        }
+
         Self {
             // TODO: initialize thru content_pool
             base_folder: Rc::new(Folder::new(Id::ZERO, None)),
@@ -45,13 +115,11 @@ impl NapoleonInstance {
             currently_playing_playlist: None,
             playlist_user_data_cache: HashMap::new(),
             client_settings: None,
-            _discord_rpc_thread: Some(thread::spawn(|| {
-                if discord_rpc_thread().is_ok() {
-                    println!("rpc thread fin ok");
-                } else {
-                    println!("rpc thread err");
-                }
-            })),
+            artist_index: ArtistIndex::default(),
+            album_index: AlbumIndex::default(),
+            loudness_analysis: None,
+            journal: Journal::default(),
+            _discord_rpc_thread: discord_rpc_thread,
         }
     }
 
@@ -65,9 +133,33 @@ impl NapoleonInstance {
         self.copied_songs = Some(selected_songs);
     }
 
-    pub fn paste_copied_songs(&self, playlist: &PlaylistType) {
-        if let Some(ref copied_songs) = self.copied_songs {
-            playlist.import_existing_songs(copied_songs);
+    pub fn paste_copied_songs(&mut self, playlist: &PlaylistType) {
+        let Some(copied_songs) = &self.copied_songs else {
+            return;
+        };
+
+        let existing_song_ids = read_rwlock(&playlist.get_song_vec_unfiltered())
+            .iter()
+            .map(|song| song.id)
+            .collect::<HashSet<_>>();
+
+        let added_song_ids = copied_songs
+            .iter()
+            .map(|song| song.id)
+            .filter(|song_id| !existing_song_ids.contains(song_id))
+            .collect::<Vec<_>>();
+
+        playlist.import_existing_songs(copied_songs);
+
+        // "All Songs" already contains every song, so nothing was actually added. Albums aren't saved, so
+        // there is nothing to undo
+        if !added_song_ids.is_empty()
+            && !matches!(playlist, PlaylistType::AllSongs(_) | PlaylistType::Album(_))
+        {
+            self.journal.record(JournalEntry::AddSongsToPlaylist {
+                playlist_id: playlist.id(),
+                song_ids: added_song_ids,
+            });
         }
     }
 
@@ -119,6 +211,25 @@ impl NapoleonInstance {
         Ok(())
     }
 
+    /// Queues every track of `album` in order, see [`Self::try_queue_song`]
+
+    pub fn try_queue_album(&self, album: &IndexedAlbum) -> Result<(), ()> {
+        for song in album.songs() {
+            self.try_queue_song(song)?;
+        }
+
+        Ok(())
+    }
+
+    /// Creates a playlist of the tracks of `album`, which can be played with [`Self::start_play_playlist`]
+
+    pub fn create_album_playlist(&self, album: &IndexedAlbum) -> Rc<PlaylistType> {
+        Rc::new(PlaylistType::Album(AlbumPlaylist::new(
+            album,
+            &self.base_folder,
+        )))
+    }
+
     pub fn get_all_songs_playlist(&mut self) -> Rc<PlaylistType> {
         let upgraded_opt = Weak::upgrade(&self.all_songs);
 
@@ -150,8 +261,8 @@ impl NapoleonInstance {
 
     pub fn get_client_settings(&mut self) -> &mut ClientSettings {
         self.client_settings.get_or_insert_with(|| {
-            let settings =
-                ClientSettings::from_file_path(client_settings_file_path()).unwrap_or_default();
+            let settings = ClientSettings::from_file_path_or_backup(client_settings_file_path())
+                .unwrap_or_default();
 
             let _ = settings.save_data(());
 
@@ -159,6 +270,355 @@ impl NapoleonInstance {
         })
     }
 
+    /// Saves the client settings, and applies those which take effect immediately
+
+    pub fn save_client_settings(&mut self) -> io::Result<()> {
+        let client_settings = self.get_client_settings();
+
+        client_settings.save_data(())?;
+
+        set_artist_separators(client_settings.inner.artist_separators.inner.clone());
+        set_crossfade_duration(Duration::from_secs(
+            client_settings.inner.crossfade_seconds.inner as u64,
+        ));
+        set_dsp_settings(client_settings.inner.dsp.inner.clone());
+
+        let new_normalization_mode = client_settings.inner.normalization_mode.inner;
+        let previous_normalization_mode = normalization_mode();
+
+        set_normalization_mode(new_normalization_mode);
+
+        if previous_normalization_mode == NormalizationMode::Off
+            && new_normalization_mode != NormalizationMode::Off
+        {
+            self.start_loudness_analysis(false);
+        }
+
+        Ok(())
+    }
+
+    /// Gets the index of every artist credited in the library, brought up to date with any songs which
+    /// changed since it was last used
+
+    pub fn get_artist_index(&mut self) -> &ArtistIndex {
+        self.artist_index.refresh();
+
+        &self.artist_index
+    }
+
+    /// Gets the index of every album in the library, brought up to date with any songs which changed since
+    /// it was last used
+
+    pub fn get_album_index(&mut self) -> &AlbumIndex {
+        self.album_index.refresh();
+
+        &self.album_index
+    }
+
+    /// Starts analyzing the loudness of the songs in the library, unless an analysis is already running. See
+    /// [`LoudnessAnalysisJob::start`]
+
+    pub fn start_loudness_analysis(&mut self, reanalyze: bool) {
+        if self
+            .loudness_analysis
+            .as_ref()
+            .is_some_and(|analysis| !analysis.is_finished())
+        {
+            return;
+        }
+
+        self.loudness_analysis = Some(LoudnessAnalysisJob::start(reanalyze));
+    }
+
+    /// The loudness analysis which was last started, if any. The ui should poll it each frame while it's
+    /// running
+
+    pub fn loudness_analysis(&mut self) -> Option<&mut LoudnessAnalysisJob> {
+        self.loudness_analysis.as_mut()
+    }
+
+    /// Gets the ids of every song registered in the library
+
+    pub fn registered_song_ids() -> Vec<Id> {
+        SONG_POOL
+            .get_registered_songs()
+            .name_map
+            .values()
+            .copied()
+            .collect()
+    }
+
+    /// Removes songs from the library, along with every playlist they are in. Their audio and data is
+    /// moved into the trash
+
+    pub fn delete_songs_from_library(&mut self, songs: &[Arc<Song>]) -> io::Result<()> {
+        let song_ids = songs.iter().map(|song| song.id).collect::<Vec<_>>();
+
+        let trash_entry_ids = self.delete_song_ids_from_library(&song_ids)?;
+
+        self.journal.record(JournalEntry::DeleteSongsFromLibrary {
+            song_ids,
+            trash_entry_ids,
+        });
+
+        Ok(())
+    }
+
+    fn delete_song_ids_from_library(&mut self, song_ids: &[Id]) -> io::Result<Vec<Id>> {
+        let song_ids = song_ids.iter().copied().collect::<HashSet<_>>();
+
+        let trash_entry_ids =
+            delete_songs_and_reload_playlists(&song_ids, Rc::clone(&self.base_folder))?;
+
+        if let Some(all_songs) = self.all_songs.upgrade() {
+            all_songs.reload_songs();
+        }
+
+        if let Some(copied_songs) = &mut self.copied_songs {
+            copied_songs.retain(|song| !song_ids.contains(&song.id));
+        }
+
+        Ok(trash_entry_ids)
+    }
+
+    /// Removes the song at `song_index` from the playlist. Removing a song from "All Songs" deletes it
+    /// from the library
+
+    pub fn delete_song_from_playlist(
+        &mut self,
+        playlist: &PlaylistType,
+        song_index: usize,
+    ) -> io::Result<()> {
+        let song = Arc::clone(&read_rwlock(&playlist.get_song_vec())[song_index]);
+
+        if let PlaylistType::AllSongs(_) = playlist {
+            return self.delete_songs_from_library(&[song]);
+        }
+
+        playlist.delete_song(song_index);
+
+        self.journal.record(JournalEntry::RemoveSongsFromPlaylist {
+            playlist_id: playlist.id(),
+            song_ids: vec![song.id],
+        });
+
+        Ok(())
+    }
+
+    /// Replaces the data of a song and saves it, the previous data can be restored with [`Self::undo`]
+
+    pub fn set_song_data(&mut self, song: &Song, new_song_data: SongData) {
+        let before = song.get_song_data().clone();
+
+        self.journal.record(JournalEntry::SetSongData {
+            song_id: song.id,
+            before,
+            after: new_song_data.clone(),
+        });
+
+        song.set_song_data_and_save(new_song_data);
+    }
+
+    pub fn rename_playlist(&mut self, playlist: &dyn Playlist, new_name: String) -> io::Result<()> {
+        let before = playlist.get_user_data().inner.content_data.name.clone();
+
+        playlist.rename(new_name.clone())?;
+
+        if before == new_name {
+            return Ok(());
+        }
+
+        self.journal.record(JournalEntry::RenamePlaylist {
+            playlist_id: playlist.id(),
+            before,
+            after: new_name,
+        });
+
+        Ok(())
+    }
+
+    /// Restores an entry from the trash, then reloads every playlist so restored songs show up again
+
+    pub fn restore_from_trash(&mut self, trash_entry: &TrashEntry) -> io::Result<()> {
+        trash_entry.restore(&self.base_folder)?;
+
+        self.reload_all_playlists();
+
+        Ok(())
+    }
+
+    /// Reverts the last change recorded in the journal. Returns `Ok(false)` if there is nothing to undo. A
+    /// change which can't be reverted (ie. the playlist was deleted since) stays the next one to undo
+
+    pub fn undo(&mut self) -> io::Result<bool> {
+        let Some(entry) = self.journal.pop_undo() else {
+            return Ok(false);
+        };
+
+        match self.apply_journal_entry(entry.clone().inverse()) {
+            Ok(applied) => self.journal.push_redo(applied.inverse()),
+
+            Err(e) => {
+                self.journal.push_undo(entry);
+                return Err(e);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Makes the last undone change again. Returns `Ok(false)` if there is nothing to redo, a change which
+    /// can't be made stays the next one to redo
+
+    pub fn redo(&mut self) -> io::Result<bool> {
+        let Some(entry) = self.journal.pop_redo() else {
+            return Ok(false);
+        };
+
+        match self.apply_journal_entry(entry.clone()) {
+            Ok(applied) => self.journal.push_undo(applied),
+
+            Err(e) => {
+                self.journal.push_redo(entry);
+                return Err(e);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// The change [`Self::undo`] would revert
+
+    pub fn next_undo(&self) -> Option<&JournalEntry> {
+        self.journal.next_undo()
+    }
+
+    /// The change [`Self::redo`] would make again
+
+    pub fn next_redo(&self) -> Option<&JournalEntry> {
+        self.journal.next_redo()
+    }
+
+    /// Makes the change described by the entry, returns the entry as it was applied since deleting songs
+    /// again creates new trash entries
+
+    fn apply_journal_entry(&mut self, entry: JournalEntry) -> io::Result<JournalEntry> {
+        match &entry {
+            JournalEntry::SetSongData { song_id, after, .. } => {
+                Self::check_song_registered(*song_id)?;
+
+                SONG_POOL
+                    .get_song_by_id(*song_id)
+                    .set_song_data_and_save(after.clone());
+            }
+
+            JournalEntry::RenamePlaylist {
+                playlist_id, after, ..
+            } => {
+                self.find_playlist_by_id(*playlist_id)?
+                    .rename(after.clone())?;
+            }
+
+            JournalEntry::AddSongsToPlaylist {
+                playlist_id,
+                song_ids,
+            } => {
+                let playlist = self.find_playlist_by_id(*playlist_id)?;
+
+                let mut songs = Vec::with_capacity(song_ids.len());
+
+                for song_id in song_ids {
+                    Self::check_song_registered(*song_id)?;
+                    songs.push(SONG_POOL.get_song_by_id(*song_id));
+                }
+
+                playlist.import_existing_songs(&songs);
+            }
+
+            JournalEntry::RemoveSongsFromPlaylist {
+                playlist_id,
+                song_ids,
+            } => {
+                self.find_playlist_by_id(*playlist_id)?
+                    .remove_songs(&song_ids.iter().copied().collect());
+            }
+
+            JournalEntry::DeleteSongsFromLibrary { song_ids, .. } => {
+                let trash_entry_ids = self.delete_song_ids_from_library(song_ids)?;
+
+                return Ok(JournalEntry::DeleteSongsFromLibrary {
+                    song_ids: song_ids.clone(),
+                    trash_entry_ids,
+                });
+            }
+
+            JournalEntry::RestoreSongsFromTrash {
+                trash_entry_ids, ..
+            } => {
+                let trash_entries = trash::trash_entries()?
+                    .into_iter()
+                    .filter(|trash_entry| trash_entry_ids.contains(&trash_entry.id))
+                    .collect::<Vec<_>>();
+
+                if trash_entries.len() != trash_entry_ids.len() {
+                    return Err(io::Error::new(
+                        ErrorKind::NotFound,
+                        "Some of the songs are no longer in the trash",
+                    ));
+                }
+
+                for trash_entry in &trash_entries {
+                    trash_entry.restore(&self.base_folder)?;
+                }
+
+                self.reload_all_playlists();
+            }
+        }
+
+        Ok(entry)
+    }
+
+    fn find_playlist_by_id(&self, playlist_id: Id) -> io::Result<Rc<PlaylistType>> {
+        self.iter_playlists()
+            .find(|playlist| playlist.id() == playlist_id)
+            .ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("Playlist {} no longer exists", playlist_id),
+                )
+            })
+    }
+
+    fn check_song_registered(song_id: Id) -> io::Result<()> {
+        if SONG_POOL
+            .get_registered_songs()
+            .name_map
+            .values()
+            .any(|registered_id| *registered_id == song_id)
+        {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                ErrorKind::NotFound,
+                format!("Song {} is no longer in the library", song_id),
+            ))
+        }
+    }
+
+    fn reload_all_playlists(&self) {
+        for playlist in self.iter_playlists() {
+            playlist.reload_songs();
+        }
+
+        if let Some(all_songs) = self.all_songs.upgrade() {
+            all_songs.reload_songs();
+        }
+    }
+
+    pub fn get_song(song_id: Id) -> Arc<Song> {
+        SONG_POOL.get_song_by_id(song_id)
+    }
+
     pub fn get_song_cover_data(song_cover_id: SongCoverId) -> Arc<SongCoverData> {
         SONG_COVER_POOL.get_or_load_value_arc_default(song_cover_id)
     }
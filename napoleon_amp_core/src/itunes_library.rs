@@ -0,0 +1,1109 @@
+//! Imports the tracks, playlists and folders of an iTunes or Music.app `Library.xml`, for users moving
+//! over from iTunes.
+//!
+//! Each track is imported into the library, or matched to the song already in it. Songs which are newly
+//! imported are given the play count, skip count, rating, start and stop time and volume adjustment of
+//! their track. Folders and playlists are recreated inside the folder the library is imported into,
+//! smart playlists become dynamic playlists when their criteria can be expressed as filter rules.
+
+use crate::content::folder::Folder;
+use crate::content::playlist::PlaylistType;
+use crate::content::playlist::import::{ImportOutcome, ImportReport};
+use crate::content::playlist::import_job::ImportJob;
+use crate::content::playlist::playlist_file::resolve_location;
+use crate::content::playlist::playlists::dynamic_playlist::filter::{
+    ComparisonMethod, FilterRule, FilterRules,
+};
+use crate::content::playlist::playlists::dynamic_playlist::rules::{ImportFrom, Rules};
+use crate::content::playlist::{ClearSongsCache, Playlist};
+use crate::content::song::Song;
+use crate::content::song::song_data::MAX_RATING;
+use crate::content::song::song_data::v4::DEFAULT_CUSTOM_VOLUME;
+use roxmltree::{Document, Node, ParsingOptions};
+use std::collections::{HashMap, HashSet};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use std::{fs, io};
+
+/// iTunes stores ratings out of 100, 20 per star
+const ITUNES_RATING_PER_STAR: i64 = 20;
+/// A volume adjustment of 255 is +100%
+const ITUNES_MAX_VOLUME_ADJUSTMENT: f32 = 255.0;
+
+// The smart criteria of a smart playlist are a binary list of rules. A 136 byte header holds the rule
+// count and whether any or all rules have to match, each rule then has a 56 byte header holding the
+// field, the operator and the length of the value which follows it.
+const SMART_CRITERIA_MAGIC: &[u8] = b"SLst";
+const SMART_CRITERIA_HEADER_LEN: usize = 136;
+const SMART_RULE_HEADER_LEN: usize = 56;
+
+const FIELD_NAME: u32 = 0x02;
+const FIELD_ALBUM: u32 = 0x03;
+const FIELD_ARTIST: u32 = 0x04;
+const FIELD_RATING: u32 = 0x19;
+
+const SIGN_INT_POSITIVE: u8 = 0x00;
+const SIGN_STRING_POSITIVE: u8 = 0x01;
+const SIGN_INT_NEGATIVE: u8 = 0x02;
+const SIGN_STRING_NEGATIVE: u8 = 0x03;
+
+const OPERATOR_IS: u8 = 0x01;
+const OPERATOR_CONTAINS: u8 = 0x02;
+const OPERATOR_GREATER_THAN: u8 = 0x10;
+const OPERATOR_LESS_THAN: u8 = 0x40;
+
+/// What was imported from an iTunes library
+
+#[derive(Debug, Default)]
+pub struct ItunesLibraryImport {
+    /// Tracks which were already in the library are reported as duplicates, they are still added to
+    /// their playlists
+    pub import_report: ImportReport,
+    /// Tracks which aren't local files, such as streams, and were left out
+    pub skipped_tracks: Vec<String>,
+    pub folders_created: usize,
+    pub playlists_created: usize,
+    pub dynamic_playlists_created: usize,
+    /// Smart playlists whose criteria can't be expressed as filter rules. These were created as standard
+    /// playlists holding the songs they matched when the library was exported
+    pub smart_playlists_as_standard: Vec<String>,
+}
+
+/// Imports the iTunes library at `library_xml_path`, recreating its folders and playlists inside `folder`
+
+pub fn import_itunes_library(
+    folder: &Rc<Folder>,
+    library_xml_path: &Path,
+) -> io::Result<ItunesLibraryImport> {
+    let library_dict = parse_library(&fs::read_to_string(library_xml_path)?)?;
+
+    let mut itunes_import = ItunesLibraryImport::default();
+
+    let tracks = read_tracks(&library_dict, &mut itunes_import.skipped_tracks);
+
+    itunes_import.import_report = ImportJob::start(
+        tracks.iter().map(|track| track.path.clone()).collect(),
+        false,
+    )
+    .wait(|_, _| {});
+
+    let outcomes = itunes_import
+        .import_report
+        .entries
+        .iter()
+        .map(|(song_path, outcome)| (song_path.as_path(), outcome))
+        .collect::<HashMap<_, _>>();
+
+    let mut songs_by_track_id = HashMap::new();
+
+    for track in &tracks {
+        match outcomes.get(track.path.as_path()) {
+            Some(ImportOutcome::Imported(song)) => {
+                track.apply_to_song(song, false);
+                songs_by_track_id.insert(track.track_id, Arc::clone(song));
+            }
+
+            Some(ImportOutcome::Duplicate(song)) => {
+                track.apply_to_song(song, true);
+                songs_by_track_id.insert(track.track_id, Arc::clone(song));
+            }
+
+            _ => {}
+        }
+    }
+
+    let playlists = read_playlists(&library_dict);
+
+    create_playlists(
+        &playlists,
+        None,
+        folder,
+        &songs_by_track_id,
+        &mut itunes_import,
+    )?;
+
+    Ok(itunes_import)
+}
+
+/// The top level dictionary of a `Library.xml`, holding its tracks and playlists
+
+fn parse_library(library_text: &str) -> io::Result<HashMap<String, PlistValue>> {
+    // The plist doctype is declared at the top of every Library.xml
+    let parsing_options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+
+    let document = Document::parse_with_options(library_text, parsing_options)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+
+    let library_value = document
+        .root_element()
+        .children()
+        .find(Node::is_element)
+        .map(PlistValue::parse)
+        .transpose()?;
+
+    match library_value {
+        Some(PlistValue::Dict(library_dict)) => Ok(library_dict),
+        _ => Err(invalid_library("it has no library dictionary")),
+    }
+}
+
+/// A track of the library which points to a local file
+#[derive(Debug)]
+struct ItunesTrack {
+    track_id: i64,
+    path: PathBuf,
+    play_count: Option<i64>,
+    skip_count: Option<i64>,
+    /// Out of 100
+    rating: Option<i64>,
+    start_time_ms: Option<i64>,
+    stop_time_ms: Option<i64>,
+    /// From -255 to 255
+    volume_adjustment: Option<i64>,
+}
+
+impl ItunesTrack {
+    /// Gives `song` the play count, skip count, rating, start and stop time and volume adjustment of the
+    /// track. With `keep_existing`, only the values the song still has the default of are replaced, so the
+    /// stats of a song already in the library aren't overwritten
+
+    fn apply_to_song(&self, song: &Song, keep_existing: bool) {
+        let replace = |is_default: bool| !keep_existing || is_default;
+
+        {
+            let mut song_data_guard = song.get_song_data_mut();
+            let song_data = &mut song_data_guard.inner;
+
+            if let Some(play_count) = self
+                .play_count
+                .filter(|_| replace(song_data.times_listened == 0))
+            {
+                song_data.times_listened = play_count.clamp(0, u32::MAX as i64) as u32;
+            }
+
+            if let Some(skip_count) = self
+                .skip_count
+                .filter(|_| replace(song_data.times_skipped.inner == 0))
+            {
+                song_data.times_skipped.inner = skip_count.clamp(0, u32::MAX as i64) as u32;
+            }
+
+            if let Some(rating) = self.rating.filter(|_| replace(song_data.rating == 0)) {
+                song_data.rating =
+                    (rating / ITUNES_RATING_PER_STAR).clamp(0, MAX_RATING as i64) as u8;
+            }
+
+            if let Some(start_time_ms) = self
+                .start_time_ms
+                .filter(|_| replace(song_data.start_offset.inner.is_none()))
+            {
+                song_data.start_offset.inner =
+                    Some(Duration::from_millis(start_time_ms.max(0) as u64));
+            }
+
+            if let Some(stop_time_ms) = self
+                .stop_time_ms
+                .filter(|_| replace(song_data.end_time.inner.is_none()))
+            {
+                song_data.end_time.inner = Some(Duration::from_millis(stop_time_ms.max(0) as u64));
+            }
+
+            if let Some(volume_adjustment) = self
+                .volume_adjustment
+                .filter(|_| replace(song_data.custom_volume.inner == DEFAULT_CUSTOM_VOLUME))
+            {
+                song_data.custom_volume.inner =
+                    (1.0 + volume_adjustment as f32 / ITUNES_MAX_VOLUME_ADJUSTMENT).max(0.0);
+            }
+        }
+
+        song.save_song_data();
+    }
+}
+
+fn read_tracks(
+    library_dict: &HashMap<String, PlistValue>,
+    skipped_tracks: &mut Vec<String>,
+) -> Vec<ItunesTrack> {
+    let Some(tracks_dict) = library_dict.get("Tracks").and_then(PlistValue::as_dict) else {
+        return Vec::new();
+    };
+
+    let mut tracks = Vec::with_capacity(tracks_dict.len());
+
+    for track_dict in tracks_dict.values().filter_map(PlistValue::as_dict) {
+        let get_integer = |key: &str| track_dict.get(key).and_then(PlistValue::as_integer);
+        let get_str = |key: &str| track_dict.get(key).and_then(PlistValue::as_str);
+
+        let Some(track_id) = get_integer("Track ID") else {
+            continue;
+        };
+
+        let track_name = get_str("Name").unwrap_or_default();
+
+        let Some(location) = get_str("Location") else {
+            skipped_tracks.push(track_name.to_string());
+            continue;
+        };
+
+        // Remote tracks have a http location, which isn't resolved
+        let Some(path) = resolve_location(Path::new(""), location) else {
+            skipped_tracks.push(format!("{} ({})", track_name, location));
+            continue;
+        };
+
+        let rating_computed = track_dict
+            .get("Rating Computed")
+            .and_then(PlistValue::as_bool)
+            .unwrap_or(false);
+
+        tracks.push(ItunesTrack {
+            track_id,
+            path,
+            play_count: get_integer("Play Count"),
+            skip_count: get_integer("Skip Count"),
+            // Computed ratings come from the album rating, not the track itself
+            rating: get_integer("Rating").filter(|_| !rating_computed),
+            start_time_ms: get_integer("Start Time"),
+            stop_time_ms: get_integer("Stop Time"),
+            volume_adjustment: get_integer("Volume Adjustment"),
+        });
+    }
+
+    tracks
+}
+
+#[derive(Debug)]
+enum ItunesPlaylistKind {
+    Folder,
+    Standard,
+    /// The filter rules are `None` when the criteria can't be expressed as filter rules
+    Smart(Option<Vec<FilterRules>>),
+}
+
+#[derive(Debug)]
+struct ItunesPlaylist<'a> {
+    name: &'a str,
+    persistent_id: Option<&'a str>,
+    parent_persistent_id: Option<&'a str>,
+    kind: ItunesPlaylistKind,
+    track_ids: Vec<i64>,
+}
+
+/// Every playlist and folder made by the user, the library itself and the built in playlists such as
+/// "Music" and "Podcasts" are left out
+
+fn read_playlists(library_dict: &HashMap<String, PlistValue>) -> Vec<ItunesPlaylist<'_>> {
+    let Some(playlist_values) = library_dict.get("Playlists").and_then(PlistValue::as_array) else {
+        return Vec::new();
+    };
+
+    let mut playlists = Vec::with_capacity(playlist_values.len());
+
+    for playlist_dict in playlist_values.iter().filter_map(PlistValue::as_dict) {
+        let get_bool = |key: &str| playlist_dict.get(key).and_then(PlistValue::as_bool);
+        let get_str = |key: &str| playlist_dict.get(key).and_then(PlistValue::as_str);
+        let get_data = |key: &str| playlist_dict.get(key).and_then(PlistValue::as_data);
+
+        if get_bool("Master") == Some(true)
+            || get_bool("Visible") == Some(false)
+            || playlist_dict.contains_key("Distinguished Kind")
+        {
+            continue;
+        }
+
+        let kind = if get_bool("Folder") == Some(true) {
+            ItunesPlaylistKind::Folder
+        } else if let Some(smart_criteria) = get_data("Smart Criteria") {
+            ItunesPlaylistKind::Smart(smart_playlist_filters(
+                get_data("Smart Info").unwrap_or_default(),
+                smart_criteria,
+            ))
+        } else {
+            ItunesPlaylistKind::Standard
+        };
+
+        let track_ids = playlist_dict
+            .get("Playlist Items")
+            .and_then(PlistValue::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(PlistValue::as_dict)
+            .filter_map(|item| item.get("Track ID").and_then(PlistValue::as_integer))
+            .collect();
+
+        playlists.push(ItunesPlaylist {
+            name: get_str("Name").unwrap_or_default(),
+            persistent_id: get_str("Playlist Persistent ID"),
+            parent_persistent_id: get_str("Parent Persistent ID"),
+            kind,
+            track_ids,
+        });
+    }
+
+    // Playlists inside a folder which was left out are created at the top instead
+    let folder_ids = playlists
+        .iter()
+        .filter(|playlist| matches!(playlist.kind, ItunesPlaylistKind::Folder))
+        .filter_map(|playlist| playlist.persistent_id)
+        .collect::<HashSet<_>>();
+
+    for playlist in &mut playlists {
+        if playlist
+            .parent_persistent_id
+            .is_some_and(|parent_id| !folder_ids.contains(parent_id))
+        {
+            playlist.parent_persistent_id = None;
+        }
+    }
+
+    playlists
+}
+
+/// Creates every playlist and folder whose parent is `parent_persistent_id` inside `folder`, along with
+/// everything inside those folders
+
+fn create_playlists(
+    playlists: &[ItunesPlaylist],
+    parent_persistent_id: Option<&str>,
+    folder: &Rc<Folder>,
+    songs_by_track_id: &HashMap<i64, Arc<Song>>,
+    itunes_import: &mut ItunesLibraryImport,
+) -> io::Result<()> {
+    let child_playlists = playlists
+        .iter()
+        .filter(|playlist| playlist.parent_persistent_id == parent_persistent_id);
+
+    for playlist in child_playlists {
+        let name = playlist.name.to_string();
+
+        match &playlist.kind {
+            ItunesPlaylistKind::Folder => {
+                let sub_folder = folder.create_folder(name)?;
+                itunes_import.folders_created += 1;
+
+                if let Some(persistent_id) = playlist.persistent_id {
+                    create_playlists(
+                        playlists,
+                        Some(persistent_id),
+                        &sub_folder,
+                        songs_by_track_id,
+                        itunes_import,
+                    )?;
+                }
+            }
+
+            ItunesPlaylistKind::Smart(Some(filters)) => {
+                let dynamic_playlist = folder.create_dynamic_playlist(name)?;
+
+                if let PlaylistType::Dynamic(dynamic_playlist) = &*dynamic_playlist {
+                    dynamic_playlist.get_dyn_user_data_mut().inner.rules = Rules {
+                        import_from: ImportFrom::AllSongs,
+                        filters: filters.clone(),
+                    };
+
+                    dynamic_playlist.save_user_data()?;
+                    dynamic_playlist.clear_songs_cache();
+                }
+
+                itunes_import.dynamic_playlists_created += 1;
+            }
+
+            ItunesPlaylistKind::Smart(None) | ItunesPlaylistKind::Standard => {
+                let standard_playlist = folder.create_standard_playlist(name)?;
+
+                let songs = playlist
+                    .track_ids
+                    .iter()
+                    .filter_map(|track_id| songs_by_track_id.get(track_id).cloned())
+                    .collect::<Vec<_>>();
+
+                standard_playlist.import_existing_songs(&songs);
+
+                if let ItunesPlaylistKind::Smart(None) = playlist.kind {
+                    itunes_import
+                        .smart_playlists_as_standard
+                        .push(playlist.name.to_string());
+                }
+
+                itunes_import.playlists_created += 1;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The filter rules matching the criteria of a smart playlist. `None` if any rule can't be expressed as
+/// a filter rule, if any rule rather than every rule has to match, or if the songs are limited
+
+fn smart_playlist_filters(smart_info: &[u8], smart_criteria: &[u8]) -> Option<Vec<FilterRules>> {
+    // The second byte is whether the criteria are used at all, the third whether the songs are limited
+    if smart_info.get(1) != Some(&1) || smart_info.get(2) != Some(&0) {
+        return None;
+    }
+
+    if !smart_criteria.starts_with(SMART_CRITERIA_MAGIC)
+        || smart_criteria.len() < SMART_CRITERIA_HEADER_LEN
+    {
+        return None;
+    }
+
+    let rule_count = read_u32_be(smart_criteria, 8)? as usize;
+    let match_any = read_u32_be(smart_criteria, 12)? != 0;
+
+    if match_any && rule_count > 1 {
+        return None;
+    }
+
+    let mut filters = Vec::with_capacity(rule_count);
+    let mut offset = SMART_CRITERIA_HEADER_LEN;
+
+    while offset < smart_criteria.len() {
+        let field = read_u32_be(smart_criteria, offset)?;
+        let sign = *smart_criteria.get(offset + 4)?;
+        let operator = *smart_criteria.get(offset + 5)?;
+        let value_len = read_u32_be(smart_criteria, offset + 52)? as usize;
+
+        let value_start = offset + SMART_RULE_HEADER_LEN;
+        let value = smart_criteria.get(value_start..value_start + value_len)?;
+
+        filters.push(smart_rule_filter(field, sign, operator, value)?);
+
+        offset = value_start + value_len;
+    }
+
+    // Anything left over means the criteria weren't laid out as expected
+    (filters.len() == rule_count && offset == smart_criteria.len()).then_some(filters)
+}
+
+fn smart_rule_filter(field: u32, sign: u8, operator: u8, value: &[u8]) -> Option<FilterRules> {
+    match field {
+        FIELD_NAME | FIELD_ALBUM | FIELD_ARTIST => {
+            let comparison_method = match (sign, operator) {
+                (SIGN_STRING_POSITIVE, OPERATOR_IS) => ComparisonMethod::EqualTo,
+                (SIGN_STRING_POSITIVE, OPERATOR_CONTAINS) => ComparisonMethod::Contains,
+                (SIGN_STRING_NEGATIVE, OPERATOR_IS) => ComparisonMethod::NotEqualTo,
+                _ => return None,
+            };
+
+            let filter_rule = FilterRule::new(decode_utf16_be(value)?, comparison_method);
+
+            Some(match field {
+                FIELD_NAME => FilterRules::Title(filter_rule),
+                FIELD_ALBUM => FilterRules::Album(filter_rule),
+                _ => FilterRules::Artist(filter_rule),
+            })
+        }
+
+        FIELD_RATING => {
+            let comparison_method = match (sign, operator) {
+                (SIGN_INT_POSITIVE, OPERATOR_IS) => ComparisonMethod::EqualTo,
+                (SIGN_INT_POSITIVE, OPERATOR_GREATER_THAN) => ComparisonMethod::GreaterThan,
+                (SIGN_INT_POSITIVE, OPERATOR_LESS_THAN) => ComparisonMethod::LessThan,
+                (SIGN_INT_NEGATIVE, OPERATOR_IS) => ComparisonMethod::NotEqualTo,
+                _ => return None,
+            };
+
+            let rating = u64::from_be_bytes(value.get(..8)?.try_into().ok()?) as i64;
+
+            if !(0..=MAX_RATING as i64 * ITUNES_RATING_PER_STAR).contains(&rating)
+                || rating % ITUNES_RATING_PER_STAR != 0
+            {
+                return None;
+            }
+
+            Some(FilterRules::Rating(FilterRule::new(
+                (rating / ITUNES_RATING_PER_STAR) as u8,
+                comparison_method,
+            )))
+        }
+
+        _ => None,
+    }
+}
+
+fn read_u32_be(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn decode_utf16_be(bytes: &[u8]) -> Option<String> {
+    if bytes.len() % 2 != 0 {
+        return None;
+    }
+
+    let code_units = bytes
+        .chunks_exact(2)
+        .map(|code_unit| u16::from_be_bytes([code_unit[0], code_unit[1]]))
+        .collect::<Vec<_>>();
+
+    String::from_utf16(&code_units).ok()
+}
+
+/// A value of a property list, dates and reals are not needed so their values are not kept
+#[derive(Debug)]
+enum PlistValue {
+    Dict(HashMap<String, PlistValue>),
+    Array(Vec<PlistValue>),
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    Data(Vec<u8>),
+    Other,
+}
+
+impl PlistValue {
+    fn parse(node: Node) -> io::Result<Self> {
+        let text = node.text().unwrap_or_default();
+
+        let plist_value = match node.tag_name().name() {
+            "dict" => {
+                let mut dict = HashMap::new();
+                let mut children = node.children().filter(Node::is_element);
+
+                while let Some(key) = children.next() {
+                    if !key.has_tag_name("key") {
+                        return Err(invalid_library("a dictionary is missing a key"));
+                    }
+
+                    let value = children
+                        .next()
+                        .ok_or_else(|| invalid_library("a dictionary is missing a value"))?;
+
+                    dict.insert(
+                        key.text().unwrap_or_default().to_string(),
+                        Self::parse(value)?,
+                    );
+                }
+
+                Self::Dict(dict)
+            }
+
+            "array" => Self::Array(
+                node.children()
+                    .filter(Node::is_element)
+                    .map(Self::parse)
+                    .collect::<io::Result<_>>()?,
+            ),
+
+            "string" => Self::String(text.to_string()),
+
+            "integer" => Self::Integer(
+                text.trim()
+                    .parse()
+                    .map_err(|_| invalid_library("an integer is malformed"))?,
+            ),
+
+            "true" => Self::Bool(true),
+            "false" => Self::Bool(false),
+
+            "data" => Self::Data(
+                decode_base64(text).ok_or_else(|| invalid_library("some data is malformed"))?,
+            ),
+
+            _ => Self::Other,
+        };
+
+        Ok(plist_value)
+    }
+
+    fn as_dict(&self) -> Option<&HashMap<String, PlistValue>> {
+        match self {
+            Self::Dict(dict) => Some(dict),
+            _ => None,
+        }
+    }
+
+    fn as_array(&self) -> Option<&Vec<PlistValue>> {
+        match self {
+            Self::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(string) => Some(string),
+            _ => None,
+        }
+    }
+
+    fn as_integer(&self) -> Option<i64> {
+        match self {
+            Self::Integer(integer) => Some(*integer),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(bool) => Some(*bool),
+            _ => None,
+        }
+    }
+
+    fn as_data(&self) -> Option<&[u8]> {
+        match self {
+            Self::Data(data) => Some(data),
+            _ => None,
+        }
+    }
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(text.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut buffered_bits = 0;
+
+    for byte in text.bytes() {
+        let sextet = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            byte if byte.is_ascii_whitespace() => continue,
+            _ => return None,
+        };
+
+        buffer = (buffer << 6) | sextet as u32;
+        buffered_bits += 6;
+
+        if buffered_bits >= 8 {
+            buffered_bits -= 8;
+            decoded.push((buffer >> buffered_bits) as u8);
+            buffer &= (1 << buffered_bits) - 1;
+        }
+    }
+
+    Some(decoded)
+}
+
+fn invalid_library(reason: &str) -> io::Error {
+    io::Error::new(
+        ErrorKind::InvalidData,
+        format!("Not an iTunes library, {}", reason),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `Library.xml` as iTunes on Windows exports it, cut down to a few tracks and playlists. The smart
+    /// playlist matches songs by an artist containing "Beatles" and rated above 3 stars
+    const LIBRARY_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple Computer//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>Major Version</key><integer>1</integer>
+	<key>Minor Version</key><integer>1</integer>
+	<key>Date</key><date>2024-03-02T18:40:11Z</date>
+	<key>Application Version</key><string>12.13.1.3</string>
+	<key>Show Content Ratings</key><true/>
+	<key>Music Folder</key><string>file:///C:/Users/me/Music/iTunes/iTunes%20Media/</string>
+	<key>Library Persistent ID</key><string>5B4A1E0F3C2D7A11</string>
+	<key>Tracks</key>
+	<dict>
+		<key>1021</key>
+		<dict>
+			<key>Track ID</key><integer>1021</integer>
+			<key>Name</key><string>Come Together</string>
+			<key>Artist</key><string>The Beatles</string>
+			<key>Album</key><string>Abbey Road</string>
+			<key>Kind</key><string>MPEG audio file</string>
+			<key>Total Time</key><integer>259813</integer>
+			<key>Play Count</key><integer>42</integer>
+			<key>Play Date UTC</key><date>2024-02-28T21:14:03Z</date>
+			<key>Skip Count</key><integer>3</integer>
+			<key>Rating</key><integer>80</integer>
+			<key>Start Time</key><integer>1500</integer>
+			<key>Stop Time</key><integer>250000</integer>
+			<key>Volume Adjustment</key><integer>-51</integer>
+			<key>Persistent ID</key><string>8E1F0C6B2A9D4E01</string>
+			<key>Track Type</key><string>File</string>
+			<key>Location</key><string>file:///C:/Users/me/Music/iTunes/iTunes%20Media/Music/The%20Beatles/Abbey%20Road/01%20Come%20Together.mp3</string>
+		</dict>
+		<key>1023</key>
+		<dict>
+			<key>Track ID</key><integer>1023</integer>
+			<key>Name</key><string>Something</string>
+			<key>Rating</key><integer>60</integer>
+			<key>Rating Computed</key><true/>
+			<key>Track Type</key><string>File</string>
+			<key>Location</key><string>file:///C:/Users/me/Music/iTunes/iTunes%20Media/Music/The%20Beatles/Abbey%20Road/02%20Something.mp3</string>
+		</dict>
+		<key>1025</key>
+		<dict>
+			<key>Track ID</key><integer>1025</integer>
+			<key>Name</key><string>Radio Paradise</string>
+			<key>Track Type</key><string>URL</string>
+			<key>Location</key><string>http://stream.radioparadise.com/mp3-192</string>
+		</dict>
+		<key>1027</key>
+		<dict>
+			<key>Track ID</key><integer>1027</integer>
+			<key>Name</key><string>Purchased Elsewhere</string>
+			<key>Track Type</key><string>Remote</string>
+		</dict>
+	</dict>
+	<key>Playlists</key>
+	<array>
+		<dict>
+			<key>Name</key><string>Library</string>
+			<key>Master</key><true/>
+			<key>Playlist ID</key><integer>1100</integer>
+			<key>Playlist Persistent ID</key><string>0A1B2C3D4E5F6071</string>
+			<key>Visible</key><false/>
+			<key>All Items</key><true/>
+			<key>Playlist Items</key>
+			<array>
+				<dict>
+					<key>Track ID</key><integer>1021</integer>
+				</dict>
+			</array>
+		</dict>
+		<dict>
+			<key>Name</key><string>Music</string>
+			<key>Playlist ID</key><integer>1200</integer>
+			<key>Playlist Persistent ID</key><string>1A1B2C3D4E5F6072</string>
+			<key>Distinguished Kind</key><integer>4</integer>
+			<key>Music</key><true/>
+			<key>All Items</key><true/>
+		</dict>
+		<dict>
+			<key>Name</key><string>Rock</string>
+			<key>Playlist ID</key><integer>1300</integer>
+			<key>Playlist Persistent ID</key><string>2A1B2C3D4E5F6073</string>
+			<key>All Items</key><true/>
+			<key>Folder</key><true/>
+		</dict>
+		<dict>
+			<key>Name</key><string>Favourites</string>
+			<key>Playlist ID</key><integer>1400</integer>
+			<key>Playlist Persistent ID</key><string>3A1B2C3D4E5F6074</string>
+			<key>Parent Persistent ID</key><string>2A1B2C3D4E5F6073</string>
+			<key>All Items</key><true/>
+			<key>Playlist Items</key>
+			<array>
+				<dict>
+					<key>Track ID</key><integer>1023</integer>
+				</dict>
+				<dict>
+					<key>Track ID</key><integer>1021</integer>
+				</dict>
+			</array>
+		</dict>
+		<dict>
+			<key>Name</key><string>Highly Rated Beatles</string>
+			<key>Playlist ID</key><integer>1500</integer>
+			<key>Playlist Persistent ID</key><string>4A1B2C3D4E5F6075</string>
+			<key>Parent Persistent ID</key><string>2A1B2C3D4E5F6073</string>
+			<key>All Items</key><true/>
+			<key>Smart Info</key>
+			<data>
+			AQEAAwAAABkAAAACAAAAAAAAAAcAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA
+			AAAAAAAAAAAAAAAAAAAA
+			</data>
+			<key>Smart Criteria</key>
+			<data>
+			U0xzdAABAAEAAAACAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA
+			AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA
+			AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA
+			AAAAAAQBAgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA
+			AAAAAAAAAAAAAAAOAEIAZQBhAHQAbABlAHMAAAAZABAAAAAAAAAAAAAAAAAA
+			AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAARAAAAAAAAAA8
+			AAAAAAAAAAAAAAAAAAAAAQAAAAAAAAA8AAAAAAAAAAAAAAAAAAAAAQAAAAAA
+			AAAAAAAAAAAAAAAAAAAA
+			</data>
+			<key>Playlist Items</key>
+			<array>
+				<dict>
+					<key>Track ID</key><integer>1021</integer>
+				</dict>
+			</array>
+		</dict>
+		<dict>
+			<key>Name</key><string>Orphaned</string>
+			<key>Playlist ID</key><integer>1600</integer>
+			<key>Playlist Persistent ID</key><string>5A1B2C3D4E5F6076</string>
+			<key>Parent Persistent ID</key><string>FFFFFFFFFFFFFFFF</string>
+			<key>All Items</key><true/>
+		</dict>
+	</array>
+</dict>
+</plist>
+"#;
+
+    /// Live updating, matching the criteria and not limited
+    const SMART_INFO: &str = "\
+AQEAAwAAABkAAAACAAAAAAAAAAcAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAAAAAAAAAAAAAAA";
+
+    /// Limited to 25 songs
+    const SMART_INFO_LIMITED: &str = "\
+AQEBAwAAABkAAAACAAAAAAAAAAcAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAAAAAAAAAAAAAAA";
+
+    /// The criteria are turned off
+    const SMART_INFO_CRITERIA_UNCHECKED: &str = "\
+AQAAAwAAABkAAAACAAAAAAAAAAcAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAAAAAAAAAAAAAAA";
+
+    /// Artist contains "Beatles" or album is "Abbey Road"
+    const CRITERIA_ANY_OF_TWO: &str = "\
+U0xzdAABAAEAAAACAAAAAQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAQBAgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAAAAAAAAAAOAEIAZQBhAHQAbABlAHMAAAADAQEAAAAAAAAAAAAAAAAA\
+AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAFABBAGIAYgBl\
+AHkAIABSAG8AYQBk";
+
+    /// Name is not "Intro", matching any of a single rule
+    const CRITERIA_ANY_OF_ONE: &str = "\
+U0xzdAABAAEAAAABAAAAAQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAIDAQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAAAAAAAAAAKAEkAbgB0AHIAbw==";
+
+    /// Plays is greater than 10, plays aren't a filter rule
+    const CRITERIA_PLAYS: &str = "\
+U0xzdAABAAEAAAABAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAABYAEAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA\
+AAAAAAAAAAAAAABEAAAAAAAAAAoAAAAAAAAAAAAAAAAAAAABAAAAAAAAAAoA\
+AAAAAAAAAAAAAAAAAAABAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+
+    fn decode(data: &str) -> Vec<u8> {
+        decode_base64(data).expect("The test data is valid base64")
+    }
+
+    fn library_dict() -> HashMap<String, PlistValue> {
+        parse_library(LIBRARY_XML).expect("The library is valid")
+    }
+
+    #[test]
+    fn decodes_base64() {
+        assert_eq!(decode_base64("TWFu"), Some(b"Man".to_vec()));
+        assert_eq!(decode_base64("TWE="), Some(b"Ma".to_vec()));
+        assert_eq!(decode_base64("TQ=="), Some(b"M".to_vec()));
+        assert_eq!(decode_base64(""), Some(Vec::new()));
+        assert_eq!(
+            decode_base64("/+8A\n\t\t\tAQ=="),
+            Some(vec![0xFF, 0xEF, 0x00, 0x01])
+        );
+        assert_eq!(decode_base64("TW-u"), None);
+    }
+
+    #[test]
+    fn parses_the_library_plist() {
+        let library_dict = library_dict();
+
+        assert_eq!(
+            library_dict
+                .get("Application Version")
+                .and_then(PlistValue::as_str),
+            Some("12.13.1.3")
+        );
+        assert_eq!(
+            library_dict
+                .get("Show Content Ratings")
+                .and_then(PlistValue::as_bool),
+            Some(true)
+        );
+        assert!(matches!(library_dict.get("Date"), Some(PlistValue::Other)));
+
+        let track = library_dict
+            .get("Tracks")
+            .and_then(PlistValue::as_dict)
+            .and_then(|tracks| tracks.get("1021"))
+            .and_then(PlistValue::as_dict)
+            .expect("The track is in the library");
+
+        assert_eq!(
+            track
+                .get("Volume Adjustment")
+                .and_then(PlistValue::as_integer),
+            Some(-51)
+        );
+        assert_eq!(
+            track.get("Artist").and_then(PlistValue::as_str),
+            Some("The Beatles")
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_libraries() {
+        assert!(parse_library("<plist version=\"1.0\"><array/></plist>").is_err());
+        assert!(
+            parse_library("<plist version=\"1.0\"><dict><key>Tracks</key></dict></plist>").is_err()
+        );
+        assert!(
+            parse_library(
+                "<plist version=\"1.0\"><dict><key>Date</key><integer>x</integer></dict></plist>"
+            )
+            .is_err()
+        );
+        assert!(
+            parse_library("<plist version=\"1.0\"><dict><key>D</key><data>*</data></dict></plist>")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn reads_local_tracks() {
+        let mut skipped_tracks = Vec::new();
+        let mut tracks = read_tracks(&library_dict(), &mut skipped_tracks);
+
+        tracks.sort_by_key(|track| track.track_id);
+        skipped_tracks.sort();
+
+        assert_eq!(
+            skipped_tracks,
+            [
+                "Purchased Elsewhere",
+                "Radio Paradise (http://stream.radioparadise.com/mp3-192)"
+            ]
+        );
+        assert_eq!(tracks.len(), 2);
+
+        let (come_together, something) = (&tracks[0], &tracks[1]);
+
+        assert_eq!(come_together.track_id, 1021);
+        assert_eq!(
+            come_together.path,
+            PathBuf::from(
+                "C:/Users/me/Music/iTunes/iTunes Media/Music/The Beatles/Abbey Road/01 Come Together.mp3"
+            )
+        );
+        assert_eq!(come_together.play_count, Some(42));
+        assert_eq!(come_together.skip_count, Some(3));
+        assert_eq!(come_together.rating, Some(80));
+        assert_eq!(come_together.start_time_ms, Some(1500));
+        assert_eq!(come_together.stop_time_ms, Some(250000));
+        assert_eq!(come_together.volume_adjustment, Some(-51));
+
+        // The rating comes from the album
+        assert_eq!(something.track_id, 1023);
+        assert_eq!(something.rating, None);
+        assert_eq!(something.play_count, None);
+    }
+
+    #[test]
+    fn reads_user_playlists() {
+        let library_dict = library_dict();
+        let playlists = read_playlists(&library_dict);
+
+        assert_eq!(
+            playlists
+                .iter()
+                .map(|playlist| playlist.name)
+                .collect::<Vec<_>>(),
+            ["Rock", "Favourites", "Highly Rated Beatles", "Orphaned"]
+        );
+
+        let (rock, favourites, smart, orphaned) =
+            (&playlists[0], &playlists[1], &playlists[2], &playlists[3]);
+
+        assert!(matches!(rock.kind, ItunesPlaylistKind::Folder));
+        assert_eq!(rock.parent_persistent_id, None);
+
+        assert!(matches!(favourites.kind, ItunesPlaylistKind::Standard));
+        assert_eq!(favourites.parent_persistent_id, rock.persistent_id);
+        assert_eq!(favourites.track_ids, [1023, 1021]);
+
+        assert!(matches!(smart.kind, ItunesPlaylistKind::Smart(Some(_))));
+        assert_eq!(smart.parent_persistent_id, rock.persistent_id);
+
+        // Its folder isn't in the library
+        assert_eq!(orphaned.parent_persistent_id, None);
+    }
+
+    #[test]
+    fn converts_smart_criteria_matching_every_rule() {
+        let library_dict = library_dict();
+        let playlists = read_playlists(&library_dict);
+
+        let ItunesPlaylistKind::Smart(Some(filters)) = &playlists[2].kind else {
+            panic!("The smart playlist has filter rules");
+        };
+
+        let [FilterRules::Artist(artist), FilterRules::Rating(rating)] = &filters[..] else {
+            panic!(
+                "The rules are an artist and a rating rule, got {:?}",
+                filters
+            );
+        };
+
+        assert_eq!(artist.value, "Beatles");
+        assert!(matches!(
+            artist.comparison_method,
+            ComparisonMethod::Contains
+        ));
+        assert_eq!(rating.value, 3);
+        assert!(matches!(
+            rating.comparison_method,
+            ComparisonMethod::GreaterThan
+        ));
+    }
+
+    #[test]
+    fn converts_a_single_rule_matching_any() {
+        let filters = smart_playlist_filters(&decode(SMART_INFO), &decode(CRITERIA_ANY_OF_ONE))
+            .expect("A single rule can match any");
+
+        let [FilterRules::Title(title)] = &filters[..] else {
+            panic!("The rule is a title rule, got {:?}", filters);
+        };
+
+        assert_eq!(title.value, "Intro");
+        assert!(matches!(
+            title.comparison_method,
+            ComparisonMethod::NotEqualTo
+        ));
+    }
+
+    #[test]
+    fn keeps_smart_criteria_which_are_not_filter_rules() {
+        let smart_info = decode(SMART_INFO);
+        let criteria = decode(CRITERIA_ANY_OF_TWO);
+
+        assert!(smart_playlist_filters(&smart_info, &criteria).is_none());
+        assert!(smart_playlist_filters(&smart_info, &decode(CRITERIA_PLAYS)).is_none());
+
+        let criteria = decode(CRITERIA_ANY_OF_ONE);
+
+        assert!(smart_playlist_filters(&decode(SMART_INFO_LIMITED), &criteria).is_none());
+        assert!(
+            smart_playlist_filters(&decode(SMART_INFO_CRITERIA_UNCHECKED), &criteria).is_none()
+        );
+        assert!(smart_playlist_filters(&[], &criteria).is_none());
+    }
+
+    #[test]
+    fn rejects_smart_criteria_laid_out_differently() {
+        let smart_info = decode(SMART_INFO);
+        let criteria = decode(CRITERIA_ANY_OF_ONE);
+
+        assert!(smart_playlist_filters(&smart_info, &criteria[..criteria.len() - 1]).is_none());
+        assert!(
+            smart_playlist_filters(&smart_info, &[criteria.clone(), vec![0; 4]].concat()).is_none()
+        );
+        assert!(smart_playlist_filters(&smart_info, &criteria[4..]).is_none());
+        assert!(
+            smart_playlist_filters(&smart_info, &criteria[..SMART_CRITERIA_HEADER_LEN]).is_none()
+        );
+    }
+}
@@ -5,14 +5,23 @@ use std::ops::{Deref, DerefMut};
 use std::sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use std::time::{Duration, SystemTime};
 
+pub mod archive;
 pub mod assets;
 pub mod content;
 pub mod discord_rpc;
+pub mod fsck;
 pub mod instance;
+pub mod itunes_library;
+#[cfg(feature = "serde")]
+pub mod library_json;
+pub mod migration;
 mod net;
 pub mod paths;
 mod pool;
 mod resetable_once_cell;
+mod safe_write;
+#[cfg(feature = "tag_write")]
+pub mod tag_write;
 
 pub use simple_id;
 
@@ -0,0 +1,699 @@
+//! Exports the metadata of the library to json and imports it back, for diffing libraries, editing in
+//! bulk, and use by other tools. Everything is keyed by its id.
+//!
+//! Only metadata is exported, the audio and covers stay in the library. Importing updates the songs,
+//! folders and playlists which already exist in the library, it never creates or deletes any. Fields
+//! which come from the audio file or the folder tree are exported for reference, and are ignored when
+//! importing.
+//!
+//! Importing works directly on the files, so anything already loaded should be reloaded afterwards, ie.
+//! by restarting.
+
+use crate::content::SaveData;
+use crate::content::folder::{FolderData, FolderDataContentVariant};
+use crate::content::playlist::PlaylistTypeVariant;
+use crate::content::playlist::data::{
+    PlaybackMode, PlaylistSongListData, PlaylistUserData, PlaylistUserDataStd,
+};
+use crate::content::playlist::dynamic_playlist_data::DynamicPlaylistData;
+use crate::content::playlist::playlists::dynamic_playlist::filter::FilterRules;
+use crate::content::playlist::playlists::dynamic_playlist::rules::{ImportFrom, Rules};
+use crate::content::playlist::song_list::SortBy;
+use crate::content::song::audio_container::AudioContainer;
+use crate::content::song::song_data::{Artist, MAX_RATING, SongData};
+use crate::content::song::song_pool::SONG_POOL;
+use crate::migration::library_version;
+use crate::paths::song::song_data_file_v2;
+use crate::paths::{content_folder_file, content_playlist_song_list_file};
+use crate::safe_write::{SafeWrite, write_atomically};
+use crate::time_now;
+use serde::{Deserialize, Serialize};
+use simple_id::prelude::Id;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, ErrorKind, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Bumped whenever the layout of the json changes in a way older versions can't read
+const LIBRARY_JSON_FORMAT_VERSION: u16 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LibraryJson {
+    pub format_version: u16,
+    /// The version the library was migrated to when it was exported
+    pub library_version: u16,
+    pub songs: BTreeMap<String, SongJson>,
+    pub folders: BTreeMap<String, FolderJson>,
+    pub playlists: BTreeMap<String, PlaylistJson>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SongJson {
+    /// The name the song was registered under, read only
+    pub registered_name: String,
+    pub title: String,
+    pub original_title: String,
+    /// None if it couldn't be read from the audio file
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    #[serde(default)]
+    pub track_number: Option<u32>,
+    #[serde(default)]
+    pub disc_number: Option<u32>,
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(default)]
+    pub genre: Option<String>,
+    #[serde(default)]
+    pub album_artist: Option<String>,
+    #[serde(default)]
+    pub composer: Option<String>,
+    #[serde(default)]
+    pub bpm: Option<u32>,
+    #[serde(default)]
+    pub comment: Option<String>,
+    pub custom_tags: Vec<String>,
+    /// From 0 to 5, where 0 is unrated
+    pub rating: u8,
+    pub user_tag: String,
+    pub times_listened: u32,
+    pub times_skipped: u32,
+    pub start_offset_ms: Option<u64>,
+    pub end_time_ms: Option<u64>,
+    pub custom_volume: f32,
+    /// Read only
+    pub audio_container: AudioContainer,
+    /// Length in seconds, read only
+    pub song_length: Option<u32>,
+    /// Id of the cover, read only
+    pub cover: Option<String>,
+}
+
+impl SongJson {
+    fn new(registered_name: String, song_data: &SongData) -> Self {
+        let song_data = &song_data.inner;
+        let meta = &song_data.meta.inner;
+
+        Self {
+            registered_name,
+            title: song_data.title.clone(),
+            original_title: song_data.original_title.clone(),
+            artist: meta
+                .artist
+                .inner
+                .as_ref()
+                .ok()
+                .map(|artist| artist.full_artist_string.clone()),
+            album: meta.album.inner.as_ref().ok().cloned(),
+            track_number: meta.track_number.inner.as_ref().ok().copied().flatten(),
+            disc_number: meta.disc_number.inner.as_ref().ok().copied().flatten(),
+            date: meta.date.inner.as_ref().ok().cloned(),
+            genre: meta.genre.inner.as_ref().ok().cloned(),
+            album_artist: meta.album_artist.inner.as_ref().ok().cloned(),
+            composer: meta.composer.inner.as_ref().ok().cloned(),
+            bpm: meta.bpm.inner.as_ref().ok().copied().flatten(),
+            comment: meta.comment.inner.as_ref().ok().cloned(),
+            custom_tags: song_data.custom_tags.clone(),
+            rating: song_data.rating,
+            user_tag: song_data.user_tag.clone(),
+            times_listened: song_data.times_listened,
+            times_skipped: song_data.times_skipped.inner,
+            start_offset_ms: song_data.start_offset.inner.map(duration_to_ms),
+            end_time_ms: song_data.end_time.inner.map(duration_to_ms),
+            custom_volume: song_data.custom_volume.inner,
+            audio_container: song_data.audio_container.inner,
+            song_length: meta.song_length.inner.as_ref().ok().copied(),
+            cover: meta
+                .cover
+                .inner
+                .as_ref()
+                .ok()
+                .copied()
+                .flatten()
+                .map(|cover_id| cover_id.to_string()),
+        }
+    }
+
+    fn apply(&self, song_data: &mut SongData) {
+        let song_data = &mut song_data.inner;
+
+        song_data.title = self.title.clone();
+        song_data.original_title = self.original_title.clone();
+        song_data.custom_tags = self.custom_tags.clone();
+        song_data.rating = self.rating;
+        song_data.user_tag = self.user_tag.clone();
+        song_data.times_listened = self.times_listened;
+        song_data.times_skipped = self.times_skipped.into();
+        song_data.start_offset = self.start_offset_ms.map(Duration::from_millis).into();
+        song_data.end_time = self.end_time_ms.map(Duration::from_millis).into();
+        song_data.custom_volume = self.custom_volume.into();
+
+        let meta = &mut song_data.meta.inner;
+
+        if let Some(artist) = &self.artist {
+            meta.artist = Artist {
+                full_artist_string: artist.clone(),
+            }
+            .into();
+        }
+
+        if let Some(album) = &self.album {
+            meta.album = album.clone().into();
+        }
+
+        let text_fields = [
+            (&mut meta.date, &self.date),
+            (&mut meta.genre, &self.genre),
+            (&mut meta.album_artist, &self.album_artist),
+            (&mut meta.composer, &self.composer),
+            (&mut meta.comment, &self.comment),
+        ];
+
+        for (field, value) in text_fields {
+            if let Some(value) = value {
+                *field = value.clone().into();
+            }
+        }
+
+        let number_fields = [
+            (&mut meta.track_number, self.track_number),
+            (&mut meta.disc_number, self.disc_number),
+            (&mut meta.bpm, self.bpm),
+        ];
+
+        for (field, value) in number_fields {
+            // A field which wasn't read from the audio file yet is exported as None too, it's left to be read
+            if field.inner.is_ok() || value.is_some() {
+                *field = value.into();
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FolderJson {
+    pub name: String,
+    /// Id of the parent folder, none for the base folder. Read only
+    pub parent: Option<String>,
+    pub expanded: bool,
+    /// Ids of the folders and playlists inside this folder, in order. Read only
+    pub contents: Vec<String>,
+}
+
+impl FolderJson {
+    fn new(folder_data: &FolderData) -> Self {
+        Self {
+            name: folder_data.content_data.name.clone(),
+            parent: folder_data
+                .content_data
+                .parent
+                .map(|parent_id| parent_id.to_string()),
+            expanded: folder_data.expanded.inner,
+            contents: folder_data
+                .contents
+                .iter()
+                .map(|content| content.id.to_string())
+                .collect(),
+        }
+    }
+
+    fn apply(&self, folder_data: &mut FolderData) {
+        folder_data.content_data.name = self.name.clone();
+        folder_data.expanded = self.expanded.into();
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PlaylistKindJson {
+    Standard,
+    Dynamic,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum ImportFromJson {
+    AllSongs,
+    PlaylistIds(Vec<String>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RulesJson {
+    pub import_from: ImportFromJson,
+    pub filters: Vec<FilterRules>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlaylistJson {
+    pub name: String,
+    /// Id of the folder the playlist is in. Read only
+    pub parent: String,
+    /// Read only
+    pub kind: PlaylistKindJson,
+    pub playback_mode: PlaybackMode,
+    pub volume: f32,
+    pub sort_by: SortBy,
+    /// Ids of the songs in a standard playlist, dynamic playlists build their own from their rules
+    pub song_ids: Option<Vec<String>>,
+    /// The rules of a dynamic playlist
+    pub rules: Option<RulesJson>,
+}
+
+impl PlaylistJson {
+    fn new(
+        kind: PlaylistKindJson,
+        user_data: &PlaylistUserDataStd,
+        song_ids: Option<&[Id]>,
+        rules: Option<&Rules>,
+    ) -> Self {
+        Self {
+            name: user_data.content_data.name.clone(),
+            parent: user_data.content_data.parent.to_string(),
+            kind,
+            playback_mode: user_data.playback_mode,
+            volume: user_data.volume,
+            sort_by: user_data.sort_by,
+            song_ids: song_ids.map(ids_to_strings),
+            rules: rules.map(|rules| RulesJson {
+                import_from: match &rules.import_from {
+                    ImportFrom::AllSongs => ImportFromJson::AllSongs,
+                    ImportFrom::PlaylistIds(playlist_ids) => {
+                        ImportFromJson::PlaylistIds(ids_to_strings(playlist_ids))
+                    }
+                },
+                filters: rules.filters.clone(),
+            }),
+        }
+    }
+
+    fn apply(&self, user_data: &mut PlaylistUserDataStd) {
+        user_data.content_data.name = self.name.clone();
+        user_data.playback_mode = self.playback_mode;
+        user_data.volume = self.volume;
+        user_data.sort_by = self.sort_by;
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct LibraryJsonImportReport {
+    pub songs_updated: usize,
+    pub folders_updated: usize,
+    pub playlists_updated: usize,
+    /// Entries which were skipped or only partly imported, along with why
+    pub problems: Vec<String>,
+}
+
+/// Every song, folder and playlist in the library, found by walking the folder tree from the base folder
+/// and going through the registered songs
+
+#[derive(Default)]
+struct LibraryIds {
+    songs: HashMap<String, (Id, String)>,
+    folders: HashMap<String, Id>,
+    playlists: HashMap<String, (Id, PlaylistKindJson)>,
+}
+
+impl LibraryIds {
+    fn find() -> Self {
+        let mut library_ids = Self::default();
+
+        for (registered_name, song_id) in &SONG_POOL.get_registered_songs().name_map {
+            library_ids
+                .songs
+                .insert(song_id.to_string(), (*song_id, registered_name.clone()));
+        }
+
+        library_ids.find_in_folder(Id::ZERO);
+
+        library_ids
+    }
+
+    fn find_in_folder(&mut self, folder_id: Id) {
+        if self
+            .folders
+            .insert(folder_id.to_string(), folder_id)
+            .is_some()
+        {
+            return;
+        }
+
+        let Ok(folder_data) = FolderData::from_file_path_or_backup(content_folder_file(folder_id))
+        else {
+            return;
+        };
+
+        for content in &folder_data.contents {
+            match content.variant {
+                FolderDataContentVariant::Folder => self.find_in_folder(content.id),
+
+                FolderDataContentVariant::Playlist(PlaylistTypeVariant::Standard(_)) => {
+                    self.playlists.insert(
+                        content.id.to_string(),
+                        (content.id, PlaylistKindJson::Standard),
+                    );
+                }
+
+                FolderDataContentVariant::Playlist(PlaylistTypeVariant::Dynamic(_)) => {
+                    self.playlists.insert(
+                        content.id.to_string(),
+                        (content.id, PlaylistKindJson::Dynamic),
+                    );
+                }
+
+                FolderDataContentVariant::Playlist(
+                    PlaylistTypeVariant::AllSongs(_) | PlaylistTypeVariant::Album(_),
+                ) => {}
+            }
+        }
+    }
+
+    /// Maps ids to those in the library, unknown ids are added to `problems` and left out
+
+    fn map_ids(
+        &self,
+        id_strs: &[String],
+        find: impl Fn(&Self, &str) -> Option<Id>,
+        problems: &mut Vec<String>,
+        context: &str,
+    ) -> Vec<Id> {
+        id_strs
+            .iter()
+            .filter_map(|id_str| {
+                let id = find(self, id_str);
+
+                if id.is_none() {
+                    problems.push(format!("{}: unknown id {}, left out", context, id_str));
+                }
+
+                id
+            })
+            .collect()
+    }
+}
+
+impl LibraryJson {
+    /// Reads the metadata of every song, folder and playlist in the library. Anything which can't be
+    /// read is left out
+
+    pub fn from_library() -> Self {
+        let library_ids = LibraryIds::find();
+        let mut library_json = Self {
+            format_version: LIBRARY_JSON_FORMAT_VERSION,
+            library_version: library_version(),
+            songs: BTreeMap::new(),
+            folders: BTreeMap::new(),
+            playlists: BTreeMap::new(),
+        };
+
+        for (id_str, (song_id, registered_name)) in &library_ids.songs {
+            match SongData::from_file_path_or_backup(song_data_file_v2(song_id)) {
+                Ok(song_data) => {
+                    library_json.songs.insert(
+                        id_str.clone(),
+                        SongJson::new(registered_name.clone(), &song_data),
+                    );
+                }
+
+                Err(e) => eprintln!("Unable to read song {}, not exporting it; {}", id_str, e),
+            }
+        }
+
+        for (id_str, folder_id) in &library_ids.folders {
+            if let Ok(folder_data) =
+                FolderData::from_file_path_or_backup(content_folder_file(*folder_id))
+            {
+                library_json
+                    .folders
+                    .insert(id_str.clone(), FolderJson::new(&folder_data));
+            }
+        }
+
+        for (id_str, (playlist_id, kind)) in &library_ids.playlists {
+            if let Some(playlist_json) = read_playlist_json(*playlist_id, *kind) {
+                library_json.playlists.insert(id_str.clone(), playlist_json);
+            }
+        }
+
+        library_json
+    }
+
+    /// Writes the metadata of every entry which differs from what is in the library. Entries whose id
+    /// isn't in the library are skipped
+
+    pub fn apply_to_library(&self) -> io::Result<LibraryJsonImportReport> {
+        if self.format_version > LIBRARY_JSON_FORMAT_VERSION {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "The json was exported by a newer version of Napoleon Amp",
+            ));
+        }
+
+        let library_ids = LibraryIds::find();
+        let current = Self::from_library();
+        let mut report = LibraryJsonImportReport::default();
+
+        for (id_str, song_json) in &self.songs {
+            let Some((song_id, _)) = library_ids.songs.get(id_str) else {
+                report
+                    .problems
+                    .push(format!("Song {}: not in the library, skipped", id_str));
+                continue;
+            };
+
+            if !differs(current.songs.get(id_str), song_json)? {
+                continue;
+            }
+
+            if song_json.rating > MAX_RATING as u8 {
+                report.problems.push(format!(
+                    "Song {}: rating {} is above {}, skipped",
+                    id_str, song_json.rating, MAX_RATING
+                ));
+                continue;
+            }
+
+            let song_data_path = song_data_file_v2(song_id);
+            let mut song_data =
+                SongData::from_file_path_or_backup(&song_data_path).map_err(invalid_data)?;
+
+            song_json.apply(&mut song_data);
+            song_data.write_to_file_path_safe(&song_data_path)?;
+
+            report.songs_updated += 1;
+        }
+
+        for (id_str, folder_json) in &self.folders {
+            let Some(folder_id) = library_ids.folders.get(id_str) else {
+                report
+                    .problems
+                    .push(format!("Folder {}: not in the library, skipped", id_str));
+                continue;
+            };
+
+            if !differs(current.folders.get(id_str), folder_json)? {
+                continue;
+            }
+
+            let mut folder_data =
+                FolderData::from_file_path_or_backup(content_folder_file(*folder_id))
+                    .map_err(invalid_data)?;
+
+            folder_json.apply(&mut folder_data);
+            folder_data.save_data(*folder_id)?;
+
+            report.folders_updated += 1;
+        }
+
+        for (id_str, playlist_json) in &self.playlists {
+            let Some((playlist_id, kind)) = library_ids.playlists.get(id_str) else {
+                report
+                    .problems
+                    .push(format!("Playlist {}: not in the library, skipped", id_str));
+                continue;
+            };
+
+            if playlist_json.kind != *kind {
+                report.problems.push(format!(
+                    "Playlist {}: is {:?} in the library but {:?} in the json, skipped",
+                    id_str, kind, playlist_json.kind
+                ));
+                continue;
+            }
+
+            if !differs(current.playlists.get(id_str), playlist_json)? {
+                continue;
+            }
+
+            apply_playlist_json(
+                *playlist_id,
+                playlist_json,
+                &library_ids,
+                &mut report.problems,
+            )?;
+
+            report.playlists_updated += 1;
+        }
+
+        Ok(report)
+    }
+}
+
+/// Writes the metadata of the library to a json file at `json_path`
+
+pub fn export_library_json(json_path: &Path) -> io::Result<LibraryJson> {
+    let library_json = LibraryJson::from_library();
+
+    write_atomically(json_path, |temp_path| {
+        let mut writer = BufWriter::new(File::create(temp_path)?);
+        serde_json::to_writer_pretty(&mut writer, &library_json).map_err(io::Error::from)?;
+
+        writer.flush()
+    })?;
+
+    Ok(library_json)
+}
+
+/// Reads a json file written by [`export_library_json`] and applies it to the library, see
+/// [`LibraryJson::apply_to_library`]
+
+pub fn import_library_json(json_path: &Path) -> io::Result<LibraryJsonImportReport> {
+    let reader = BufReader::new(File::open(json_path)?);
+    let library_json: LibraryJson = serde_json::from_reader(reader).map_err(io::Error::from)?;
+
+    library_json.apply_to_library()
+}
+
+fn read_playlist_json(playlist_id: Id, kind: PlaylistKindJson) -> Option<PlaylistJson> {
+    match kind {
+        PlaylistKindJson::Standard => {
+            let user_data =
+                PlaylistUserData::from_file_path_or_backup(PlaylistUserData::get_path(playlist_id))
+                    .ok()?;
+
+            let song_list_data = PlaylistSongListData::from_file_path_or_backup(
+                content_playlist_song_list_file(playlist_id),
+            )
+            .unwrap_or_default();
+
+            Some(PlaylistJson::new(
+                kind,
+                &user_data.inner,
+                Some(song_list_data.song_ids.as_slice()),
+                None,
+            ))
+        }
+
+        PlaylistKindJson::Dynamic => {
+            let dynamic_data = DynamicPlaylistData::from_file_path_or_backup(
+                DynamicPlaylistData::get_path(playlist_id),
+            )
+            .ok()?;
+
+            Some(PlaylistJson::new(
+                kind,
+                &dynamic_data.inner.user_data.inner,
+                None,
+                Some(&dynamic_data.inner.rules),
+            ))
+        }
+    }
+}
+
+fn apply_playlist_json(
+    playlist_id: Id,
+    playlist_json: &PlaylistJson,
+    library_ids: &LibraryIds,
+    problems: &mut Vec<String>,
+) -> io::Result<()> {
+    let context = format!("Playlist {}", playlist_id);
+
+    match playlist_json.kind {
+        PlaylistKindJson::Standard => {
+            let mut user_data =
+                PlaylistUserData::from_file_path_or_backup(PlaylistUserData::get_path(playlist_id))
+                    .map_err(invalid_data)?;
+
+            playlist_json.apply(&mut user_data.inner);
+            user_data.save_data(playlist_id)?;
+
+            if let Some(song_id_strs) = &playlist_json.song_ids {
+                let song_list_path = content_playlist_song_list_file(playlist_id);
+                let song_list_data = PlaylistSongListData {
+                    song_ids: library_ids.map_ids(
+                        song_id_strs,
+                        |library_ids, id_str| {
+                            library_ids.songs.get(id_str).map(|(song_id, _)| *song_id)
+                        },
+                        problems,
+                        &context,
+                    ),
+                    ..Default::default()
+                };
+
+                // Dynamic playlists compare against this to know when to rebuild their song list
+                song_list_data.last_updated.set(time_now().as_secs());
+                song_list_data.write_to_file_path_safe(song_list_path)?;
+            }
+        }
+
+        PlaylistKindJson::Dynamic => {
+            let mut dynamic_data = DynamicPlaylistData::from_file_path_or_backup(
+                DynamicPlaylistData::get_path(playlist_id),
+            )
+            .map_err(invalid_data)?;
+
+            playlist_json.apply(&mut dynamic_data.inner.user_data.inner);
+
+            if let Some(rules_json) = &playlist_json.rules {
+                let rules = &mut dynamic_data.inner.rules;
+
+                rules.import_from = match &rules_json.import_from {
+                    ImportFromJson::AllSongs => ImportFrom::AllSongs,
+
+                    ImportFromJson::PlaylistIds(playlist_id_strs) => {
+                        ImportFrom::PlaylistIds(library_ids.map_ids(
+                            playlist_id_strs,
+                            |library_ids, id_str| {
+                                library_ids
+                                    .playlists
+                                    .get(id_str)
+                                    .map(|(playlist_id, _)| *playlist_id)
+                            },
+                            problems,
+                            &context,
+                        ))
+                    }
+                };
+
+                rules.filters = rules_json.filters.clone();
+            }
+
+            dynamic_data.save_data(playlist_id)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Compares through json so only the exported fields are compared
+
+fn differs<T: Serialize>(current: Option<&T>, imported: &T) -> io::Result<bool> {
+    let Some(current) = current else {
+        return Ok(true);
+    };
+
+    Ok(serde_json::to_value(current).map_err(io::Error::from)?
+        != serde_json::to_value(imported).map_err(io::Error::from)?)
+}
+
+fn ids_to_strings(ids: &[Id]) -> Vec<String> {
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+fn duration_to_ms(duration: Duration) -> u64 {
+    duration.as_millis() as u64
+}
+
+fn invalid_data(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, e.to_string())
+}
@@ -0,0 +1,624 @@
+//! Upgrades the files of a library written by older versions. The library records the version it was
+//! last migrated to, and every migration after that is applied in order at startup, after a copy of the
+//! library data is taken.
+//!
+//! Migrations only add to or rewrite the library, so running one again on a library which already had
+//! it applied changes nothing. This keeps a library whose version file was lost safe to migrate.
+
+use crate::content::song::audio_container::AudioContainer;
+use crate::content::song::content_hash::{ContentHash, files_equal, hash_file};
+use crate::content::song::song_data::{SongData, get_song_data_from_song_file_with_paths};
+use crate::content::song::song_pool::{RegisteredSongs, SONG_POOL};
+use crate::paths::song::{
+    registered_songs_data_file_v2, song_audio_file_v2, song_data_file_v2, songs_audio_dir_v2,
+    songs_data_dir_v2, songs_dir_v1,
+};
+use crate::paths::{
+    SONG_DATA_EXT_NO_PER, backups_dir, library_version_file, log_dir, lost_found_dir,
+    migration_backup_dir, migration_backups_dir, napoleon_amp_dir, replaced_by_restore_dir,
+    restore_staging_dir, trash_dir,
+};
+use crate::safe_write::{SafeWrite, is_temp_file};
+use serbytes::prelude::{SerBytes, SerBytesFs};
+use simple_id::prelude::SmallRngIdGenerator;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+struct Migration {
+    description: &'static str,
+    /// Applies the migration, or only works out what it would change when `dry_run` is set. Returns a
+    /// description of each change
+    apply: fn(dry_run: bool) -> io::Result<Vec<String>>,
+}
+
+/// Every migration in the order they are applied, a library at version `n` has had the first `n`
+/// applied. New migrations are only ever added to the end
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        description: "Move songs from the v1 songs directory into songs_v2",
+        apply: migrate_songs_v1_to_v2,
+    },
+    Migration {
+        description: "Detect the real audio container of songs stored with an mp3 extension",
+        apply: migrate_audio_containers,
+    },
+    Migration {
+        description: "Upgrade every song data file to the current song data version",
+        apply: upgrade_song_data_files,
+    },
+    Migration {
+        description: "Read the track, disc, date, genre, album artist, composer, BPM and comment of every song from its audio",
+        apply: read_extended_song_metadata,
+    },
+];
+
+/// The version of a library which has had every migration applied
+
+pub const CURRENT_LIBRARY_VERSION: u16 = MIGRATIONS.len() as u16;
+
+#[derive(SerBytes, Default, Debug, Copy, Clone)]
+struct LibraryVersion {
+    version: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStepReport {
+    /// The library version once this step is applied
+    pub version: u16,
+    pub description: &'static str,
+    pub changes: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationReport {
+    pub from_version: u16,
+    pub to_version: u16,
+    pub dry_run: bool,
+    /// Where the library data was copied before migrating, none for dry runs or when nothing was pending
+    pub backup_dir: Option<PathBuf>,
+    pub steps: Vec<MigrationStepReport>,
+}
+
+impl MigrationReport {
+    pub fn is_up_to_date(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+impl Display for MigrationReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_up_to_date() {
+            return write!(f, "Library is up to date at version {}", self.from_version);
+        }
+
+        if self.dry_run {
+            writeln!(
+                f,
+                "Migrating from version {} to {} would:",
+                self.from_version, self.to_version
+            )?;
+        } else {
+            writeln!(
+                f,
+                "Migrated from version {} to {}:",
+                self.from_version, self.to_version
+            )?;
+        }
+
+        for step in &self.steps {
+            writeln!(f, "{}. {}", step.version, step.description)?;
+
+            if step.changes.is_empty() {
+                writeln!(f, "    Nothing to change")?;
+            }
+
+            for change in &step.changes {
+                writeln!(f, "    {}", change)?;
+            }
+        }
+
+        if let Some(backup_dir) = &self.backup_dir {
+            write!(f, "Library data was backed up to {}", backup_dir.display())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The version the library was last migrated to, 0 if it has never been migrated
+
+pub fn library_version() -> u16 {
+    LibraryVersion::from_file_path_or_backup(library_version_file())
+        .map(|library_version| library_version.version)
+        .unwrap_or_default()
+}
+
+/// Works out what each pending migration would change, nothing is written. Files are only read as they
+/// are, a file which can't be read is not restored from its backup and songs are not loaded through the
+/// song pool, since both of those write to the library
+
+pub fn plan_migrations() -> io::Result<MigrationReport> {
+    let from_version = LibraryVersion::from_file_path(library_version_file())
+        .map(|library_version| library_version.version)
+        .unwrap_or_default();
+    let mut steps = Vec::new();
+
+    for (version, migration) in pending_migrations(from_version) {
+        steps.push(MigrationStepReport {
+            version,
+            description: migration.description,
+            changes: (migration.apply)(true)?,
+        });
+    }
+
+    Ok(MigrationReport {
+        from_version,
+        to_version: CURRENT_LIBRARY_VERSION,
+        dry_run: true,
+        backup_dir: None,
+        steps,
+    })
+}
+
+/// Backs up the library data, then applies every pending migration in order. The version is recorded
+/// after each migration, so a failed migration is retried from where it left off next time
+
+pub fn run_migrations() -> io::Result<MigrationReport> {
+    let from_version = library_version();
+    let pending = pending_migrations(from_version).collect::<Vec<_>>();
+
+    if pending.is_empty() {
+        return Ok(MigrationReport {
+            from_version,
+            to_version: from_version,
+            dry_run: false,
+            backup_dir: None,
+            steps: Vec::new(),
+        });
+    }
+
+    let backup_dir = back_up_library_data(from_version)?;
+    let mut steps = Vec::new();
+
+    for (version, migration) in pending {
+        println!("Migrating library to version {}", version);
+
+        let changes = (migration.apply)(false)?;
+
+        LibraryVersion { version }.write_to_file_path_safe(library_version_file())?;
+
+        steps.push(MigrationStepReport {
+            version,
+            description: migration.description,
+            changes,
+        });
+    }
+
+    Ok(MigrationReport {
+        from_version,
+        to_version: CURRENT_LIBRARY_VERSION,
+        dry_run: false,
+        backup_dir: Some(backup_dir),
+        steps,
+    })
+}
+
+fn pending_migrations(from_version: u16) -> impl Iterator<Item = (u16, &'static Migration)> {
+    MIGRATIONS
+        .iter()
+        .enumerate()
+        .skip(from_version as usize)
+        .map(|(index, migration)| (index as u16 + 1, migration))
+}
+
+/// Copies every file of the library other than audio into the migration backup directory. Audio files
+/// are left out since they are large and migrations never overwrite them
+
+fn back_up_library_data(from_version: u16) -> io::Result<PathBuf> {
+    let backup_dir = migration_backup_dir(from_version);
+    let library_dir = napoleon_amp_dir();
+
+    let excluded_dirs = [
+        migration_backups_dir(),
+        backups_dir(),
+        trash_dir(),
+        restore_staging_dir(),
+        replaced_by_restore_dir(),
+        lost_found_dir(),
+        log_dir(),
+        songs_audio_dir_v2(),
+        songs_dir_v1(),
+    ];
+
+    if library_dir.try_exists()? {
+        copy_dir_recursive(&library_dir, &library_dir, &backup_dir, &excluded_dirs)?;
+    }
+
+    Ok(backup_dir)
+}
+
+fn copy_dir_recursive(
+    dir: &Path,
+    library_dir: &Path,
+    backup_dir: &Path,
+    excluded_dirs: &[PathBuf],
+) -> io::Result<()> {
+    for dir_entry in dir.read_dir()? {
+        let dir_entry = dir_entry?;
+        let path = dir_entry.path();
+
+        if dir_entry.file_type()?.is_dir() {
+            if !excluded_dirs
+                .iter()
+                .any(|excluded_dir| path.starts_with(excluded_dir))
+            {
+                copy_dir_recursive(&path, library_dir, backup_dir, excluded_dirs)?;
+            }
+
+            continue;
+        }
+
+        let relative_path = path
+            .strip_prefix(library_dir)
+            .expect("Found by walking the library directory");
+        let backup_path = backup_dir.join(relative_path);
+
+        if let Some(backup_parent) = backup_path.parent() {
+            fs::create_dir_all(backup_parent)?;
+        }
+
+        fs::copy(&path, backup_path)?;
+    }
+
+    Ok(())
+}
+
+/// Songs in the v1 songs directory are copied into songs_v2 under a new id and registered, their song
+/// data is upgraded as it is read. Songs which are already in the library are skipped, and the v1
+/// directory is left as is
+
+fn migrate_songs_v1_to_v2(dry_run: bool) -> io::Result<Vec<String>> {
+    let songs_dir = songs_dir_v1();
+    let mut changes = Vec::new();
+
+    if !songs_dir.try_exists()? {
+        return Ok(changes);
+    }
+
+    let mut generator = SmallRngIdGenerator::default();
+    let mut library_audio = if dry_run {
+        library_audio_files()?
+    } else {
+        Vec::new()
+    };
+
+    for dir_entry in songs_dir.read_dir()? {
+        let old_audio_path = dir_entry?.path();
+
+        if !old_audio_path.is_file()
+            || old_audio_path.extension().and_then(|ext| ext.to_str()) == Some(SONG_DATA_EXT_NO_PER)
+        {
+            continue;
+        }
+
+        let Some(audio_container) = AudioContainer::from_path(&old_audio_path)? else {
+            changes.push(format!(
+                "Skip {}, it is not a supported audio format",
+                old_audio_path.display()
+            ));
+            continue;
+        };
+
+        let content_hash = hash_file(&old_audio_path)?;
+
+        if dry_run {
+            if find_audio_copy(&old_audio_path, content_hash, &mut library_audio)? {
+                continue;
+            }
+
+            library_audio.push((old_audio_path.clone(), Some(content_hash)));
+        } else if SONG_POOL
+            .find_duplicate(&old_audio_path, content_hash)?
+            .is_some()
+        {
+            continue;
+        }
+
+        changes.push(format!(
+            "Copy {} into the library as a new song",
+            old_audio_path.display()
+        ));
+
+        if dry_run {
+            continue;
+        }
+
+        let song_id = generator.generate_new_id();
+        let new_audio_path = song_audio_file_v2(&song_id, audio_container);
+        let song_name = old_audio_path
+            .file_stem()
+            .map(|file_stem| file_stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "Unnamed Song".to_string());
+
+        let mut song_data =
+            SongData::from_file_path_or_backup(old_audio_path.with_extension(SONG_DATA_EXT_NO_PER))
+                .unwrap_or_default();
+
+        song_data.inner.audio_container = audio_container.into();
+
+        if song_data.inner.title.is_empty() {
+            song_data.inner.title = song_name.clone();
+        }
+
+        fs::create_dir_all(songs_audio_dir_v2())?;
+        fs::create_dir_all(songs_data_dir_v2())?;
+
+        fs::copy(&old_audio_path, &new_audio_path)?;
+        song_data.write_to_file_path_safe(song_data_file_v2(&song_id))?;
+
+        SONG_POOL.register_new_song(song_id, song_name, content_hash);
+    }
+
+    if !dry_run && !changes.is_empty() {
+        SONG_POOL.save_registered_songs()?;
+    }
+
+    Ok(changes)
+}
+
+/// Songs used to always be stored with an mp3 extension regardless of their actual format. Detects the
+/// real container of those files, records it in the song data, then renames the file to match
+
+fn migrate_audio_containers(dry_run: bool) -> io::Result<Vec<String>> {
+    let audio_dir = songs_audio_dir_v2();
+    let mut changes = Vec::new();
+
+    if !audio_dir.try_exists()? {
+        return Ok(changes);
+    }
+
+    for dir_entry in audio_dir.read_dir()? {
+        let dir_entry = dir_entry?;
+        let audio_path = dir_entry.path();
+
+        if audio_path.extension().and_then(|ext| ext.to_str())
+            != Some(AudioContainer::Mp3.extension())
+        {
+            continue;
+        }
+
+        let audio_container = match AudioContainer::sniff_file(&audio_path)? {
+            Some(AudioContainer::Mp3) | None => continue,
+            Some(audio_container) => audio_container,
+        };
+
+        changes.push(format!(
+            "Rename {} to match its {} audio container",
+            audio_path.display(),
+            audio_container
+        ));
+
+        if dry_run {
+            continue;
+        }
+
+        let mut song_data_path = songs_data_dir_v2().join(dir_entry.file_name());
+        song_data_path.set_extension(SONG_DATA_EXT_NO_PER);
+
+        // Song data that doesn't exist yet will locate the audio file by its new extension when created
+        if let Ok(mut song_data) = SongData::from_file_path_or_backup(&song_data_path) {
+            song_data.inner.audio_container = audio_container.into();
+            song_data.write_to_file_path_safe(&song_data_path)?;
+        }
+
+        fs::rename(
+            &audio_path,
+            audio_path.with_extension(audio_container.extension()),
+        )?;
+    }
+
+    Ok(changes)
+}
+
+/// Rewrites every song data file written with an older song data version, song data which can't be read
+/// is left for the library check to report
+
+fn upgrade_song_data_files(dry_run: bool) -> io::Result<Vec<String>> {
+    let data_dir = songs_data_dir_v2();
+    let mut changes = Vec::new();
+
+    if !data_dir.try_exists()? {
+        return Ok(changes);
+    }
+
+    for dir_entry in data_dir.read_dir()? {
+        let data_path = dir_entry?.path();
+
+        let read_result = if dry_run {
+            SongData::from_file_path(&data_path)
+        } else {
+            SongData::from_file_path_or_backup(&data_path)
+        };
+
+        let song_data = match read_result {
+            Ok(song_data) => song_data,
+
+            Err(e) => {
+                eprintln!("Unable to read {:?}, not upgrading it; {}", data_path, e);
+                continue;
+            }
+        };
+
+        if !song_data.did_update() {
+            continue;
+        }
+
+        changes.push(format!("Upgrade {}", data_path.display()));
+
+        if !dry_run {
+            song_data.write_to_file_path_safe(&data_path)?;
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Reads the metadata added by song data V6 from the audio of every song which doesn't have it yet, then
+/// saves the song data. Songs whose audio is missing are skipped and reported, their metadata is read
+/// once they load with their audio back in place
+
+fn read_extended_song_metadata(dry_run: bool) -> io::Result<Vec<String>> {
+    let data_dir = songs_data_dir_v2();
+    let mut changes = Vec::new();
+
+    if !data_dir.try_exists()? {
+        return Ok(changes);
+    }
+
+    for dir_entry in data_dir.read_dir()? {
+        let data_path = dir_entry?.path();
+
+        if !data_path.is_file() || is_temp_file(&data_path) {
+            continue;
+        }
+
+        let read_result = if dry_run {
+            SongData::from_file_path(&data_path)
+        } else {
+            SongData::from_file_path_or_backup(&data_path)
+        };
+
+        let mut song_data = match read_result {
+            Ok(song_data) => song_data,
+
+            Err(e) => {
+                eprintln!(
+                    "Unable to read {:?}, not reading its metadata; {}",
+                    data_path, e
+                );
+                continue;
+            }
+        };
+
+        if !song_data.inner.meta.inner.has_err() {
+            continue;
+        }
+
+        // Shared audio is stored under its own id rather than the id of the song
+        let audio_id_str = match song_data.inner.shared_audio_id.inner {
+            Some(audio_id) => audio_id.to_string(),
+            None => data_path
+                .file_stem()
+                .map(|file_stem| file_stem.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        };
+
+        let audio_path = songs_audio_dir_v2().join(format!(
+            "{}.{}",
+            audio_id_str,
+            song_data.inner.audio_container.inner.extension()
+        ));
+
+        if !audio_path.try_exists()? {
+            eprintln!(
+                "The audio of {:?} is missing, not reading its metadata",
+                data_path
+            );
+            changes.push(format!(
+                "Skip {}, its audio {} is missing",
+                data_path.display(),
+                audio_path.display()
+            ));
+            continue;
+        }
+
+        changes.push(format!(
+            "Read the metadata of {} from {}",
+            data_path.display(),
+            audio_path.display()
+        ));
+
+        if dry_run {
+            continue;
+        }
+
+        if !get_song_data_from_song_file_with_paths(&audio_path, &data_path, &mut song_data)? {
+            changes.push(format!(
+                "Unable to probe {}, its metadata was left empty",
+                audio_path.display()
+            ));
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Every audio file of a song stored in songs_v2, with its content hash once it has been worked out.
+/// Used by dry runs in place of the song pool, which loads and may rewrite the song data of each song it
+/// compares. Shared audio is left out, as the song pool doesn't count a copy of it as a duplicate
+
+fn library_audio_files() -> io::Result<Vec<(PathBuf, Option<ContentHash>)>> {
+    let audio_dir = songs_audio_dir_v2();
+
+    if !audio_dir.try_exists()? {
+        return Ok(Vec::new());
+    }
+
+    let shared_audio_ids = RegisteredSongs::from_file_path(registered_songs_data_file_v2())
+        .map(|registered_songs| {
+            registered_songs
+                .shared_audio_users
+                .inner
+                .keys()
+                .map(|audio_id| audio_id.to_string())
+                .collect::<HashSet<_>>()
+        })
+        .unwrap_or_default();
+
+    let mut audio_files = Vec::new();
+
+    for dir_entry in audio_dir.read_dir()? {
+        let audio_path = dir_entry?.path();
+
+        let is_shared_audio = audio_path
+            .file_stem()
+            .and_then(|file_stem| file_stem.to_str())
+            .is_some_and(|file_stem| shared_audio_ids.contains(file_stem));
+
+        if audio_path.is_file() && !is_temp_file(&audio_path) && !is_shared_audio {
+            audio_files.push((audio_path, None));
+        }
+    }
+
+    Ok(audio_files)
+}
+
+/// Whether any of `audio_files` has the exact same contents as the file at `audio_path`. Files are only
+/// hashed once their length matches, and the hash is kept for the next call
+
+fn find_audio_copy(
+    audio_path: &Path,
+    content_hash: ContentHash,
+    audio_files: &mut [(PathBuf, Option<ContentHash>)],
+) -> io::Result<bool> {
+    let audio_len = fs::metadata(audio_path)?.len();
+
+    for (library_path, library_hash) in audio_files.iter_mut() {
+        if fs::metadata(library_path.as_path())?.len() != audio_len {
+            continue;
+        }
+
+        let library_hash = match *library_hash {
+            Some(library_hash) => library_hash,
+            None => *library_hash.insert(hash_file(library_path.as_path())?),
+        };
+
+        if library_hash == content_hash && files_equal(audio_path, library_path.as_path())? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
@@ -0,0 +1,185 @@
+use crate::instance::client_settings::ClientSettings;
+use crate::paths::{DATA_EXT, home_dir};
+use crate::safe_write::SafeWrite;
+use serbytes::prelude::SerBytesFs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::{env, io};
+
+/// Environment variable which, when set, overrides the directory the library is stored in
+
+pub const LIBRARY_ROOT_ENV_VAR: &str = "NAPOLEON_AMP_DIR";
+
+/// If a file with this name is next to the executable, the library is stored next to the executable
+
+pub const PORTABLE_MARKER_FILE: &str = "napoleon_amp.portable";
+
+const PORTABLE_DATA_DIR: &str = "napoleon_amp_data/";
+const DATA_DIR_NAME: &str = "napoleon_amp/";
+
+static LIBRARY_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Where the library (folders, playlists, songs, settings and logs) is stored
+
+#[derive(Debug, Clone)]
+pub enum LibraryRoot {
+    /// The original location in the home directory
+    Home,
+    /// The platform data directory, `$XDG_DATA_HOME` (or `~/.local/share`) on linux
+    DataDir,
+    /// Next to the executable
+    Portable,
+    Custom(PathBuf),
+}
+
+impl LibraryRoot {
+    pub fn dir(&self) -> io::Result<PathBuf> {
+        match self {
+            Self::Home => Ok(home_dir().join("/napoleon_amp/")),
+
+            Self::DataDir => dirs_next::data_dir()
+                .map(|data_dir| data_dir.join(DATA_DIR_NAME))
+                .ok_or_else(|| io::Error::other("No data directory for this platform")),
+
+            Self::Portable => Ok(executable_dir()?.join(PORTABLE_DATA_DIR)),
+
+            Self::Custom(dir) => Ok(dir.clone()),
+        }
+    }
+
+    /// Picks the library root when one has not been set explicitly.
+    ///
+    /// In order of priority: [`LIBRARY_ROOT_ENV_VAR`], a [`PORTABLE_MARKER_FILE`] next to the
+    /// executable, the library root saved in the client settings of the default library, and finally
+    /// the default library itself.
+
+    fn detect() -> Self {
+        if let Some(env_dir) = env::var_os(LIBRARY_ROOT_ENV_VAR) {
+            if !env_dir.is_empty() {
+                return Self::Custom(env_dir.into());
+            }
+        }
+
+        let has_portable_marker = executable_dir()
+            .map(|exe_dir| exe_dir.join(PORTABLE_MARKER_FILE).is_file())
+            .unwrap_or(false);
+
+        if has_portable_marker {
+            return Self::Portable;
+        }
+
+        let default_root = Self::default_root();
+
+        if let Ok(default_dir) = default_root.dir() {
+            if let Some(saved_dir) = read_saved_library_root(&default_dir) {
+                return Self::Custom(saved_dir);
+            }
+        }
+
+        default_root
+    }
+
+    /// The home directory, unless on linux where the XDG data directory is used when there isn't
+    /// already a library in the home directory
+
+    fn default_root() -> Self {
+        if cfg!(target_os = "linux") {
+            let home_library_exists = Self::Home
+                .dir()
+                .and_then(|home_dir| home_dir.try_exists())
+                .unwrap_or(false);
+
+            if !home_library_exists && dirs_next::data_dir().is_some() {
+                return Self::DataDir;
+            }
+        }
+
+        Self::Home
+    }
+}
+
+#[derive(Debug)]
+pub struct LibraryRootAlreadySet {
+    pub current_dir: PathBuf,
+}
+
+/// Sets the library root for the rest of the program.
+///
+/// Must be called before anything from the library is loaded, as every pool resolves its paths from
+/// the library root the first time it is used. Returns an error if the library root has already been
+/// resolved.
+
+pub fn set_library_root(library_root: LibraryRoot) -> Result<(), LibraryRootAlreadySet> {
+    let dir = library_root.dir().unwrap_or_else(|_| fallback_dir());
+
+    LIBRARY_ROOT.set(dir).map_err(|_| LibraryRootAlreadySet {
+        current_dir: library_root_dir().to_path_buf(),
+    })
+}
+
+/// The directory the library is stored in, resolved on first use
+
+pub fn library_root_dir() -> &'static Path {
+    LIBRARY_ROOT.get_or_init(|| {
+        LibraryRoot::detect()
+            .dir()
+            .unwrap_or_else(|_| fallback_dir())
+    })
+}
+
+/// The library root saved in the client settings of the default library, which redirects the library
+/// to another directory on the next start
+
+pub fn saved_library_root() -> Option<PathBuf> {
+    read_saved_library_root(&LibraryRoot::default_root().dir().ok()?)
+}
+
+/// Saves a library root into the client settings of the default library, takes effect on the next start.
+/// Written safely, as a torn write would silently send the library back to the default location
+
+pub fn set_saved_library_root(library_root: Option<PathBuf>) -> io::Result<()> {
+    let settings_path = settings_file_in(&LibraryRoot::default_root().dir()?);
+
+    let mut client_settings = ClientSettings::from_file_path(&settings_path).unwrap_or_default();
+
+    client_settings.inner.library_root = library_root
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .into();
+
+    if let Some(parent) = settings_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    client_settings.write_to_file_path_safe(settings_path)
+}
+
+fn read_saved_library_root(default_dir: &Path) -> Option<PathBuf> {
+    let client_settings = ClientSettings::from_file_path(settings_file_in(default_dir)).ok()?;
+
+    client_settings
+        .inner
+        .library_root
+        .inner
+        .as_ref()
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+fn settings_file_in(library_dir: &Path) -> PathBuf {
+    library_dir.join("instance_data").join(DATA_EXT)
+}
+
+fn executable_dir() -> io::Result<PathBuf> {
+    let exe_path = env::current_exe()?;
+
+    exe_path
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| io::Error::other("Executable has no parent directory"))
+}
+
+fn fallback_dir() -> PathBuf {
+    LibraryRoot::Home
+        .dir()
+        .expect("Home library root is always resolvable")
+}
@@ -1,5 +1,7 @@
+pub mod library_root;
 pub(super) mod song;
 
+use crate::paths::library_root::library_root_dir;
 use chrono::{Datelike, Local, Timelike};
 use simple_id::prelude::Id;
 use std::path::{Path, PathBuf};
@@ -16,8 +18,8 @@ fn home_dir() -> PathBuf {
     dirs_next::home_dir().expect("Forced home directory")
 }
 
-fn napoleon_amp_dir() -> PathBuf {
-    home_dir().join("/napoleon_amp/")
+pub(crate) fn napoleon_amp_dir() -> PathBuf {
+    library_root_dir().to_path_buf()
 }
 
 pub(crate) fn client_settings_file_path() -> PathBuf {
@@ -56,6 +58,62 @@ pub(crate) fn content_playlist_song_list_file(id: Id) -> PathBuf {
     content_playlist_song_list_path().join(format!("{}{}", id, PLAYLIST_SONG_LIST_EXT))
 }
 
+/// Where the library check moves files it can't make sense of, instead of deleting them
+
+pub fn lost_found_dir() -> PathBuf {
+    napoleon_amp_dir().join("lost_found/")
+}
+
+/// Deleted content is moved here, each deletion in its own directory with the files laid out as they
+/// were in the library
+
+pub(crate) fn trash_dir() -> PathBuf {
+    napoleon_amp_dir().join("trash/")
+}
+
+pub(crate) fn trash_entry_dir(id: Id) -> PathBuf {
+    trash_dir().join(format!("{}/", id))
+}
+
+pub(crate) fn trash_entry_file(trash_entry_dir: &Path) -> PathBuf {
+    trash_entry_dir.join(format!("entry{}", DATA_EXT))
+}
+
+/// Previous versions of library files, laid out as the files are in the library
+
+pub(crate) fn backups_dir() -> PathBuf {
+    napoleon_amp_dir().join("backups/")
+}
+
+/// Which migrations have been applied to the library, see [`crate::migration`]
+
+pub(crate) fn library_version_file() -> PathBuf {
+    napoleon_amp_dir().join(format!("library_version{}", DATA_EXT))
+}
+
+/// Copies of the library data taken before each migration
+
+pub(crate) fn migration_backups_dir() -> PathBuf {
+    napoleon_amp_dir().join("migration_backups/")
+}
+
+pub(crate) fn migration_backup_dir(from_version: u16) -> PathBuf {
+    migration_backups_dir().join(format!("v{}/", from_version))
+}
+
+/// Where an archive is unpacked while it is being restored, before it replaces the library data
+
+pub(crate) fn restore_staging_dir() -> PathBuf {
+    napoleon_amp_dir().join(".restore/")
+}
+
+/// The library data replaced by restoring archives, each restore in its own directory named after when
+/// it happened
+
+pub fn replaced_by_restore_dir() -> PathBuf {
+    napoleon_amp_dir().join("replaced_by_restore/")
+}
+
 pub fn log_dir() -> PathBuf {
     napoleon_amp_dir().join("logs/")
 }
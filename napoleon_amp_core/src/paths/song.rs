@@ -1,8 +1,16 @@
+use crate::content::song::audio_container::AudioContainer;
 use crate::content::song::song_cover_pool::SongCoverId;
 use crate::paths::{DATA_EXT, SONG_DATA_EXT, napoleon_amp_dir};
 use simple_id::prelude::Id;
 use std::path::PathBuf;
 
+/// Songs used to be stored here with their audio and data files side by side, named after the original
+/// audio file
+
+pub(crate) fn songs_dir_v1() -> PathBuf {
+    napoleon_amp_dir().join("songs/")
+}
+
 pub(crate) fn songs_blanket_dir_v2() -> PathBuf {
     napoleon_amp_dir().join("songs_v2/")
 }
@@ -27,9 +35,12 @@ pub(crate) fn song_data_file_v2(song_id: &Id) -> PathBuf {
     songs_data_dir_v2().join(format!("{}{}", song_id.to_string(), SONG_DATA_EXT))
 }
 
-pub(crate) fn song_audio_file_v2(song_id: &Id) -> PathBuf {
-    // TODO: work with any audio type
-    songs_audio_dir_v2().join(format!("{}.mp3", song_id.to_string()))
+pub(crate) fn song_audio_file_v2(song_id: &Id, audio_container: AudioContainer) -> PathBuf {
+    songs_audio_dir_v2().join(format!(
+        "{}.{}",
+        song_id.to_string(),
+        audio_container.extension()
+    ))
 }
 
 pub(crate) fn song_cover_file(song_cover_id: &SongCoverId) -> PathBuf {
@@ -0,0 +1,207 @@
+//! Files are written to a temporary file which is then renamed over the original, so a crash part way
+//! through a write never leaves a half written file behind. The last few versions of each file which
+//! could still be read are kept in the backups directory, and are restored when the file itself can't
+//! be read anymore.
+
+use crate::paths::{backups_dir, napoleon_amp_dir};
+use serbytes::prelude::{FromFileResult, SerBytesFs};
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+
+/// How many of the previous versions of each file are kept
+const BACKUP_COUNT: usize = 3;
+
+pub(crate) trait SafeWrite: SerBytesFs + Sized {
+    /// Writes to a temporary file next to `path` which is then renamed into place. What is currently at
+    /// `path` is backed up first, as long as it can be read
+
+    fn write_to_file_path_safe(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+
+        write_atomically(path, |temp_path| {
+            self.write_to_file_path(temp_path)?;
+
+            if path.try_exists()? && Self::from_file_path(path).is_ok() {
+                back_up_file(path)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Reads the file at `path`, if it exists but can't be read the newest backup which can be read is
+    /// copied over it and returned instead. Missing files are not restored, since they were deleted or
+    /// moved to the trash on purpose
+
+    fn from_file_path_or_backup(path: impl AsRef<Path>) -> FromFileResult<'static, Self> {
+        let path = path.as_ref();
+        let read_result = Self::from_file_path(path);
+
+        if read_result.is_ok() || !path.try_exists().unwrap_or(false) {
+            return read_result;
+        }
+
+        for backup_path in backup_paths(path) {
+            let Ok(data) = Self::from_file_path(&backup_path) else {
+                continue;
+            };
+
+            eprintln!(
+                "Unable to read {:?}, restoring it from the backup {:?}",
+                path, backup_path
+            );
+
+            if let Err(e) = fs::copy(&backup_path, path) {
+                eprintln!("Unable to restore {:?} from backup; {}", path, e);
+            }
+
+            return Ok(data);
+        }
+
+        read_result
+    }
+}
+
+impl<T: SerBytesFs> SafeWrite for T {}
+
+/// Writes a file through a temporary file next to `path`, which `write` is given the path of. Once written
+/// the temporary file is synced and renamed over `path`, if anything fails it is removed instead so
+/// whatever was at `path` is left untouched
+
+pub(crate) fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&Path) -> io::Result<()>,
+) -> io::Result<()> {
+    let temp_path = temp_file_path(path)?;
+
+    let write_result = (|| {
+        write(&temp_path)?;
+        OpenOptions::new()
+            .write(true)
+            .open(&temp_path)?
+            .sync_all()?;
+
+        fs::rename(&temp_path, path)
+    })();
+
+    if write_result.is_err() {
+        // The temporary file may not have been created yet
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    write_result?;
+
+    sync_parent_dir(path)
+}
+
+/// Deletes every backup of the file at `path`, for when the file itself is deleted for good
+
+pub(crate) fn remove_backups(path: &Path) -> io::Result<()> {
+    let Some(backup_base_path) = backup_base_path(path) else {
+        return Ok(());
+    };
+
+    for backup_index in 1..=BACKUP_COUNT {
+        match fs::remove_file(numbered_backup_path(&backup_base_path, backup_index)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether `path` is the temporary file of a write, left behind if the write was interrupted
+
+pub(crate) fn is_temp_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|file_name| file_name.to_str())
+        .is_some_and(|file_name| file_name.starts_with('.') && file_name.ends_with(".tmp"))
+}
+
+/// Syncs the directory holding `path`, so a rename into it survives a crash. Directories can't be opened
+/// to be synced on every platform, so failing to open one is ignored
+
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    let Some(parent_dir) = path.parent() else {
+        return Ok(());
+    };
+
+    match File::open(parent_dir) {
+        Ok(dir_file) => dir_file.sync_all(),
+        Err(_) => Ok(()),
+    }
+}
+
+/// The temporary file a write to `path` goes through, in the same directory so it can be renamed over it
+
+fn temp_file_path(path: &Path) -> io::Result<PathBuf> {
+    let file_name = path.file_name().ok_or(ErrorKind::InvalidFilename)?;
+
+    let mut temp_file_name = OsString::from(".");
+    temp_file_name.push(file_name);
+    temp_file_name.push(".tmp");
+
+    Ok(path.with_file_name(temp_file_name))
+}
+
+/// Shifts every backup of the file at `path` back by one, dropping the oldest, then copies the file in
+/// as the newest backup
+
+fn back_up_file(path: &Path) -> io::Result<()> {
+    let Some(backup_base_path) = backup_base_path(path) else {
+        return Ok(());
+    };
+
+    if let Some(backup_dir) = backup_base_path.parent() {
+        fs::create_dir_all(backup_dir)?;
+    }
+
+    for backup_index in (1..BACKUP_COUNT).rev() {
+        let backup_path = numbered_backup_path(&backup_base_path, backup_index);
+
+        if backup_path.try_exists()? {
+            fs::rename(
+                backup_path,
+                numbered_backup_path(&backup_base_path, backup_index + 1),
+            )?;
+        }
+    }
+
+    fs::copy(path, numbered_backup_path(&backup_base_path, 1))?;
+
+    Ok(())
+}
+
+/// Every backup of the file at `path` which exists, newest first
+
+fn backup_paths(path: &Path) -> Vec<PathBuf> {
+    let Some(backup_base_path) = backup_base_path(path) else {
+        return Vec::new();
+    };
+
+    (1..=BACKUP_COUNT)
+        .map(|backup_index| numbered_backup_path(&backup_base_path, backup_index))
+        .filter(|backup_path| backup_path.try_exists().unwrap_or(false))
+        .collect()
+}
+
+/// Backups are laid out in the backups directory as the files are in the library, files outside the
+/// library keep their backups in a backups directory next to them
+
+fn backup_base_path(path: &Path) -> Option<PathBuf> {
+    match path.strip_prefix(napoleon_amp_dir()) {
+        Ok(relative_path) => Some(backups_dir().join(relative_path)),
+        Err(_) => Some(path.parent()?.join("backups").join(path.file_name()?)),
+    }
+}
+
+fn numbered_backup_path(backup_base_path: &Path, backup_index: usize) -> PathBuf {
+    let mut backup_path = backup_base_path.as_os_str().to_owned();
+    backup_path.push(format!(".{}", backup_index));
+
+    PathBuf::from(backup_path)
+}
@@ -0,0 +1,571 @@
+//! Writes the title, artist, album, rating and cover of a song back into the tags embedded in its stored
+//! audio file, so audio exported from the library shows the same metadata as napoleon does.
+//!
+//! Ratings are written as a POPM frame to ID3v2 tags, and as an FMPS rating to Vorbis comments, MP4 and
+//! APE tags. Nothing is written without first listing the changes with [`tag_changes`], and every other
+//! frame or field of the tag is kept as it was.
+
+use crate::content::song::content_hash::hash_file;
+use crate::content::song::song_cover_pool::SONG_COVER_POOL;
+use crate::content::song::song_data::MAX_RATING;
+use crate::content::song::song_pool::SONG_POOL;
+use crate::content::song::{Song, UNKNOWN_ALBUM_STR, UNKNOWN_ARTIST_STR};
+use crate::safe_write::write_atomically;
+use lofty::ape::{ApeFile, ApeTag};
+use lofty::config::{ParseOptions, WriteOptions};
+use lofty::error::LoftyError;
+use lofty::file::FileType;
+use lofty::flac::FlacFile;
+use lofty::id3::v2::Id3v2Tag;
+use lofty::iff::aiff::AiffFile;
+use lofty::iff::wav::WavFile;
+use lofty::mp4::{Ilst, Mp4File};
+use lofty::mpeg::MpegFile;
+use lofty::ogg::{OpusFile, VorbisComments, VorbisFile};
+use lofty::picture::{MimeType, Picture, PictureType};
+use lofty::prelude::{Accessor, AudioFile, ItemKey, MergeTag, SplitTag, TagExt};
+use lofty::probe::Probe;
+use lofty::tag::{ItemValue, Tag, TagItem, TagType};
+use lofty::wavpack::WavPackFile;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::sync::Arc;
+use std::{fs, io};
+
+/// The email of the POPM frame written, the one most other players read ratings from. POPM frames with
+/// other emails belong to other players and are kept
+const POPM_EMAIL: &str = "Windows Media Player 9 Series";
+/// The POPM rating of each star rating from 1 to 5
+const POPM_STAR_RATINGS: [u8; MAX_RATING as usize] = [1, 64, 128, 196, 255];
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TagField {
+    Title,
+    Artist,
+    Album,
+    Rating,
+    Cover,
+}
+
+impl Display for TagField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let display_str = match self {
+            Self::Title => "Title",
+            Self::Artist => "Artist",
+            Self::Album => "Album",
+            Self::Rating => "Rating",
+            Self::Cover => "Cover",
+        };
+
+        f.write_str(display_str)
+    }
+}
+
+/// A tag which differs between the song data and the audio file
+
+#[derive(Debug, Clone)]
+pub struct TagChange {
+    pub field: TagField,
+    /// As embedded in the audio file, empty if it isn't set
+    pub current: String,
+    pub new: String,
+}
+
+impl Display for TagChange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: \"{}\" -> \"{}\"",
+            self.field, self.current, self.new
+        )
+    }
+}
+
+/// The tags the audio file should have, taken from the song data. Placeholders such as the unknown artist
+/// are left out, so they never replace a tag
+struct SongTags {
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    rating: u8,
+    cover: Option<(String, Arc<[u8]>)>,
+}
+
+impl SongTags {
+    fn new(song: &Song) -> Self {
+        let song_data = song.get_song_data();
+        let song_data = &song_data.inner;
+        let meta = &song_data.meta.inner;
+
+        let cover = meta
+            .cover
+            .inner
+            .as_ref()
+            .ok()
+            .copied()
+            .flatten()
+            .map(|cover_id| {
+                let song_cover_data = SONG_COVER_POOL.get_or_load_value_arc_default(cover_id);
+
+                (
+                    song_cover_data.inner.mime_type.clone(),
+                    Arc::clone(&song_cover_data.inner.bytes.inner),
+                )
+            });
+
+        Self {
+            title: Some(song_data.title.clone()).filter(|title| !title.is_empty()),
+            artist: meta
+                .artist
+                .inner
+                .as_ref()
+                .ok()
+                .map(|artist| artist.full_artist_string.clone())
+                .filter(|artist| !artist.is_empty() && artist != UNKNOWN_ARTIST_STR),
+            album: meta
+                .album
+                .inner
+                .as_ref()
+                .ok()
+                .cloned()
+                .filter(|album| !album.is_empty() && album != UNKNOWN_ALBUM_STR),
+            rating: song_data.rating.min(MAX_RATING as u8),
+            cover,
+        }
+    }
+
+    fn changes(&self, tag: &Tag) -> Vec<TagChange> {
+        let mut changes = Vec::new();
+
+        let text_fields = [
+            (TagField::Title, tag.title(), &self.title),
+            (TagField::Artist, tag.artist(), &self.artist),
+            (TagField::Album, tag.album(), &self.album),
+        ];
+
+        for (field, current, new) in text_fields {
+            let (current, Some(new)) = (current.unwrap_or_default(), new) else {
+                continue;
+            };
+
+            if current != new.as_str() {
+                changes.push(TagChange {
+                    field,
+                    current: current.into_owned(),
+                    new: new.clone(),
+                });
+            }
+        }
+
+        let current_rating = read_rating(tag);
+
+        if current_rating != self.rating {
+            changes.push(TagChange {
+                field: TagField::Rating,
+                current: rating_str(current_rating),
+                new: rating_str(self.rating),
+            });
+        }
+
+        // A song without a cover never removes the cover of the file
+        if let Some((mime_type, bytes)) = &self.cover {
+            let current_cover = front_cover(tag);
+
+            if current_cover.is_none_or(|picture| picture.data() != &**bytes) {
+                changes.push(TagChange {
+                    field: TagField::Cover,
+                    current: current_cover.map(picture_str).unwrap_or_default(),
+                    new: format!("{} image, {} KB", mime_type, bytes.len() / 1024),
+                });
+            }
+        }
+
+        changes
+    }
+
+    fn apply(&self, tag: &mut Tag, changes: &[TagChange]) {
+        for change in changes {
+            match change.field {
+                TagField::Title => tag.set_title(change.new.clone()),
+                TagField::Artist => tag.set_artist(change.new.clone()),
+                TagField::Album => tag.set_album(change.new.clone()),
+                TagField::Rating => write_rating(tag, self.rating),
+
+                TagField::Cover => {
+                    if let Some((mime_type, bytes)) = &self.cover {
+                        tag.remove_picture_type(PictureType::CoverFront);
+                        tag.push_picture(Picture::new_unchecked(
+                            PictureType::CoverFront,
+                            Some(MimeType::from_str(mime_type)),
+                            None,
+                            bytes.to_vec(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The main tag of an audio file, kept as its own type rather than lofty's generic [`Tag`] so frames and
+/// fields which have no generic equivalent are written back as they were read
+#[derive(Clone)]
+enum PrimaryTag {
+    Id3v2(Id3v2Tag),
+    VorbisComments(VorbisComments),
+    Ilst(Ilst),
+    Ape(ApeTag),
+}
+
+impl PrimaryTag {
+    /// Reads the main tag of the audio file, or an empty tag of the type the file would have if it has
+    /// none
+
+    fn read(song_audio_path: &Path) -> io::Result<Self> {
+        let file_type = Probe::open(song_audio_path)
+            .map_err(tag_error)?
+            .guess_file_type()?
+            .file_type();
+
+        let primary_tag = match file_type {
+            Some(FileType::Mpeg) => Self::Id3v2(
+                read_audio_file::<MpegFile>(song_audio_path)?
+                    .id3v2()
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+
+            Some(FileType::Wav) => Self::Id3v2(
+                read_audio_file::<WavFile>(song_audio_path)?
+                    .id3v2()
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+
+            Some(FileType::Aiff) => Self::Id3v2(
+                read_audio_file::<AiffFile>(song_audio_path)?
+                    .id3v2()
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+
+            Some(FileType::Flac) => Self::VorbisComments(
+                read_audio_file::<FlacFile>(song_audio_path)?
+                    .vorbis_comments()
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+
+            Some(FileType::Vorbis) => Self::VorbisComments(
+                read_audio_file::<VorbisFile>(song_audio_path)?
+                    .vorbis_comments()
+                    .clone(),
+            ),
+
+            Some(FileType::Opus) => Self::VorbisComments(
+                read_audio_file::<OpusFile>(song_audio_path)?
+                    .vorbis_comments()
+                    .clone(),
+            ),
+
+            Some(FileType::Mp4) => Self::Ilst(
+                read_audio_file::<Mp4File>(song_audio_path)?
+                    .ilst()
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+
+            Some(FileType::Ape) => Self::Ape(
+                read_audio_file::<ApeFile>(song_audio_path)?
+                    .ape()
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+
+            Some(FileType::WavPack) => Self::Ape(
+                read_audio_file::<WavPackFile>(song_audio_path)?
+                    .ape()
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+
+            _ => {
+                return Err(io::Error::new(
+                    ErrorKind::Unsupported,
+                    "Tags can't be written to this type of audio file",
+                ));
+            }
+        };
+
+        Ok(primary_tag)
+    }
+
+    /// The fields of the tag which lofty can map to its generic [`Tag`]
+
+    fn generic_tag(&self) -> Tag {
+        match self.clone() {
+            Self::Id3v2(tag) => tag.split_tag().1,
+            Self::VorbisComments(tag) => tag.split_tag().1,
+            Self::Ilst(tag) => tag.split_tag().1,
+            Self::Ape(tag) => tag.split_tag().1,
+        }
+    }
+
+    /// Edits the generic fields of the tag, then merges them back into the rest of it, so only the
+    /// fields `edit` changes are changed
+
+    fn edit(self, edit: impl FnOnce(&mut Tag)) -> Self {
+        match self {
+            Self::Id3v2(tag) => Self::Id3v2(edit_split_tag(tag, edit)),
+            Self::VorbisComments(tag) => Self::VorbisComments(edit_split_tag(tag, edit)),
+            Self::Ilst(tag) => Self::Ilst(edit_split_tag(tag, edit)),
+            Self::Ape(tag) => Self::Ape(edit_split_tag(tag, edit)),
+        }
+    }
+
+    /// Replaces the tag of this type in the audio file at `path`, any other tags of the file are left as
+    /// they are
+
+    fn save_to_path(&self, path: &Path) -> io::Result<()> {
+        let write_options = WriteOptions::default();
+
+        match self {
+            Self::Id3v2(tag) => tag.save_to_path(path, write_options),
+            Self::VorbisComments(tag) => tag.save_to_path(path, write_options),
+            Self::Ilst(tag) => tag.save_to_path(path, write_options),
+            Self::Ape(tag) => tag.save_to_path(path, write_options),
+        }
+        .map_err(tag_error)
+    }
+}
+
+/// Lists every tag of the stored audio file which differs from the song data, nothing is written
+
+pub fn tag_changes(song: &Song) -> io::Result<Vec<TagChange>> {
+    let song_audio_path = writable_audio_path(song)?;
+    let primary_tag = PrimaryTag::read(song_audio_path)?;
+
+    Ok(SongTags::new(song).changes(&primary_tag.generic_tag()))
+}
+
+/// Writes `changes`, as listed by [`tag_changes`] and confirmed, into the tags of the stored audio file.
+/// Only the listed fields change, and the tags are written into a copy of the file which then replaces
+/// it, so a failed write never leaves the audio half written
+
+pub fn write_tags(song: &Song, changes: &[TagChange]) -> io::Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let song_audio_path = writable_audio_path(song)?;
+    let song_tags = SongTags::new(song);
+
+    let primary_tag = PrimaryTag::read(song_audio_path)?.edit(|tag| song_tags.apply(tag, changes));
+
+    write_atomically(song_audio_path, |temp_path| {
+        fs::copy(song_audio_path, temp_path)?;
+
+        primary_tag.save_to_path(temp_path)
+    })?;
+
+    // The contents changed, so the old hash would no longer find copies of the file
+    SONG_POOL.update_content_hash(song.id(), hash_file(song_audio_path)?);
+    SONG_POOL.save_registered_songs()
+}
+
+fn writable_audio_path(song: &Song) -> io::Result<&Path> {
+    // The tags of a shared file belong to the whole album rather than a single track
+    if song.get_song_data().inner.shared_audio_id.inner.is_some() {
+        return Err(io::Error::new(
+            ErrorKind::Unsupported,
+            "The song shares its audio file with other songs, such as the tracks of a CUE sheet",
+        ));
+    }
+
+    Ok(song.song_audio_path())
+}
+
+fn read_audio_file<F: AudioFile>(song_audio_path: &Path) -> io::Result<F> {
+    F::read_from(&mut File::open(song_audio_path)?, ParseOptions::new()).map_err(tag_error)
+}
+
+/// Splits the generic fields out of `tag` for `edit`, then merges them back with the fields lofty has no
+/// generic equivalent for
+
+fn edit_split_tag<T>(tag: T, edit: impl FnOnce(&mut Tag)) -> T
+where
+    T: SplitTag,
+    T::Remainder: MergeTag<Merged = T>,
+{
+    let (remainder, mut generic_tag) = tag.split_tag();
+    edit(&mut generic_tag);
+
+    remainder.merge_tag(generic_tag)
+}
+
+fn read_rating(tag: &Tag) -> u8 {
+    if tag.tag_type() == TagType::Id3v2 {
+        let popm_frames = popm_frames(tag);
+
+        // Other players may have rated the song as well, the frame napoleon writes is read first
+        let popm_rating = popm_frames
+            .iter()
+            .filter_map(|popm| split_popm(popm))
+            .find(|(email, _, _)| *email == POPM_EMAIL.as_bytes())
+            .or_else(|| popm_frames.iter().find_map(|popm| split_popm(popm)))
+            .map_or(0, |(_, popm_rating, _)| popm_rating);
+
+        return match popm_rating {
+            0 => 0,
+            1..=31 => 1,
+            32..=95 => 2,
+            96..=159 => 3,
+            160..=223 => 4,
+            _ => 5,
+        };
+    }
+
+    let Some(fmps_key) = fmps_rating_key(tag.tag_type()) else {
+        return 0;
+    };
+
+    // FMPS ratings go from 0.0 to 1.0
+    tag.get_string(&fmps_key)
+        .and_then(|fmps_rating| fmps_rating.trim().parse::<f32>().ok())
+        .map_or(0, |fmps_rating| {
+            (fmps_rating.clamp(0.0, 1.0) * MAX_RATING as f32).round() as u8
+        })
+}
+
+fn write_rating(tag: &mut Tag, rating: u8) {
+    if tag.tag_type() == TagType::Id3v2 {
+        write_popm_rating(tag, rating);
+        return;
+    }
+
+    let Some(fmps_key) = fmps_rating_key(tag.tag_type()) else {
+        return;
+    };
+
+    tag.remove_key(&fmps_key);
+
+    if rating != 0 {
+        tag.insert(TagItem::new(
+            fmps_key,
+            ItemValue::Text(format!("{}", rating as f32 / MAX_RATING as f32)),
+        ));
+    }
+}
+
+/// Sets the rating of the POPM frame napoleon writes, keeping its play counter. The frames of other
+/// players are left as they are
+
+fn write_popm_rating(tag: &mut Tag, rating: u8) {
+    let mut play_counter = None;
+    let mut other_frames = Vec::new();
+
+    for popm in popm_frames(tag) {
+        let own_play_counter = split_popm(&popm)
+            .filter(|(email, _, _)| *email == POPM_EMAIL.as_bytes())
+            .map(|(_, _, play_counter)| play_counter.to_vec());
+
+        match own_play_counter {
+            Some(own_play_counter) => play_counter = Some(own_play_counter),
+            None => other_frames.push(popm),
+        }
+    }
+
+    // Unrated songs keep the frame when other players rated them, or their rating would be read instead
+    let keep_frame = rating != 0 || play_counter.is_some() || !other_frames.is_empty();
+
+    tag.remove_key(&ItemKey::Popularimeter);
+
+    for popm in other_frames {
+        tag.push(TagItem::new(
+            ItemKey::Popularimeter,
+            ItemValue::Binary(popm),
+        ));
+    }
+
+    if !keep_frame {
+        return;
+    }
+
+    let mut popm = POPM_EMAIL.as_bytes().to_vec();
+    popm.push(0);
+    popm.push(match rating {
+        0 => 0,
+        rating => POPM_STAR_RATINGS[rating as usize - 1],
+    });
+    popm.extend(play_counter.unwrap_or_else(|| 0u32.to_be_bytes().to_vec()));
+
+    tag.push(TagItem::new(
+        ItemKey::Popularimeter,
+        ItemValue::Binary(popm),
+    ));
+}
+
+/// Every POPM frame of an ID3v2 tag, one is written by each player which rated the song
+
+fn popm_frames(tag: &Tag) -> Vec<Vec<u8>> {
+    tag.get_items(&ItemKey::Popularimeter)
+        .filter_map(|item| match item.value() {
+            ItemValue::Binary(popm) => Some(popm.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The email, rating and play counter of a POPM frame. The email is null terminated, followed by the
+/// rating then the play counter
+
+fn split_popm(popm: &[u8]) -> Option<(&[u8], u8, &[u8])> {
+    let email_end = popm.iter().position(|byte| *byte == 0)?;
+    let popm_rating = *popm.get(email_end + 1)?;
+
+    Some((&popm[..email_end], popm_rating, &popm[email_end + 2..]))
+}
+
+/// The key the FMPS rating is stored under, `None` for tags which don't have one
+
+fn fmps_rating_key(tag_type: TagType) -> Option<ItemKey> {
+    let fmps_key = match tag_type {
+        TagType::VorbisComments => "FMPS_RATING",
+        TagType::Mp4Ilst => "----:com.apple.iTunes:FMPS_Rating",
+        TagType::Ape => "FMPS_Rating",
+        _ => return None,
+    };
+
+    Some(ItemKey::Unknown(fmps_key.to_string()))
+}
+
+fn front_cover(tag: &Tag) -> Option<&Picture> {
+    tag.pictures()
+        .iter()
+        .find(|picture| picture.pic_type() == PictureType::CoverFront)
+        .or_else(|| tag.pictures().first())
+}
+
+fn rating_str(rating: u8) -> String {
+    match rating {
+        0 => "Unrated".to_string(),
+        1 => "1 star".to_string(),
+        rating => format!("{} stars", rating),
+    }
+}
+
+fn picture_str(picture: &Picture) -> String {
+    format!(
+        "{} image, {} KB",
+        picture
+            .mime_type()
+            .map_or("Unknown", |mime_type| mime_type.as_str()),
+        picture.data().len() / 1024
+    )
+}
+
+fn tag_error(e: LoftyError) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, e)
+}